@@ -0,0 +1,157 @@
+//! Benchmark of the cuts computed on nalgebra views of the momenta, as the
+//! code does, against the same cuts computed on outgoing momenta gathered in
+//! struct-of-arrays layout with hand-written 3-vector products
+//!
+//! Both versions are applied to the same generated events, whose decisions
+//! must agree, then the speedup of the struct-of-arrays version is reported.
+//! Run it with `cargo bench --bench cuts`.
+//!
+//! It finds no speedup beyond the noise of a few percent between runs, as
+//! nalgebra's fixed-size views compile down to the same products, so the code
+//! keeps using them.
+
+use rand_xoshiro::Xoshiro256PlusPlus;
+use std::{hint::black_box, time::Instant};
+use trois_photons::{
+    config::Configuration,
+    evcut::{Cut, EventCut},
+    event::{Event, NUM_OUTGOING},
+    evgen::EventGenerator,
+    momentum::{E, X, Y, Z},
+    numeric::Float,
+    random::{RandGenerator, RandomGenerator},
+};
+
+/// Number of generated events to which the cuts are applied
+const NUM_EVENTS: usize = 1024;
+
+/// Number of times the cuts are applied to each event
+const NUM_ROUNDS: usize = 20_000;
+
+fn main() {
+    // Generate events with the default configuration
+    let cfg = Configuration::default();
+    let evgen = EventGenerator::new(cfg.e_total).expect("The default configuration is valid");
+    let mut rng = RandGenerator::<Xoshiro256PlusPlus>::with_seed(0);
+    let events = (0..NUM_EVENTS)
+        .map(|_| evgen.generate(&mut rng))
+        .collect::<Vec<_>>();
+
+    // Check that both versions of the cuts reach the same decisions
+    let cut = &cfg.event_cut;
+    for event in &events {
+        assert_eq!(cut.apply(event), apply_arrays(cut, event));
+    }
+
+    // Time both versions
+    let views = time("nalgebra views", &events, |event| cut.apply(event));
+    let arrays = time("struct of arrays", &events, |event| {
+        apply_arrays(cut, event)
+    });
+    println!("Speedup          : {:.2}", views / arrays);
+}
+
+/// Time the cuts over all events, repeated NUM_ROUNDS times, and return the
+/// average time per event (ns)
+fn time(name: &str, events: &[Event], cut: impl Fn(&Event) -> Result<(), Cut>) -> f64 {
+    let start = Instant::now();
+    let mut num_passed = 0;
+    for _ in 0..NUM_ROUNDS {
+        for event in events {
+            num_passed += usize::from(cut(black_box(event)).is_ok());
+        }
+    }
+    black_box(num_passed);
+    let elapsed = start.elapsed().as_secs_f64() * 1e9 / (NUM_ROUNDS * events.len()) as f64;
+    println!("{name:<16} : {elapsed:.3} ns");
+    elapsed
+}
+
+/// 4-momenta of the outgoing photons, in struct-of-arrays layout
+struct MomentumArrays {
+    /// X coordinates of the photons
+    x: [Float; NUM_OUTGOING],
+
+    /// Y coordinates of the photons
+    y: [Float; NUM_OUTGOING],
+
+    /// Z coordinates of the photons
+    z: [Float; NUM_OUTGOING],
+
+    /// Energies of the photons
+    e: [Float; NUM_OUTGOING],
+}
+//
+impl MomentumArrays {
+    /// Gather the outgoing 4-momenta of an event
+    fn new(event: &Event) -> Self {
+        let column = |coord| std::array::from_fn(|ph| event.outgoing_momentum(ph)[coord]);
+        Self {
+            x: column(X),
+            y: column(Y),
+            z: column(Z),
+            e: column(E),
+        }
+    }
+
+    /// 3-momentum of a photon
+    fn xyz(&self, ph: usize) -> [Float; 3] {
+        [self.x[ph], self.y[ph], self.z[ph]]
+    }
+
+    /// Dot product of the 3-momentum of a photon with a 3-vector
+    fn dot3(&self, ph: usize, [x, y, z]: [Float; 3]) -> Float {
+        self.x[ph] * x + self.y[ph] * y + self.z[ph] * z
+    }
+
+    /// Cross product of the 3-momenta of two photons
+    fn cross3(&self, ph1: usize, ph2: usize) -> [Float; 3] {
+        let ([x1, y1, z1], [x2, y2, z2]) = (self.xyz(ph1), self.xyz(ph2));
+        [y1 * z2 - z1 * y2, z1 * x2 - x1 * z2, x1 * y2 - y1 * x2]
+    }
+}
+
+/// Apply the standard cuts like EventCut::apply(), in struct-of-arrays layout
+/// (the default configuration has no additional cut and no filter)
+fn apply_arrays(cut: &EventCut, event: &Event) -> Result<(), Cut> {
+    // Check if the outgoing photons pass the energy cut
+    if event.min_photon_energy() < cut.e_min {
+        return Err(Cut::PhotonEnergy);
+    }
+
+    // Get the incoming electron 3-momentum and energy, and the outgoing
+    // 4-momenta in a layout which suits the following products
+    let p_el = event.electron_momentum();
+    let (p_el_xyz, p_el_e) = ([p_el[X], p_el[Y], p_el[Z]], p_el[E]);
+    let ps_out = MomentumArrays::new(event);
+
+    // Check if the (beam, photon) angles pass the cut
+    for ph in 0..NUM_OUTGOING {
+        let cos_num = ps_out.dot3(ph, p_el_xyz);
+        let cos_denom = ps_out.e[ph] * p_el_e;
+        if cos_num.abs() > cut.beam_photons_cut * cos_denom {
+            return Err(Cut::BeamPhotonAngle);
+        }
+    }
+
+    // Check if the (photon1, photon{2, 3}) angles pass the cut
+    for ph1 in 0..NUM_OUTGOING - 1 {
+        for ph2 in ph1 + 1..NUM_OUTGOING {
+            let cos_num = ps_out.dot3(ph1, ps_out.xyz(ph2));
+            let cos_denom = ps_out.e[ph1] * ps_out.e[ph2];
+            if cos_num > cut.photon_photon_cut * cos_denom {
+                return Err(Cut::PhotonPhotonAngle);
+            }
+        }
+    }
+
+    // Check if the (beam, normal to photon plane) angle passes the cut
+    let [n_x, n_y, n_z] = ps_out.cross3(0, 1);
+    let [el_x, el_y, el_z] = p_el_xyz;
+    let cos_num = el_x * n_x + el_y * n_y + el_z * n_z;
+    let cos_denom = p_el_e * (n_x * n_x + n_y * n_y + n_z * n_z).sqrt();
+    if cos_num.abs() < cut.beam_photon_plane_cut * cos_denom {
+        return Err(Cut::BeamPhotonPlaneAngle);
+    }
+    Ok(())
+}
@@ -0,0 +1,69 @@
+//! Benchmark of powi() against hand-written multiplications, for the constant
+//! exponents (2, 3, 4 and 8) which the matrix elements and the results
+//! computations use
+//!
+//! This evaluates x² + x³ + x⁴ + x⁸ over the photon energies of generated
+//! events, once with powi() as the code does and once with the products of
+//! repeated squaring that pow2/pow3/pow4/pow8 helpers would compute, then
+//! reports the speedup of the latter. Run it with `cargo bench --bench powi`.
+//!
+//! It finds no speedup beyond the noise of a few percent between runs, as the
+//! compiler already expands powi() with a constant exponent into
+//! multiplications, so the code keeps using powi().
+
+use rand_xoshiro::Xoshiro256PlusPlus;
+use std::{hint::black_box, time::Instant};
+use trois_photons::{
+    config::Configuration,
+    event::NUM_OUTGOING,
+    evgen::EventGenerator,
+    momentum::E,
+    numeric::Float,
+    random::{RandGenerator, RandomGenerator},
+};
+
+/// Number of generated events whose photon energies are used as inputs
+const NUM_EVENTS: usize = 1024;
+
+/// Number of times each input is evaluated
+const NUM_ROUNDS: usize = 20_000;
+
+fn main() {
+    // Use the photon energies of events of the default configuration
+    let cfg = Configuration::default();
+    let evgen = EventGenerator::new(cfg.e_total).expect("The default configuration is valid");
+    let mut rng = RandGenerator::<Xoshiro256PlusPlus>::with_seed(0);
+    let inputs = (0..NUM_EVENTS)
+        .flat_map(|_| {
+            let event = evgen.generate(&mut rng);
+            (0..NUM_OUTGOING).map(move |ph| event.outgoing_momentum(ph)[E])
+        })
+        .collect::<Vec<Float>>();
+
+    // Time both implementations
+    let powi = time("powi()", &inputs, |x| {
+        x.powi(2) + x.powi(3) + x.powi(4) + x.powi(8)
+    });
+    let multiplications = time("multiplications", &inputs, |x| {
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        x2 + x * x2 + x4 + x4 * x4
+    });
+    println!("Speedup          : {:.2}", powi / multiplications);
+}
+
+/// Time the evaluation of a function over all inputs, repeated NUM_ROUNDS
+/// times, and return the average time per evaluation (ns)
+fn time(name: &str, inputs: &[Float], function: impl Fn(Float) -> Float) -> f64 {
+    let start = Instant::now();
+    let mut sum: Float = 0.;
+    for _ in 0..NUM_ROUNDS {
+        for &x in inputs {
+            sum += function(black_box(x));
+        }
+    }
+    black_box(sum);
+    let elapsed = start.elapsed().as_secs_f64() * 1e9 / (NUM_ROUNDS * inputs.len()) as f64;
+    println!("{name:<16} : {elapsed:.3} ns");
+    elapsed
+}
@@ -0,0 +1,20 @@
+//! Build script, which records the git commit that the program is built from
+
+use std::process::Command;
+
+fn main() {
+    // Query the current git commit, if the sources are in a git repository
+    let commit = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_owned())
+        .unwrap_or_else(|| "unknown".to_owned());
+    println!("cargo:rustc-env=GIT_COMMIT={commit}");
+
+    // Re-run this script whenever a commit is made or checked out
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+}
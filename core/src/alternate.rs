@@ -0,0 +1,123 @@
+//! Alternate computation of the matrix elements, used to cross-validate the
+//! spinor product implementation
+//!
+//! The simulation computes its amplitudes from the spinor products of M.
+//! Mangano and S. Parke (see spinor). Here, the standard amplitudes are instead
+//! computed from their Feynman diagrams, by contracting explicit Dirac spinors
+//! and photon polarization vectors (see dirac), and the anomalous amplitudes
+//! from spinor products built from the Weyl spinors of the dirac module. These
+//! spinor products only differ from the simulated ones by a phase per
+//! particle, which cancels out of the squared matrix elements, so that both
+//! computations must agree up to rounding errors.
+//!
+//! The spin interference terms of the matrix elements depend on the phase
+//! conventions of the spinors, so they are left out of the comparison.
+
+use crate::{
+    coupling::Couplings,
+    dirac,
+    event::{Event, ParticleMatrix, NUM_OUTGOING, NUM_PARTICLES},
+    matelems::{
+        HelicityAmplitudes, HelicityVector, MEsContributions, MEsVector, A, B_P, I_MX,
+        NUM_MAT_ELEMS, R_MX,
+    },
+    numeric::{functions::*, real::*, Complex, Float},
+    spinor::{PhotonHelicities, SpinorProducts, NUM_HELICITIES},
+};
+
+/// Spinor products of an event, computed from the left-handed Weyl spinors of
+/// the particles as given by dirac::massless_spinors
+///
+/// The spinor product s(i, j) is the complex conjugate of the antisymmetric
+/// contraction of the Weyl spinors of particles i and j.
+///
+pub fn dirac_spinor_products(event: &Event) -> SpinorProducts {
+    let weyl: [[Complex; 2]; NUM_PARTICLES] = core::array::from_fn(|par| {
+        let [left, _right] = dirac::massless_spinors(&event.momentum(par));
+        [left[0], left[1]]
+    });
+    SpinorProducts::from_gram_matrix(ParticleMatrix::from_fn(|i, j| {
+        conj(weyl[i][1] * weyl[j][0] - weyl[i][0] * weyl[j][1])
+    }))
+}
+
+/// Compute the matrix element contributions of an event in the alternate way,
+/// for the simulated electron helicity
+pub fn contributions(couplings: &Couplings, event: &Event) -> MEsContributions {
+    const { assert!(NUM_OUTGOING == 3) };
+
+    // Compute the anomalous amplitudes, and the standard ones that they
+    // interfere with, from the alternate spinor products
+    let spinor = dirac_spinor_products(event);
+    let helicities = HelicityVector::from(PhotonHelicities::ALL);
+    let amplitudes = HelicityAmplitudes {
+        a: helicities.map(|hel| spinor.a(hel) * couplings.g_a),
+        b_p: helicities.map(|hel| spinor.b_p(hel) * couplings.g_beta_p),
+        b_m: helicities.map(|hel| spinor.b_m(hel) * couplings.g_beta_m),
+    };
+    let from_spinors = MEsContributions::from_amplitudes(&amplitudes);
+
+    // Compute the standard contribution from the Feynman diagrams, with the
+    // right-handed electron and positron spinors of the simulated helicity
+    let [_, electron] = dirac::massless_spinors(&event.electron_momentum());
+    let [_, positron] = dirac::massless_spinors(&event.positron_momentum());
+    let polarizations: [_; NUM_OUTGOING] = core::array::from_fn(|par| {
+        dirac::polarization_vectors(&event.outgoing_momentum(par)).map(|eps| eps.map(conj))
+    });
+    let mut per_helicity = *from_spinors.per_helicity();
+    for (hel_idx, hel) in PhotonHelicities::ALL.into_iter().enumerate() {
+        let eps = core::array::from_fn(|par| polarizations[par][hel.photon(par)]);
+        let amplitude = dirac::qed_amplitude(event, &electron, &positron, &eps);
+        per_helicity[(A, hel_idx)] = norm_sqr(amplitude * couplings.g_a);
+    }
+    MEsContributions::from_parts(per_helicity, from_spinors.spin_interference())
+}
+
+/// Comparison of the matrix elements of an event, as computed by the
+/// simulation and in the alternate way
+pub struct MatrixElementComparison {
+    /// Largest deviation between the two computations of each contribution
+    /// over photon helicities, relative to the magnitude of the contribution
+    /// summed over helicities
+    ///
+    /// The magnitude of the interference contributions is bounded by that of
+    /// twice the product of the interfering amplitudes.
+    ///
+    pub deviations: MEsVector,
+}
+//
+impl MatrixElementComparison {
+    /// Compare both computations of the matrix elements of an event
+    pub fn new(couplings: &Couplings, event: &Event) -> Self {
+        const { assert!(NUM_MAT_ELEMS == 5) };
+        let reference = MEsContributions::new(couplings, event);
+        let alternate = contributions(couplings, event);
+        let (reference, alternate) = (reference.per_helicity(), alternate.per_helicity());
+        let magnitude = |contrib| -> Float {
+            (0..NUM_HELICITIES)
+                .map(|hel| match contrib {
+                    R_MX | I_MX => 2. * sqrt(reference[(A, hel)] * reference[(B_P, hel)]),
+                    _ => abs(reference[(contrib, hel)]),
+                })
+                .sum()
+        };
+        Self {
+            deviations: MEsVector::from_fn(|contrib, _| {
+                let deviation = (0..NUM_HELICITIES)
+                    .map(|hel| abs(alternate[(contrib, hel)] - reference[(contrib, hel)]))
+                    .fold(0., Float::max);
+                let magnitude = magnitude(contrib);
+                if magnitude > 0. {
+                    deviation / magnitude
+                } else {
+                    deviation
+                }
+            }),
+        }
+    }
+
+    /// Largest relative deviation over all contributions
+    pub fn max_deviation(&self) -> Float {
+        self.deviations.max()
+    }
+}
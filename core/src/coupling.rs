@@ -0,0 +1,106 @@
+//! Physical couplings used for result computations
+
+use crate::numeric::{real::*, Float, Real};
+use core::fmt::{self, Display, LowerExp};
+
+/// Set of physical couplings
+///
+/// Besides the couplings which enter the matrix elements, this keeps the
+/// electroweak parameters that they were derived from, so that the exact
+/// inputs of a simulation can be checked.
+///
+pub struct Couplings<R: Real = Float> {
+    /// Standard Model contribution electromagnetic coupling √(4𝜋𝛼)³
+    pub g_a: R,
+
+    /// 𝛽₊ anomalous contribution electroweak coupling
+    pub g_beta_p: R,
+
+    /// 𝛽₋ anomalous contribution electroweak coupling
+    pub g_beta_m: R,
+
+    /// Fine structure constant of the Standard Model contribution
+    alpha: R,
+
+    /// Fine structure constant of the anomalous contributions
+    alpha_z: R,
+
+    /// Squared sine of the Weinberg angle
+    sin2_weinberg: R,
+
+    /// Z⁰ mass (GeV)
+    m_z0: R,
+}
+//
+impl<R: Real> Couplings<R> {
+    /// Compute the couplings from the fine structure constant of the Standard
+    /// Model contribution, that of the anomalous contributions (at the Z⁰
+    /// mass), the squared sine of the Weinberg angle and the Z⁰ mass (GeV)
+    pub fn new(alpha: R, alpha_z: R, sin2_weinberg: R, m_z0: R) -> Self {
+        let four = cast::<R>(4.);
+        let e2 = four * R::PI() * alpha;
+        let e2_z = four * R::PI() * alpha_z;
+        let cos2_weinberg = R::one() - sin2_weinberg;
+        let g_beta = -sqrt(e2_z / (four * cos2_weinberg * sin2_weinberg)) / powi(m_z0, 4);
+        Couplings {
+            g_a: -powi(sqrt(e2), 3),
+            g_beta_p: g_beta,
+            g_beta_m: g_beta,
+            alpha,
+            alpha_z,
+            sin2_weinberg,
+            m_z0,
+        }
+    }
+
+    /// Fine structure constant of the Standard Model contribution
+    pub fn alpha(&self) -> R {
+        self.alpha
+    }
+
+    /// Fine structure constant of the anomalous contributions, at the Z⁰ mass
+    pub fn alpha_z(&self) -> R {
+        self.alpha_z
+    }
+
+    /// Squared sine of the Weinberg angle
+    pub fn sin2_weinberg(&self) -> R {
+        self.sin2_weinberg
+    }
+
+    /// Squared cosine of the Weinberg angle
+    pub fn cos2_weinberg(&self) -> R {
+        R::one() - self.sin2_weinberg
+    }
+
+    /// Z⁰ mass (GeV)
+    pub fn m_z0(&self) -> R {
+        self.m_z0
+    }
+
+    /// Elementary charge of the Standard Model contribution, √(4𝜋𝛼)
+    pub fn e(&self) -> R {
+        sqrt(cast::<R>(4.) * R::PI() * self.alpha)
+    }
+
+    /// Elementary charge of the anomalous contributions, √(4𝜋𝛼_Z)
+    pub fn e_z(&self) -> R {
+        sqrt(cast::<R>(4.) * R::PI() * self.alpha_z)
+    }
+}
+
+impl<R: Real + LowerExp> Display for Couplings<R> {
+    /// List the electroweak parameters, then the couplings derived from them
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(fmt, "Fine structure constant  : {}", self.alpha)?;
+        writeln!(fmt, "Fine structure at Z peak : {}", self.alpha_z)?;
+        writeln!(fmt, "sin^2(theta_W)           : {}", self.sin2_weinberg)?;
+        writeln!(fmt, "cos^2(theta_W)           : {}", self.cos2_weinberg())?;
+        writeln!(fmt, "Z0 mass (GeV)            : {}", self.m_z0)?;
+        writeln!(fmt, "Elementary charge        : {}", self.e())?;
+        writeln!(fmt, "Elementary charge at Z   : {}", self.e_z())?;
+        writeln!(fmt, "Coupling g_a             : {:e}", self.g_a)?;
+        writeln!(fmt, "Coupling g_beta_plus     : {:e}", self.g_beta_p)?;
+        write!(fmt, "Coupling g_beta_minus    : {:e}", self.g_beta_m)
+    }
+}
@@ -0,0 +1,171 @@
+//! Explicit Dirac algebra, used to cross-check the spinor product formulas
+//!
+//! The amplitudes of the simulation are computed from compact spinor product
+//! formulas, where the photon polarization vectors do not appear. This module
+//! instead computes the standard amplitude from its Feynman diagrams, with
+//! explicit Dirac matrices, spinors and polarization vectors in the chiral
+//! basis. This is much slower, but it allows substituting any vector for the
+//! polarization of a photon, which is how gauge invariance is checked.
+
+use crate::{
+    event::{Event, NUM_OUTGOING},
+    momentum::{Momentum, E, MOMENTUM_DIM, X, Y, Z},
+    numeric::{floats::consts::FRAC_1_SQRT_2, functions::*, real::*, Complex, Float},
+};
+use nalgebra::{SMatrix, SVector};
+use num_traits::Zero;
+
+/// Dirac spinor
+pub type Spinor = SVector<Complex, 4>;
+
+/// Dirac matrix
+pub type DiracMatrix = SMatrix<Complex, 4, 4>;
+
+/// Complex 4-vector, with the same coordinate layout as Momentum
+pub type ComplexMomentum = SVector<Complex, MOMENTUM_DIM>;
+
+/// Feynman slash of a 4-vector, i.e. its contraction with the Dirac matrices
+/// 𝛾^𝜇, in the chiral basis and with the (+, -, -, -) metric
+pub fn slash(v: &ComplexMomentum) -> DiracMatrix {
+    // In the chiral basis, v̸ = [[0, v·𝜎], [v·𝜎̄, 0]] with v·𝜎 = v⁰ - v⃗·𝜎⃗
+    // and v·𝜎̄ = v⁰ + v⃗·𝜎⃗
+    let i: Complex = Complex::i();
+    let (v_x, v_y, v_z, v_e) = (v[X], v[Y], v[Z], v[E]);
+    let mut result = DiracMatrix::zeros();
+    let v_sigma = [[v_e - v_z, -(v_x - i * v_y)], [-(v_x + i * v_y), v_e + v_z]];
+    let v_sigma_bar = [[v_e + v_z, v_x - i * v_y], [v_x + i * v_y, v_e - v_z]];
+    for row in 0..2 {
+        for col in 0..2 {
+            result[(row, 2 + col)] = v_sigma[row][col];
+            result[(2 + row, col)] = v_sigma_bar[row][col];
+        }
+    }
+    result
+}
+
+/// Convert a real 4-momentum into a complex one
+pub fn complexify(p: &Momentum) -> ComplexMomentum {
+    p.map(Complex::from)
+}
+
+/// Dirac conjugate ψ̄ = ψ†𝛾⁰ of a spinor, as a row vector
+pub fn dirac_conjugate(psi: &Spinor) -> SMatrix<Complex, 1, 4> {
+    // In the chiral basis, 𝛾⁰ swaps the upper and lower components
+    let psi_dagger = psi.adjoint();
+    SMatrix::<Complex, 1, 4>::new(psi_dagger[2], psi_dagger[3], psi_dagger[0], psi_dagger[1])
+}
+
+/// Both solutions of the massless Dirac equation p̸ψ = 0, normalized to
+/// ψ†ψ = 2E, with negative and positive chirality respectively
+///
+/// They are the spinors of a massless fermion (or anti-fermion) with each
+/// helicity, up to a phase.
+///
+pub fn massless_spinors(p: &Momentum) -> [Spinor; 2] {
+    let (p_x, p_y, p_z, p_e) = (p[X], p[Y], p[Z], p[E]);
+    let i: Complex = Complex::i();
+    let transverse = Complex::new(p_x, 0.) - i * p_y;
+
+    // Pick the best-conditioned candidate of each 2-component kernel
+    let best = |a: [Complex; 2], b: [Complex; 2]| {
+        let (norm_a, norm_b) = (
+            norm_sqr(a[0]) + norm_sqr(a[1]),
+            norm_sqr(b[0]) + norm_sqr(b[1]),
+        );
+        let (best, norm) = if norm_a >= norm_b {
+            (a, norm_a)
+        } else {
+            (b, norm_b)
+        };
+        let scale = sqrt(2. * p_e / norm);
+        [best[0] * scale, best[1] * scale]
+    };
+
+    // Left-handed spinor: kernel of p·𝜎̄ in the upper components
+    let left = best(
+        [transverse, -Complex::from(p_e + p_z)],
+        [Complex::from(p_e - p_z), -conj(transverse)],
+    );
+
+    // Right-handed spinor: kernel of p·𝜎 in the lower components
+    let right = best(
+        [transverse, Complex::from(p_e - p_z)],
+        [Complex::from(p_e + p_z), conj(transverse)],
+    );
+
+    let zero = Complex::zero();
+    [
+        Spinor::new(left[0], left[1], zero, zero),
+        Spinor::new(zero, zero, right[0], right[1]),
+    ]
+}
+
+/// Polarization vectors of a photon of momentum k, for each helicity
+pub fn polarization_vectors(k: &Momentum) -> [ComplexMomentum; 2] {
+    // Build two unit vectors that are orthogonal to the photon direction,
+    // starting from the axis that is least aligned with it
+    let k_dir = k.fixed_rows::<3>(X).normalize();
+    let axis = (0..3)
+        .min_by(|&a, &b| abs(k_dir[a]).total_cmp(&abs(k_dir[b])))
+        .expect("There are three axes");
+    let mut reference = nalgebra::Vector3::<Float>::zeros();
+    reference[axis] = 1.;
+    let e_1 = k_dir.cross(&reference).normalize();
+    let e_2 = k_dir.cross(&e_1);
+
+    // Combine them into circular polarizations
+    let polarization = |sign: Float| {
+        let mut eps = ComplexMomentum::zeros();
+        for coord in 0..3 {
+            eps[X + coord] = Complex::new(e_1[coord], sign * e_2[coord]) * FRAC_1_SQRT_2;
+        }
+        eps
+    };
+    [polarization(-1.), polarization(1.)]
+}
+
+/// Standard (QED) amplitude of an event, without couplings, for given spinors
+/// of the incoming electron and positron and given (conjugate) polarization
+/// vectors of the outgoing photons
+///
+/// This is the sum of the 6 diagrams where the photons are emitted in every
+/// order along the electron line.
+///
+pub fn qed_amplitude(
+    event: &Event,
+    electron: &Spinor,
+    positron: &Spinor,
+    polarizations: &[ComplexMomentum; NUM_OUTGOING],
+) -> Complex {
+    const { assert!(NUM_OUTGOING == 3) };
+    let p_el = event.electron_momentum();
+    let k = |par: usize| event.outgoing_momentum(par);
+
+    // Massless fermion propagator i·q̸/q², without the factor of i
+    let propagator = |q: Momentum| {
+        let q2 = powi(q[E], 2) - q.fixed_rows::<3>(X).norm_squared();
+        slash(&complexify(&q)) / Complex::from(q2)
+    };
+
+    let v_bar = dirac_conjugate(positron);
+    let mut amplitude = Complex::zero();
+    for [first, second, third] in [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ] {
+        let q_1 = p_el - k(first);
+        let q_2 = q_1 - k(second);
+        let line = slash(&polarizations[third])
+            * propagator(q_2)
+            * slash(&polarizations[second])
+            * propagator(q_1)
+            * slash(&polarizations[first])
+            * electron;
+        amplitude += (v_bar * line)[0];
+    }
+    amplitude
+}
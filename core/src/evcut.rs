@@ -0,0 +1,330 @@
+//! Mechanism to apply a cut to generated events
+
+use crate::{
+    event::{Event, NUM_OUTGOING},
+    momentum::{E, X},
+    numeric::{real::*, Float},
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Cuts on generated events
+///
+/// Additional cuts are stored in a container of AdditionalCuts, which is a Vec
+/// in the simulation, but can be a slice or an array where memory cannot be
+/// allocated. Similarly, the optional filter which is applied after all cuts
+/// is of any type implementing EventFilter, such as the expressions of the
+/// simulation configuration, and there is none by default.
+///
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct EventCut<Additional, Filter = NoFilter> {
+    /// Cut on maximum cosine of (beam, photons) angle
+    pub beam_photons_cut: Float,
+
+    /// Cut on maximum cosine of (photon, photon) angle
+    pub photon_photon_cut: Float,
+
+    /// Cut on minimum photon energy
+    pub e_min: Float,
+
+    /// Cut on minimum cosine of (beam, normal to the photon plane) angle
+    pub beam_photon_plane_cut: Float,
+
+    /// Additional cuts, applied after the previous ones (not in the legacy
+    /// format)
+    pub additional: Additional,
+
+    /// Filter, applied after all cuts (not in the legacy format)
+    pub filter: Option<Filter>,
+}
+//
+impl<Additional: Default, Filter> EventCut<Additional, Filter> {
+    /// Setup the cuts on generated events
+    pub fn new(
+        beam_photons_cut: Float,
+        photon_photon_cut: Float,
+        e_min: Float,
+        beam_photon_plane_cut: Float,
+    ) -> Self {
+        EventCut {
+            beam_photons_cut,
+            photon_photon_cut,
+            e_min,
+            beam_photon_plane_cut,
+            additional: Additional::default(),
+            filter: None,
+        }
+    }
+}
+//
+impl<Additional: AsRef<[AdditionalCut]>, Filter: EventFilter> EventCut<Additional, Filter> {
+    /// Decide whether a generated event passes the cut or should be rejected
+    ///
+    /// If the event is rejected, the first individual cut which rejected it is
+    /// returned. Cuts are applied in the order of the Cut enum, and the filter
+    /// counts as the last additional cut.
+    ///
+    pub fn apply(&self, event: &Event) -> Result<(), Cut> {
+        // Check if the outgoing photons pass the energy cut
+        if event.min_photon_energy() < self.e_min {
+            return Err(Cut::PhotonEnergy);
+        }
+
+        // Get the incoming electron 4-momentum
+        let p_el = event.electron_momentum();
+
+        // Check if the (beam, photon) angles pass the cut
+        {
+            let ps_out = event.outgoing_momenta();
+            let ps_out_xyz = ps_out.fixed_columns::<3>(X);
+            let cos_nums = ps_out_xyz * p_el.xyz();
+            let cos_denoms = ps_out.column(E) * p_el[E];
+            for (&num, denom) in cos_nums.iter().zip(cos_denoms.iter()) {
+                if abs(num) > self.beam_photons_cut * denom {
+                    return Err(Cut::BeamPhotonAngle);
+                }
+            }
+        }
+
+        // Check if the (photon1, photon{2, 3}) angles pass the cut
+        for ph1 in 0..NUM_OUTGOING - 1 {
+            for ph2 in ph1 + 1..NUM_OUTGOING {
+                let p_ph1 = event.outgoing_momentum(ph1);
+                let p_ph2 = event.outgoing_momentum(ph2);
+                let cos_num = p_ph1.xyz().dot(&p_ph2.xyz());
+                let cos_denom = p_ph1[E] * p_ph2[E];
+                if cos_num > self.photon_photon_cut * cos_denom {
+                    return Err(Cut::PhotonPhotonAngle);
+                }
+            }
+        }
+
+        // Compute a vector which is normal to the outgoing photon plane
+        // This notion is only valid because we have three output photons
+        const { assert!(NUM_OUTGOING == 3, "This part assumes 3 outgoing particles") };
+        let n_ppp = event
+            .outgoing_momentum(0)
+            .xyz()
+            .cross(&event.outgoing_momentum(1).xyz());
+
+        // Compute the cosine of the angle between the beam and this vector
+        let cos_num = p_el.xyz().dot(&n_ppp);
+        let cos_denom = p_el[E] * n_ppp.norm();
+
+        // Check if the (beam, normal to photon plane) angle passes the cut
+        if abs(cos_num) < self.beam_photon_plane_cut * cos_denom {
+            return Err(Cut::BeamPhotonPlaneAngle);
+        }
+
+        // Check if the additional cuts pass
+        for (index, cut) in self.additional.as_ref().iter().enumerate() {
+            if !cut.keep(event) {
+                return Err(Cut::Additional(index));
+            }
+        }
+
+        // Check if the filter passes
+        if let Some(filter) = &self.filter {
+            if !filter.keep(event) {
+                return Err(Cut::Additional(self.additional.as_ref().len()));
+            }
+        }
+
+        // If all checks passed, we're good
+        Ok(())
+    }
+
+    /// Individual cuts, in order of application
+    pub fn cuts(&self) -> impl Iterator<Item = Cut> {
+        let num_additional = self.additional.as_ref().len() + usize::from(self.filter.is_some());
+        (0..Cut::NUM_STANDARD + num_additional).map(Cut::from_index)
+    }
+
+    /// Short human-readable description of an individual cut
+    pub fn cut_name(&self, cut: Cut) -> &'static str {
+        match cut {
+            Cut::PhotonEnergy => "E_min",
+            Cut::BeamPhotonAngle => "cos(beam,photon)",
+            Cut::PhotonPhotonAngle => "cos(photon,photon)",
+            Cut::BeamPhotonPlaneAngle => "cos(beam,normal)",
+            Cut::Additional(index) => match self.additional.as_ref().get(index) {
+                Some(cut) => cut.name(),
+                None => "filter",
+            },
+        }
+    }
+}
+
+impl<Additional: Default, Filter> Default for EventCut<Additional, Filter> {
+    /// Default cuts, matching the reference `valeurs` file
+    fn default() -> Self {
+        Self::new(0.9, 0.9396, 4.559, 0.)
+    }
+}
+
+/// Additional cut on generated events
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "cut", rename_all = "snake_case", deny_unknown_fields)
+)]
+pub enum AdditionalCut {
+    /// Cut on maximum photon energy
+    MaxPhotonEnergy {
+        /// Maximum photon energy (GeV)
+        max: Float,
+    },
+
+    /// Cut on maximum absolute photon rapidity with respect to the beam axis
+    MaxPhotonRapidity {
+        /// Maximum absolute photon rapidity
+        max: Float,
+    },
+
+    /// Cut on minimum invariant mass of each photon pair
+    MinDiphotonMass {
+        /// Minimum invariant mass of each photon pair (GeV)
+        min: Float,
+    },
+
+    /// Cut on minimum angle between the two most energetic photons, which
+    /// only applies when both of them pass an energy threshold, so that
+    /// several such cuts describe how a detector identifies isolated photons
+    PhotonIsolation {
+        /// Minimum angle between the two most energetic photons (degrees)
+        min_angle: Float,
+
+        /// Energy above which both photons must be for the cut to apply (GeV)
+        #[cfg_attr(feature = "serde", serde(default))]
+        min_energy: Float,
+    },
+}
+//
+impl AdditionalCut {
+    /// Decide whether a generated event passes this cut
+    pub fn keep(&self, event: &Event) -> bool {
+        let photons = || (0..NUM_OUTGOING).map(|ph| event.outgoing_momentum(ph));
+        match *self {
+            Self::MaxPhotonEnergy { max } => photons().all(|p| p[E] <= max),
+            Self::MaxPhotonRapidity { max } => {
+                // For massless particles, |y| <= y_max <=> |p_beam| <= tanh(y_max) * E
+                let max_cos = tanh(max);
+                photons().all(|p| abs(p[X]) <= max_cos * p[E])
+            }
+            Self::MinDiphotonMass { min } => (0..NUM_OUTGOING).all(|ph1| {
+                (ph1 + 1..NUM_OUTGOING).all(|ph2| {
+                    let p_sum = event.outgoing_momentum(ph1) + event.outgoing_momentum(ph2);
+                    powi(p_sum[E], 2) - p_sum.xyz().norm_squared() >= powi(min, 2)
+                })
+            }),
+            Self::PhotonIsolation {
+                min_angle,
+                min_energy,
+            } => {
+                // Find the two most energetic photons, which are not
+                // necessarily sorted by energy
+                let energy = |ph| event.outgoing_momentum(ph)[E];
+                let (mut hardest, mut second) = if energy(0) >= energy(1) {
+                    (0, 1)
+                } else {
+                    (1, 0)
+                };
+                for ph in 2..NUM_OUTGOING {
+                    if energy(ph) > energy(hardest) {
+                        (hardest, second) = (ph, hardest);
+                    } else if energy(ph) > energy(second) {
+                        second = ph;
+                    }
+                }
+                if energy(second) < min_energy {
+                    return true;
+                }
+
+                // Photons are massless, so cos(angle) = p1.p2 / (E1 * E2)
+                let (p1, p2) = (
+                    event.outgoing_momentum(hardest),
+                    event.outgoing_momentum(second),
+                );
+                let cos_num = p1.xyz().dot(&p2.xyz());
+                cos_num <= cos(min_angle.to_radians()) * p1[E] * p2[E]
+            }
+        }
+    }
+
+    /// Short human-readable description of the cut
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::MaxPhotonEnergy { .. } => "E_max",
+            Self::MaxPhotonRapidity { .. } => "|y_photon|",
+            Self::MinDiphotonMass { .. } => "m(photon,photon)",
+            Self::PhotonIsolation { .. } => "angle(hard photons)",
+        }
+    }
+}
+
+/// Selection of generated events beyond the cuts, e.g. one that was expressed
+/// by the user
+pub trait EventFilter {
+    /// Decide whether a generated event passes this filter
+    fn keep(&self, event: &Event) -> bool;
+}
+
+/// Filter of EventCuts which cannot have any
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum NoFilter {}
+//
+impl EventFilter for NoFilter {
+    fn keep(&self, _event: &Event) -> bool {
+        match *self {}
+    }
+}
+
+/// Individual cuts applied to generated events, in order of application
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Cut {
+    /// Cut on minimum photon energy
+    PhotonEnergy,
+
+    /// Cut on maximum cosine of (beam, photons) angle
+    BeamPhotonAngle,
+
+    /// Cut on maximum cosine of (photon, photon) angle
+    PhotonPhotonAngle,
+
+    /// Cut on minimum cosine of (beam, normal to the photon plane) angle
+    BeamPhotonPlaneAngle,
+
+    /// N-th additional cut (or the filter, after the additional cuts)
+    Additional(usize),
+}
+//
+impl Cut {
+    /// Number of standard cuts, which are always applied
+    const NUM_STANDARD: usize = 4;
+
+    /// Position of the cut in the order of application
+    pub fn index(self) -> usize {
+        match self {
+            Self::PhotonEnergy => 0,
+            Self::BeamPhotonAngle => 1,
+            Self::PhotonPhotonAngle => 2,
+            Self::BeamPhotonPlaneAngle => 3,
+            Self::Additional(index) => Self::NUM_STANDARD + index,
+        }
+    }
+
+    /// Cut at a certain position in the order of application
+    pub fn from_index(index: usize) -> Self {
+        match index {
+            0 => Self::PhotonEnergy,
+            1 => Self::BeamPhotonAngle,
+            2 => Self::PhotonPhotonAngle,
+            3 => Self::BeamPhotonPlaneAngle,
+            _ => Self::Additional(index - Self::NUM_STANDARD),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! This module defines the properties and storage of generated events
+
+use crate::{
+    momentum::{Momentum, E, MOMENTUM_DIM},
+    numeric::{real::*, Float, Real},
+};
+use core::fmt::Display;
+use nalgebra::{Const, MatrixSlice, SMatrix, SVector};
+
+/// Number of incoming particles
+pub const NUM_INCOMING: usize = 2;
+
+/// Number of outgoing particles (replaces original INP)
+pub const NUM_OUTGOING: usize = 3;
+
+/// Number of particles in an event
+pub const NUM_PARTICLES: usize = NUM_INCOMING + NUM_OUTGOING;
+
+/// Vector type whose side is the number of particles in an event
+pub type ParticleVector<T> = SVector<T, NUM_PARTICLES>;
+
+/// Square matrix type whose side is the number of particles in an event
+pub type ParticleMatrix<T> = SMatrix<T, NUM_PARTICLES, NUM_PARTICLES>;
+
+/// Event data matrix type (columns are 4-coordinates, rows are particles)
+pub type EventMatrix<R = Float> = SMatrix<R, NUM_PARTICLES, MOMENTUM_DIM>;
+
+/// Slice of the event data matrix containing only outgoing particles
+type OutgoingMomentaSlice<'matrix, R> = MatrixSlice<
+    'matrix,
+    R,
+    Const<NUM_OUTGOING>,
+    Const<MOMENTUM_DIM>,
+    Const<1>,
+    Const<NUM_PARTICLES>,
+>;
+
+/// Row of the incoming electron in the event data matrix
+pub const INCOMING_E_M: usize = 0;
+
+/// Index of the incoming positron in the 4-momentum array
+pub const INCOMING_E_P: usize = 1;
+
+/// Number of possible spin values of the outgoing particles
+///
+/// Like the numbers of particles, code which assumes its value checks it at
+/// compile time.
+///
+pub const NUM_SPINS: usize = 2;
+
+/// Storage for ee -> ppp event data
+///
+/// Encapsulates a vector of incoming and outgoing 4-momenta, along with the
+/// factor by which adaptive sampling (if enabled) scales the event weight.
+///
+#[derive(Clone)]
+pub struct Event<R: Real = Float> {
+    /// Event data matrix
+    momenta: EventMatrix<R>,
+
+    /// Factor by which the event weight differs from that of flat phase space
+    /// sampling
+    weight_factor: R,
+}
+//
+impl<R: Real> Event<R> {
+    /// Build an event from an event data matrix
+    pub fn new(matrix: EventMatrix<R>) -> Self {
+        Self {
+            momenta: matrix,
+            weight_factor: R::one(),
+        }
+    }
+
+    /// Build an event from an event data matrix and a weight factor
+    pub fn with_weight_factor(matrix: EventMatrix<R>, weight_factor: R) -> Self {
+        Self {
+            momenta: matrix,
+            weight_factor,
+        }
+    }
+
+    /// Access the full internal 4-momentum array by reference
+    pub fn all_momenta(&self) -> &EventMatrix<R> {
+        &self.momenta
+    }
+
+    /// Factor by which the event weight differs from that of flat phase space
+    /// sampling (i.e. EventGenerator::event_weight())
+    pub fn weight_factor(&self) -> R {
+        self.weight_factor
+    }
+
+    /// Extract the 4-momentum of a single particle
+    ///
+    /// We return an owned momentum vector because that's more convenient to
+    /// handle than a slice and the compiler is smart enough to elide the copy.
+    ///
+    pub fn momentum(&self, par: usize) -> Momentum<R> {
+        Momentum::from_iterator(self.momenta.row(par).iter().copied())
+    }
+
+    /// Extract the electron 4-momentum
+    pub fn electron_momentum(&self) -> Momentum<R> {
+        self.momentum(INCOMING_E_M)
+    }
+
+    /// Extract the positron 4-momentum
+    pub fn positron_momentum(&self) -> Momentum<R> {
+        self.momentum(INCOMING_E_P)
+    }
+
+    /// Center-of-mass energy of the collision, which is lower than the nominal
+    /// one if initial-state radiation is simulated
+    pub fn collision_energy(&self) -> R {
+        let (p_el, p_pos) = (self.electron_momentum(), self.positron_momentum());
+        cast::<R>(2.) * sqrt(p_el[E] * p_pos[E])
+    }
+
+    /// Extract a photon's 4-momentum
+    pub fn outgoing_momentum(&self, par: usize) -> Momentum<R> {
+        self.momentum(NUM_INCOMING + par)
+    }
+
+    /// Access the outgoing 4-momenta
+    pub fn outgoing_momenta(&self) -> OutgoingMomentaSlice<'_, R> {
+        self.momenta.fixed_rows::<NUM_OUTGOING>(NUM_INCOMING)
+    }
+
+    /// Minimal outgoing photon energy
+    ///
+    /// Photons are not assumed to be sorted by energy, since they may be
+    /// sorted otherwise, or smeared by a detector simulation.
+    ///
+    pub fn min_photon_energy(&self) -> R {
+        // This somewhat elaborate method (compared to, say, min_by()) allows
+        // us to propagate what we know about the matrix' layout and contents
+        // to the compiler, enabling better optimization.
+        let first_out_e = self.outgoing_momentum(0)[E];
+        self.outgoing_momenta()
+            .column(E)
+            .iter()
+            .skip(1)
+            .fold(first_out_e, |e1, &e2| if e1 < e2 { e1 } else { e2 })
+    }
+}
+
+impl<R: Real> Display for Event<R> {
+    /// Dump 4-momenta of the 3 outgoing photons
+    fn fmt(&self, fmt: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let p_out = self.outgoing_momenta();
+        for coord in 0..4 {
+            write!(fmt, "{coord}\t")?;
+            for part in 0..NUM_OUTGOING {
+                write!(fmt, "{}\t", p_out[(part, coord)])?;
+            }
+            writeln!(fmt)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,582 @@
+//! Extended-precision arithmetic, which computes with about twice the
+//! significant digits of Float
+//!
+//! A DoubleFloat represents a number as the unevaluated sum of two Floats, the
+//! low-order one holding the rounding error of the high-order one ("double-
+//! double" arithmetic when Float is f64). Since it implements the Real trait,
+//! the kinematics and matrix elements can be computed with it, e.g. to
+//! recompute the events where cancellations between spinor products would eat
+//! up the precision of Float.
+//!
+//! Arithmetic and square roots are carried out in extended precision, using
+//! error-free transformations of floating-point sums and products, and so are
+//! the mathematical constants when Float is f64. These are the only operations
+//! that the matrix elements use. Other functions are only computed in the
+//! precision of Float, and conversions to primitive numbers round to Float
+//! unless the target type is more precise.
+
+use crate::{
+    numeric::Float,
+    scalar::{complex_field_methods, float_ops_methods, scalar_traits, signed_methods},
+};
+use approx::{AbsDiffEq, RelativeEq, UlpsEq};
+use core::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    num::FpCategory,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+use nalgebra::ComplexField;
+use num_traits::{Float as FloatOps, FloatConst, Num, One, Signed, ToPrimitive, Zero};
+use simba::scalar::SubsetOf;
+
+/// Real number with about twice the precision of Float
+///
+/// The number is normalized so that its low-order part is at most half a unit
+/// in the last place of its high-order part, which is therefore the nearest
+/// Float. Numbers which are not finite only use their high-order part.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DoubleFloat {
+    /// High-order part
+    hi: Float,
+
+    /// Low-order part
+    lo: Float,
+}
+//
+impl DoubleFloat {
+    /// High-order part of the number, which is its nearest Float
+    pub fn hi(self) -> Float {
+        self.hi
+    }
+
+    /// Low-order part of the number, which is the rounding error of hi()
+    pub fn lo(self) -> Float {
+        self.lo
+    }
+
+    /// Number which is exactly a Float
+    pub fn single(x: Float) -> Self {
+        Self { hi: x, lo: 0. }
+    }
+
+    /// Round the number to the nearest Float
+    pub fn value(self) -> Float {
+        self.hi + self.lo
+    }
+
+    /// Sum of two Floats, the first of which has the largest magnitude (or is
+    /// zero), normalized without rounding error
+    fn normalized(hi: Float, lo: Float) -> Self {
+        let sum = hi + lo;
+        if !hi.is_finite() {
+            Self::single(hi)
+        } else if !sum.is_finite() {
+            Self::single(sum)
+        } else {
+            Self {
+                hi: sum,
+                lo: lo - (sum - hi),
+            }
+        }
+    }
+
+    /// Apply a function to the number in the precision of Float
+    fn approximate(self, f: impl Fn(Float) -> Float) -> Self {
+        Self::single(f(self.hi))
+    }
+
+    /// Convert a double-precision number, which may not be representable
+    fn from_f64(x: f64) -> Self {
+        let hi = x as Float;
+        let lo = hi.to_f64().map_or(0., |hi| x - hi) as Float;
+        Self::normalized(hi, lo)
+    }
+}
+
+/// Sum of two Floats, as its rounded value and rounding error
+fn two_sum(a: Float, b: Float) -> (Float, Float) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    let a_virtual = sum - b_virtual;
+    (sum, (a - a_virtual) + (b - b_virtual))
+}
+
+/// Product of two Floats, as its rounded value and rounding error
+fn two_prod(a: Float, b: Float) -> (Float, Float) {
+    let product = a * b;
+    (product, FloatOps::mul_add(a, b, -product))
+}
+
+impl From<Float> for DoubleFloat {
+    fn from(x: Float) -> Self {
+        Self::single(x)
+    }
+}
+
+impl Display for DoubleFloat {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.lo == 0. {
+            write!(fmt, "{}", self.hi)
+        } else {
+            write!(fmt, "({} {:+})", self.hi, self.lo)
+        }
+    }
+}
+
+impl PartialOrd for DoubleFloat {
+    /// Normalized numbers are ordered by their high-order part first
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.hi.partial_cmp(&other.hi) {
+            Some(Ordering::Equal) => self.lo.partial_cmp(&other.lo),
+            ordering => ordering,
+        }
+    }
+}
+
+// ### ARITHMETIC ###
+
+impl Neg for DoubleFloat {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Add for DoubleFloat {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let (hi, hi_error) = two_sum(self.hi, rhs.hi);
+        let (lo, lo_error) = two_sum(self.lo, rhs.lo);
+        let sum = Self::normalized(hi, hi_error + lo);
+        Self::normalized(sum.hi, sum.lo + lo_error)
+    }
+}
+
+impl Sub for DoubleFloat {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl Mul for DoubleFloat {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let (hi, hi_error) = two_prod(self.hi, rhs.hi);
+        Self::normalized(hi, hi_error + (self.hi * rhs.lo + self.lo * rhs.hi))
+    }
+}
+
+impl Div for DoubleFloat {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        // Long division, each quotient digit correcting the remainder of the
+        // previous ones
+        let q1 = self.hi / rhs.hi;
+        if !q1.is_finite() {
+            return Self::single(q1);
+        }
+        let remainder = self - rhs * Self::single(q1);
+        let q2 = remainder.hi / rhs.hi;
+        let remainder = remainder - rhs * Self::single(q2);
+        let q3 = remainder.hi / rhs.hi;
+        Self::normalized(q1, q2) + Self::single(q3)
+    }
+}
+
+impl Zero for DoubleFloat {
+    fn zero() -> Self {
+        Self::single(0.)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.hi == 0.
+    }
+}
+
+impl Num for DoubleFloat {
+    type FromStrRadixErr = <Float as Num>::FromStrRadixErr;
+
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        Float::from_str_radix(s, radix).map(Self::single)
+    }
+}
+
+impl Signed for DoubleFloat {
+    signed_methods!();
+
+    fn is_positive(&self) -> bool {
+        self.hi > 0.
+    }
+
+    fn is_negative(&self) -> bool {
+        self.hi < 0.
+    }
+}
+
+// ### CONVERSIONS ###
+
+impl ToPrimitive for DoubleFloat {
+    fn to_i64(&self) -> Option<i64> {
+        self.value().to_i64()
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        self.value().to_u64()
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        Some(self.hi.to_f64()? + self.lo.to_f64()?)
+    }
+}
+
+impl SubsetOf<DoubleFloat> for f64 {
+    fn to_superset(&self) -> DoubleFloat {
+        DoubleFloat::from_f64(*self)
+    }
+
+    fn from_superset_unchecked(element: &DoubleFloat) -> Self {
+        (element.to_f64()).expect("DoubleFloat should be convertible to f64")
+    }
+
+    fn is_in_subset(element: &DoubleFloat) -> bool {
+        DoubleFloat::from_f64(Self::from_superset_unchecked(element)) == *element
+    }
+}
+
+// ### FLOATING-POINT FUNCTIONS ###
+
+/// Mathematical constants, given by their double-precision value and the
+/// rounding error thereof
+macro_rules! constants {
+    ($($name:ident => $error:expr),*) => {
+        $(
+            fn $name() -> Self {
+                Self::from_f64(core::f64::consts::$name) + Self::from_f64($error)
+            }
+        )*
+    };
+}
+//
+impl FloatConst for DoubleFloat {
+    constants!(
+        E => 1.4456468917292502e-16,
+        FRAC_1_PI => -1.9678676675182486e-17,
+        FRAC_1_SQRT_2 => -4.833646656726457e-17,
+        FRAC_2_PI => -3.935735335036497e-17,
+        FRAC_2_SQRT_PI => 1.533545961316588e-17,
+        FRAC_PI_2 => 6.123233995736766e-17,
+        FRAC_PI_3 => -1.072081766451091e-16,
+        FRAC_PI_4 => 3.061616997868383e-17,
+        FRAC_PI_6 => -5.360408832255455e-17,
+        FRAC_PI_8 => 1.5308084989341915e-17,
+        LN_10 => -2.1707562233822494e-16,
+        LN_2 => 2.3190468138462996e-17,
+        LOG10_E => 1.098319650216765e-17,
+        LOG2_E => 2.0355273740931033e-17,
+        PI => 1.2246467991473532e-16,
+        SQRT_2 => -9.667293313452913e-17
+    );
+}
+
+impl FloatOps for DoubleFloat {
+    float_ops_methods!();
+
+    fn epsilon() -> Self {
+        Self::single(Float::EPSILON * Float::EPSILON)
+    }
+
+    fn is_nan(self) -> bool {
+        self.hi.is_nan()
+    }
+
+    fn is_infinite(self) -> bool {
+        self.hi.is_infinite()
+    }
+
+    fn is_finite(self) -> bool {
+        self.hi.is_finite()
+    }
+
+    fn is_normal(self) -> bool {
+        self.hi.is_normal()
+    }
+
+    fn classify(self) -> FpCategory {
+        self.hi.classify()
+    }
+
+    fn floor(self) -> Self {
+        let hi = FloatOps::floor(self.hi);
+        if hi == self.hi {
+            Self::normalized(hi, FloatOps::floor(self.lo))
+        } else {
+            Self::single(hi)
+        }
+    }
+
+    fn ceil(self) -> Self {
+        -FloatOps::floor(-self)
+    }
+
+    fn round(self) -> Self {
+        let half = Self::single(0.5);
+        if self.hi >= 0. {
+            FloatOps::floor(self + half)
+        } else {
+            FloatOps::ceil(self - half)
+        }
+    }
+
+    fn trunc(self) -> Self {
+        if self.hi >= 0. {
+            FloatOps::floor(self)
+        } else {
+            FloatOps::ceil(self)
+        }
+    }
+
+    fn fract(self) -> Self {
+        self - FloatOps::trunc(self)
+    }
+
+    fn abs(self) -> Self {
+        if self.hi.is_sign_negative() {
+            -self
+        } else {
+            self
+        }
+    }
+
+    fn signum(self) -> Self {
+        self.approximate(FloatOps::signum)
+    }
+
+    fn is_sign_positive(self) -> bool {
+        self.hi.is_sign_positive()
+    }
+
+    fn is_sign_negative(self) -> bool {
+        self.hi.is_sign_negative()
+    }
+
+    fn powi(self, n: i32) -> Self {
+        // Integer powers are computed by repeated squaring
+        let mut power = Self::one();
+        let mut square = self;
+        let mut exponent = n.unsigned_abs();
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                power *= square;
+            }
+            square *= square;
+            exponent /= 2;
+        }
+        if n < 0 {
+            FloatOps::recip(power)
+        } else {
+            power
+        }
+    }
+
+    fn sqrt(self) -> Self {
+        // Refine the square root of the high-order part by one Newton step,
+        // whose residual is computed without rounding error
+        let sqrt = FloatOps::sqrt(self.hi);
+        if self.hi <= 0. || !sqrt.is_finite() {
+            return Self::single(sqrt);
+        }
+        let (square, square_error) = two_prod(sqrt, sqrt);
+        let residual = (self.hi - square) - square_error + self.lo;
+        Self::normalized(sqrt, residual / (2. * sqrt))
+    }
+
+    fn exp(self) -> Self {
+        self.approximate(FloatOps::exp)
+    }
+
+    fn exp2(self) -> Self {
+        self.approximate(FloatOps::exp2)
+    }
+
+    fn ln(self) -> Self {
+        self.approximate(FloatOps::ln)
+    }
+
+    fn log2(self) -> Self {
+        self.approximate(FloatOps::log2)
+    }
+
+    fn log10(self) -> Self {
+        self.approximate(FloatOps::log10)
+    }
+
+    fn max(self, other: Self) -> Self {
+        if self.is_nan() || self < other {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn min(self, other: Self) -> Self {
+        if self.is_nan() || self > other {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn cbrt(self) -> Self {
+        self.approximate(FloatOps::cbrt)
+    }
+
+    fn hypot(self, other: Self) -> Self {
+        FloatOps::sqrt(self * self + other * other)
+    }
+
+    fn sin(self) -> Self {
+        self.approximate(FloatOps::sin)
+    }
+
+    fn cos(self) -> Self {
+        self.approximate(FloatOps::cos)
+    }
+
+    fn tan(self) -> Self {
+        self.approximate(FloatOps::tan)
+    }
+
+    fn asin(self) -> Self {
+        self.approximate(FloatOps::asin)
+    }
+
+    fn acos(self) -> Self {
+        self.approximate(FloatOps::acos)
+    }
+
+    fn atan(self) -> Self {
+        self.approximate(FloatOps::atan)
+    }
+
+    fn atan2(self, other: Self) -> Self {
+        Self::single(FloatOps::atan2(self.hi, other.hi))
+    }
+
+    fn exp_m1(self) -> Self {
+        self.approximate(FloatOps::exp_m1)
+    }
+
+    fn ln_1p(self) -> Self {
+        self.approximate(FloatOps::ln_1p)
+    }
+
+    fn sinh(self) -> Self {
+        self.approximate(FloatOps::sinh)
+    }
+
+    fn cosh(self) -> Self {
+        self.approximate(FloatOps::cosh)
+    }
+
+    fn tanh(self) -> Self {
+        self.approximate(FloatOps::tanh)
+    }
+
+    fn asinh(self) -> Self {
+        self.approximate(FloatOps::asinh)
+    }
+
+    fn acosh(self) -> Self {
+        self.approximate(FloatOps::acosh)
+    }
+
+    fn atanh(self) -> Self {
+        self.approximate(FloatOps::atanh)
+    }
+
+    fn integer_decode(self) -> (u64, i16, i8) {
+        self.hi.integer_decode()
+    }
+
+    fn copysign(self, sign: Self) -> Self {
+        if self.hi.is_sign_negative() == sign.hi.is_sign_negative() {
+            self
+        } else {
+            -self
+        }
+    }
+}
+
+// ### LINEAR ALGEBRA ###
+
+impl AbsDiffEq for DoubleFloat {
+    type Epsilon = Self;
+
+    fn default_epsilon() -> Self {
+        FloatOps::epsilon()
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: Self) -> bool {
+        FloatOps::abs(*self - *other) <= epsilon
+    }
+}
+
+impl RelativeEq for DoubleFloat {
+    fn default_max_relative() -> Self {
+        FloatOps::epsilon()
+    }
+
+    fn relative_eq(&self, other: &Self, epsilon: Self, max_relative: Self) -> bool {
+        if self == other {
+            return true;
+        }
+        let difference = FloatOps::abs(*self - *other);
+        let largest = FloatOps::max(FloatOps::abs(*self), FloatOps::abs(*other));
+        difference <= epsilon || difference <= largest * max_relative
+    }
+}
+
+impl UlpsEq for DoubleFloat {
+    fn default_max_ulps() -> u32 {
+        4
+    }
+
+    fn ulps_eq(&self, other: &Self, epsilon: Self, max_ulps: u32) -> bool {
+        self.hi.ulps_eq(&other.hi, epsilon.hi, max_ulps)
+            && self.lo.ulps_eq(&other.lo, epsilon.hi, max_ulps)
+    }
+}
+
+impl ComplexField for DoubleFloat {
+    complex_field_methods!();
+
+    fn modulus_squared(self) -> Self {
+        self * self
+    }
+
+    fn argument(self) -> Self {
+        if self.hi >= 0. {
+            Self::zero()
+        } else {
+            Self::PI()
+        }
+    }
+
+    fn try_sqrt(self) -> Option<Self> {
+        (self.hi >= 0.).then(|| FloatOps::sqrt(self))
+    }
+}
+
+// Traits which are implemented in terms of the above ones
+scalar_traits!(DoubleFloat);
@@ -0,0 +1,660 @@
+//! Interval arithmetic, which bounds the rounding errors of computations
+//!
+//! An Interval encloses the exact result of the computations which produced
+//! it, so that its width bounds their accumulated rounding errors. Since it
+//! implements the Real trait, the kinematics and matrix elements can be
+//! computed with it, e.g. to quantify the floating-point uncertainty of the
+//! spinor product chain.
+//!
+//! Each bound is computed in the default rounding mode, then widened by one
+//! unit in the last place. This encloses the exact results of correctly
+//! rounded operations, namely arithmetic and square roots, which are the only
+//! ones that the matrix elements use. Other functions are only enclosed as
+//! long as their libm implementation is accurate to one unit in the last
+//! place. Comparisons only succeed if they hold for all enclosed numbers, and
+//! conversions to primitive numbers yield the midpoint of the interval.
+
+use crate::{
+    numeric::{floats, Float},
+    scalar::{complex_field_methods, float_ops_methods, scalar_traits, signed_methods},
+};
+use approx::{AbsDiffEq, RelativeEq, UlpsEq};
+use core::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    num::FpCategory,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+use nalgebra::ComplexField;
+use num_traits::{Float as FloatOps, FloatConst, Num, Signed, ToPrimitive, Zero};
+use simba::scalar::SubsetOf;
+
+/// Closed interval of real numbers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interval {
+    /// Lower bound
+    lo: Float,
+
+    /// Upper bound
+    hi: Float,
+}
+//
+impl Interval {
+    /// Interval between two bounds
+    pub fn new(lo: Float, hi: Float) -> Self {
+        debug_assert!(
+            lo.partial_cmp(&hi) != Some(Ordering::Greater),
+            "Interval bounds should be ordered"
+        );
+        Self { lo, hi }
+    }
+
+    /// Interval which only contains a number
+    pub fn point(x: Float) -> Self {
+        Self { lo: x, hi: x }
+    }
+
+    /// Lower bound of the interval
+    pub fn lo(self) -> Float {
+        self.lo
+    }
+
+    /// Upper bound of the interval
+    pub fn hi(self) -> Float {
+        self.hi
+    }
+
+    /// Midpoint of the interval
+    pub fn mid(self) -> Float {
+        self.lo / 2. + self.hi / 2.
+    }
+
+    /// Width of the interval
+    pub fn width(self) -> Float {
+        self.hi - self.lo
+    }
+
+    /// Width of the interval, relative to the largest magnitude that it
+    /// contains
+    pub fn relative_width(self) -> Float {
+        self.width() / FloatOps::max(FloatOps::abs(self.lo), FloatOps::abs(self.hi))
+    }
+
+    /// Truth that the interval contains a number
+    pub fn contains(self, x: Float) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    /// Interval whose bounds are the rounded results of some computation,
+    /// widened so that it encloses the exact results
+    fn rounded(lo: Float, hi: Float) -> Self {
+        Self {
+            lo: next_down(lo),
+            hi: next_up(hi),
+        }
+    }
+
+    /// Smallest interval which contains two intervals
+    fn hull(self, other: Self) -> Self {
+        Self {
+            lo: FloatOps::min(self.lo, other.lo),
+            hi: FloatOps::max(self.hi, other.hi),
+        }
+    }
+
+    /// Interval which contains all real numbers
+    fn entire() -> Self {
+        Self::new(Float::NEG_INFINITY, Float::INFINITY)
+    }
+
+    /// Apply a nondecreasing function to the interval
+    fn increasing(self, f: impl Fn(Float) -> Float) -> Self {
+        Self::rounded(f(self.lo), f(self.hi))
+    }
+
+    /// Apply a nonincreasing function to the interval
+    fn decreasing(self, f: impl Fn(Float) -> Float) -> Self {
+        Self::rounded(f(self.hi), f(self.lo))
+    }
+
+    /// Convert a double-precision number, which may not be representable
+    fn from_f64(x: f64) -> Self {
+        let rounded = x as Float;
+        if rounded.to_f64() == Some(x) {
+            Self::point(rounded)
+        } else {
+            Self::rounded(rounded, rounded)
+        }
+    }
+}
+
+/// Next floating-point number above a certain number
+fn next_up(x: Float) -> Float {
+    if x.is_nan() || x == Float::INFINITY {
+        x
+    } else if x == 0. {
+        Float::from_bits(1)
+    } else if x > 0. {
+        Float::from_bits(x.to_bits() + 1)
+    } else {
+        Float::from_bits(x.to_bits() - 1)
+    }
+}
+
+/// Next floating-point number below a certain number
+fn next_down(x: Float) -> Float {
+    -next_up(-x)
+}
+
+impl From<Float> for Interval {
+    fn from(x: Float) -> Self {
+        Self::point(x)
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "[{}, {}]", self.lo, self.hi)
+    }
+}
+
+impl PartialOrd for Interval {
+    /// Intervals are only ordered if all their numbers are
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self == other {
+            Some(Ordering::Equal)
+        } else if self.hi < other.lo {
+            Some(Ordering::Less)
+        } else if self.lo > other.hi {
+            Some(Ordering::Greater)
+        } else {
+            None
+        }
+    }
+}
+
+// ### ARITHMETIC ###
+
+impl Neg for Interval {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.hi, -self.lo)
+    }
+}
+
+impl Add for Interval {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::rounded(self.lo + rhs.lo, self.hi + rhs.hi)
+    }
+}
+
+impl Sub for Interval {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::rounded(self.lo - rhs.hi, self.hi - rhs.lo)
+    }
+}
+
+impl Mul for Interval {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let products = [
+            self.lo * rhs.lo,
+            self.lo * rhs.hi,
+            self.hi * rhs.lo,
+            self.hi * rhs.hi,
+        ];
+        Self::rounded(
+            products.into_iter().fold(Float::INFINITY, FloatOps::min),
+            products
+                .into_iter()
+                .fold(Float::NEG_INFINITY, FloatOps::max),
+        )
+    }
+}
+
+impl Div for Interval {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        if rhs.contains(0.) {
+            return Self::entire();
+        }
+        let quotients = [
+            self.lo / rhs.lo,
+            self.lo / rhs.hi,
+            self.hi / rhs.lo,
+            self.hi / rhs.hi,
+        ];
+        Self::rounded(
+            quotients.into_iter().fold(Float::INFINITY, FloatOps::min),
+            quotients
+                .into_iter()
+                .fold(Float::NEG_INFINITY, FloatOps::max),
+        )
+    }
+}
+
+impl Zero for Interval {
+    fn zero() -> Self {
+        Self::point(0.)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.lo == 0. && self.hi == 0.
+    }
+}
+
+impl Num for Interval {
+    type FromStrRadixErr = <Float as Num>::FromStrRadixErr;
+
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        let x = Float::from_str_radix(s, radix)?;
+        Ok(Self::rounded(x, x))
+    }
+}
+
+impl Signed for Interval {
+    signed_methods!();
+
+    fn is_positive(&self) -> bool {
+        self.lo > 0.
+    }
+
+    fn is_negative(&self) -> bool {
+        self.hi < 0.
+    }
+}
+
+// ### CONVERSIONS ###
+
+impl ToPrimitive for Interval {
+    fn to_i64(&self) -> Option<i64> {
+        self.mid().to_i64()
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        self.mid().to_u64()
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        self.mid().to_f64()
+    }
+}
+
+impl SubsetOf<Interval> for f64 {
+    fn to_superset(&self) -> Interval {
+        Interval::from_f64(*self)
+    }
+
+    fn from_superset_unchecked(element: &Interval) -> Self {
+        (element.mid().to_f64()).expect("Float should be convertible to f64")
+    }
+
+    fn is_in_subset(element: &Interval) -> bool {
+        element.lo == element.hi
+    }
+}
+
+// ### FLOATING-POINT FUNCTIONS ###
+
+/// Enclosures of the mathematical constants
+macro_rules! constants {
+    ($($name:ident),*) => {
+        $(
+            fn $name() -> Self {
+                Self::rounded(floats::consts::$name, floats::consts::$name)
+            }
+        )*
+    };
+}
+//
+impl FloatConst for Interval {
+    constants!(
+        E,
+        FRAC_1_PI,
+        FRAC_1_SQRT_2,
+        FRAC_2_PI,
+        FRAC_2_SQRT_PI,
+        FRAC_PI_2,
+        FRAC_PI_3,
+        FRAC_PI_4,
+        FRAC_PI_6,
+        FRAC_PI_8,
+        LN_10,
+        LN_2,
+        LOG10_E,
+        LOG2_E,
+        PI,
+        SQRT_2
+    );
+}
+
+impl FloatOps for Interval {
+    float_ops_methods!();
+
+    fn epsilon() -> Self {
+        Self::point(Float::EPSILON)
+    }
+
+    fn is_nan(self) -> bool {
+        self.lo.is_nan() || self.hi.is_nan()
+    }
+
+    fn is_infinite(self) -> bool {
+        self.lo.is_infinite() || self.hi.is_infinite()
+    }
+
+    fn is_finite(self) -> bool {
+        self.lo.is_finite() && self.hi.is_finite()
+    }
+
+    fn is_normal(self) -> bool {
+        self.lo.is_normal() && self.hi.is_normal()
+    }
+
+    fn classify(self) -> FpCategory {
+        self.mid().classify()
+    }
+
+    fn floor(self) -> Self {
+        Self::new(FloatOps::floor(self.lo), FloatOps::floor(self.hi))
+    }
+
+    fn ceil(self) -> Self {
+        Self::new(FloatOps::ceil(self.lo), FloatOps::ceil(self.hi))
+    }
+
+    fn round(self) -> Self {
+        Self::new(FloatOps::round(self.lo), FloatOps::round(self.hi))
+    }
+
+    fn trunc(self) -> Self {
+        Self::new(FloatOps::trunc(self.lo), FloatOps::trunc(self.hi))
+    }
+
+    fn fract(self) -> Self {
+        if FloatOps::trunc(self.lo) == FloatOps::trunc(self.hi) {
+            self - FloatOps::trunc(self)
+        } else {
+            Self::new(-1., 1.)
+        }
+    }
+
+    fn abs(self) -> Self {
+        if self.lo >= 0. {
+            self
+        } else if self.hi <= 0. {
+            -self
+        } else {
+            Self::new(0., FloatOps::max(-self.lo, self.hi))
+        }
+    }
+
+    fn signum(self) -> Self {
+        Self::new(FloatOps::signum(self.lo), FloatOps::signum(self.hi))
+    }
+
+    fn is_sign_positive(self) -> bool {
+        self.lo.is_sign_positive()
+    }
+
+    fn is_sign_negative(self) -> bool {
+        self.hi.is_sign_negative()
+    }
+
+    fn powi(self, n: i32) -> Self {
+        // Integer powers are computed by repeated multiplication, rounding
+        // each intermediate product outwards
+        let pow = |x: Float, up: bool| {
+            (0..n.unsigned_abs()).fold(1., |acc, _| {
+                let product = acc * x;
+                if up {
+                    next_up(product)
+                } else {
+                    next_down(product)
+                }
+            })
+        };
+        if n < 0 {
+            FloatOps::recip(FloatOps::powi(self, -n))
+        } else if n % 2 == 0 {
+            let abs = FloatOps::abs(self);
+            Self::new(pow(abs.lo, false), pow(abs.hi, true))
+        } else {
+            let odd_pow = |x: Float, up: bool| {
+                if x >= 0. {
+                    pow(x, up)
+                } else {
+                    -pow(-x, !up)
+                }
+            };
+            Self::new(odd_pow(self.lo, false), odd_pow(self.hi, true))
+        }
+    }
+
+    fn sqrt(self) -> Self {
+        let sqrt = self.increasing(|x| FloatOps::sqrt(FloatOps::max(x, 0.)));
+        Self::new(FloatOps::max(sqrt.lo, 0.), sqrt.hi)
+    }
+
+    fn exp(self) -> Self {
+        self.increasing(FloatOps::exp)
+    }
+
+    fn exp2(self) -> Self {
+        self.increasing(FloatOps::exp2)
+    }
+
+    fn ln(self) -> Self {
+        self.increasing(FloatOps::ln)
+    }
+
+    fn log2(self) -> Self {
+        self.increasing(FloatOps::log2)
+    }
+
+    fn log10(self) -> Self {
+        self.increasing(FloatOps::log10)
+    }
+
+    fn max(self, other: Self) -> Self {
+        Self::new(
+            FloatOps::max(self.lo, other.lo),
+            FloatOps::max(self.hi, other.hi),
+        )
+    }
+
+    fn min(self, other: Self) -> Self {
+        Self::new(
+            FloatOps::min(self.lo, other.lo),
+            FloatOps::min(self.hi, other.hi),
+        )
+    }
+
+    fn cbrt(self) -> Self {
+        self.increasing(FloatOps::cbrt)
+    }
+
+    fn hypot(self, other: Self) -> Self {
+        FloatOps::sqrt(FloatOps::powi(self, 2) + FloatOps::powi(other, 2))
+    }
+
+    fn sin(self) -> Self {
+        FloatOps::cos(self - Self::FRAC_PI_2())
+    }
+
+    fn cos(self) -> Self {
+        if self.width().partial_cmp(&(2. * floats::consts::PI)) != Some(Ordering::Less) {
+            return Self::new(-1., 1.);
+        }
+
+        // The cosine reaches its extrema at the multiples of pi that the
+        // interval contains, and is monotonic in between
+        let (cos_lo, cos_hi) = (FloatOps::cos(self.lo), FloatOps::cos(self.hi));
+        let (mut min, mut max) = (FloatOps::min(cos_lo, cos_hi), FloatOps::max(cos_lo, cos_hi));
+        let mut k = FloatOps::ceil(self.lo / floats::consts::PI);
+        while k * floats::consts::PI <= self.hi {
+            if k % 2. == 0. {
+                max = 1.;
+            } else {
+                min = -1.;
+            }
+            k += 1.;
+        }
+        Self::new(
+            FloatOps::max(next_down(min), -1.),
+            FloatOps::min(next_up(max), 1.),
+        )
+    }
+
+    fn tan(self) -> Self {
+        // The tangent increases between its poles, at odd multiples of pi/2
+        let first_pole = FloatOps::ceil(self.lo / floats::consts::PI - 0.5);
+        if (first_pole + 0.5) * floats::consts::PI <= self.hi || self.is_nan() {
+            Self::entire()
+        } else {
+            self.increasing(FloatOps::tan)
+        }
+    }
+
+    fn asin(self) -> Self {
+        self.increasing(FloatOps::asin)
+    }
+
+    fn acos(self) -> Self {
+        self.decreasing(FloatOps::acos)
+    }
+
+    fn atan(self) -> Self {
+        self.increasing(FloatOps::atan)
+    }
+
+    fn atan2(self, other: Self) -> Self {
+        if other.lo > 0. {
+            FloatOps::atan(self / other)
+        } else {
+            Self::rounded(-floats::consts::PI, floats::consts::PI)
+        }
+    }
+
+    fn exp_m1(self) -> Self {
+        self.increasing(FloatOps::exp_m1)
+    }
+
+    fn ln_1p(self) -> Self {
+        self.increasing(FloatOps::ln_1p)
+    }
+
+    fn sinh(self) -> Self {
+        self.increasing(FloatOps::sinh)
+    }
+
+    fn cosh(self) -> Self {
+        if self.lo >= 0. {
+            self.increasing(FloatOps::cosh)
+        } else if self.hi <= 0. {
+            self.decreasing(FloatOps::cosh)
+        } else {
+            let max = FloatOps::max(FloatOps::cosh(self.lo), FloatOps::cosh(self.hi));
+            Self::new(1., next_up(max))
+        }
+    }
+
+    fn tanh(self) -> Self {
+        self.increasing(FloatOps::tanh)
+    }
+
+    fn asinh(self) -> Self {
+        self.increasing(FloatOps::asinh)
+    }
+
+    fn acosh(self) -> Self {
+        self.increasing(FloatOps::acosh)
+    }
+
+    fn atanh(self) -> Self {
+        self.increasing(FloatOps::atanh)
+    }
+
+    fn integer_decode(self) -> (u64, i16, i8) {
+        self.mid().integer_decode()
+    }
+
+    fn copysign(self, sign: Self) -> Self {
+        let abs = FloatOps::abs(self);
+        if sign.lo.is_sign_positive() {
+            abs
+        } else if sign.hi.is_sign_negative() {
+            -abs
+        } else {
+            abs.hull(-abs)
+        }
+    }
+}
+
+// ### LINEAR ALGEBRA ###
+
+impl AbsDiffEq for Interval {
+    type Epsilon = Self;
+
+    fn default_epsilon() -> Self {
+        Self::point(Float::EPSILON)
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: Self) -> bool {
+        self.lo.abs_diff_eq(&other.lo, epsilon.hi) && self.hi.abs_diff_eq(&other.hi, epsilon.hi)
+    }
+}
+
+impl RelativeEq for Interval {
+    fn default_max_relative() -> Self {
+        Self::point(Float::EPSILON)
+    }
+
+    fn relative_eq(&self, other: &Self, epsilon: Self, max_relative: Self) -> bool {
+        self.lo.relative_eq(&other.lo, epsilon.hi, max_relative.hi)
+            && self.hi.relative_eq(&other.hi, epsilon.hi, max_relative.hi)
+    }
+}
+
+impl UlpsEq for Interval {
+    fn default_max_ulps() -> u32 {
+        4
+    }
+
+    fn ulps_eq(&self, other: &Self, epsilon: Self, max_ulps: u32) -> bool {
+        self.lo.ulps_eq(&other.lo, epsilon.hi, max_ulps)
+            && self.hi.ulps_eq(&other.hi, epsilon.hi, max_ulps)
+    }
+}
+
+impl ComplexField for Interval {
+    complex_field_methods!();
+
+    fn modulus_squared(self) -> Self {
+        FloatOps::powi(self, 2)
+    }
+
+    fn argument(self) -> Self {
+        if self.lo >= 0. {
+            Self::zero()
+        } else if self.hi < 0. {
+            Self::PI()
+        } else {
+            Self::zero().hull(Self::PI())
+        }
+    }
+
+    fn try_sqrt(self) -> Option<Self> {
+        (self.lo >= 0.).then(|| FloatOps::sqrt(self))
+    }
+}
+
+// Traits which are implemented in terms of the above ones
+scalar_traits!(Interval);
@@ -0,0 +1,28 @@
+//! Pure computations of the 3photons simulation: kinematics, spinor products,
+//! matrix elements and cuts on generated events
+//!
+//! This crate uses neither memory allocation nor I/O, and does not depend on
+//! the Rust standard library, so that the same code can be compiled for GPU
+//! kernels, WebAssembly and the host program. Everything else (configuration,
+//! random number generation, result accumulation and output) is found in the
+//! trois_photons crate, which re-exports the modules of this one.
+
+#![no_std]
+#![warn(missing_docs)]
+
+#[cfg(feature = "validate-me")]
+pub mod alternate;
+pub mod coupling;
+pub mod dirac;
+pub mod evcut;
+pub mod event;
+#[cfg(feature = "extended-precision")]
+pub mod extended;
+#[cfg(feature = "interval")]
+pub mod interval;
+pub mod matelems;
+pub mod momentum;
+pub mod numeric;
+#[cfg(any(feature = "extended-precision", feature = "interval"))]
+mod scalar;
+pub mod spinor;
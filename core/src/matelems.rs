@@ -0,0 +1,318 @@
+//! Manipulation of matrix elements
+
+use crate::{
+    coupling::Couplings,
+    dirac,
+    event::{Event, NUM_OUTGOING, NUM_SPINS},
+    momentum::E,
+    numeric::{functions::*, real::*, Complex, Float, Real},
+    spinor::{PhotonHelicities, SpinorProducts, NUM_HELICITIES},
+};
+use core::fmt::Display;
+use nalgebra::{SMatrix, SVector};
+
+// ### MATRIX ELEMENTS ###
+
+/// Number of matrix elements
+///
+/// Storage is sized by this constant, but code which computes or combines the
+/// individual matrix elements assumes its value, which it checks at compile
+/// time, so that adding a matrix element points at all code which must be
+/// extended accordingly.
+///
+pub const NUM_MAT_ELEMS: usize = 5;
+
+/// Names of the matrix element contributions, in result vector order
+pub const CONTRIBUTIONS: [&str; NUM_MAT_ELEMS] = ["A", "B+", "B-", "Re(M+M-*)", "Im(M+M-*)"];
+
+/// Storage for per-matrix element data
+pub type MEsVector<R = Float> = SVector<R, NUM_MAT_ELEMS>;
+
+/// Storage for data about each pair of matrix elements
+pub type MEsMatrix<R = Float> = SMatrix<R, NUM_MAT_ELEMS, NUM_MAT_ELEMS>;
+
+/// Index of the electromagnetic element
+pub const A: usize = 0;
+
+/// Index of the positive electroweak element
+pub const B_P: usize = 1;
+
+/// Index of the negative electroweak element
+pub const B_M: usize = 2;
+
+/// Index of the real part of the mixed element
+pub const R_MX: usize = 3;
+
+/// Index of the imaginary part of the mixed element
+pub const I_MX: usize = 4;
+
+// ### HELICITY AMPLITUDES ###
+
+/// Storage for per-helicity data, indexed like PhotonHelicities::ALL
+pub type HelicityVector<T> = SVector<T, NUM_HELICITIES>;
+
+/// Amplitudes of an event for each configuration of the outgoing photon
+/// helicities, including the couplings
+///
+/// These are the building blocks of the matrix elements, which only feature
+/// their squares summed over helicities. They enable polarization-sensitive
+/// studies.
+///
+pub struct HelicityAmplitudes<R: Real = Float> {
+    /// Standard amplitudes
+    pub a: HelicityVector<Complex<R>>,
+
+    /// Anomalous amplitudes 𝛽₊
+    pub b_p: HelicityVector<Complex<R>>,
+
+    /// Anomalous amplitudes 𝛽₋
+    pub b_m: HelicityVector<Complex<R>>,
+}
+//
+impl<R: Real> HelicityAmplitudes<R> {
+    /// Compute the helicity amplitudes of an event
+    pub fn new(couplings: &Couplings<R>, event: &Event<R>) -> Self {
+        // This code is very specific to the current problem definition
+        const { assert!(NUM_OUTGOING == 3) };
+
+        // Compute spinor inner products
+        let spinor = SpinorProducts::new(event);
+
+        // Compute the helicity amplitudes, formerly known as a, b_p and b_m,
+        // for each possible output spin configuration
+        let helicities = HelicityVector::from(PhotonHelicities::ALL);
+        Self {
+            a: helicities.map(|hel| spinor.a(hel) * couplings.g_a),
+            b_p: helicities.map(|hel| spinor.b_p(hel) * couplings.g_beta_p),
+            b_m: helicities.map(|hel| spinor.b_m(hel) * couplings.g_beta_m),
+        }
+    }
+}
+
+// ### PER-HELICITY CONTRIBUTIONS TO MATRIX ELEMENTS ###
+
+/// Array of square matrix elements contribution with detail of helicities
+pub struct MEsContributions<R: Real = Float> {
+    /// Array of squared matrix elements, featuring five contributions with the
+    /// detail of outgoing helicities configuration
+    ///
+    /// The rows are the same as in ResultVector, the columns map to spin
+    /// configurations encoded as a binary number:
+    ///     - Configuration 0 (0b000) is ---
+    ///     - Configuration 1 (0b001) is --+
+    ///     - And so on...
+    ///
+    m2: SMatrix<R, NUM_MAT_ELEMS, NUM_HELICITIES>,
+
+    /// Interference between the anomalous amplitudes of the two incoming
+    /// electron helicities, summed over outgoing helicities, for the 𝛽₊ and
+    /// 𝛽₋ amplitudes (see spin_interference())
+    spin_interference: [Complex<R>; 2],
+}
+//
+impl<R: Real> MEsContributions<R> {
+    /// Construct the matrix element contributions of an event
+    pub fn new(couplings: &Couplings<R>, event: &Event<R>) -> Self {
+        Self::from_amplitudes(&HelicityAmplitudes::new(couplings, event))
+    }
+
+    /// Assemble matrix element contributions which were computed by other
+    /// means, e.g. by an external matrix element library, from their values
+    /// for each configuration of the outgoing photon helicities (as returned
+    /// by per_helicity()) and their spin interference terms (as returned by
+    /// spin_interference())
+    pub fn from_parts(
+        per_helicity: SMatrix<R, NUM_MAT_ELEMS, NUM_HELICITIES>,
+        spin_interference: [Complex<R>; 2],
+    ) -> Self {
+        Self {
+            m2: per_helicity,
+            spin_interference,
+        }
+    }
+
+    /// Construct the matrix element contributions from the helicity amplitudes
+    pub fn from_amplitudes(amps: &HelicityAmplitudes<R>) -> Self {
+        // This code is very specific to the current problem definition
+        const { assert!(NUM_MAT_ELEMS == 5) };
+
+        // Compute the matrix elements
+        let mixed_amps = amps
+            .a
+            .zip_map(&amps.b_p, |a, b_p| a * cast::<R>(2.) * conj(b_p));
+
+        // Only one electron helicity is simulated. The amplitudes of the other
+        // follow from parity, which flips all helicities and swaps the spinor
+        // products with their opposite conjugate. The anomalous amplitudes are
+        // of degree 5 in spinor products, so for outgoing helicities h they
+        // are minus the conjugate of the simulated amplitudes for helicities
+        // -h, whose index is the binary complement of that of h.
+        let interference = |b: &HelicityVector<Complex<R>>| {
+            (0..NUM_HELICITIES)
+                .map(|hel| -b[hel] * b[NUM_HELICITIES - 1 - hel])
+                .sum::<Complex<R>>()
+        };
+        MEsContributions {
+            m2: SMatrix::from_fn(|contrib, hel| match contrib {
+                A => norm_sqr(amps.a[hel]),
+                B_P => norm_sqr(amps.b_p[hel]),
+                B_M => norm_sqr(amps.b_m[hel]),
+                R_MX => re(mixed_amps[hel]),
+                I_MX => im(mixed_amps[hel]),
+                _ => unreachable!(),
+            }),
+            spin_interference: [interference(&amps.b_p), interference(&amps.b_m)],
+        }
+    }
+
+    /// Squared matrix elements for each contribution (rows) and configuration
+    /// of the outgoing photon helicities (columns, as in PhotonHelicities::ALL)
+    pub fn per_helicity(&self) -> &SMatrix<R, NUM_MAT_ELEMS, NUM_HELICITIES> {
+        &self.m2
+    }
+
+    /// Compute the sums of the squared matrix elements for each contribution
+    pub fn m2_sums(&self) -> MEsVector<R> {
+        self.m2.column_sum()
+    }
+
+    /// Interference term M₋·M₊* between the anomalous amplitudes of the
+    /// negative and positive incoming electron helicities, summed over
+    /// outgoing helicities, for the 𝛽₊ and 𝛽₋ amplitudes respectively
+    ///
+    /// These are the off-diagonal counterparts of the B_P and B_M squared
+    /// matrix elements, before electroweak polarization factors are applied.
+    /// Their phase depends on the spinor phase conventions, and on the azimuth
+    /// of the photons around the beam axis.
+    ///
+    pub fn spin_interference(&self) -> [Complex<R>; 2] {
+        self.spin_interference
+    }
+}
+
+impl<R: Real> Display for MEsContributions<R> {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        const { assert!(NUM_OUTGOING == 3) };
+        const { assert!(NUM_SPINS == 2) };
+
+        for index in 0..NUM_MAT_ELEMS {
+            writeln!(fmt, "Matrix element #{index}")?;
+            writeln!(fmt, "---  \t--+  \t-+-  \t-++  \t+--  \t+-+  \t++-  \t+++")?;
+            let contribution = self.m2.row(index);
+            for &matrix_elem in contribution.iter() {
+                write!(fmt, "{matrix_elem}  \t")?;
+            }
+            writeln!(fmt)?;
+        }
+        Ok(())
+    }
+}
+
+// ### PROCESS INTERFACE ###
+
+/// Matrix elements of a simulated process
+///
+/// Phase space sampling, cuts and result accumulation only interact with the
+/// matrix elements through this trait, so that other processes with the same
+/// final state, or matrix elements computed by external code, can be plugged
+/// into them. The e+e- -> ppp process of 3photons is implemented by its
+/// Couplings.
+///
+/// Results are accumulated and reported per contribution, assuming the meaning
+/// of the contributions of this process (A, B_P, B_M, R_MX and I_MX), so other
+/// processes should map their matrix elements to these contributions, and set
+/// those which they do not feature to zero.
+///
+pub trait MatrixElement<R: Real = Float>: Sync {
+    /// Compute the squared matrix elements of an event, for each contribution
+    /// and configuration of the outgoing photon helicities
+    fn contributions(&self, event: &Event<R>) -> MEsContributions<R>;
+
+    /// Masses of the outgoing particles (GeV), which phase space sampling
+    /// accounts for, massless by default like photons
+    fn outgoing_masses(&self) -> [R; NUM_OUTGOING] {
+        [R::zero(); NUM_OUTGOING]
+    }
+}
+//
+impl<R: Real> MatrixElement<R> for Couplings<R> {
+    fn contributions(&self, event: &Event<R>) -> MEsContributions<R> {
+        MEsContributions::new(self, event)
+    }
+}
+
+// ### GAUGE INVARIANCE CHECKS ###
+
+/// Gauge invariance checks of the standard amplitudes of an event
+///
+/// The standard amplitudes are recomputed from their Feynman diagrams, with
+/// explicit polarization vectors (see the dirac module). Substituting the
+/// polarization vector of any photon with its momentum must then make the
+/// amplitude vanish (Ward identity), and the squared amplitudes summed over
+/// helicities must be proportional to those which the spinor product formulas
+/// of the simulation yield, with a ratio that does not depend on the event.
+///
+pub struct GaugeInvarianceCheck {
+    /// Largest amplitude obtained by substituting a photon's polarization
+    /// vector with its momentum divided by its energy, relative to the largest
+    /// amplitude with physical polarizations
+    pub ward_violation: Float,
+
+    /// Ratio of the squared standard amplitudes summed over helicities, as
+    /// computed from Feynman diagrams and from spinor products
+    pub spinor_ratio: Float,
+}
+//
+impl GaugeInvarianceCheck {
+    /// Check the gauge invariance of the standard amplitudes of an event
+    pub fn new(event: &Event) -> Self {
+        const { assert!(NUM_OUTGOING == 3) };
+
+        // Collect the spinors and polarization vectors of the particles
+        let electrons = dirac::massless_spinors(&event.electron_momentum());
+        let positrons = dirac::massless_spinors(&event.positron_momentum());
+        let polarizations: [_; NUM_OUTGOING] = core::array::from_fn(|par| {
+            dirac::polarization_vectors(&event.outgoing_momentum(par)).map(|eps| eps.map(conj))
+        });
+
+        // Compute the amplitudes for each configuration of the helicities (the
+        // spinors of incompatible helicities yield zero amplitudes)
+        let spinor_pairs = (electrons.iter())
+            .flat_map(|electron| positrons.iter().map(move |positron| (electron, positron)));
+        let mut max_amplitude: Float = 0.;
+        let mut sum_m2 = 0.;
+        for (electron, positron) in spinor_pairs.clone() {
+            for hel in PhotonHelicities::ALL {
+                let eps = core::array::from_fn(|par| polarizations[par][hel.photon(par)]);
+                let amplitude = dirac::qed_amplitude(event, electron, positron, &eps);
+                max_amplitude = max_amplitude.max(amplitude.norm());
+                sum_m2 += norm_sqr(amplitude);
+            }
+        }
+
+        // Substitute each photon's polarization vector with its momentum
+        let mut ward_violation: Float = 0.;
+        for (electron, positron) in spinor_pairs {
+            for hel in PhotonHelicities::ALL {
+                for par in 0..NUM_OUTGOING {
+                    let mut eps = core::array::from_fn(|par| polarizations[par][hel.photon(par)]);
+                    let k = event.outgoing_momentum(par);
+                    eps[par] = dirac::complexify(&(k / k[E]));
+                    let amplitude = dirac::qed_amplitude(event, electron, positron, &eps);
+                    ward_violation = ward_violation.max(amplitude.norm() / max_amplitude);
+                }
+            }
+        }
+
+        // Compare with the spinor product formulas, for one electron helicity
+        let spinor = SpinorProducts::new(event);
+        let spinor_m2 = (PhotonHelicities::ALL.iter())
+            .map(|&hel| norm_sqr(spinor.a(hel)))
+            .sum::<Float>();
+
+        Self {
+            ward_violation,
+            spinor_ratio: sum_m2 / (NUM_SPINS as Float * spinor_m2),
+        }
+    }
+}
@@ -7,7 +7,7 @@ use nalgebra::SVector;
 pub const MOMENTUM_DIM: usize = 4;
 
 /// Relativistic 4-momentum
-pub type Momentum = SVector<Float, MOMENTUM_DIM>;
+pub type Momentum<R = Float> = SVector<R, MOMENTUM_DIM>;
 
 /// Convenience const for accessing the X coordinate of a 4-vector
 pub const X: usize = 0;
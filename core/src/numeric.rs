@@ -0,0 +1,143 @@
+//! Basic numerical concepts used throughout the program
+
+#![allow(missing_docs)]
+
+// Floating-point precision is configured here
+#[cfg(feature = "f32")]
+pub type Float = f32;
+#[cfg(feature = "f32")]
+pub use core::f32 as floats;
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+#[cfg(not(feature = "f32"))]
+pub use core::f64 as floats;
+pub type Complex<R = Float> = num_complex::Complex<R>;
+
+/// Real number type which computations can be carried out with
+///
+/// Computations use Float unless told otherwise, but the kinematics, matrix
+/// elements and event generation are generic over this trait, so that several
+/// precisions (e.g. f32 and f64), or dual numbers for automatic
+/// differentiation, can be instantiated side by side in comparison runs.
+///
+/// Both supertraits provide methods of the same name (sqrt, powi...), which
+/// generic code should therefore call through the real module.
+///
+pub trait Real: num_traits::Float + num_traits::FloatConst + nalgebra::RealField {}
+//
+impl<T: num_traits::Float + num_traits::FloatConst + nalgebra::RealField> Real for T {}
+
+/// Mathematical functions
+pub mod functions {
+    use super::{Complex, Real};
+
+    /// Compute the conjugate of a Complex number
+    pub fn conj<R: Real>(z: Complex<R>) -> Complex<R> {
+        z.conj()
+    }
+
+    /// Compute the squared norm of a Complex number
+    pub fn norm_sqr<R: Real>(z: Complex<R>) -> R {
+        if cfg!(feature = "fma") {
+            super::real::mul_add(z.re, z.re, z.im * z.im)
+        } else {
+            z.norm_sqr()
+        }
+    }
+
+    /// Get the real part of of a Complex number
+    pub fn re<R: Real>(z: Complex<R>) -> R {
+        z.re
+    }
+
+    /// Get the imaginary part of of a Complex number
+    pub fn im<R: Real>(z: Complex<R>) -> R {
+        z.im
+    }
+}
+
+/// Real functions in prefix notation, like those of prefix_num_ops::real, but
+/// which are also available without the standard library and for any Real
+pub mod real {
+    use super::Real;
+    use num_traits::{Float, NumCast, ToPrimitive};
+
+    /// Convert a number (typically a constant) to some Real type
+    pub fn cast<R: Real>(x: impl ToPrimitive) -> R {
+        <R as NumCast>::from(x).expect("Number should be representable")
+    }
+
+    /// Compute the absolute value of a number
+    pub fn abs<R: Real>(x: R) -> R {
+        Float::abs(x)
+    }
+
+    /// Restrict a number to an interval
+    pub fn clamp<R: Real>(x: R, min: R, max: R) -> R {
+        num_traits::clamp(x, min, max)
+    }
+
+    /// Compute the cosine of a number
+    pub fn cos<R: Real>(x: R) -> R {
+        Float::cos(x)
+    }
+
+    /// Compute the exponential of a number
+    pub fn exp<R: Real>(x: R) -> R {
+        Float::exp(x)
+    }
+
+    /// Compute a * b + c, rounding only once if fused multiply-add is enabled
+    /// (fma feature), and like the legacy code otherwise
+    #[inline]
+    pub fn mul_add<R: Real>(a: R, b: R, c: R) -> R {
+        if cfg!(feature = "fma") {
+            Float::mul_add(a, b, c)
+        } else {
+            a * b + c
+        }
+    }
+
+    /// Compute a * b - c * d, which suffers from cancellation when both
+    /// products are close
+    ///
+    /// If fused multiply-add is enabled (fma feature), Kahan's algorithm
+    /// recovers the rounding error of c * d, so that the result is accurate to
+    /// a few units in the last place even under cancellation.
+    ///
+    #[inline]
+    pub fn diff_of_products<R: Real>(a: R, b: R, c: R, d: R) -> R {
+        if cfg!(feature = "fma") {
+            let cd = c * d;
+            let cd_error = Float::mul_add(-c, d, cd);
+            Float::mul_add(a, b, -cd) + cd_error
+        } else {
+            a * b - c * d
+        }
+    }
+
+    /// Compute the natural logarithm of a number
+    pub fn ln<R: Real>(x: R) -> R {
+        Float::ln(x)
+    }
+
+    /// Raise a number to an integer power
+    pub fn powi<R: Real>(x: R, n: i32) -> R {
+        Float::powi(x, n)
+    }
+
+    /// Compute the sine of a number
+    pub fn sin<R: Real>(x: R) -> R {
+        Float::sin(x)
+    }
+
+    /// Compute the square root of a number
+    pub fn sqrt<R: Real>(x: R) -> R {
+        Float::sqrt(x)
+    }
+
+    /// Compute the hyperbolic tangent of a number
+    pub fn tanh<R: Real>(x: R) -> R {
+        Float::tanh(x)
+    }
+}
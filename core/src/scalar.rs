@@ -0,0 +1,372 @@
+//! Trait implementations shared by the number types which stand in for Float,
+//! namely the Interval and DoubleFloat types
+//!
+//! Both types implement the num_traits and nalgebra traits that the Real
+//! trait requires on top of their own arithmetic, comparisons and elementary
+//! functions. The macros of this module implement the parts of these traits
+//! which only forward to other traits, so that each type only needs to
+//! implement what is specific to its representation.
+//!
+//! They expect the type to implement From<Float>, PartialOrd, the arithmetic
+//! operators, Zero, FloatConst and num_traits::Float, and to have an inherent
+//! `from_f64` constructor.
+
+/// Implement the traits which are entirely defined in terms of others:
+/// remainder and compound assignment operators, One, NumCast, FromPrimitive,
+/// SubsetOf, SimdValue, Field and RealField
+macro_rules! scalar_traits {
+    ($type:ident) => {
+        impl ::core::ops::Rem for $type {
+            type Output = Self;
+
+            fn rem(self, rhs: Self) -> Self {
+                self - ::num_traits::Float::trunc(self / rhs) * rhs
+            }
+        }
+
+        $crate::scalar::assign_ops!(
+            $type,
+            AddAssign::add_assign => +,
+            SubAssign::sub_assign => -,
+            MulAssign::mul_assign => *,
+            DivAssign::div_assign => /,
+            RemAssign::rem_assign => %
+        );
+
+        impl ::num_traits::One for $type {
+            fn one() -> Self {
+                <Self as From<$crate::numeric::Float>>::from(1.)
+            }
+        }
+
+        impl ::num_traits::NumCast for $type {
+            fn from<T: ::num_traits::ToPrimitive>(n: T) -> Option<Self> {
+                n.to_f64().map(Self::from_f64)
+            }
+        }
+
+        impl ::num_traits::FromPrimitive for $type {
+            fn from_i64(n: i64) -> Option<Self> {
+                Some(Self::from_f64(n as f64))
+            }
+
+            fn from_u64(n: u64) -> Option<Self> {
+                Some(Self::from_f64(n as f64))
+            }
+
+            fn from_f64(n: f64) -> Option<Self> {
+                Some(Self::from_f64(n))
+            }
+        }
+
+        impl ::simba::scalar::SubsetOf<$type> for $type {
+            fn to_superset(&self) -> $type {
+                *self
+            }
+
+            fn from_superset_unchecked(element: &$type) -> Self {
+                *element
+            }
+
+            fn is_in_subset(_element: &$type) -> bool {
+                true
+            }
+        }
+
+        impl ::nalgebra::SimdValue for $type {
+            type Element = Self;
+            type SimdBool = bool;
+
+            fn lanes() -> usize {
+                1
+            }
+
+            fn splat(val: Self) -> Self {
+                val
+            }
+
+            fn extract(&self, _i: usize) -> Self {
+                *self
+            }
+
+            unsafe fn extract_unchecked(&self, _i: usize) -> Self {
+                *self
+            }
+
+            fn replace(&mut self, _i: usize, val: Self) {
+                *self = val
+            }
+
+            unsafe fn replace_unchecked(&mut self, _i: usize, val: Self) {
+                *self = val
+            }
+
+            fn select(self, cond: bool, other: Self) -> Self {
+                if cond {
+                    self
+                } else {
+                    other
+                }
+            }
+        }
+
+        impl ::nalgebra::Field for $type {}
+
+        impl ::nalgebra::RealField for $type {
+            $crate::scalar::float_methods!(
+                fn copysign(self, sign: Self) -> Self;
+                fn max(self, other: Self) -> Self;
+                fn min(self, other: Self) -> Self;
+                fn clamp(self, min: Self, max: Self) -> Self;
+                fn atan2(self, other: Self) -> Self;
+            );
+
+            fn is_sign_positive(&self) -> bool {
+                ::num_traits::Float::is_sign_positive(*self)
+            }
+
+            fn is_sign_negative(&self) -> bool {
+                ::num_traits::Float::is_sign_negative(*self)
+            }
+
+            fn min_value() -> Option<Self> {
+                Some(::num_traits::Float::min_value())
+            }
+
+            fn max_value() -> Option<Self> {
+                Some(::num_traits::Float::max_value())
+            }
+
+            $crate::scalar::float_consts!(
+                pi => PI,
+                two_pi => TAU,
+                frac_pi_2 => FRAC_PI_2,
+                frac_pi_3 => FRAC_PI_3,
+                frac_pi_4 => FRAC_PI_4,
+                frac_pi_6 => FRAC_PI_6,
+                frac_pi_8 => FRAC_PI_8,
+                frac_1_pi => FRAC_1_PI,
+                frac_2_pi => FRAC_2_PI,
+                frac_2_sqrt_pi => FRAC_2_SQRT_PI,
+                e => E,
+                log2_e => LOG2_E,
+                log10_e => LOG10_E,
+                ln_2 => LN_2,
+                ln_10 => LN_10
+            );
+        }
+    };
+}
+//
+pub(crate) use scalar_traits;
+
+/// Implement compound assignment operators in terms of binary operators
+macro_rules! assign_ops {
+    ($type:ident, $($trait:ident::$method:ident => $op:tt),*) => {
+        $(
+            impl ::core::ops::$trait for $type {
+                fn $method(&mut self, rhs: Self) {
+                    *self = *self $op rhs;
+                }
+            }
+        )*
+    };
+}
+//
+pub(crate) use assign_ops;
+
+/// Implement constants of a nalgebra trait with those of FloatConst
+macro_rules! float_consts {
+    ($($name:ident => $const:ident),*) => {
+        $(
+            fn $name() -> Self {
+                <Self as ::num_traits::FloatConst>::$const()
+            }
+        )*
+    };
+}
+//
+pub(crate) use float_consts;
+
+/// Implement methods of a nalgebra trait with those of num_traits::Float
+macro_rules! float_methods {
+    ($(fn $name:ident(self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            fn $name(self $(, $arg: $ty)*) -> $ret {
+                <Self as ::num_traits::Float>::$name(self $(, $arg)*)
+            }
+        )*
+    };
+}
+//
+pub(crate) use float_methods;
+
+/// Implement the methods of Signed which forward to num_traits::Float
+macro_rules! signed_methods {
+    () => {
+        fn abs(&self) -> Self {
+            ::num_traits::Float::abs(*self)
+        }
+
+        fn abs_sub(&self, other: &Self) -> Self {
+            ::num_traits::Float::abs_sub(*self, *other)
+        }
+
+        fn signum(&self) -> Self {
+            ::num_traits::Float::signum(*self)
+        }
+    };
+}
+//
+pub(crate) use signed_methods;
+
+/// Implement the special values of num_traits::Float, except for the machine
+/// epsilon, along with the methods that are defined in terms of others
+macro_rules! float_ops_methods {
+    () => {
+        fn nan() -> Self {
+            <Self as From<$crate::numeric::Float>>::from($crate::numeric::Float::NAN)
+        }
+
+        fn infinity() -> Self {
+            <Self as From<$crate::numeric::Float>>::from($crate::numeric::Float::INFINITY)
+        }
+
+        fn neg_infinity() -> Self {
+            <Self as From<$crate::numeric::Float>>::from($crate::numeric::Float::NEG_INFINITY)
+        }
+
+        fn neg_zero() -> Self {
+            <Self as From<$crate::numeric::Float>>::from(-0.)
+        }
+
+        fn min_value() -> Self {
+            <Self as From<$crate::numeric::Float>>::from($crate::numeric::Float::MIN)
+        }
+
+        fn min_positive_value() -> Self {
+            <Self as From<$crate::numeric::Float>>::from($crate::numeric::Float::MIN_POSITIVE)
+        }
+
+        fn max_value() -> Self {
+            <Self as From<$crate::numeric::Float>>::from($crate::numeric::Float::MAX)
+        }
+
+        fn mul_add(self, a: Self, b: Self) -> Self {
+            self * a + b
+        }
+
+        fn recip(self) -> Self {
+            <Self as ::num_traits::One>::one() / self
+        }
+
+        fn powf(self, n: Self) -> Self {
+            ::num_traits::Float::exp(n * ::num_traits::Float::ln(self))
+        }
+
+        fn log(self, base: Self) -> Self {
+            ::num_traits::Float::ln(self) / ::num_traits::Float::ln(base)
+        }
+
+        fn abs_sub(self, other: Self) -> Self {
+            ::num_traits::Float::max(self - other, <Self as ::num_traits::Zero>::zero())
+        }
+
+        fn sin_cos(self) -> (Self, Self) {
+            (
+                ::num_traits::Float::sin(self),
+                ::num_traits::Float::cos(self),
+            )
+        }
+    };
+}
+//
+pub(crate) use float_ops_methods;
+
+/// Implement the methods of ComplexField which a real number type implements
+/// in the same way as num_traits::Float, or as the identity
+macro_rules! complex_field_methods {
+    () => {
+        type RealField = Self;
+
+        $crate::scalar::float_methods!(
+            fn floor(self) -> Self;
+            fn ceil(self) -> Self;
+            fn round(self) -> Self;
+            fn trunc(self) -> Self;
+            fn fract(self) -> Self;
+            fn mul_add(self, a: Self, b: Self) -> Self;
+            fn abs(self) -> Self;
+            fn hypot(self, other: Self) -> Self;
+            fn recip(self) -> Self;
+            fn sin(self) -> Self;
+            fn cos(self) -> Self;
+            fn sin_cos(self) -> (Self, Self);
+            fn tan(self) -> Self;
+            fn asin(self) -> Self;
+            fn acos(self) -> Self;
+            fn atan(self) -> Self;
+            fn sinh(self) -> Self;
+            fn cosh(self) -> Self;
+            fn tanh(self) -> Self;
+            fn asinh(self) -> Self;
+            fn acosh(self) -> Self;
+            fn atanh(self) -> Self;
+            fn log(self, base: Self) -> Self;
+            fn log2(self) -> Self;
+            fn log10(self) -> Self;
+            fn ln(self) -> Self;
+            fn ln_1p(self) -> Self;
+            fn sqrt(self) -> Self;
+            fn exp(self) -> Self;
+            fn exp2(self) -> Self;
+            fn exp_m1(self) -> Self;
+            fn powi(self, n: i32) -> Self;
+            fn powf(self, n: Self) -> Self;
+            fn cbrt(self) -> Self;
+            fn signum(self) -> Self;
+        );
+
+        fn powc(self, n: Self) -> Self {
+            ::num_traits::Float::powf(self, n)
+        }
+
+        fn from_real(re: Self) -> Self {
+            re
+        }
+
+        fn real(self) -> Self {
+            self
+        }
+
+        fn imaginary(self) -> Self {
+            <Self as ::num_traits::Zero>::zero()
+        }
+
+        fn modulus(self) -> Self {
+            ::num_traits::Float::abs(self)
+        }
+
+        fn norm1(self) -> Self {
+            ::num_traits::Float::abs(self)
+        }
+
+        fn scale(self, factor: Self) -> Self {
+            self * factor
+        }
+
+        fn unscale(self, factor: Self) -> Self {
+            self / factor
+        }
+
+        fn conjugate(self) -> Self {
+            self
+        }
+
+        fn is_finite(&self) -> bool {
+            ::num_traits::Float::is_finite(*self)
+        }
+    };
+}
+//
+pub(crate) use complex_field_methods;
@@ -0,0 +1,396 @@
+//! Facilities for computing spinor products
+//!
+//! The spinor products of massless 4-momenta are the building blocks of the
+//! helicity amplitudes of the simulation. They can be computed for any pair of
+//! massless momenta via MasslessSpinor, or for all the particles of an event
+//! at once via SpinorProducts, which also computes the amplitudes of the
+//! e⁺e⁻ → 𝛾𝛾𝛾 process. Related amplitudes can be built upon the s() and t()
+//! products, whose algebraic identities are checked by SpinorIdentityCheck.
+
+use crate::{
+    event::{
+        Event, EventMatrix, ParticleMatrix, INCOMING_E_M as E_M, INCOMING_E_P as E_P, NUM_INCOMING,
+        NUM_OUTGOING, NUM_PARTICLES, NUM_SPINS,
+    },
+    momentum::{Momentum, E, X, Y, Z},
+    numeric::{functions::*, real::*, Complex, Float, Real},
+};
+use num_traits::Zero;
+
+/// Square root of eight
+fn rac8<R: Real>() -> R {
+    cast::<R>(2.) * R::SQRT_2()
+}
+
+/// Spinor of a massless 4-momentum, in the representation of M. Mangano and
+/// S. Parke, which its spinor products with other momenta are computed from
+#[derive(Clone, Copy)]
+pub struct MasslessSpinor<R: Real = Float> {
+    /// Square root of E + p_z
+    xx: R,
+
+    /// (p_x + i·p_y) / √(E + p_z), or √(2E) for momenta along -Z
+    fx: Complex<R>,
+}
+//
+impl<R: Real> MasslessSpinor<R> {
+    /// Build the spinor of a massless 4-momentum
+    pub fn new(p: &Momentum<R>) -> Self {
+        let xx = sqrt(p[E] + p[Z]);
+        let fx = if xx > R::min_positive_value() {
+            Complex::new(p[X], p[Y]) / xx
+        } else {
+            Complex::from(sqrt(cast::<R>(2.) * p[E]))
+        };
+        Self { xx, fx }
+    }
+
+    /// Spinor product s(p, q) with the spinor of another momentum q
+    ///
+    /// Its squared norm is 2·p·q, and it cancels out for collinear momenta.
+    ///
+    pub fn product(&self, other: &Self) -> Complex<R> {
+        Complex::new(
+            diff_of_products(self.fx.re, other.xx, other.fx.re, self.xx),
+            diff_of_products(self.fx.im, other.xx, other.fx.im, self.xx),
+        )
+    }
+}
+
+/// Massless 4-momenta spinor inner products
+pub struct SpinorProducts<R: Real = Float> {
+    /// Gram matrix associated with the inner products
+    sx: ParticleMatrix<Complex<R>>,
+}
+//
+impl<R: Real> SpinorProducts<R> {
+    // ### CONSTRUCTION ###
+
+    /// Build spinor products from previously generated particle 4-momenta
+    pub fn new(event: &Event<R>) -> Self {
+        Self::from_momenta(event.all_momenta())
+    }
+
+    /// Build spinor products from massless particle 4-momenta, laid out as in
+    /// events (rows are e-, e+ and photons, columns are X, Y, Z and E)
+    pub fn from_momenta(ps: &EventMatrix<R>) -> Self {
+        // The underlying Gram matrix is not specific to the physics of
+        // e+e- -> ppp collisions, but our methods are specific to it.
+        const { assert!(NUM_INCOMING == 2) };
+        const { assert!(NUM_OUTGOING == 3) };
+        const { assert!(NUM_SPINS == 2) };
+
+        // Compute the spinors of the particles
+        let spinors: [MasslessSpinor<R>; NUM_PARTICLES] =
+            core::array::from_fn(|par| MasslessSpinor::new(&ps.row(par).transpose()));
+
+        // Fill up the Gram matrix, whose elements cancel out for nearly
+        // collinear particles
+        // TODO: Can we leverage antisymmetry + zero diagonal better?
+        Self {
+            sx: ParticleMatrix::from_fn(|i, j| spinors[i].product(&spinors[j])),
+        }
+    }
+
+    /// Build spinor products from their Gram matrix, as computed by other
+    /// means
+    #[cfg(feature = "validate-me")]
+    pub(crate) fn from_gram_matrix(sx: ParticleMatrix<Complex<R>>) -> Self {
+        Self { sx }
+    }
+
+    // ### GRAM MATRIX ACCESSORS ###
+
+    /// Spinor product s(i, j) of two particles, identified by their row in
+    /// the event (see MasslessSpinor::product)
+    #[inline]
+    pub fn s(&self, i: usize, j: usize) -> Complex<R> {
+        self.sx[(i, j)]
+    }
+
+    /// Spinor product t(i, j) = -s(i, j)* of two particles, of opposite
+    /// helicity to s(i, j)
+    #[inline]
+    pub fn t(&self, i: usize, j: usize) -> Complex<R> {
+        -conj(self.s(i, j))
+    }
+
+    // ### AMPLITUDE COMPUTATIONS ###
+
+    /// Standard amplitude for given photon helicities
+    #[inline]
+    pub fn a(&self, helicities: PhotonHelicities) -> Complex<R> {
+        use self::PhotonHelicities::*;
+        match helicities {
+            MMM => Complex::zero(),
+            MMP => self.a_pmm(4, 2, 3),
+            MPM => self.a_pmm(3, 2, 4),
+            MPP => self.a_ppm(3, 4, 2),
+            PMM => self.a_pmm(2, 3, 4),
+            PMP => self.a_ppm(2, 4, 3),
+            PPM => self.a_ppm(2, 3, 4),
+            PPP => Complex::zero(),
+        }
+    }
+
+    /// Anomalous amplitude 𝛽₊ for given photon helicities
+    #[inline]
+    pub fn b_p(&self, helicities: PhotonHelicities) -> Complex<R> {
+        use self::PhotonHelicities::*;
+        match helicities {
+            MMM => Complex::zero(),
+            MMP => self.bp_pmm(4, 2, 3),
+            MPM => self.bp_pmm(3, 2, 4),
+            MPP => self.bp_ppm(3, 4, 2),
+            PMM => self.bp_pmm(2, 3, 4),
+            PMP => self.bp_ppm(2, 4, 3),
+            PPM => self.bp_ppm(2, 3, 4),
+            PPP => Complex::zero(),
+        }
+    }
+
+    /// Anomalous amplitude 𝛽₋ for given photon helicities
+    #[inline]
+    pub fn b_m(&self, helicities: PhotonHelicities) -> Complex<R> {
+        use self::PhotonHelicities::*;
+        match helicities {
+            MMM => self.bm_mmm(2, 3, 4),
+            MMP => Complex::zero(),
+            MPM => Complex::zero(),
+            MPP => Complex::zero(),
+            PMM => Complex::zero(),
+            PMP => Complex::zero(),
+            PPM => Complex::zero(),
+            PPP => self.bm_ppp(2, 3, 4),
+        }
+    }
+
+    /// Standard amplitude for helicities ++-
+    #[inline]
+    fn a_ppm(&self, k1: usize, k2: usize, k3: usize) -> Complex<R> {
+        self.s(E_M, E_P) * -rac8::<R>() * self.s(E_M, k3).powi(2)
+            / (self.s(E_M, k1) * self.s(E_M, k2) * self.s(E_P, k1) * self.s(E_P, k2))
+    }
+
+    /// Standard amplitude for helicities +--
+    #[inline]
+    fn a_pmm(&self, k1: usize, k2: usize, k3: usize) -> Complex<R> {
+        self.t(E_M, E_P) * -rac8::<R>() * self.t(E_P, k1).powi(2)
+            / (self.t(E_P, k2) * self.t(E_P, k3) * self.t(E_M, k2) * self.t(E_M, k3))
+    }
+
+    /// Anomalous amplitude 𝛽₊ for helicities ++-
+    #[inline]
+    fn bp_ppm(&self, k1: usize, k2: usize, k3: usize) -> Complex<R> {
+        self.t(E_M, E_P) * -rac8::<R>() * (self.t(k1, k2) * self.s(k3, E_M)).powi(2)
+    }
+
+    /// Anomalous amplitude 𝛽₊ for helicities +--
+    #[inline]
+    fn bp_pmm(&self, k1: usize, k2: usize, k3: usize) -> Complex<R> {
+        self.s(E_M, E_P) * -rac8::<R>() * (self.t(k1, E_P) * self.s(k2, k3)).powi(2)
+    }
+
+    /// Anomalous amplitude 𝛽₋ for helicities +++
+    #[inline]
+    fn bm_ppp(&self, k1: usize, k2: usize, k3: usize) -> Complex<R> {
+        self.s(E_M, E_P)
+            * -rac8::<R>()
+            * ((self.t(k1, k2) * self.t(k3, E_P)).powi(2)
+                + (self.t(k1, k3) * self.t(k2, E_P)).powi(2)
+                + (self.t(k2, k3) * self.t(k1, E_P)).powi(2))
+    }
+
+    /// Anomalous amplitude 𝛽₋ for helicities ---
+    #[inline]
+    fn bm_mmm(&self, k1: usize, k2: usize, k3: usize) -> Complex<R> {
+        self.t(E_M, E_P)
+            * -rac8::<R>()
+            * ((self.s(k1, E_M) * self.s(k2, k3)).powi(2)
+                + (self.s(k2, E_M) * self.s(k1, k3)).powi(2)
+                + (self.s(k3, E_M) * self.s(k1, k2)).powi(2))
+    }
+}
+
+/// Deviations of the spinor products of an event from their algebraic
+/// identities, relative to the collision energy for the spinor products and to
+/// its square for products of two spinor products
+///
+/// These identities hold for any massless momenta, so they do not depend on
+/// the physics of the simulation, only on the correctness and numerical
+/// accuracy of the spinor products.
+///
+pub struct SpinorIdentityCheck {
+    /// Antisymmetry s(i, j) = -s(j, i)
+    pub antisymmetry: Float,
+
+    /// Relation |s(i, j)|² = 2·p_i·p_j with the invariant masses
+    pub invariant_mass: Float,
+
+    /// Schouten identity s(i, j)·s(k, l) + s(i, k)·s(l, j) + s(i, l)·s(j, k) = 0
+    pub schouten: Float,
+
+    /// Momentum conservation, in the form of the sum of t(i, k)·s(k, j) over
+    /// incoming particles k being equal to that over outgoing particles k
+    pub momentum_conservation: Float,
+}
+//
+impl SpinorIdentityCheck {
+    /// Check the spinor products of an event against their identities
+    pub fn new(event: &Event) -> Self {
+        let spinor = SpinorProducts::new(event);
+        let scale = event.collision_energy();
+        let scale2 = powi(scale, 2);
+        let pairs = (0..NUM_PARTICLES).flat_map(|i| (0..NUM_PARTICLES).map(move |j| (i, j)));
+
+        let mut antisymmetry: Float = 0.;
+        let mut invariant_mass: Float = 0.;
+        let mut momentum_conservation: Float = 0.;
+        for (i, j) in pairs.clone() {
+            antisymmetry = antisymmetry.max((spinor.s(i, j) + spinor.s(j, i)).norm() / scale);
+
+            let (p_i, p_j) = (event.momentum(i), event.momentum(j));
+            let p_i_p_j = p_i[E] * p_j[E] - p_i.fixed_rows::<3>(X).dot(&p_j.fixed_rows::<3>(X));
+            let mass_error = norm_sqr(spinor.s(i, j)) - 2. * p_i_p_j;
+            invariant_mass = invariant_mass.max(abs(mass_error) / scale2);
+
+            let chain = |k| spinor.t(i, k) * spinor.s(k, j);
+            let incoming: Complex = (0..NUM_INCOMING).map(chain).sum();
+            let outgoing: Complex = (NUM_INCOMING..NUM_PARTICLES).map(chain).sum();
+            momentum_conservation =
+                momentum_conservation.max((incoming - outgoing).norm() / scale2);
+        }
+
+        let mut schouten: Float = 0.;
+        for ((i, j), (k, l)) in pairs
+            .clone()
+            .flat_map(|ij| pairs.clone().map(move |kl| (ij, kl)))
+        {
+            let s = |a, b| spinor.s(a, b);
+            let sum = s(i, j) * s(k, l) + s(i, k) * s(l, j) + s(i, l) * s(j, k);
+            schouten = schouten.max(sum.norm() / scale2);
+        }
+
+        Self {
+            antisymmetry,
+            invariant_mass,
+            schouten,
+            momentum_conservation,
+        }
+    }
+}
+
+/// Output photon helicities (M is - and P is +)
+#[allow(missing_docs, clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PhotonHelicities {
+    MMM,
+    MMP,
+    MPM,
+    MPP,
+    PMM,
+    PMP,
+    PPM,
+    PPP,
+}
+//
+impl PhotonHelicities {
+    /// All photon helicity configurations, in binary order (M is 0, P is 1)
+    pub const ALL: [Self; NUM_HELICITIES] = [
+        Self::MMM,
+        Self::MMP,
+        Self::MPM,
+        Self::MPP,
+        Self::PMM,
+        Self::PMP,
+        Self::PPM,
+        Self::PPP,
+    ];
+
+    /// Helicity of one of the photons (0 for M, 1 for P)
+    pub fn photon(self, par: usize) -> usize {
+        assert!(par < NUM_OUTGOING);
+        ((self as usize) >> (NUM_OUTGOING - 1 - par)) & 1
+    }
+}
+
+/// Number of photon helicities
+pub const NUM_HELICITIES: usize = 2usize.pow(NUM_OUTGOING as u32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::numeric::floats::consts::PI;
+    use proptest::prelude::*;
+
+    /// Tolerance on the spinor identities, relative to their natural scale
+    const TOLERANCE: Float = 1e3 * Float::EPSILON;
+
+    /// Massless 4-momentum of random energy and direction, which is often
+    /// along the Z axis, where spinors take a special form
+    fn massless_momentum() -> impl Strategy<Value = Momentum> {
+        let cos_theta = prop_oneof![
+            4 => -1.0 as Float..=1.0,
+            1 => Just(-1.0),
+            1 => Just(1.0),
+        ];
+        let phi = 0.0..2. * PI;
+        (0.1 as Float..100.0, cos_theta, phi).prop_map(|(e, cos_theta, phi)| {
+            let sin_theta = sqrt(1. - powi(cos_theta, 2));
+            Momentum::new(
+                e * sin_theta * cos(phi),
+                e * sin_theta * sin(phi),
+                e * cos_theta,
+                e,
+            )
+        })
+    }
+
+    /// Factor by which the rounding errors on a massless momentum are amplified
+    /// in its spinor, as E + p_z cancels out near the -Z axis
+    fn conditioning(p: &Momentum) -> Float {
+        if p[E] + p[Z] > 0. {
+            p[E] / (p[E] + p[Z])
+        } else {
+            1.
+        }
+    }
+
+    /// Spinor products of each pair among several momenta
+    fn products<const N: usize>(ps: &[Momentum; N]) -> [[Complex; N]; N] {
+        let spinors = ps.map(|p| MasslessSpinor::new(&p));
+        core::array::from_fn(|i| core::array::from_fn(|j| spinors[i].product(&spinors[j])))
+    }
+
+    proptest! {
+        #[test]
+        fn antisymmetry(ps in [massless_momentum(), massless_momentum()]) {
+            let [[_, s_pq], [s_qp, _]] = products(&ps);
+            let scale = sqrt(ps[0][E] * ps[1][E]);
+            prop_assert!((s_pq + s_qp).norm() <= TOLERANCE * scale);
+        }
+
+        #[test]
+        fn invariant_mass(ps in [massless_momentum(), massless_momentum()]) {
+            let [[_, s_pq], _] = products(&ps);
+            let [p, q] = ps;
+            let p_q = p[E] * q[E] - p.fixed_rows::<3>(X).dot(&q.fixed_rows::<3>(X));
+            let scale = p[E] * q[E] * (conditioning(&p) + conditioning(&q));
+            prop_assert!(abs(norm_sqr(s_pq) - 2. * p_q) <= TOLERANCE * scale);
+        }
+
+        #[test]
+        fn schouten(ps in [
+            massless_momentum(),
+            massless_momentum(),
+            massless_momentum(),
+            massless_momentum(),
+        ]) {
+            let s = products(&ps);
+            let [i, j, k, l] = [0, 1, 2, 3];
+            let sum = s[i][j] * s[k][l] + s[i][k] * s[l][j] + s[i][l] * s[j][k];
+            let scale = sqrt(ps.iter().map(|p| p[E]).product::<Float>());
+            prop_assert!(sum.norm() <= TOLERANCE * scale);
+        }
+    }
+}
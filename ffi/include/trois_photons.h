@@ -0,0 +1,79 @@
+/*
+ * C interface of the 3photons simulation
+ *
+ * This header declares the functions of the trois_photons_ffi library, which
+ * make the 3photons event generator usable as a source of weighted
+ * e+e- -> 3 photons events from C, C++ and Fortran programs.
+ *
+ * Typical usage:
+ *
+ *     tp_generator* gen = tp_init("valeurs");
+ *     double momenta[20], weight;
+ *     for (long i = 0; i < num_events; ++i) {
+ *         if (tp_generate_event(gen, momenta, &weight) == 1) {
+ *             // ...analyze the event...
+ *         }
+ *     }
+ *     double sigma, sigma_stddev;
+ *     tp_finalize(gen, &sigma, &sigma_stddev);
+ */
+
+#ifndef TROIS_PHOTONS_H
+#define TROIS_PHOTONS_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Opaque event generator state */
+typedef struct TpGenerator tp_generator;
+
+/*
+ * Set up event generation, given the path to a configuration file in the same
+ * format as that of the 3photons program (legacy or TOML).
+ *
+ * Returns a null pointer if the configuration could not be loaded, after
+ * printing the reason on stderr.
+ */
+tp_generator* tp_init(const char* config_path);
+
+/*
+ * Generate an event.
+ *
+ * Writes the 4-momenta (Px, Py, Pz, E) of the incoming electron and positron,
+ * then of the three outgoing photons, into momenta (in GeV, with the beam along
+ * the X axis as in the original 3photons). In Fortran, this array can be
+ * declared as DOUBLE PRECISION P(4, 5).
+ *
+ * Writes the event weight into weight (in pb), such that the average weight of
+ * all generated events is the total cross-section. Events which fail the cuts
+ * have zero weight. As in the 3photons program, the cuts apply to the event as
+ * measured by the detector, if one is configured, while the momenta are those
+ * of the generated event.
+ *
+ * Returns 1 if the event passes the cuts, 0 if it does not, and -1 if a null
+ * pointer was passed in or the event could not be generated (e.g. because event
+ * validation is enabled and the event is invalid), after printing the reason on
+ * stderr in the latter case.
+ */
+int tp_generate_event(tp_generator* generator, double momenta[20], double* weight);
+
+/*
+ * Finalize event generation and release the associated resources. The
+ * generator must not be used afterwards.
+ *
+ * Writes the total cross-section estimated from the generated events, and its
+ * statistical uncertainty, into sigma and sigma_stddev (in pb), unless they are
+ * null pointers.
+ *
+ * Returns the number of generated events, or -1 if the generator is null.
+ */
+int64_t tp_finalize(tp_generator* generator, double* sigma, double* sigma_stddev);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TROIS_PHOTONS_H */
@@ -0,0 +1,266 @@
+//! C interface of the 3photons simulation
+//!
+//! This lets programs written in C, C++ or Fortran use the event generator of
+//! 3photons as a source of weighted e⁺e⁻ → 𝛾𝛾𝛾 events. The corresponding C
+//! declarations can be found in `include/trois_photons.h`.
+
+#![warn(missing_docs)]
+
+use anyhow::Result;
+use std::{
+    ffi::{c_char, c_double, c_int, CStr},
+    ptr,
+};
+use trois_photons::{
+    config::Configuration,
+    event::{Event, NUM_PARTICLES},
+    evgen::EventGenerator,
+    matelems::MatrixElement,
+    momentum::MOMENTUM_DIM,
+    numeric::Float,
+    random::{RandomGenerator, WithRandomGenerator},
+    resacc::ResultsAccumulator,
+    setup_event_generation,
+    stability::StabilityGuard,
+    timings::PhaseTimer,
+    EventSimulator, SimulatedEvent,
+};
+
+/// Number of coordinates written by tp_generate_event()
+const NUM_COORDINATES: usize = NUM_PARTICLES * MOMENTUM_DIM;
+
+/// Convert a floating-point number to the C double type
+// The conversion is only needed in single precision
+#[allow(clippy::useless_conversion)]
+fn to_c(x: Float) -> c_double {
+    c_double::from(x)
+}
+
+/// Random number generator of any supported engine
+///
+/// The RandomGenerator trait is not object-safe, so this exposes the only
+/// operation that we need from it.
+///
+trait AnyRandomGenerator {
+    /// Simulate an event using this random number generator
+    fn simulate_event(
+        &mut self,
+        simulator: &EventSimulator,
+        res_builder: &mut ResultsAccumulator,
+    ) -> Result<SimulatedEvent>;
+}
+//
+impl<R: RandomGenerator> AnyRandomGenerator for R {
+    fn simulate_event(
+        &mut self,
+        simulator: &EventSimulator,
+        res_builder: &mut ResultsAccumulator,
+    ) -> Result<SimulatedEvent> {
+        Ok(simulator.simulate_event(self, res_builder, &mut PhaseTimer::new(false), false)?)
+    }
+}
+
+/// Setup of the configured random number generator
+struct RandomGeneratorSetup<'cfg>(&'cfg Configuration);
+//
+impl WithRandomGenerator for RandomGeneratorSetup<'_> {
+    type Output = Box<dyn AnyRandomGenerator>;
+
+    fn run<R: RandomGenerator + 'static>(self) -> Self::Output {
+        Box::new(R::from_config(self.0))
+    }
+}
+
+/// Event generator, which C code manipulates through an opaque pointer
+pub struct TpGenerator {
+    /// Configuration of the simulation
+    ///
+    /// It is allocated by Box::into_raw() so that the results accumulator can
+    /// borrow it, and reclaimed by tp_finalize().
+    ///
+    cfg: *mut Configuration,
+
+    /// Matrix elements of the configured process
+    process: Box<dyn MatrixElement>,
+
+    /// Phase space generator
+    evgen: EventGenerator,
+
+    /// Recomputation of unstable events, if enabled
+    guard: Option<StabilityGuard>,
+
+    /// Random number generator
+    rng: Box<dyn AnyRandomGenerator>,
+
+    /// Accumulator, used to compute event weights
+    res_builder: ResultsAccumulator<'static>,
+
+    /// Sum of the weights of generated events, and of their squares
+    weight_sums: (c_double, c_double),
+
+    /// Number of generated events
+    num_events: usize,
+}
+//
+impl TpGenerator {
+    /// Set up event generation, given a configuration file
+    fn new(config_path: &str) -> Result<Self> {
+        let cfg_ptr = Box::into_raw(Box::new(Configuration::load(config_path, |_cfg| {})?));
+        // SAFETY: The configuration is only freed by tp_finalize(), after
+        //         everything which borrows it is dropped
+        let cfg: &'static Configuration = unsafe { &*cfg_ptr };
+        let (process, evgen) = match setup_event_generation(cfg) {
+            Ok(setup) => setup,
+            Err(e) => {
+                // SAFETY: Nothing borrows the configuration yet
+                drop(unsafe { Box::from_raw(cfg_ptr) });
+                return Err(e.into());
+            }
+        };
+        Ok(Self {
+            cfg: cfg_ptr,
+            rng: cfg.rng.dispatch(RandomGeneratorSetup(cfg)),
+            res_builder: ResultsAccumulator::new(cfg, evgen.event_weight()),
+            guard: StabilityGuard::new(cfg),
+            process,
+            evgen,
+            weight_sums: (0., 0.),
+            num_events: 0,
+        })
+    }
+
+    /// Generate an event, measure it as the simulation would, and compute its
+    /// weight if the measurement passes the cuts
+    fn generate(&mut self) -> Result<(Event, Option<Float>)> {
+        self.num_events += 1;
+        // SAFETY: The configuration lives as long as the generator
+        let cfg: &Configuration = unsafe { &*self.cfg };
+        let simulator = EventSimulator::new(cfg, &*self.process, &self.evgen, self.guard.as_ref());
+        let simulated = self.rng.simulate_event(&simulator, &mut self.res_builder)?;
+        let weight = (simulated.sigma_contrib).map(|sigma| sigma * (cfg.num_events as Float));
+        let weight_c = to_c(weight.unwrap_or(0.));
+        self.weight_sums.0 += weight_c;
+        self.weight_sums.1 += weight_c.powi(2);
+        Ok((simulated.event, weight))
+    }
+}
+
+/// Set up event generation, given the path to a configuration file in the
+/// same format as that of the 3photons program
+///
+/// Returns a null pointer if the configuration could not be loaded, after
+/// printing the reason on stderr.
+///
+/// # Safety
+///
+/// `config_path` must be a valid pointer to a null-terminated string.
+///
+#[no_mangle]
+pub unsafe extern "C" fn tp_init(config_path: *const c_char) -> *mut TpGenerator {
+    if config_path.is_null() {
+        eprintln!("tp_init: null configuration path");
+        return ptr::null_mut();
+    }
+    let setup = CStr::from_ptr(config_path)
+        .to_str()
+        .map_err(anyhow::Error::from)
+        .and_then(TpGenerator::new);
+    match setup {
+        Ok(generator) => Box::into_raw(Box::new(generator)),
+        Err(e) => {
+            eprintln!("tp_init: failed to set up event generation: {e:?}");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Generate an event
+///
+/// Writes the 4-momenta (Px, Py, Pz, E) of the incoming electron and positron,
+/// then of the three outgoing photons, into `momenta` (in GeV, with the beam
+/// along the X axis as in the original 3photons). Writes the event weight into
+/// `weight` (in pb), such that the average weight of all generated events is
+/// the total cross-section. Events which fail the cuts have zero weight. As in
+/// the 3photons program, the cuts apply to the event as measured by the
+/// detector, if one is configured, while the momenta are those of the
+/// generated event.
+///
+/// Returns 1 if the event passes the cuts, 0 if it does not, and -1 if a null
+/// pointer was passed in or the event could not be generated (e.g. because
+/// event validation is enabled and the event is invalid), after printing the
+/// reason on stderr in the latter case.
+///
+/// # Safety
+///
+/// `generator` must have been produced by tp_init() and not finalized yet,
+/// `momenta` must point to 20 writable doubles, and `weight` to a writable
+/// double.
+///
+#[no_mangle]
+pub unsafe extern "C" fn tp_generate_event(
+    generator: *mut TpGenerator,
+    momenta: *mut c_double,
+    weight: *mut c_double,
+) -> c_int {
+    let (Some(generator), false, false) = (generator.as_mut(), momenta.is_null(), weight.is_null())
+    else {
+        return -1;
+    };
+    let (event, event_weight) = match generator.generate() {
+        Ok(generated) => generated,
+        Err(e) => {
+            eprintln!("tp_generate_event: failed to generate an event: {e:?}");
+            return -1;
+        }
+    };
+    let momenta = std::slice::from_raw_parts_mut(momenta, NUM_COORDINATES);
+    let event_momenta = event.all_momenta();
+    for (index, dst) in momenta.iter_mut().enumerate() {
+        *dst = to_c(event_momenta[(index / MOMENTUM_DIM, index % MOMENTUM_DIM)]);
+    }
+    *weight = to_c(event_weight.unwrap_or(0.));
+    c_int::from(event_weight.is_some())
+}
+
+/// Finalize event generation and release the associated resources
+///
+/// Writes the total cross-section estimated from the generated events, and its
+/// statistical uncertainty, into `sigma` and `sigma_stddev` (in pb), unless
+/// they are null pointers.
+///
+/// Returns the number of generated events, or -1 if the generator is null.
+///
+/// # Safety
+///
+/// `generator` must have been produced by tp_init() and not finalized yet. It
+/// must not be used after this call. `sigma` and `sigma_stddev` must either be
+/// null or point to writable doubles.
+///
+#[no_mangle]
+pub unsafe extern "C" fn tp_finalize(
+    generator: *mut TpGenerator,
+    sigma: *mut c_double,
+    sigma_stddev: *mut c_double,
+) -> i64 {
+    if generator.is_null() {
+        return -1;
+    }
+    let generator = Box::from_raw(generator);
+    let n_ev = generator.num_events as c_double;
+    let (sum_w, sum_w2) = generator.weight_sums;
+    let mean = sum_w / n_ev;
+    if let Some(sigma) = sigma.as_mut() {
+        *sigma = mean;
+    }
+    if let Some(sigma_stddev) = sigma_stddev.as_mut() {
+        *sigma_stddev = ((sum_w2 / n_ev - mean.powi(2)) / (n_ev - 1.)).sqrt();
+    }
+    let num_events = generator.num_events as i64;
+
+    // The results accumulator borrows the configuration, so it must be gone
+    // before the configuration can be reclaimed
+    let cfg = generator.cfg;
+    drop(generator);
+    drop(Box::from_raw(cfg));
+    num_events
+}
@@ -0,0 +1,216 @@
+//! Angular asymmetries of the photons, which are the primary observables used
+//! to constrain the anomalous couplings 𝛽₊ and 𝛽₋
+//!
+//! Each asymmetry compares the cross-section of the events in which a certain
+//! angular variable is positive and negative, (σ₊ - σ₋) / (σ₊ + σ₋), which is
+//! the weighted mean of the sign of that variable.
+
+use crate::{
+    config::Configuration,
+    error::ensure,
+    event::{Event, NUM_OUTGOING},
+    momentum::E,
+    numeric::{functions::*, Complex, Float, WeightedMean},
+    Result,
+};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+/// Photons whose polar angle enters the forward-backward asymmetry
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhotonSelection {
+    /// Each photon, with a third of the event weight
+    #[default]
+    All,
+
+    /// The most energetic photon
+    MostEnergetic,
+
+    /// The least energetic photon
+    LeastEnergetic,
+}
+
+/// Configuration of the angular asymmetries (not in the legacy format)
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AsymmetryConfig {
+    /// Photons whose angle to the electron beam enters the forward-backward
+    /// asymmetry
+    pub photons: PhotonSelection,
+
+    /// Harmonic n of the azimuthal asymmetry, which is measured on the sign of
+    /// cos(n·Δφ), where Δφ is the azimuthal angle between the two most
+    /// energetic photons around the beam axis
+    pub harmonic: u32,
+}
+//
+impl AsymmetryConfig {
+    /// Check that the configuration is sensible
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            self.harmonic > 0,
+            InvalidConfig,
+            "The harmonic of the azimuthal asymmetry must be positive"
+        );
+        Ok(())
+    }
+}
+//
+impl Default for AsymmetryConfig {
+    fn default() -> Self {
+        Self {
+            photons: PhotonSelection::default(),
+            harmonic: 1,
+        }
+    }
+}
+
+/// Number of angular asymmetries
+const NUM_ASYMMETRIES: usize = 2;
+
+/// Index of the forward-backward asymmetry
+const FORWARD_BACKWARD: usize = 0;
+
+/// Index of the azimuthal asymmetry
+const AZIMUTHAL: usize = 1;
+
+/// Accumulator of the angular asymmetries over events
+#[derive(Clone, Deserialize, Serialize)]
+pub struct AsymmetryAccumulator {
+    /// Configuration of the asymmetries
+    config: AsymmetryConfig,
+
+    /// Weighted means of the sign of each angular variable
+    signs: [WeightedMean; NUM_ASYMMETRIES],
+}
+//
+impl AsymmetryAccumulator {
+    /// Set up the accumulation of the asymmetries, if they are configured
+    pub fn new(cfg: &Configuration) -> Option<Self> {
+        cfg.asymmetries.as_ref().map(|config| Self {
+            config: config.clone(),
+            signs: Default::default(),
+        })
+    }
+
+    /// Record an event, with a certain weight
+    pub fn fill(&mut self, event: &Event, weight: Float) {
+        self.signs[FORWARD_BACKWARD].add(self.forward_backward_sign(event), weight);
+        self.signs[AZIMUTHAL].add(self.azimuthal_sign(event), weight);
+    }
+
+    /// Integrate the contents of another accumulator
+    pub fn merge(&mut self, other: &Self) {
+        for (sign, other_sign) in self.signs.iter_mut().zip(other.signs) {
+            sign.merge(other_sign);
+        }
+    }
+
+    /// Multiply the weights of the recorded events by a certain factor
+    pub fn scale_weights(&mut self, factor: Float) {
+        for sign in &mut self.signs {
+            sign.scale_weights(factor);
+        }
+    }
+
+    /// Compute the asymmetries and their statistical errors, given the number
+    /// of generated events
+    pub fn finalize(&self, num_events: usize) -> Asymmetries {
+        let fb = &self.signs[FORWARD_BACKWARD];
+        let azimuthal = &self.signs[AZIMUTHAL];
+        Asymmetries {
+            forward_backward: fb.mean(),
+            forward_backward_stddev: fb.stddev(num_events),
+            harmonic: self.config.harmonic,
+            azimuthal: azimuthal.mean(),
+            azimuthal_stddev: azimuthal.stddev(num_events),
+        }
+    }
+
+    /// Sign of the cosine of the angle between the selected photon(s) and the
+    /// electron beam, averaged over the selected photons
+    fn forward_backward_sign(&self, event: &Event) -> Float {
+        let p_el = event.electron_momentum();
+        let sign_of = |ph: usize| sign(event.outgoing_momentum(ph).xyz().dot(&p_el.xyz()));
+        match self.config.photons {
+            PhotonSelection::All => {
+                (0..NUM_OUTGOING).map(sign_of).sum::<Float>() / NUM_OUTGOING as Float
+            }
+            PhotonSelection::MostEnergetic => sign_of(energy_ordered(event)[0]),
+            PhotonSelection::LeastEnergetic => sign_of(energy_ordered(event)[NUM_OUTGOING - 1]),
+        }
+    }
+
+    /// Sign of cos(n·Δφ), where Δφ is the azimuthal angle between the two most
+    /// energetic photons around the beam axis
+    fn azimuthal_sign(&self, event: &Event) -> Float {
+        let beam = event.electron_momentum().xyz().normalize();
+        let [ph1, ph2, ..] = energy_ordered(event);
+        let transverse = |ph: usize| {
+            let p = event.outgoing_momentum(ph).xyz();
+            p - beam * p.dot(&beam)
+        };
+        let (t1, t2) = (transverse(ph1), transverse(ph2));
+        let rotation = Complex::new(t1.dot(&t2), beam.dot(&t1.cross(&t2)));
+        sign(re(rotation.powu(self.config.harmonic)))
+    }
+}
+
+/// Indices of the photons of an event, by decreasing energy
+fn energy_ordered(event: &Event) -> [usize; NUM_OUTGOING] {
+    let mut indices = std::array::from_fn(|ph| ph);
+    indices.sort_unstable_by(|&ph1, &ph2| {
+        let energy = |ph| event.outgoing_momentum(ph)[E];
+        energy(ph2).total_cmp(&energy(ph1))
+    });
+    indices
+}
+
+/// Sign of a number, which is zero for zero
+fn sign(x: Float) -> Float {
+    if x > 0. {
+        1.
+    } else if x < 0. {
+        -1.
+    } else {
+        0.
+    }
+}
+
+/// Angular asymmetries of the photons, with their statistical errors
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Asymmetries {
+    /// Forward-backward asymmetry of the selected photon(s) with respect to
+    /// the electron beam
+    pub forward_backward: Float,
+
+    /// Standard deviation of the forward-backward asymmetry
+    pub forward_backward_stddev: Float,
+
+    /// Harmonic of the azimuthal asymmetry
+    pub harmonic: u32,
+
+    /// Azimuthal asymmetry of the two most energetic photons
+    pub azimuthal: Float,
+
+    /// Standard deviation of the azimuthal asymmetry
+    pub azimuthal_stddev: Float,
+}
+//
+impl Asymmetries {
+    /// Display the asymmetries and their statistical errors
+    pub fn print(&self) {
+        info!("Asymmetries        :      Value | Std. dev.");
+        info!(
+            "Forward-backward   : {:>+10.6} | {:.6}",
+            self.forward_backward, self.forward_backward_stddev
+        );
+        let name = format!("Azimuthal (n = {})", self.harmonic);
+        info!(
+            "{name:<19}: {:>+10.6} | {:.6}",
+            self.azimuthal, self.azimuthal_stddev
+        );
+        info!("");
+    }
+}
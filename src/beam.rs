@@ -0,0 +1,34 @@
+//! Energy spread of the beams, which smears the collision energy of events
+//! around its nominal value
+
+use crate::numeric::{floats::consts::PI, Float};
+use prefix_num_ops::real::*;
+
+/// Number of random numbers which are drawn to sample the collision energy
+pub const NUM_RANDOM_NUMBERS: usize = 2;
+
+/// Gaussian spread of the collision energy
+#[derive(Clone, Copy, Debug)]
+pub struct BeamSpread {
+    /// Standard deviation of the collision energy, relative to its nominal
+    /// value
+    relative_sigma: Float,
+}
+//
+impl BeamSpread {
+    /// Set up the spread, given the standard deviation and nominal value of
+    /// the collision energy (GeV)
+    pub fn new(sigma: Float, e_total: Float) -> Self {
+        Self {
+            relative_sigma: sigma / e_total,
+        }
+    }
+
+    /// Sample the ratio of the collision energy to its nominal value, from
+    /// uniform random numbers in [0, 1[ (using the Box-Muller transform)
+    pub fn sample(&self, randoms: [Float; NUM_RANDOM_NUMBERS]) -> Float {
+        let [u_1, u_2] = randoms;
+        let gaussian = sqrt(-2. * ln(1. - u_1)) * cos(2. * PI * u_2);
+        1. + self.relative_sigma * gaussian
+    }
+}
@@ -0,0 +1,132 @@
+//! Benchmark mode, which measures the event throughput of each stage of the
+//! simulation, to compare machines and build configurations
+
+use crate::cli::BenchArgs;
+use anyhow::{ensure, Context, Result};
+use clap::ValueEnum;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+use trois_photons::{
+    config::Configuration,
+    evgen::EventGenerator,
+    matelems::MatrixElement,
+    provenance::Provenance,
+    random::{
+        RandGenerator, RandomEngine, RandomGenerator, RanfGenerator, RanluxGenerator,
+        SobolGenerator, StdRng, XoshiroPlus,
+    },
+    setup_event_generation, simulate,
+};
+
+/// Measure how many events per second are generated, generated and cut, and
+/// generated, cut and turned into matrix elements on a single thread, then
+/// how many are fully simulated with the configured scheduling
+pub fn run(args: &BenchArgs) -> Result<()> {
+    ensure!(args.events > 0, "At least one event must be generated");
+
+    // Set up event generation as for a simulation, over the requested number
+    // of events (which also sizes the full simulation)
+    let cfg = Configuration::load_with_overlay(&args.args.config, &args.args.set, |cfg| {
+        args.args.apply_overrides(cfg);
+        cfg.num_events = args.events;
+    })
+    .context("Failed to load the configuration")?;
+    ensure!(
+        cfg.event_output.is_none() && cfg.checkpoint.is_none(),
+        "Event output and checkpointing are not supported in benchmark mode"
+    );
+    let (process, evgen) =
+        setup_event_generation(&cfg).context("Failed to set up event generation")?;
+
+    // Time the stages of the simulation
+    let stages = match cfg.rng {
+        RandomEngine::Ranf => time_stages::<RanfGenerator>(&cfg, &*process, &evgen),
+        RandomEngine::Ranlux => time_stages::<RanluxGenerator>(&cfg, &*process, &evgen),
+        RandomEngine::XoshiroPlus => {
+            time_stages::<RandGenerator<XoshiroPlus>>(&cfg, &*process, &evgen)
+        }
+        RandomEngine::Xoshiro256PlusPlus => {
+            time_stages::<RandGenerator<Xoshiro256PlusPlus>>(&cfg, &*process, &evgen)
+        }
+        RandomEngine::StdRng => time_stages::<RandGenerator<StdRng>>(&cfg, &*process, &evgen),
+        RandomEngine::Sobol => time_stages::<SobolGenerator>(&cfg, &*process, &evgen),
+    };
+    let start = Instant::now();
+    simulate(&cfg, &*process, &evgen, None, None, None).context("Failed to run the simulation")?;
+    let full_simulation = start.elapsed();
+
+    // Report the throughput of each stage, along with the build configuration
+    let provenance = Provenance::new(&cfg);
+    println!();
+    println!("Build              : {}", provenance.build_profile);
+    let features = if provenance.features.is_empty() {
+        "none".to_owned()
+    } else {
+        provenance.features.join(", ")
+    };
+    println!("Features           : {features}");
+    let rng = cfg.rng.to_possible_value().expect("Engines are not hidden");
+    println!("Random numbers     : {}", rng.get_name());
+    println!();
+    println!(
+        "{:<28} {:>12} {:>12} {:>14}",
+        "Stage", "Events", "Time (s)", "Events/s"
+    );
+    let stages = stages
+        .into_iter()
+        .chain(std::iter::once(("Full simulation", full_simulation)));
+    for (name, duration) in stages {
+        let secs = duration.as_secs_f64();
+        println!(
+            "{name:<28} {:>12} {secs:>12.4} {:>14.4e}",
+            args.events,
+            args.events as f64 / secs
+        );
+    }
+    Ok(())
+}
+
+/// Time event generation alone, then with cuts, then with cuts and matrix
+/// elements, on a single thread with a certain random number generator
+fn time_stages<R: RandomGenerator>(
+    cfg: &Configuration,
+    process: &dyn MatrixElement,
+    evgen: &EventGenerator,
+) -> [(&'static str, Duration); 3] {
+    let num_events = cfg.num_events;
+    let new_rng = || R::from_config(cfg);
+
+    let mut rng = new_rng();
+    let start = Instant::now();
+    for _ in 0..num_events {
+        black_box(evgen.generate(&mut rng));
+    }
+    let generation = start.elapsed();
+
+    let mut rng = new_rng();
+    let start = Instant::now();
+    for _ in 0..num_events {
+        let event = evgen.generate(&mut rng);
+        black_box(cfg.event_cut.apply(&event).is_ok());
+    }
+    let cuts = start.elapsed();
+
+    let mut rng = new_rng();
+    let start = Instant::now();
+    for _ in 0..num_events {
+        let event = evgen.generate(&mut rng);
+        if cfg.event_cut.apply(&event).is_ok() {
+            black_box(process.contributions(&event));
+        }
+    }
+    let matrix_elements = start.elapsed();
+
+    [
+        ("Event generation", generation),
+        ("+ Cuts", cuts),
+        ("+ Matrix elements", matrix_elements),
+    ]
+}
@@ -0,0 +1,138 @@
+//! Cache of simulation results, so that a run which was already performed
+//! does not need to be simulated again
+//!
+//! Results are stored in the JSON format, which features the sums that they
+//! derive from, under a name which hashes everything that affects them: the
+//! configuration (including the random number generator seed) and the
+//! executable of 3photons, which accounts for its version, its enabled build
+//! features and any uncommitted change to its sources.
+
+use crate::{
+    checkpoint,
+    config::Configuration,
+    resfin::FinalResults,
+    resmerge::{self, RunResults},
+    Result,
+};
+use log::{debug, info, warn};
+use std::{env, fs, io, path::PathBuf, time::Duration};
+
+/// Cached results of a certain simulation
+pub struct ResultsCache {
+    /// Path of the JSON results file of this simulation in the cache
+    path: PathBuf,
+
+    /// Hash of the executable which performs the simulation
+    executable_hash: String,
+}
+//
+impl ResultsCache {
+    /// Locate the cached results of the simulation that a configuration
+    /// describes, if caching is enabled
+    ///
+    /// Runs which write events are not cached, since their events could not be
+    /// written without simulating them again.
+    ///
+    pub fn new(cfg: &Configuration) -> Option<Self> {
+        let cache_dir = cfg.cache_dir.as_ref()?;
+        if cfg.event_output.is_some() {
+            info!("Results are not cached when events are written");
+            return None;
+        }
+        let executable_hash = match executable_hash() {
+            Ok(hash) => hash,
+            Err(e) => {
+                warn!("Results are not cached, since the executable cannot be read: {e}");
+                return None;
+            }
+        };
+        let key = cache_key(cfg, &executable_hash);
+        Some(Self {
+            path: PathBuf::from(cache_dir).join(format!("{key}.json")),
+            executable_hash,
+        })
+    }
+
+    /// Load the cached results of the simulation, if it was already performed,
+    /// along with the time that it took
+    ///
+    /// Timings of the simulation phases, if requested, are not available from
+    /// cached results.
+    ///
+    pub fn load<'cfg>(
+        &self,
+        cfg: &'cfg Configuration,
+    ) -> Result<Option<(FinalResults<'cfg>, Duration)>> {
+        if !self.path.exists() {
+            debug!("No cached results at {}", self.path.display());
+            return Ok(None);
+        }
+        let run = RunResults::load(&self.path.to_string_lossy())?;
+        if cache_key(&run.cfg, &self.executable_hash) != cache_key(cfg, &self.executable_hash) {
+            debug!("Cached results at {} do not match", self.path.display());
+            return Ok(None);
+        }
+        info!("Reusing the cached results at {}", self.path.display());
+        let provenance = run.provenance.clone();
+        let mut results = resmerge::merge(cfg, vec![run])?;
+        let elapsed_time = Duration::from_secs_f64(provenance.wall_time.unwrap_or(0.));
+        results.provenance = provenance;
+        Ok(Some((results, elapsed_time)))
+    }
+
+    /// Store the results of the simulation in the cache
+    ///
+    /// The results are first written to a temporary file, so that concurrent
+    /// runs never read incomplete results.
+    ///
+    pub fn store(&self, results: &FinalResults) -> Result<()> {
+        if let Some(cache_dir) = self.path.parent() {
+            fs::create_dir_all(cache_dir)?;
+        }
+        let tmp_path = self.path.with_extension("json.tmp");
+        results.write_json(&tmp_path.to_string_lossy())?;
+        fs::rename(&tmp_path, &self.path)?;
+        debug!("Cached the results at {}", self.path.display());
+        Ok(())
+    }
+}
+
+/// Name of the cached results of the simulation that a configuration
+/// describes, when it is performed by a certain executable
+fn cache_key(cfg: &Configuration, executable_hash: &str) -> String {
+    // Every configuration entry may affect the results, except for the
+    // destinations of the output files and the cache itself
+    let mut cfg = serde_json::to_value(cfg).expect("Configuration should be serializable");
+    let cfg_entries = cfg
+        .as_object_mut()
+        .expect("Configuration should serialize to a map");
+    cfg_entries.remove("output_dir");
+    cfg_entries.remove("output_name");
+    cfg_entries.remove("json_output");
+    cfg_entries.remove("summary_yaml");
+    cfg_entries.remove("yoda_output");
+    cfg_entries.remove("checkpoint");
+    cfg_entries.remove("pin_threads");
+    cfg_entries.remove("cache_dir");
+
+    // Only whether these outputs are recorded affects the results, not where
+    // they are written
+    for name in ["bad_events_output", "convergence_output"] {
+        if let Some(path) = cfg_entries.get_mut(name) {
+            *path = (!path.is_null()).into();
+        }
+    }
+    if let Some(grid) = cfg_entries
+        .get_mut("coupling_grid")
+        .and_then(|grid| grid.as_object_mut())
+    {
+        grid.remove("path");
+    }
+    checkpoint::fnv1a_hash(format!("{executable_hash} {cfg}"))
+}
+
+/// Hash of the running executable, which identifies the code that performs
+/// the simulation more precisely than its version or git commit do
+fn executable_hash() -> io::Result<String> {
+    Ok(checkpoint::fnv1a_hash(fs::read(env::current_exe()?)?))
+}
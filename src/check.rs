@@ -0,0 +1,55 @@
+//! Configuration check mode, which validates a configuration and displays its
+//! effective entries and derived quantities without generating any event
+
+use crate::cli::CheckArgs;
+use anyhow::{Context, Result};
+use trois_photons::{
+    config::Configuration, coupling::Couplings, evgen::EventGenerator, numeric::Float,
+};
+
+/// Load and check the configuration, then print it out in TOML format along
+/// with the quantities that the simulation would derive from it
+///
+/// The derived quantities are printed as TOML comments, so that the output can
+/// be used as the configuration file of a later run.
+///
+pub fn run(args: &CheckArgs) -> Result<()> {
+    // Load and check the configuration as for a simulation
+    let cfg = Configuration::load_with_overlay(&args.args.config, &args.args.set, |cfg| {
+        args.args.apply_overrides(cfg)
+    })
+    .context("Failed to load the configuration")?;
+
+    // Compute the derived quantities, without adaptive sampling warm-up
+    let couplings = Couplings::<Float>::from(&cfg);
+    let event_weight = EventGenerator::new(cfg.e_total)
+        .context("Failed to set up event generation")?
+        .event_weight();
+    let incident_flux = 1. / (2. * cfg.e_total.powi(2));
+    let inv_propagator = cfg.z_lineshape.inverse_propagator(&cfg, cfg.e_total);
+    let seed = cfg.rng.effective_seed(cfg.seed);
+
+    // Print the effective configuration, then the derived quantities
+    let config_toml =
+        toml::to_string_pretty(&cfg).context("Failed to express the configuration as TOML")?;
+    println!();
+    println!("# Effective configuration");
+    println!("{}", config_toml.trim_end());
+    println!();
+    println!("# Derived quantities");
+    for line in couplings.to_string().lines() {
+        println!("# {line}");
+    }
+    println!("# Incident flux (GeV^-2)   : {incident_flux:e}");
+    println!("# Phase space weight       : {event_weight:e}");
+    println!(
+        "# Inverse Z propagator     : {} - {}i (in units of M_Z*G_Z)",
+        inv_propagator.re, -inv_propagator.im
+    );
+    println!(
+        "# Reduced Z propagator     : {}",
+        1. / inv_propagator.norm_sqr()
+    );
+    println!("# Random number seed       : {seed}");
+    Ok(())
+}
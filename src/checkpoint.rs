@@ -0,0 +1,243 @@
+//! Periodic saving of the simulation state, so that long runs which get
+//! interrupted can be resumed instead of being restarted from scratch
+
+use crate::{
+    config::Configuration,
+    output::events::{EventWriter, EventWriterCheckpoint},
+    random::RandomGenerator,
+    resacc::{AccumulatorCheckpoint, ResultsAccumulator},
+    Error, Result,
+};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    time::{Duration, Instant},
+};
+
+/// Configuration of simulation checkpointing (not in the legacy format)
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CheckpointConfig {
+    /// File where checkpoints should be written
+    pub path: String,
+
+    /// Minimal time between two checkpoints (seconds)
+    #[serde(default = "CheckpointConfig::default_interval")]
+    pub interval: u64,
+}
+//
+impl CheckpointConfig {
+    /// Default time between two checkpoints (seconds)
+    pub const DEFAULT_INTERVAL: u64 = 600;
+
+    /// Default value of the interval configuration entry
+    fn default_interval() -> u64 {
+        Self::DEFAULT_INTERVAL
+    }
+}
+
+/// State of an interrupted simulation, as saved to disk
+#[derive(Deserialize, Serialize)]
+pub struct Checkpoint {
+    /// Description of the build and configuration that produced this state
+    fingerprint: String,
+
+    /// Number of event batches that were fully simulated
+    pub batches_done: usize,
+
+    /// State of the random number generator after these batches, as saved by
+    /// RandomGenerator::save_state()
+    rng_state: Vec<u8>,
+
+    /// Results accumulated over these batches
+    pub results: AccumulatorCheckpoint,
+
+    /// State of the accepted event output, if enabled
+    pub events: Option<EventWriterCheckpoint>,
+}
+//
+impl Checkpoint {
+    /// Load a checkpoint, making sure that it matches the configuration
+    pub fn load(path: &str, cfg: &Configuration) -> Result<Self> {
+        let file = File::open(path)?;
+        let checkpoint: Self =
+            bincode::deserialize_from(BufReader::new(file)).map_err(|source| {
+                Error::Checkpoint {
+                    what: "Failed to read the checkpoint",
+                    source,
+                }
+            })?;
+        if checkpoint.fingerprint != fingerprint(cfg) {
+            return Err(Error::CheckpointMismatch);
+        }
+        Ok(checkpoint)
+    }
+
+    /// Restore the random number generator state, so that the resumed
+    /// simulation draws the same random numbers as the interrupted one
+    pub fn rng<R: RandomGenerator>(&self) -> Result<R> {
+        R::restore_state(&self.rng_state)
+    }
+}
+
+/// Mechanism for periodically saving the simulation state
+pub struct Checkpointer<'a> {
+    /// File where checkpoints should be written
+    path: &'a str,
+
+    /// Minimal time between two checkpoints
+    interval: Duration,
+
+    /// Description of the current build and configuration
+    fingerprint: String,
+
+    /// Writer of accepted events, if enabled
+    event_writer: Option<&'a EventWriter>,
+
+    /// Time at which the last checkpoint was written (or the simulation began)
+    last_save: Instant,
+}
+//
+impl<'a> Checkpointer<'a> {
+    /// Set up checkpointing, if enabled by the configuration
+    pub fn new(cfg: &'a Configuration, event_writer: Option<&'a EventWriter>) -> Option<Self> {
+        cfg.checkpoint.as_ref().map(|checkpoint_cfg| Self {
+            path: &checkpoint_cfg.path,
+            interval: Duration::from_secs(checkpoint_cfg.interval),
+            fingerprint: fingerprint(cfg),
+            event_writer,
+            last_save: Instant::now(),
+        })
+    }
+
+    /// Set up checkpointing to a certain file only if the simulation is
+    /// interrupted (see the interrupt module), which is never due otherwise
+    pub fn on_interruption(
+        cfg: &'a Configuration,
+        path: &'a str,
+        event_writer: Option<&'a EventWriter>,
+    ) -> Self {
+        Self {
+            path,
+            interval: Duration::MAX,
+            fingerprint: fingerprint(cfg),
+            event_writer,
+            last_save: Instant::now(),
+        }
+    }
+
+    /// Truth that enough time has elapsed since the last checkpoint
+    pub fn is_due(&self) -> bool {
+        self.last_save.elapsed() >= self.interval
+    }
+
+    /// Save the simulation state
+    ///
+    /// Must not be called while events are being simulated. The checkpoint is
+    /// first written to a temporary file, so that an interruption while it is
+    /// being written does not destroy the previous checkpoint.
+    ///
+    pub fn save<R: RandomGenerator>(
+        &mut self,
+        batches_done: usize,
+        rng: &R,
+        results: &ResultsAccumulator,
+    ) -> Result<()> {
+        let checkpoint_error = |source| Error::Checkpoint {
+            what: "Failed to save the checkpoint",
+            source,
+        };
+        let events = self.event_writer.map(EventWriter::checkpoint).transpose()?;
+        let checkpoint = Checkpoint {
+            fingerprint: self.fingerprint.clone(),
+            batches_done,
+            rng_state: rng.save_state()?,
+            results: results.checkpoint(),
+            events,
+        };
+        let tmp_path = format!("{}.tmp", self.path);
+        let mut out = BufWriter::new(File::create(&tmp_path)?);
+        bincode::serialize_into(&mut out, &checkpoint).map_err(checkpoint_error)?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp_path, self.path)?;
+        self.last_save = Instant::now();
+        debug!(
+            "Saved a checkpoint to {} after {batches_done} batches",
+            self.path
+        );
+        Ok(())
+    }
+}
+
+/// Describe the build and configuration entries which affect the simulation
+/// state, so that a checkpoint is only resumed by a compatible simulation
+pub(crate) fn fingerprint(cfg: &Configuration) -> String {
+    // Build features which affect the simulation results
+    let features = [
+        ("f32", cfg!(feature = "f32")),
+        ("faster-evgen", cfg!(feature = "faster-evgen")),
+        (
+            "faster-threading",
+            cfg!(all(
+                feature = "multi-threading",
+                feature = "faster-threading"
+            )),
+        ),
+        ("fma", cfg!(feature = "fma")),
+        ("no-photon-sorting", cfg!(feature = "no-photon-sorting")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect::<Vec<_>>()
+    .join(",");
+
+    // Configuration, minus the entries which only control result output and
+    // the simulation threads
+    let mut cfg = serde_json::to_value(cfg).expect("Configuration should be serializable");
+    let cfg_entries = cfg
+        .as_object_mut()
+        .expect("Configuration should serialize to a map");
+    cfg_entries.remove("output_dir");
+    cfg_entries.remove("output_name");
+    cfg_entries.remove("data_format");
+    cfg_entries.remove("tag");
+    cfg_entries.remove("json_output");
+    cfg_entries.remove("summary_yaml");
+    cfg_entries.remove("yoda_output");
+    cfg_entries.remove("bad_events_output");
+    cfg_entries.remove("checkpoint");
+    cfg_entries.remove("systematics");
+    cfg_entries.remove("resampling");
+    cfg_entries.remove("validation");
+    cfg_entries.remove("timings");
+    cfg_entries.remove("threads");
+    cfg_entries.remove("pin_threads");
+    cfg_entries.remove("cache_dir");
+
+    // Only whether the convergence history and the coupling grid are recorded
+    // affects the simulation state, not where they are written
+    for name in ["convergence_output", "coupling_grid"] {
+        if let Some(path) = cfg_entries.get_mut(name) {
+            *path = (!path.is_null()).into();
+        }
+    }
+
+    // Put it all together
+    format!("{} [{features}] {cfg}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Short identifier of the build and configuration entries which affect the
+/// simulation results, which is stable across runs and machines
+pub(crate) fn config_hash(cfg: &Configuration) -> String {
+    fnv1a_hash(fingerprint(cfg))
+}
+
+/// 64-bit FNV-1a hash of a string or of raw bytes, in hexadecimal
+pub(crate) fn fnv1a_hash(data: impl AsRef<[u8]>) -> String {
+    let hash = (data.as_ref().iter()).fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
@@ -0,0 +1,679 @@
+//! Command-line interface of the simulation
+
+use clap::{
+    builder::{PossibleValuesParser, TypedValueParser},
+    ArgGroup, Parser, Subcommand, ValueEnum,
+};
+use log::LevelFilter;
+use trois_photons::{
+    checkpoint::CheckpointConfig,
+    config::{ConfigAssignment, Configuration},
+    coupling::{AlphaScheme, ElectroweakScheme, ZLineshape},
+    event::ValidationConfig,
+    evfilter::FilterExpression,
+    evgen::{PhaseSpaceGenerator, PhotonOrdering},
+    numeric::Float,
+    output::{
+        events::{EventFileFormat, EventOutputConfig},
+        resdata::DataFormat,
+        unweighting::UnweightingConfig,
+    },
+    random::RandomEngine,
+    stability::StabilityGuardConfig,
+};
+
+/// Monte Carlo simulation of the e⁺e⁻ → 𝛾𝛾𝛾 process
+///
+/// Configuration entries specified on the command line take precedence over
+/// those from the configuration file.
+///
+#[derive(Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// Configuration of the simulation
+    #[command(flatten)]
+    pub args: Args,
+
+    /// Run this many statistically independent replicas of the simulation,
+    /// with consecutive seeds, and compare the spread of their cross-sections
+    /// with the claimed statistical error
+    #[arg(long)]
+    pub replicas: Option<usize>,
+
+    /// Alternative operating mode, if any
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Only display warnings and errors, so that the standard output only
+    /// contains the machine-readable results of the operating mode (if any)
+    #[arg(long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Also display debugging messages
+    #[arg(long, global = true)]
+    pub verbose: bool,
+}
+//
+impl Cli {
+    /// Maximal level of the log messages that should be displayed
+    pub fn log_level(&self) -> LevelFilter {
+        if self.quiet {
+            LevelFilter::Warn
+        } else if self.verbose {
+            LevelFilter::Debug
+        } else {
+            LevelFilter::Info
+        }
+    }
+}
+
+/// Alternative operating modes of the program
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the simulation over a grid of collision energies, and tabulate the
+    /// resulting cross-sections
+    Scan(ScanArgs),
+
+    /// Check the gauge invariance of the standard amplitudes over a sample of
+    /// generated events, to detect errors in the spinor product formulas
+    SelfTest(SelfTestArgs),
+
+    /// Integrate test functions of known integral over the events of each flat
+    /// phase space generator, to detect normalization errors in event
+    /// generation
+    PhaseSpaceTest(PhaseSpaceTestArgs),
+
+    /// Evaluate the cuts and matrix elements over phase space points read from
+    /// a file instead of generated ones, and output the results as the
+    /// simulation would, to cross-validate other codes on identical points
+    Replay(ReplayArgs),
+
+    /// Answer requests for the matrix elements of phase space points, sent as
+    /// JSON lines on the standard input, so that other generators can reuse
+    /// the matrix elements of this one
+    Serve(ServeArgs),
+
+    /// Run the simulation with the random number generator of the original
+    /// 3photons, and compare the resulting res.data file with a reference one
+    /// produced by another version, failing if they do not agree
+    Compare(CompareArgs),
+
+    /// Measure the event throughput of each stage of the simulation, to
+    /// compare machines and build configurations
+    Bench(BenchArgs),
+
+    /// Load and validate the configuration, then display its effective entries
+    /// and the quantities derived from them, without generating any event
+    Check(CheckArgs),
+
+    /// Merge the JSON results of independent runs of the same simulation with
+    /// different seeds, as if a single run had simulated all of their events
+    Merge(MergeArgs),
+
+    /// Compare the matrix elements and their sums with an extended-precision
+    /// computation over a sample of generated events, to detect cancellations
+    /// which eat up the precision of Float
+    PrecisionCheck(PrecisionCheckArgs),
+
+    /// Compute the matrix elements with interval arithmetic over a sample of
+    /// generated events, to bound the floating-point rounding errors of the
+    /// spinor product chain
+    #[cfg(feature = "interval-check")]
+    IntervalCheck(IntervalCheckArgs),
+
+    /// Compare the matrix elements with an alternate computation, from Feynman
+    /// diagrams and independently built spinor products, over a sample of
+    /// generated events, to detect errors in the spinor product implementation
+    #[cfg(feature = "validate-me")]
+    ValidateMe(ValidateMeArgs),
+}
+
+/// Parameters of the collision energy scan mode
+#[derive(clap::Args)]
+pub struct ScanArgs {
+    /// Lowest collision energy at center of mass (GeV)
+    #[arg(long)]
+    pub from: Float,
+
+    /// Highest collision energy at center of mass (GeV)
+    #[arg(long)]
+    pub to: Float,
+
+    /// Collision energy step (GeV)
+    #[arg(long)]
+    pub step: Float,
+
+    /// File where the table of cross-sections should be written
+    #[arg(long, default_value = "res.scan")]
+    pub output: String,
+
+    /// Configuration of the simulation at each collision energy
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// Parameters of the self-test mode
+#[derive(clap::Args)]
+pub struct SelfTestArgs {
+    /// Number of events which pass the cuts to be checked
+    #[arg(long, default_value_t = 1000)]
+    pub events: usize,
+
+    /// Tolerance of the checks, relative to the amplitudes
+    #[arg(long, default_value_t = 1e6 * Float::EPSILON)]
+    pub tolerance: Float,
+
+    /// Configuration of the event generation
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// Parameters of the phase space test mode
+#[derive(clap::Args)]
+pub struct PhaseSpaceTestArgs {
+    /// Number of events to be generated by each generator
+    #[arg(long, default_value_t = 1_000_000)]
+    pub events: usize,
+
+    /// Tolerance of the checks, in standard deviations of the integrals
+    #[arg(long, default_value_t = 5.)]
+    pub tolerance: Float,
+
+    /// Configuration of the event generation
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// Parameters of the replay mode
+#[derive(clap::Args)]
+pub struct ReplayArgs {
+    /// File of phase space points, with one point per line: its phase space
+    /// weight, then the (Px, Py, Pz, E) 4-momentum of each outgoing particle
+    /// in the event record frame
+    #[arg(long)]
+    pub points: String,
+
+    /// Tolerance of the kinematic checks of the points, relative to the
+    /// collision energy
+    #[arg(long, default_value_t = 1e6 * Float::EPSILON)]
+    pub tolerance: Float,
+
+    /// Configuration of the simulation
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// Parameters of the matrix element service mode
+#[derive(clap::Args)]
+pub struct ServeArgs {
+    /// Tolerance of the kinematic checks of the requested phase space points,
+    /// relative to their collision energy
+    #[arg(long, default_value_t = 1e6 * Float::EPSILON)]
+    pub tolerance: Float,
+
+    /// Configuration of the matrix elements
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// Parameters of the extended-precision check mode
+#[derive(clap::Args)]
+pub struct PrecisionCheckArgs {
+    /// Number of events to be checked
+    #[arg(long, default_value_t = 1000)]
+    pub events: usize,
+
+    /// Also check events which fail the cuts, whose photons may be soft or
+    /// nearly collinear to the beam or to each other
+    #[arg(long)]
+    pub no_cuts: bool,
+
+    /// Configuration of the event generation
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// Parameters of the interval check mode
+#[cfg(feature = "interval-check")]
+#[derive(clap::Args)]
+pub struct IntervalCheckArgs {
+    /// Number of events which pass the cuts to be checked
+    #[arg(long, default_value_t = 1000)]
+    pub events: usize,
+
+    /// Configuration of the event generation
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// Parameters of the matrix element validation mode
+#[cfg(feature = "validate-me")]
+#[derive(clap::Args)]
+pub struct ValidateMeArgs {
+    /// Number of events which pass the cuts to be checked
+    #[arg(long, default_value_t = 1000)]
+    pub events: usize,
+
+    /// Tolerance of the comparison, relative to the magnitude of each
+    /// contribution
+    #[arg(long, default_value_t = 1e6 * Float::EPSILON)]
+    pub tolerance: Float,
+
+    /// Configuration of the event generation
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// Parameters of the reference comparison mode
+#[derive(clap::Args)]
+pub struct CompareArgs {
+    /// res.data file produced by the reference version
+    #[arg(long)]
+    pub reference: String,
+
+    /// Tolerance on numerical values, relative to the largest of the two
+    #[arg(long, default_value_t = 1e-5)]
+    pub rel_tolerance: Float,
+
+    /// Tolerance on numerical values, in absolute terms (useful for values
+    /// which should be zero)
+    #[arg(long, default_value_t = 0.)]
+    pub abs_tolerance: Float,
+
+    /// Configuration of the simulation
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// Parameters of the benchmark mode
+#[derive(clap::Args)]
+pub struct BenchArgs {
+    /// Number of events to be generated at each stage
+    #[arg(long, default_value_t = 1_000_000)]
+    pub events: usize,
+
+    /// Configuration of the event generation
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// Parameters of the configuration check mode
+#[derive(clap::Args)]
+pub struct CheckArgs {
+    /// Configuration to be checked
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// Parameters of the merge mode
+#[derive(clap::Args)]
+pub struct MergeArgs {
+    /// JSON results files of the runs to be merged
+    #[arg(required = true, num_args = 2..)]
+    pub inputs: Vec<String>,
+
+    /// Write the merged results to this file in JSON format
+    #[arg(long)]
+    pub json: Option<String>,
+
+    /// Write the merged histograms to this file in YODA format
+    #[arg(long)]
+    pub yoda: Option<String>,
+}
+
+/// Command-line configuration of the simulation
+#[derive(clap::Args)]
+#[command(group(ArgGroup::new("event_sink").args(["event_output", "stream"]).multiple(true)))]
+pub struct Args {
+    /// Configuration file (parsed as TOML if it has a .toml extension, and in
+    /// the strict `key value` legacy format if it has a .cfg extension)
+    #[arg(default_value = "valeurs")]
+    pub config: String,
+
+    /// Set a configuration entry, e.g. `event_cut.e_min=5` (can be repeated)
+    ///
+    /// Nested entries are designated by dotted keys, and values use the TOML
+    /// syntax. These assignments are applied before the other options.
+    ///
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub set: Vec<ConfigAssignment>,
+
+    /// Number of events to be simulated
+    #[arg(long)]
+    num_events: Option<usize>,
+
+    /// Collision energy at center of mass (GeV)
+    #[arg(long)]
+    e_total: Option<Float>,
+
+    /// Cut on maximum cosine of (beam, photons) angle
+    #[arg(long)]
+    beam_photons_cut: Option<Float>,
+
+    /// Cut on maximum cosine of (photon, photon) angle
+    #[arg(long)]
+    photon_photon_cut: Option<Float>,
+
+    /// Cut on minimum photon energy (GeV)
+    #[arg(long)]
+    e_min: Option<Float>,
+
+    /// Cut on minimum cosine of (beam, normal to the photon plane) angle
+    #[arg(long)]
+    beam_photon_plane_cut: Option<Float>,
+
+    /// Filter applied after all cuts, e.g. "min_photon_energy > 5 && max_cos_theta < 0.95"
+    #[arg(long)]
+    filter: Option<FilterExpression>,
+
+    /// Scheme in which the fine structure constant is evaluated
+    #[arg(long, value_enum)]
+    alpha_scheme: Option<AlphaScheme>,
+
+    /// Scheme in which the inputs of the electroweak sector are specified
+    #[arg(long, value_enum)]
+    electroweak_scheme: Option<ElectroweakScheme>,
+
+    /// Scheme in which the Z⁰ propagator is evaluated
+    #[arg(long, value_enum)]
+    z_lineshape: Option<ZLineshape>,
+
+    /// Simulate initial-state radiation
+    #[arg(long)]
+    isr: bool,
+
+    /// Standard deviation of the collision energy due to beam energy spread
+    /// (GeV)
+    #[arg(long)]
+    beam_spread: Option<Float>,
+
+    /// Ordering of the outgoing photons, which labels them in event outputs
+    /// and histograms
+    #[arg(long, value_enum)]
+    photon_ordering: Option<PhotonOrdering>,
+
+    /// Generator of flat phase space, used unless adaptive sampling is enabled
+    #[arg(long, value_enum)]
+    phase_space_generator: Option<PhaseSpaceGenerator>,
+
+    /// Longitudinal polarization of the electron beam, from -1 to +1
+    #[arg(long, allow_negative_numbers = true)]
+    electron_polarization: Option<Float>,
+
+    /// Longitudinal polarization of the positron beam, from -1 to +1
+    #[arg(long, allow_negative_numbers = true)]
+    positron_polarization: Option<Float>,
+
+    /// Random number generation engine
+    #[arg(long, value_enum)]
+    rng: Option<RandomEngine>,
+
+    /// Luxury level of the ranlux engine, from 0 (fastest) to 4 (least
+    /// correlated)
+    #[arg(long, value_name = "LEVEL")]
+    ranlux_luxury: Option<u8>,
+
+    /// Random number generator seed
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Write the results to this file in JSON format
+    #[arg(long)]
+    json: Option<String>,
+
+    /// Append a summary of the results to this file in YAML format
+    #[arg(long)]
+    summary_yaml: Option<String>,
+
+    /// Write the histograms to this file in YODA format, for use with Rivet
+    #[arg(long)]
+    yoda: Option<String>,
+
+    /// Write accepted events to this file
+    #[arg(long)]
+    event_output: Option<String>,
+
+    /// Format of the accepted event file (deduced from extension by default)
+    #[arg(long, value_enum, requires = "event_output", conflicts_with = "stream")]
+    event_format: Option<EventFileFormat>,
+
+    /// Stream accepted events in this format as they are generated, to the
+    /// standard output or to the --event-output file (e.g. a named pipe)
+    #[arg(long, value_name = "FORMAT", value_parser = stream_format_parser())]
+    stream: Option<EventFileFormat>,
+
+    /// Write unit-weight events, selected using the hit-or-miss method
+    #[arg(long, requires = "event_sink")]
+    unweighted: bool,
+
+    /// Periodically save the simulation state to this file
+    #[arg(long)]
+    checkpoint: Option<String>,
+
+    /// Minimal time between two checkpoints (seconds)
+    #[arg(long, requires = "checkpoint")]
+    checkpoint_interval: Option<u64>,
+
+    /// Check 4-momentum conservation and on-shellness of each generated event,
+    /// aborting on the first violation
+    #[arg(long)]
+    validate: bool,
+
+    /// Tolerance of the event checks, relative to the collision energy
+    #[arg(long, requires = "validate")]
+    validation_tolerance: Option<Float>,
+
+    /// Recompute the matrix elements of events where particles are nearly
+    /// collinear or soft with extended precision, and report how many were
+    #[arg(long)]
+    stability_guard: bool,
+
+    /// Invariant mass of a pair of particles, relative to the squared
+    /// collision energy, below which events are recomputed
+    #[arg(long, requires = "stability_guard")]
+    stability_threshold: Option<Float>,
+
+    /// Measure the time spent in each phase of the simulation, and display it
+    /// at the end
+    #[arg(long)]
+    timings: bool,
+
+    /// Number of simulation threads (one per CPU core by default,
+    /// multi-threaded builds only)
+    #[arg(long, value_name = "N")]
+    threads: Option<usize>,
+
+    /// Pin each simulation thread to a CPU core (multi-threaded builds only)
+    #[arg(long)]
+    pin_threads: bool,
+
+    /// Compute the Legendre moments of the photon angular distribution, up
+    /// to this order
+    #[arg(long)]
+    legendre_moments: Option<usize>,
+
+    /// Record the distribution of the event weights, and display it at the end
+    #[arg(long)]
+    weight_distribution: bool,
+
+    /// Display the spin density matrix of the anomalous contribution at the end
+    #[arg(long)]
+    spin_density: bool,
+
+    /// Write the first events whose weight is not finite or negative to this
+    /// file, in the JSON Lines format
+    #[arg(long, value_name = "PATH")]
+    bad_events: Option<String>,
+
+    /// Write the total cross-section and its error after each batch of events
+    /// to this CSV file
+    #[arg(long, value_name = "PATH")]
+    convergence: Option<String>,
+
+    /// Display the first N events which pass the cuts (momenta, angles
+    /// between photons, cut quantities and matrix element contributions)
+    /// before running the simulation
+    #[arg(long, value_name = "N")]
+    pub dump_events: Option<usize>,
+
+    /// Directory where the results of simulations are cached, so that an
+    /// identical run (same configuration, seed and code version) reuses them
+    /// instead of simulating again
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<String>,
+
+    /// Directory where the output files should be written (created if need
+    /// be), against which relative output paths are resolved
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<String>,
+
+    /// Name of the res.data, res.times and res.histos output files, without
+    /// extension (e.g. "{tag}_{seed}_{date}", see README.md)
+    #[arg(long, value_name = "TEMPLATE")]
+    output_name: Option<String>,
+
+    /// Format of the res.data output file
+    #[arg(long, value_enum)]
+    data_format: Option<DataFormat>,
+
+    /// Label of the run, which replaces {tag} in output file names
+    #[arg(long)]
+    tag: Option<String>,
+
+    /// Resume an interrupted simulation from this checkpoint file
+    ///
+    /// The configuration must be the same as that of the interrupted run.
+    /// Unless configured otherwise, checkpoints keep being saved to this file.
+    ///
+    #[arg(long)]
+    pub resume: Option<String>,
+}
+//
+impl Args {
+    /// Apply the configuration overrides from the command line
+    pub fn apply_overrides(&self, cfg: &mut Configuration) {
+        /// Overwrite a configuration entry if a replacement value was provided
+        fn set<T: Copy>(target: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *target = value;
+            }
+        }
+        set(&mut cfg.num_events, self.num_events);
+        set(&mut cfg.e_total, self.e_total);
+        set(&mut cfg.event_cut.beam_photons_cut, self.beam_photons_cut);
+        set(&mut cfg.event_cut.photon_photon_cut, self.photon_photon_cut);
+        set(&mut cfg.event_cut.e_min, self.e_min);
+        let beam_phpl_cut = &mut cfg.event_cut.beam_photon_plane_cut;
+        set(beam_phpl_cut, self.beam_photon_plane_cut);
+        if self.filter.is_some() {
+            cfg.event_cut.filter = self.filter.clone();
+        }
+        set(&mut cfg.alpha_scheme, self.alpha_scheme);
+        set(&mut cfg.electroweak_scheme, self.electroweak_scheme);
+        set(&mut cfg.z_lineshape, self.z_lineshape);
+        cfg.isr |= self.isr;
+        cfg.timings |= self.timings;
+        cfg.weight_distribution |= self.weight_distribution;
+        cfg.spin_density |= self.spin_density;
+        if self.threads.is_some() {
+            cfg.threads = self.threads;
+        }
+        cfg.pin_threads |= self.pin_threads;
+        if self.beam_spread.is_some() {
+            cfg.beam_spread = self.beam_spread;
+        }
+        set(&mut cfg.photon_ordering, self.photon_ordering);
+        set(&mut cfg.phase_space_generator, self.phase_space_generator);
+        set(&mut cfg.electron_polarization, self.electron_polarization);
+        set(&mut cfg.positron_polarization, self.positron_polarization);
+        set(&mut cfg.rng, self.rng);
+        set(&mut cfg.ranlux_luxury, self.ranlux_luxury);
+        if self.seed.is_some() {
+            cfg.seed = self.seed;
+        }
+        if self.cache_dir.is_some() {
+            cfg.cache_dir = self.cache_dir.clone();
+        }
+        if self.output_dir.is_some() {
+            cfg.output_dir = self.output_dir.clone();
+        }
+        if self.output_name.is_some() {
+            cfg.output_name = self.output_name.clone();
+        }
+        set(&mut cfg.data_format, self.data_format);
+        if self.tag.is_some() {
+            cfg.tag = self.tag.clone();
+        }
+        if self.json.is_some() {
+            cfg.json_output = self.json.clone();
+        }
+        if self.summary_yaml.is_some() {
+            cfg.summary_yaml = self.summary_yaml.clone();
+        }
+        if self.yoda.is_some() {
+            cfg.yoda_output = self.yoda.clone();
+        }
+        if self.legendre_moments.is_some() {
+            cfg.legendre_moments = self.legendre_moments;
+        }
+        if self.bad_events.is_some() {
+            cfg.bad_events_output = self.bad_events.clone();
+        }
+        if self.convergence.is_some() {
+            cfg.convergence_output = self.convergence.clone();
+        }
+        if self.event_output.is_some() || self.stream.is_some() {
+            cfg.event_output = Some(EventOutputConfig {
+                path: self
+                    .event_output
+                    .clone()
+                    .unwrap_or_else(|| EventOutputConfig::STDOUT.to_owned()),
+                format: self.stream.or(self.event_format),
+                stream: self.stream.is_some(),
+                unweighting: self.unweighted.then(UnweightingConfig::default),
+            });
+        }
+        if self.validate {
+            cfg.validation = Some(ValidationConfig {
+                tolerance: self
+                    .validation_tolerance
+                    .unwrap_or(ValidationConfig::DEFAULT_TOLERANCE),
+            });
+        }
+        if self.stability_guard {
+            cfg.stability_guard = Some(StabilityGuardConfig {
+                threshold: self
+                    .stability_threshold
+                    .unwrap_or(StabilityGuardConfig::DEFAULT_THRESHOLD),
+            });
+        }
+        if let Some(path) = &self.checkpoint {
+            cfg.checkpoint = Some(CheckpointConfig {
+                path: path.clone(),
+                interval: self
+                    .checkpoint_interval
+                    .unwrap_or(CheckpointConfig::DEFAULT_INTERVAL),
+            });
+        } else if let (Some(path), None) = (&self.resume, &cfg.checkpoint) {
+            // Keep saving checkpoints to the resumed file, wherever the output
+            // directory is
+            let path = std::path::absolute(path)
+                .map_or_else(|_| path.clone(), |path| path.to_string_lossy().into_owned());
+            cfg.checkpoint = Some(CheckpointConfig {
+                path,
+                interval: CheckpointConfig::DEFAULT_INTERVAL,
+            });
+        }
+    }
+}
+
+/// Parser of the --stream option, which only accepts the event formats that
+/// can be streamed
+fn stream_format_parser() -> impl TypedValueParser<Value = EventFileFormat> {
+    let streamable = (EventFileFormat::value_variants().iter())
+        .filter(|format| format.streamable())
+        .filter_map(EventFileFormat::to_possible_value);
+    PossibleValuesParser::new(streamable).map(|name| {
+        EventFileFormat::from_str(&name, false).expect("Possible values are valid formats")
+    })
+}
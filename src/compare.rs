@@ -0,0 +1,209 @@
+//! Reference comparison mode, which checks that the results of this version
+//! agree with those of another version of 3photons (e.g. Fortran or C++)
+
+use crate::cli::CompareArgs;
+use anyhow::{ensure, Context, Result};
+use log::info;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::Instant,
+};
+use trois_photons::{
+    config::Configuration,
+    numeric::Float,
+    output::{self, resdata::DataFormat},
+    random::RandomEngine,
+    setup_event_generation, simulate,
+};
+
+/// Run the simulation like the original 3photons would, then compare the
+/// resulting res.data file with the reference one
+pub fn run(args: &CompareArgs) -> Result<()> {
+    // Load the configuration, forcing the original random number generator
+    // (whose default seed is that of the original code) and res.data format
+    let mut cfg = Configuration::load_with_overlay(&args.args.config, &args.args.set, |cfg| {
+        args.args.apply_overrides(cfg);
+        cfg.rng = RandomEngine::Ranf;
+        cfg.data_format = DataFormat::Legacy;
+    })
+    .context("Failed to load the configuration")?;
+    let reference = fs::read_to_string(&args.reference)
+        .with_context(|| format!("Failed to read the reference file {}", args.reference))?;
+
+    // Write the results to a scratch directory, so that they cannot overwrite
+    // a reference file in the output directory
+    let scratch_dir = ScratchDir::new().context("Failed to create a scratch directory")?;
+    cfg.output_dir = Some(scratch_dir.path().to_string_lossy().into_owned());
+
+    // Run the simulation and output the results, as in the default mode
+    let start_time = Instant::now();
+    let (process, evgen) =
+        setup_event_generation(&cfg).context("Failed to set up event generation")?;
+    info!("IBegin");
+    let res = simulate(&cfg, &*process, &evgen, None, None, None)
+        .context("Failed to run the simulation")?;
+    output::dump_results(&cfg, &res, start_time.elapsed())
+        .context("Failed to output the results")?;
+    let results =
+        fs::read_to_string(cfg.results_path("data")).context("Failed to read back res.data")?;
+
+    // Compare the two files line by line. The largest relative difference is
+    // only meaningful if all lines could be compared numerically.
+    let (results_lines, reference_lines) = (lines(&results), lines(&reference));
+    println!();
+    let mut mismatches = 0;
+    let mut max_rel_diff = Some(0.);
+    let num_lines = results_lines.len().max(reference_lines.len());
+    for idx in 0..num_lines {
+        match (results_lines.get(idx), reference_lines.get(idx)) {
+            (Some(&(line_idx, result)), Some(&(_, expected))) => {
+                let comparison = compare_line(result, expected, args);
+                max_rel_diff = max_rel_diff
+                    .zip(comparison.rel_diff)
+                    .map(|(max, rel_diff): (Float, Float)| max.max(rel_diff));
+                if let Some(reason) = comparison.mismatch {
+                    mismatches += 1;
+                    println!("Line {:<3}: {reason}", line_idx + 1);
+                    println!("  result    : {}", result.trim());
+                    println!("  reference : {}", expected.trim());
+                }
+            }
+            (Some(&(line_idx, result)), None) => {
+                mismatches += 1;
+                max_rel_diff = None;
+                println!("Line {:<3}: Missing from the reference", line_idx + 1);
+                println!("  result    : {}", result.trim());
+            }
+            (None, Some(&(line_idx, expected))) => {
+                mismatches += 1;
+                max_rel_diff = None;
+                println!("Line {:<3}: Missing from res.data", line_idx + 1);
+                println!("  reference : {}", expected.trim());
+            }
+            (None, None) => unreachable!("Line index beyond both files"),
+        }
+    }
+
+    // Report the outcome
+    match max_rel_diff {
+        Some(max_rel_diff) => println!("Max. relative diff.: {max_rel_diff:.3e}"),
+        None => println!("Max. relative diff.: n/a"),
+    }
+    ensure!(
+        mismatches == 0,
+        "{mismatches} line(s) of res.data disagree with the reference {}",
+        args.reference
+    );
+    println!("Results agree with the reference");
+    Ok(())
+}
+
+/// Non-empty lines of a res.data file, along with their index
+///
+/// The blank lines which separate the blocks of per-spin results are ignored,
+/// since not all versions of 3photons emit them consistently.
+///
+fn lines(contents: &str) -> Vec<(usize, &str)> {
+    (contents.lines().enumerate())
+        .filter(|(_, line)| !line.trim().is_empty())
+        .collect()
+}
+
+/// Outcome of the comparison of a line of res.data with its reference
+struct LineComparison {
+    /// Largest relative difference between numerical tokens, if the line
+    /// could be compared numerically
+    rel_diff: Option<Float>,
+
+    /// Reason why the line disagrees with its reference, if it does
+    mismatch: Option<String>,
+}
+
+/// Compare a line of res.data with its reference, token by token
+///
+/// Numerical tokens must agree within the tolerances, other tokens (such as
+/// the labels of key-value entries) must be identical. The largest relative
+/// difference between numerical tokens is measured over the whole line, even
+/// when some of them disagree, but it is undefined if the line has different
+/// fields than its reference.
+///
+fn compare_line(result: &str, expected: &str, args: &CompareArgs) -> LineComparison {
+    let (result_tokens, expected_tokens) = (tokens(result), tokens(expected));
+    if result_tokens.len() != expected_tokens.len() {
+        return LineComparison {
+            rel_diff: None,
+            mismatch: Some("Different number of fields".to_owned()),
+        };
+    }
+    let mut comparison = LineComparison {
+        rel_diff: Some(0.),
+        mismatch: None,
+    };
+    for (result, expected) in result_tokens.into_iter().zip(expected_tokens) {
+        match (parse_number(result), parse_number(expected)) {
+            (Some(value), Some(reference)) => {
+                let diff = (value - reference).abs();
+                let scale = value.abs().max(reference.abs());
+                if diff > args.abs_tolerance.max(args.rel_tolerance * scale) {
+                    (comparison.mismatch)
+                        .get_or_insert_with(|| format!("{value} differs from {reference}"));
+                }
+                if scale > 0. {
+                    comparison.rel_diff = comparison.rel_diff.map(|max| max.max(diff / scale));
+                }
+            }
+            _ if result == expected => {}
+            _ => {
+                comparison.rel_diff = None;
+                (comparison.mismatch)
+                    .get_or_insert_with(|| format!("\"{result}\" differs from \"{expected}\""));
+            }
+        }
+    }
+    comparison
+}
+
+/// Split a line of res.data into whitespace-separated tokens, treating the
+/// colon which separates the key and value of an entry as a token of its own
+fn tokens(line: &str) -> Vec<&str> {
+    match line.split_once(':') {
+        Some((key, value)) => (key.split_whitespace())
+            .chain(std::iter::once(":"))
+            .chain(value.split_whitespace())
+            .collect(),
+        None => line.split_whitespace().collect(),
+    }
+}
+
+/// Parse a numerical token, accepting the D exponent marker of Fortran
+fn parse_number(token: &str) -> Option<Float> {
+    token.replace(['D', 'd'], "e").parse().ok()
+}
+
+/// Temporary directory, which is removed along with its contents when dropped
+struct ScratchDir(PathBuf);
+//
+impl ScratchDir {
+    /// Create a directory of the system temporary directory, whose name is
+    /// specific to this process
+    fn new() -> std::io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("3photons-compare-{}", process::id()));
+        fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+
+    /// Path of the directory
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+//
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        // Failing to clean up leaves some files behind, which is not worth
+        // failing the comparison over
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -1,11 +1,209 @@
 //! Mechanism for loading and sharing the simulation configuration
 
-use crate::{evcut::EventCut, numeric::Float, Result};
-use anyhow::{ensure, format_err, Context, Error};
-use std::{fmt::Display, fs::File, io::Read, str::FromStr};
+use crate::{
+    asymmetry::AsymmetryConfig,
+    checkpoint::CheckpointConfig,
+    coupling::{AlphaScheme, ElectroweakScheme, ZLineshape},
+    detector::DetectorConfig,
+    error::ensure,
+    evcut::{self, EventCut},
+    event::{ValidationConfig, NUM_OUTGOING},
+    evgen::{PhaseSpaceGenerator, PhotonOrdering},
+    histogram::DistributionConfig,
+    numeric::Float,
+    output::{compression::Compression, events::EventOutputConfig, resdata::DataFormat},
+    process::DEFAULT_PROCESS,
+    random::{RandomEngine, RanluxGenerator},
+    resampling::ResamplingConfig,
+    resfin::CouplingGridConfig,
+    scheduling::EVENT_BATCH_SIZE,
+    stability::StabilityGuardConfig,
+    systematics::SystematicsConfig,
+    vegas::AdaptiveSamplingConfig,
+    Error, Result,
+};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Formats in which the simulation configuration can be specified
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConfigFormat {
+    /// Legacy positional format of 3photons
+    Legacy,
+
+    /// Legacy format with named entries, one `key value` pair per line, which
+    /// detects misplaced, unknown and duplicate entries
+    StrictLegacy,
+
+    /// TOML format with named keys
+    Toml,
+}
+//
+impl ConfigFormat {
+    /// Format that the extension of a configuration file suggests
+    ///
+    /// Files with a `.toml` extension are parsed as TOML, files with a `.cfg`
+    /// extension use the strict legacy format, and other files are assumed to
+    /// use the legacy positional format.
+    ///
+    pub fn of_file(path: &Path) -> Self {
+        let has_extension = |expected: &str| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(expected))
+        };
+        if has_extension("toml") {
+            Self::Toml
+        } else if has_extension("cfg") {
+            Self::StrictLegacy
+        } else {
+            Self::Legacy
+        }
+    }
+}
+
+/// Fermi constant (GeV^-2), as quoted by the Particle Data Group
+const DEFAULT_G_FERMI: Float = 1.166_378_8e-5;
+
+/// W boson mass (GeV), as quoted by the Particle Data Group
+const DEFAULT_M_W: Float = 80.369_2;
+
+/// Key of the TOML configuration entry which lists the included files
+const INCLUDE_KEY: &str = "include";
+
+/// Error message for ill-typed include entries
+const INCLUDE_TYPE_ERROR: &str = "The include entry must be a file name or an array of file names";
+
+/// Keys of the entries of the legacy formats, in positional order
+const LEGACY_KEYS: [&str; 18] = [
+    "num_events",
+    "e_total",
+    "beam_photons_cut",
+    "photon_photon_cut",
+    "e_min",
+    "beam_photon_plane_cut",
+    "alpha",
+    "alpha_z",
+    "gev2_to_picobarn",
+    "m_z0",
+    "g_z0",
+    "sin2_weinberg",
+    "branching_ep_em",
+    "beta_plus",
+    "beta_minus",
+    "num_bins",
+    "impr",
+    "plot",
+];
+
+/// Non-blank lines of a configuration in a legacy format, with their 1-based
+/// line numbers
+fn numbered_lines(config_str: &str) -> impl Iterator<Item = (usize, &str)> {
+    (1..)
+        .zip(config_str.lines())
+        .filter(|(_, text)| !text.trim().is_empty())
+}
+
+/// Assignment of a value to a configuration entry, which can be parsed from a
+/// `key=value` string
+///
+/// Nested entries are designated by dotted keys (e.g. `event_cut.e_min`), and
+/// values use the TOML syntax, except that strings may be left unquoted.
+///
+#[derive(Clone, Debug)]
+pub struct ConfigAssignment {
+    /// Path of the configuration entry, as a sequence of nested keys
+    keys: Vec<String>,
+
+    /// Value to be assigned
+    value: toml::Value,
+}
+//
+impl ConfigAssignment {
+    /// Assign the value to the configuration entry in a TOML table, creating
+    /// the enclosing tables if need be
+    fn apply(&self, table: &mut toml::Table) -> Result<()> {
+        let (last_key, parent_keys) = self.keys.split_last().expect("Keys cannot be empty");
+        let mut table = table;
+        for (depth, key) in parent_keys.iter().enumerate() {
+            let entry = table
+                .entry(key.clone())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            let toml::Value::Table(inner) = entry else {
+                return Err(Error::InvalidConfig(format!(
+                    "Configuration entry {} is not a table",
+                    self.keys[..=depth].join(".")
+                )));
+            };
+            table = inner;
+        }
+        table.insert(last_key.clone(), self.value.clone());
+        Ok(())
+    }
+}
+//
+impl FromStr for ConfigAssignment {
+    type Err = Error;
+
+    fn from_str(assignment: &str) -> Result<Self> {
+        let Some((key, value)) = assignment.split_once('=') else {
+            return Err(Error::InvalidConfig(format!(
+                "Configuration assignment {assignment:?} should have the form key=value"
+            )));
+        };
+        let keys = key
+            .trim()
+            .split('.')
+            .map(|key| key.trim().to_owned())
+            .collect::<Vec<_>>();
+        ensure!(
+            keys.iter().all(|key| !key.is_empty()),
+            InvalidConfig,
+            "Configuration assignment {assignment:?} has an empty key"
+        );
+        let value = value.trim();
+        let value = toml::from_str::<toml::Table>(&format!("value = {value}"))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| toml::Value::String(value.to_owned()));
+        Ok(Self { keys, value })
+    }
+}
+
+/// Merge a TOML table into another, the entries of the former taking
+/// precedence over those of the latter, except that nested tables are merged
+fn merge_tables(target: &mut toml::Table, source: toml::Table) {
+    for (key, value) in source {
+        match (target.get_mut(&key), value) {
+            (Some(toml::Value::Table(target_table)), toml::Value::Table(source_table)) => {
+                merge_tables(target_table, source_table);
+            }
+            (_, value) => {
+                target.insert(key, value);
+            }
+        }
+    }
+}
 
 /// Simulation configuration
+///
+/// Can be loaded either from the legacy positional file format of 3photons, or
+/// from a TOML file with named keys where missing keys take the value that is
+/// featured in the reference `valeurs` file.
+///
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Configuration {
+    /// Name of the simulated process, among those of the process registry
+    /// (not in the legacy format)
+    pub process: String,
+
     /// Number of events to be simulated
     pub num_events: usize,
 
@@ -18,9 +216,29 @@ pub struct Configuration {
     /// Fine structure constant
     pub alpha: Float,
 
+    /// Scheme in which the fine structure constant of the Standard Model
+    /// contribution is evaluated (not in the legacy format)
+    pub alpha_scheme: AlphaScheme,
+
     /// Fine structure constant at the Z⁰ mass peak
     pub alpha_z: Float,
 
+    /// Scheme in which the inputs of the electroweak sector are specified
+    /// (not in the legacy format)
+    ///
+    /// Outside of the default scheme, alpha_z and sin2_weinberg are deduced
+    /// from the other inputs when the configuration is loaded.
+    ///
+    pub electroweak_scheme: ElectroweakScheme,
+
+    /// Fermi constant (GeV^-2), used by the (G_F, M_Z, M_W) electroweak
+    /// scheme (not in the legacy format)
+    pub g_fermi: Float,
+
+    /// W boson mass (GeV), used by the (G_F, M_Z, M_W) electroweak scheme
+    /// (not in the legacy format)
+    pub m_w: Float,
+
     /// Conversion factor from GeV^(-2) to pb
     pub gev2_to_picobarn: Float,
 
@@ -30,6 +248,10 @@ pub struct Configuration {
     /// Z⁰ boson width (GeV)
     pub g_z0: Float,
 
+    /// Scheme in which the Z⁰ propagator is evaluated (not in the legacy
+    /// format)
+    pub z_lineshape: ZLineshape,
+
     /// Square sine of Weinberg's Theta
     pub sin2_weinberg: Float,
 
@@ -42,46 +264,812 @@ pub struct Configuration {
     /// Beta - (???)
     pub beta_minus: Float,
 
-    /// Number of histogram bins (UNUSED)
-    num_bins: i32,
+    /// Number of histogram bins
+    pub num_bins: usize,
 
     /// Whether intermediary results should be displayed (UNUSED)
     impr: bool,
 
-    /// Whether results should be plotted in a histogram (UNUSED)
-    plot: bool,
+    /// Whether histograms of the photon distributions should be produced
+    pub plot: bool,
+
+    /// Random number generation engine (not in the legacy format)
+    pub rng: RandomEngine,
+
+    /// Luxury level of the ranlux engine, from 0 (fastest) to 4 (least
+    /// correlated), as in the original RANLUX (not in the legacy format)
+    pub ranlux_luxury: u8,
+
+    /// Random number generator seed, if not the engine's default (not in the
+    /// legacy format)
+    pub seed: Option<u64>,
+
+    /// Differential distributions to be computed if histogramming is enabled
+    /// (not in the legacy format, enables histogramming if non-empty)
+    pub distributions: Vec<DistributionConfig>,
+
+    /// Directory where the output files should be written, if not the
+    /// working directory (not in the legacy format)
+    ///
+    /// Relative paths of the other output files are resolved against it, and
+    /// it is created when the configuration is loaded if it does not exist.
+    ///
+    pub output_dir: Option<String>,
+
+    /// Name of the res.data, res.times and res.histos output files, without
+    /// extension, if not "res" (not in the legacy format)
+    pub output_name: Option<String>,
+
+    /// Format of the res.data output file (not in the legacy format)
+    pub data_format: DataFormat,
+
+    /// Label of the run, which output file names can feature (not in the
+    /// legacy format)
+    pub tag: Option<String>,
+
+    /// File where results should be written in JSON format, if any (not in
+    /// the legacy format)
+    pub json_output: Option<String>,
+
+    /// File where a summary of the results should be appended in YAML format,
+    /// if any (not in the legacy format)
+    pub summary_yaml: Option<String>,
+
+    /// File where the histograms should be written in YODA format, if any (not
+    /// in the legacy format, requires histogramming)
+    pub yoda_output: Option<String>,
+
+    /// Output of accepted events, if any (not in the legacy format)
+    pub event_output: Option<EventOutputConfig>,
+
+    /// Periodic checkpointing of the simulation state, if any (not in the
+    /// legacy format)
+    pub checkpoint: Option<CheckpointConfig>,
+
+    /// Grid of anomalous couplings over which the results should be
+    /// reweighted, if any (not in the legacy format)
+    pub coupling_grid: Option<CouplingGridConfig>,
+
+    /// Adaptive importance sampling of the event generator, if any (not in
+    /// the legacy format)
+    pub adaptive_sampling: Option<AdaptiveSamplingConfig>,
+
+    /// Uncertainties on the electroweak parameters, to be propagated to the
+    /// total cross-section, if any (not in the legacy format)
+    pub systematics: Option<SystematicsConfig>,
+
+    /// Resampling of event batches, to estimate the uncertainty of derived
+    /// quantities, if any (not in the legacy format)
+    pub resampling: Option<ResamplingConfig>,
+
+    /// Checks of the kinematics of each generated event, if any (not in the
+    /// legacy format)
+    pub validation: Option<ValidationConfig>,
+
+    /// Recomputation of numerically unstable events with extended precision,
+    /// if any (not in the legacy format)
+    pub stability_guard: Option<StabilityGuardConfig>,
+
+    /// Angular asymmetries of the photons to be computed, if any (not in the
+    /// legacy format)
+    pub asymmetries: Option<AsymmetryConfig>,
+
+    /// Highest order of the Legendre moments of the photon angular
+    /// distribution to be computed, if any (not in the legacy format)
+    pub legendre_moments: Option<usize>,
+
+    /// Whether the distribution of the event weights should be recorded and
+    /// displayed (not in the legacy format)
+    pub weight_distribution: bool,
+
+    /// Whether the spin density matrix of the anomalous contribution should
+    /// be displayed (not in the legacy format)
+    pub spin_density: bool,
+
+    /// File where the first events whose weight is not finite or negative
+    /// should be written, if any (not in the legacy format)
+    pub bad_events_output: Option<String>,
+
+    /// File where the total cross-section after each batch of events should
+    /// be written, if any (not in the legacy format)
+    pub convergence_output: Option<String>,
+
+    /// Directory where the results of simulations are cached, so that
+    /// identical runs reuse them instead of simulating again, if any (not in
+    /// the legacy format)
+    pub cache_dir: Option<String>,
+
+    /// Whether the time spent in each phase of the simulation should be
+    /// measured and displayed (not in the legacy format)
+    pub timings: bool,
+
+    /// Number of simulation threads, if not one per CPU core (multi-threaded
+    /// builds only, not in the legacy format)
+    pub threads: Option<usize>,
+
+    /// Whether each simulation thread should be pinned to a CPU core
+    /// (multi-threaded builds only, not in the legacy format)
+    pub pin_threads: bool,
+
+    /// Whether initial-state radiation should be simulated (not in the legacy
+    /// format)
+    pub isr: bool,
+
+    /// Standard deviation of the collision energy (GeV), if it should be
+    /// smeared event by event to simulate the energy spread of the beams (not
+    /// in the legacy format)
+    pub beam_spread: Option<Float>,
+
+    /// Simulation of the detector, whose measurements of the photons the cuts
+    /// apply to, if enabled (not in the legacy format)
+    pub detector: Option<DetectorConfig>,
+
+    /// Ordering of the outgoing photons, which labels them in event outputs
+    /// and histograms (not in the legacy format)
+    pub photon_ordering: PhotonOrdering,
+
+    /// Generator of flat phase space, which is used unless adaptive sampling
+    /// is enabled (not in the legacy format)
+    pub phase_space_generator: PhaseSpaceGenerator,
+
+    /// Longitudinal polarization of the electron beam, from -1 (left-handed)
+    /// to +1 (right-handed) (not in the legacy format)
+    pub electron_polarization: Float,
+
+    /// Longitudinal polarization of the positron beam, from -1 (left-handed)
+    /// to +1 (right-handed) (not in the legacy format)
+    pub positron_polarization: Float,
 }
 //
 impl Configuration {
-    /// Load the configuration from a file, check it, and print it out
-    pub fn load(file_name: &str) -> Result<Self> {
+    /// Load the configuration from a file, apply overrides, check it, and
+    /// print it out
+    ///
+    /// The format of the file is deduced from its extension, as described in
+    /// [`ConfigFormat::of_file()`].
+    ///
+    pub fn load(file_name: &str, overrides: impl FnOnce(&mut Self)) -> Result<Self> {
+        Self::load_with_overlay(file_name, &[], overrides)
+    }
+
+    /// Load the configuration from a file, assign some of its entries, apply
+    /// overrides, check it, and print it out
+    ///
+    /// The assignments are applied in order, before the overrides, so that
+    /// individual runs can share a base configuration file and only change a
+    /// few of its entries.
+    ///
+    pub fn load_with_overlay(
+        file_name: &str,
+        overlay: &[ConfigAssignment],
+        overrides: impl FnOnce(&mut Self),
+    ) -> Result<Self> {
         // Read out the simulation's configuration file or die trying.
-        let config_str = {
-            let mut config_file = File::open(file_name)?;
-            let mut buffer = String::new();
-            config_file.read_to_string(&mut buffer)?;
-            buffer
+        let path = Path::new(file_name);
+        let config_str = fs::read_to_string(path)?;
+
+        // Decode the configuration using the format that the extension
+        // suggests, resolving includes relative to the configuration file
+        let format = ConfigFormat::of_file(path);
+        debug!("Loading the configuration from {file_name} ({format:?} format)");
+        let mut config = match format {
+            ConfigFormat::Toml => {
+                let base_dir = path.parent().unwrap_or(Path::new(""));
+                let mut include_stack = vec![fs::canonicalize(path)?];
+                Self::parse_toml(&config_str, base_dir, &mut include_stack)?
+            }
+            ConfigFormat::Legacy => Self::parse_legacy(&config_str)?,
+            ConfigFormat::StrictLegacy => Self::parse_strict_legacy(&config_str)?,
+        };
+
+        // Assign the requested entries
+        if !overlay.is_empty() {
+            config = config.with_overlay(overlay)?;
+        }
+        config.finish_loading(overrides)
+    }
+
+    /// Parse the configuration from a string, apply overrides, check it, and
+    /// print it out
+    ///
+    /// Files included by a TOML configuration are looked up relative to the
+    /// current directory.
+    ///
+    pub fn parse(
+        config_str: &str,
+        format: ConfigFormat,
+        overrides: impl FnOnce(&mut Self),
+    ) -> Result<Self> {
+        let config = match format {
+            ConfigFormat::Toml => Self::parse_toml(config_str, Path::new(""), &mut Vec::new())?,
+            ConfigFormat::Legacy => Self::parse_legacy(config_str)?,
+            ConfigFormat::StrictLegacy => Self::parse_strict_legacy(config_str)?,
+        };
+        config.finish_loading(overrides)
+    }
+
+    /// Apply overrides to a freshly decoded configuration, print it out, and
+    /// check it
+    fn finish_loading(self, overrides: impl FnOnce(&mut Self)) -> Result<Self> {
+        let mut config = self;
+
+        // Apply configuration overrides (e.g. from the command line)
+        overrides(&mut config);
+        config.resolve_output_paths()?;
+        config.derive_electroweak_parameters()?;
+
+        // Display it the way the C++ version used to (this eases comparisons)
+        info!("{}", config.to_string().trim_end());
+
+        // Check that it makes sense
+        config.check()?;
+        config.create_output_dir()?;
+
+        // If nothing bad occured, we can now return the configuration
+        Ok(config)
+    }
+
+    /// Start building a configuration programmatically, from the values of the
+    /// reference `valeurs` file
+    pub fn builder() -> ConfigurationBuilder {
+        ConfigurationBuilder::default()
+    }
+
+    /// Placeholders which output file names can feature, with their values
+    /// (if available)
+    ///
+    /// The date and time are those at which the configuration is loaded, so
+    /// that all output files of a run agree.
+    ///
+    fn output_placeholders(&self) -> [(&'static str, Option<String>); 4] {
+        let now = chrono::Local::now();
+        [
+            ("tag", self.tag.clone()),
+            ("seed", Some(self.rng.effective_seed(self.seed).to_string())),
+            ("date", Some(now.format("%Y-%m-%d").to_string())),
+            ("time", Some(now.format("%H-%M-%S").to_string())),
+        ]
+    }
+
+    /// Substitute the placeholders of the output directory and file names,
+    /// and resolve relative output paths against the output directory
+    fn resolve_output_paths(&mut self) -> Result<()> {
+        let placeholders = self.output_placeholders();
+        let expand = |template: &str| expand_placeholders(template, &placeholders);
+        self.output_dir = self.output_dir.as_deref().map(expand).transpose()?;
+        self.output_name = self.output_name.as_deref().map(expand).transpose()?;
+        let output_dir = self.output_dir.clone();
+        let resolve = |path: &mut String| -> Result<()> {
+            let expanded = expand(path)?;
+            *path = match &output_dir {
+                Some(dir) => Path::new(dir).join(expanded).to_string_lossy().into_owned(),
+                None => expanded,
+            };
+            Ok(())
+        };
+        let output_paths = [
+            &mut self.json_output,
+            &mut self.summary_yaml,
+            &mut self.yoda_output,
+            &mut self.bad_events_output,
+            &mut self.convergence_output,
+        ];
+        for path in output_paths.into_iter().flatten() {
+            resolve(path)?;
+        }
+        if let Some(event_output) = &mut self.event_output {
+            if event_output.path != EventOutputConfig::STDOUT {
+                resolve(&mut event_output.path)?;
+            }
+        }
+        if let Some(checkpoint) = &mut self.checkpoint {
+            resolve(&mut checkpoint.path)?;
+        }
+        if let Some(grid) = &mut self.coupling_grid {
+            resolve(&mut grid.path)?;
+        }
+        Ok(())
+    }
+
+    /// Create the output directory, if any and if it does not exist yet
+    fn create_output_dir(&self) -> Result<()> {
+        if let Some(dir) = &self.output_dir {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Path of a file in the output directory
+    pub fn output_path(&self, file_name: &str) -> PathBuf {
+        match &self.output_dir {
+            Some(dir) => Path::new(dir).join(file_name),
+            None => PathBuf::from(file_name),
+        }
+    }
+
+    /// Path of the res.data, res.times or res.histos output file, given its
+    /// extension
+    pub fn results_path(&self, extension: &str) -> PathBuf {
+        let name = self.output_name.as_deref().unwrap_or("res");
+        self.output_path(&format!("{name}.{extension}"))
+    }
+
+    /// Whether the collision energy varies from one event to the next, due to
+    /// initial-state radiation or beam energy spread
+    pub fn varying_collision_energy(&self) -> bool {
+        self.isr || self.beam_spread.is_some()
+    }
+
+    /// Deduce the fine structure constant at the Z⁰ mass peak and the squared
+    /// sine of the Weinberg angle from the inputs of the configured electroweak
+    /// scheme, and report them
+    fn derive_electroweak_parameters(&mut self) -> Result<()> {
+        let scheme = self.electroweak_scheme;
+        if scheme == ElectroweakScheme::AlphaMz {
+            return Ok(());
+        }
+        ensure!(
+            self.g_fermi > 0. && self.g_fermi.is_finite(),
+            InvalidConfig,
+            "g_fermi must be a positive number (got {})",
+            self.g_fermi
+        );
+        ensure!(
+            self.m_w > 0. && self.m_w < self.m_z0,
+            InvalidConfig,
+            "m_w must be positive and below m_z0 = {} GeV, or the Weinberg angle would be \
+             unphysical (got {})",
+            self.m_z0,
+            self.m_w
+        );
+        (self.alpha_z, self.sin2_weinberg) = scheme.parameters(self);
+        info!(
+            "Electroweak inputs ({} scheme): 1/alpha_z = {}, sin2_weinberg = {}",
+            scheme.name(),
+            1. / self.alpha_z,
+            self.sin2_weinberg
+        );
+        Ok(())
+    }
+
+    /// Check that the configuration makes sense
+    fn check(&self) -> Result<()> {
+        // A sensible simulation must run for at least one event
+        ensure!(
+            self.num_events > 0,
+            InvalidConfig,
+            "Please simulate at least one event"
+        );
+
+        // Physical parameters must be finite and in their physical range
+        for (key, value) in [
+            ("e_total", self.e_total),
+            ("alpha", self.alpha),
+            ("alpha_z", self.alpha_z),
+            ("gev2_to_picobarn", self.gev2_to_picobarn),
+            ("m_z0", self.m_z0),
+            ("g_z0", self.g_z0),
+        ] {
+            ensure!(
+                value > 0. && value.is_finite(),
+                InvalidConfig,
+                "{key} must be a positive number (got {value})"
+            );
+        }
+        for (key, value) in [
+            ("beta_plus", self.beta_plus),
+            ("beta_minus", self.beta_minus),
+        ] {
+            ensure!(
+                value.is_finite(),
+                InvalidConfig,
+                "{key} must be a finite number (got {value})"
+            );
+        }
+        ensure!(
+            self.sin2_weinberg > 0. && self.sin2_weinberg < 1.,
+            InvalidConfig,
+            "sin2_weinberg is the squared sine of the Weinberg angle, so it must be strictly \
+             between 0 and 1 (got {})",
+            self.sin2_weinberg
+        );
+        ensure!(
+            self.branching_ep_em > 0. && self.branching_ep_em <= 1.,
+            InvalidConfig,
+            "branching_ep_em is a branching ratio, so it must be in ]0, 1] (got {})",
+            self.branching_ep_em
+        );
+
+        // Cuts must make sense, and let some events through
+        evcut::check(&self.event_cut)?;
+        ensure!(
+            self.event_cut.e_min < self.e_total / 3.,
+            InvalidConfig,
+            "event_cut.e_min must be below e_total / 3 = {:.4} GeV, since the least energetic of the \
+             three photons has at most a third of the collision energy (got {})",
+            self.e_total / 3.,
+            self.event_cut.e_min
+        );
+
+        // Histograms need at least one bin and a non-empty range
+        ensure!(
+            !self.plot || self.num_bins > 0,
+            InvalidConfig,
+            "Histograms must have at least one bin"
+        );
+        ensure!(
+            self.yoda_output.is_none() || self.plot || !self.distributions.is_empty(),
+            InvalidConfig,
+            "YODA output requires histograms, enable plot or configure distributions"
+        );
+        for dist in &self.distributions {
+            ensure!(
+                dist.bins.unwrap_or(self.num_bins) > 0,
+                InvalidConfig,
+                "Histogram of {:?} must have at least one bin",
+                dist.observable
+            );
+            let (min, max) = dist.range(self.e_total);
+            ensure!(
+                min < max,
+                InvalidConfig,
+                "Histogram range of {:?} must not be empty",
+                dist.observable
+            );
+            if let Some(label) = dist.photon {
+                ensure!(
+                    dist.observable.per_photon(),
+                    InvalidConfig,
+                    "Histogram of {} cannot be restricted to a photon, since it does not take \
+                     one value per photon",
+                    dist.observable.name()
+                );
+                ensure!(
+                    (1..=NUM_OUTGOING).contains(&label),
+                    InvalidConfig,
+                    "Photon labels range from 1 (the hardest) to {NUM_OUTGOING} (got {label})"
+                );
+            }
+        }
+
+        // We do not support the initial code's debugging feature which displays
+        // all intermediary results during sampling. Such a feature should be
+        // set up at build time to avoid run-time costs.
+        ensure!(
+            !self.impr,
+            InvalidConfig,
+            "Individual result printing is not supported. This debugging feature has a run-time \
+         performance cost even when unused. It should be implemented at compile-time instead."
+        );
+
+        // Events must be written in a suitable format, and streamed events
+        // cannot be rewound to a checkpoint
+        if let Some(event_output) = &self.event_output {
+            event_output.check()?;
+            ensure!(
+                !(event_output.stream && self.checkpoint.is_some()),
+                InvalidConfig,
+                "Streamed events cannot be checkpointed"
+            );
+        }
+
+        // Compressed output files require the compressed-output feature
+        let output_paths = [&self.json_output, &self.summary_yaml, &self.yoda_output];
+        let grid_path = self.coupling_grid.as_ref().map(|grid| &grid.path);
+        for path in output_paths.into_iter().flatten().chain(grid_path) {
+            Compression::check_path(path)?;
+        }
+
+        // Anomalous coupling grids must be well-formed
+        if let Some(grid) = &self.coupling_grid {
+            grid.beta_plus.check("beta_plus")?;
+            grid.beta_minus.check("beta_minus")?;
+        }
+
+        // Adaptive sampling must be sensibly configured
+        if let Some(adaptive) = &self.adaptive_sampling {
+            adaptive.check()?;
+        }
+
+        // Parameter uncertainties must keep the parameters physical
+        if let Some(systematics) = &self.systematics {
+            systematics.check(self)?;
+        }
+
+        // Kinematic checks need a sensible tolerance
+        if let Some(validation) = &self.validation {
+            ensure!(
+                validation.tolerance > 0.,
+                InvalidConfig,
+                "The event validation tolerance must be positive"
+            );
+        }
+
+        // The stability guard recomputes the matrix elements of the built-in
+        // process, and needs a sensible threshold
+        if let Some(guard) = &self.stability_guard {
+            ensure!(
+                guard.threshold > 0. && guard.threshold < 1.,
+                InvalidConfig,
+                "The stability guard threshold must lie between 0 and 1"
+            );
+            ensure!(
+                self.process == DEFAULT_PROCESS,
+                InvalidConfig,
+                "The stability guard only supports the {DEFAULT_PROCESS:?} process"
+            );
+        }
+
+        // Asymmetries must be sensibly configured
+        if let Some(asymmetries) = &self.asymmetries {
+            asymmetries.check()?;
+        }
+
+        // A simulation needs at least one thread
+        ensure!(
+            self.threads != Some(0),
+            InvalidConfig,
+            "Please use at least one simulation thread"
+        );
+
+        // Legendre moments start at order 1
+        ensure!(
+            self.legendre_moments != Some(0),
+            InvalidConfig,
+            "The highest order of the Legendre moments must be positive"
+        );
+
+        // Resampling needs several batches of events
+        if let Some(resampling) = &self.resampling {
+            resampling.check()?;
+            ensure!(
+                self.num_events > EVENT_BATCH_SIZE,
+                InvalidConfig,
+                "Resampling needs more than one batch of {EVENT_BATCH_SIZE} events"
+            );
+        }
+
+        // The random number generator must be well-configured: the ranlux
+        // luxury level must be one of the original ones, and the seed must be
+        // valid for the chosen engine
+        ensure!(
+            self.ranlux_luxury <= RanluxGenerator::MAX_LUXURY,
+            InvalidConfig,
+            "ranlux_luxury must be between 0 and {} (got {})",
+            RanluxGenerator::MAX_LUXURY,
+            self.ranlux_luxury
+        );
+        if let Some(seed) = self.seed {
+            self.rng.check_seed(seed)?;
+        }
+        self.rng.check_build()?;
+
+        // The beam energy spread must leave the collision energy positive
+        if let Some(beam_spread) = self.beam_spread {
+            ensure!(
+                beam_spread > 0. && beam_spread <= 0.1 * self.e_total,
+                InvalidConfig,
+                "The beam energy spread must be positive and at most 10% of the collision energy"
+            );
+        }
+
+        // Beam polarizations must be physical
+        for (beam, polarization) in [
+            ("electron", self.electron_polarization),
+            ("positron", self.positron_polarization),
+        ] {
+            ensure!(
+                (-1. ..=1.).contains(&polarization),
+                InvalidConfig,
+                "The {beam} beam polarization must be between -1 and 1"
+            );
+        }
+
+        // The detector simulation must make sense
+        if let Some(detector) = &self.detector {
+            detector.check()?;
+        }
+
+        // Beam effects and the detector simulation draw extra random numbers
+        // per event
+        ensure!(
+            !(self.varying_collision_energy() && self.rng == RandomEngine::Sobol),
+            InvalidConfig,
+            "The sobol engine does not provide the extra random numbers that initial-state \
+             radiation and beam energy spread need"
+        );
+        ensure!(
+            !(self.detector.is_some() && self.rng == RandomEngine::Sobol),
+            InvalidConfig,
+            "The sobol engine does not provide the extra random numbers that the detector \
+             simulation needs"
+        );
+
+        // If nothing bad occured, the configuration is usable
+        Ok(())
+    }
+
+    /// Parse a configuration in TOML format, resolving its includes relative
+    /// to a certain directory
+    ///
+    /// The include stack lists the canonical paths of the files which are
+    /// being included, so that include cycles can be detected.
+    ///
+    fn parse_toml(
+        config_str: &str,
+        base_dir: &Path,
+        include_stack: &mut Vec<PathBuf>,
+    ) -> Result<Self> {
+        let parse_error = |e| Error::ConfigParse {
+            what: "TOML configuration".to_owned(),
+            source: Box::new(e),
         };
+        let table = toml::from_str::<toml::Table>(config_str).map_err(parse_error)?;
+        if table.contains_key(INCLUDE_KEY) {
+            let table = Self::resolve_includes(table, base_dir, include_stack)?;
+            Self::from_table(table, "TOML configuration")
+        } else {
+            // Deserializing from the text gives more precise error messages
+            toml::from_str(config_str).map_err(parse_error)
+        }
+    }
+
+    /// Merge the files included by a TOML configuration into it, the entries
+    /// of the including file taking precedence over those of included files,
+    /// and those of later included files over those of earlier ones
+    fn resolve_includes(
+        mut table: toml::Table,
+        base_dir: &Path,
+        include_stack: &mut Vec<PathBuf>,
+    ) -> Result<toml::Table> {
+        // Collect the names of the included files
+        let file_names = match table.remove(INCLUDE_KEY) {
+            None => return Ok(table),
+            Some(toml::Value::String(file_name)) => vec![file_name],
+            Some(toml::Value::Array(file_names)) => file_names
+                .into_iter()
+                .map(|file_name| match file_name {
+                    toml::Value::String(file_name) => Ok(file_name),
+                    _ => Err(Error::InvalidConfig(INCLUDE_TYPE_ERROR.to_owned())),
+                })
+                .collect::<Result<Vec<_>>>()?,
+            Some(_) => return Err(Error::InvalidConfig(INCLUDE_TYPE_ERROR.to_owned())),
+        };
+
+        // Merge them in order, then apply the including file on top
+        let mut merged = toml::Table::new();
+        for file_name in file_names {
+            let path = base_dir.join(file_name);
+            let canonical_path = fs::canonicalize(&path)?;
+            ensure!(
+                !include_stack.contains(&canonical_path),
+                InvalidConfig,
+                "Configuration file {} includes itself",
+                path.display()
+            );
+            include_stack.push(canonical_path);
+            let config_str = fs::read_to_string(&path)?;
+            debug!("Including the configuration from {}", path.display());
+            let included = match ConfigFormat::of_file(&path) {
+                ConfigFormat::Toml => {
+                    let included_dir = path.parent().unwrap_or(Path::new(""));
+                    let included = toml::from_str(&config_str).map_err(|e| Error::ConfigParse {
+                        what: format!("included configuration {}", path.display()),
+                        source: Box::new(e),
+                    })?;
+                    Self::resolve_includes(included, included_dir, include_stack)?
+                }
+                ConfigFormat::Legacy => Self::parse_legacy(&config_str)?.to_table()?,
+                ConfigFormat::StrictLegacy => Self::parse_strict_legacy(&config_str)?.to_table()?,
+            };
+            include_stack.pop();
+            merge_tables(&mut merged, included);
+        }
+        merge_tables(&mut merged, table);
+        Ok(merged)
+    }
+
+    /// Assign some entries of the configuration
+    fn with_overlay(&self, overlay: &[ConfigAssignment]) -> Result<Self> {
+        let mut table = self.to_table()?;
+        for assignment in overlay {
+            assignment.apply(&mut table)?;
+        }
+        Self::from_table(table, "configuration overlay")
+    }
 
+    /// Express the configuration as a TOML table
+    fn to_table(&self) -> Result<toml::Table> {
+        toml::Table::try_from(self).map_err(|e| Error::ConfigParse {
+            what: "configuration (as TOML)".to_owned(),
+            source: Box::new(e),
+        })
+    }
+
+    /// Decode the configuration from a TOML table, given a description of
+    /// where it comes from for error reporting purposes
+    fn from_table(table: toml::Table, what: &str) -> Result<Self> {
+        toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| Error::ConfigParse {
+                what: what.to_owned(),
+                source: Box::new(e),
+            })
+    }
+
+    /// Parse a configuration in the legacy positional format of 3photons
+    fn parse_legacy(config_str: &str) -> Result<Self> {
         // We will iterate over the configuration items. In 3photons' simple
         // config file format, these should be the first non-whitespace chunk of
         // text on each line. We will ignore blank lines.
-        let mut config_iter = config_str
-            .lines()
-            .filter_map(|line| line.split_whitespace().next());
+        let mut config_iter = numbered_lines(config_str)
+            .map(|(line, text)| (line, text.split_whitespace().next().unwrap_or_default()));
 
         // This closure fetches the next configuration item, tagging it with
         // the name of the configuration field which it is supposed to fill to
         // ease error reporting, and handling unexpected end-of-file too.
-        let mut next_item = |name: &'static str| -> Result<ConfigItem> {
+        let next_item = |name: &'static str| -> Result<ConfigItem> {
             config_iter
                 .next()
-                .map(|data| ConfigItem::new(name, data))
-                .ok_or_else(|| format_err!("Missing configuration of {}", name))
+                .map(|(line, data)| ConfigItem::new(name, line, data))
+                .ok_or(Error::MissingConfig(name))
         };
+        let config = Self::from_legacy_items(next_item)?;
 
-        // Decode the configuration items into concrete values
+        // The original 3photons ignored whatever follows the configuration
+        // items, which can hide misplaced or extra entries. The reference
+        // valeurs file ends with such lines (alternate cut sets), so this is
+        // only reported in verbose mode.
+        if let Some((first_line, _)) = config_iter.next() {
+            let num_ignored = 1 + config_iter.count();
+            debug!(
+                "Ignoring {num_ignored} trailing configuration line(s) from line {first_line} on \
+                 (use the strict legacy format to detect misplaced entries)"
+            );
+        }
+        Ok(config)
+    }
+
+    /// Parse a configuration in the strict legacy format, where each non-blank
+    /// line is a `key value` pair optionally followed by a comment
+    fn parse_strict_legacy(config_str: &str) -> Result<Self> {
+        // Collect the entries, along with the line where they are set
+        let mut entries = HashMap::new();
+        for (line, text) in numbered_lines(config_str) {
+            let mut words = text.split_whitespace();
+            let key = words.next().unwrap_or_default();
+            ensure!(
+                LEGACY_KEYS.contains(&key),
+                InvalidConfig,
+                "Unknown configuration entry {key} on line {line}"
+            );
+            let data = words.next().ok_or_else(|| {
+                Error::InvalidConfig(format!("Missing value of {key} on line {line}"))
+            })?;
+            if let Some((first_line, _)) = entries.insert(key, (line, data)) {
+                return Err(Error::InvalidConfig(format!(
+                    "Duplicate configuration entry {key} on line {line} \
+                     (first set on line {first_line})"
+                )));
+            }
+        }
+
+        // Look up the configuration items by name
+        Self::from_legacy_items(|name| {
+            entries
+                .get(name)
+                .map(|&(line, data)| ConfigItem::new(name, line, data))
+                .ok_or(Error::MissingConfig(name))
+        })
+    }
+
+    /// Decode the configuration items of the legacy formats into concrete
+    /// values, fetching them in the positional order by name
+    fn from_legacy_items<'data>(
+        mut next_item: impl FnMut(&'static str) -> Result<ConfigItem<'data>>,
+    ) -> Result<Self> {
         let config = Configuration {
+            process: DEFAULT_PROCESS.to_owned(),
             num_events: next_item("num_events")?.parse::<usize>()?,
             e_total: next_item("e_total")?.parse::<Float>()?,
             event_cut: EventCut::new(
@@ -91,43 +1079,129 @@ impl Configuration {
                 next_item("beam_photon_plane_cut")?.parse::<Float>()?,
             ),
             alpha: next_item("alpha")?.parse::<Float>()?,
+            alpha_scheme: AlphaScheme::default(),
             alpha_z: next_item("alpha_z")?.parse::<Float>()?,
+            electroweak_scheme: ElectroweakScheme::default(),
+            g_fermi: DEFAULT_G_FERMI,
+            m_w: DEFAULT_M_W,
             gev2_to_picobarn: next_item("gev2_to_picobarn")?.parse::<Float>()?,
             m_z0: next_item("m_z0")?.parse::<Float>()?,
             g_z0: next_item("g_z0")?.parse::<Float>()?,
+            z_lineshape: ZLineshape::default(),
             sin2_weinberg: next_item("sin2_weinberg")?.parse::<Float>()?,
             branching_ep_em: next_item("branching_ep_em")?.parse::<Float>()?,
             beta_plus: next_item("beta_plus")?.parse::<Float>()?,
-            beta_minus: next_item("beta_moins")?.parse::<Float>()?,
-            num_bins: next_item("num_bins")?.parse::<i32>()?,
+            beta_minus: next_item("beta_minus")?.parse::<Float>()?,
+            num_bins: next_item("num_bins")?.parse::<usize>()?,
             impr: next_item("impr")?.parse_bool()?,
             plot: next_item("plot")?.parse_bool()?,
+            rng: RandomEngine::default(),
+            ranlux_luxury: RanluxGenerator::DEFAULT_LUXURY,
+            seed: None,
+            distributions: Vec::new(),
+            output_dir: None,
+            output_name: None,
+            data_format: DataFormat::default(),
+            tag: None,
+            json_output: None,
+            summary_yaml: None,
+            yoda_output: None,
+            event_output: None,
+            checkpoint: None,
+            coupling_grid: None,
+            adaptive_sampling: None,
+            systematics: None,
+            resampling: None,
+            validation: None,
+            stability_guard: None,
+            asymmetries: None,
+            legendre_moments: None,
+            weight_distribution: false,
+            spin_density: false,
+            bad_events_output: None,
+            convergence_output: None,
+            cache_dir: None,
+            timings: false,
+            threads: None,
+            pin_threads: false,
+            isr: false,
+            beam_spread: None,
+            detector: None,
+            photon_ordering: PhotonOrdering::default(),
+            phase_space_generator: PhaseSpaceGenerator::default(),
+            electron_polarization: 0.,
+            positron_polarization: 0.,
         };
-
-        // Display it the way the C++ version used to (this eases comparisons)
-        print!("{config}");
-
-        // A sensible simulation must run for at least one event
-        ensure!(config.num_events > 0, "Please simulate at least one event");
-
-        // We don't support the original code's PAW-based plotting features,
-        // so we make sure that it was not enabled.
-        ensure!(!config.plot, "Plotting is not supported by this version");
-
-        // We do not support the initial code's debugging feature which displays
-        // all intermediary results during sampling. Such a feature should be
-        // set up at build time to avoid run-time costs.
-        ensure!(
-            !config.impr,
-            "Individual result printing is not supported. This debugging feature has a run-time \
-             performance cost even when unused. It should be implemented at compile-time instead."
-        );
-
-        // If nothing bad occured, we can now return the configuration
         Ok(config)
     }
 }
 
+impl Default for Configuration {
+    /// Default configuration, matching the reference `valeurs` file
+    #[allow(clippy::excessive_precision)]
+    fn default() -> Self {
+        Configuration {
+            process: DEFAULT_PROCESS.to_owned(),
+            num_events: 10_000_000,
+            e_total: 91.187,
+            event_cut: EventCut::default(),
+            alpha: 7.297_353_079_644_818e-3,
+            alpha_scheme: AlphaScheme::default(),
+            alpha_z: 7.8125e-3,
+            electroweak_scheme: ElectroweakScheme::default(),
+            g_fermi: DEFAULT_G_FERMI,
+            m_w: DEFAULT_M_W,
+            gev2_to_picobarn: 0.389_379_66e9,
+            m_z0: 91.187,
+            g_z0: 2.490,
+            z_lineshape: ZLineshape::default(),
+            sin2_weinberg: 0.2319,
+            branching_ep_em: 0.03367,
+            beta_plus: 1.,
+            beta_minus: 1.,
+            num_bins: 200,
+            impr: false,
+            plot: false,
+            rng: RandomEngine::default(),
+            ranlux_luxury: RanluxGenerator::DEFAULT_LUXURY,
+            seed: None,
+            distributions: Vec::new(),
+            output_dir: None,
+            output_name: None,
+            data_format: DataFormat::default(),
+            tag: None,
+            json_output: None,
+            summary_yaml: None,
+            yoda_output: None,
+            event_output: None,
+            checkpoint: None,
+            coupling_grid: None,
+            adaptive_sampling: None,
+            systematics: None,
+            resampling: None,
+            validation: None,
+            stability_guard: None,
+            asymmetries: None,
+            legendre_moments: None,
+            weight_distribution: false,
+            spin_density: false,
+            bad_events_output: None,
+            convergence_output: None,
+            cache_dir: None,
+            timings: false,
+            threads: None,
+            pin_threads: false,
+            isr: false,
+            beam_spread: None,
+            detector: None,
+            photon_ordering: PhotonOrdering::default(),
+            phase_space_generator: PhaseSpaceGenerator::default(),
+            electron_polarization: 0.,
+            positron_polarization: 0.,
+        }
+    }
+}
+
 impl Display for Configuration {
     /// Display the configuration, following formatting of the original version
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -154,17 +1228,276 @@ impl Display for Configuration {
     }
 }
 
+/// Builder of simulation configurations, for library users who would rather
+/// not write a configuration file
+///
+/// Entries which are not explicitly set take the value that is featured in the
+/// reference `valeurs` file, as in TOML configuration files.
+///
+#[derive(Default)]
+pub struct ConfigurationBuilder(Configuration);
+//
+impl ConfigurationBuilder {
+    /// Check the configuration and return it
+    pub fn build(self) -> Result<Configuration> {
+        let mut cfg = self.0;
+        cfg.resolve_output_paths()?;
+        cfg.derive_electroweak_parameters()?;
+        cfg.check()?;
+        cfg.create_output_dir()?;
+        Ok(cfg)
+    }
+}
+
+/// Substitute the placeholders of an output file name, such as
+/// `{tag}_{seed}_{date}`, with their values
+fn expand_placeholders(
+    template: &str,
+    placeholders: &[(&'static str, Option<String>)],
+) -> Result<String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            return Err(Error::InvalidConfig(format!(
+                "Output file name {template} has an unterminated placeholder"
+            )));
+        };
+        let name = &rest[start + 1..end];
+        let Some((_, value)) = placeholders.iter().find(|(key, _)| *key == name) else {
+            let known = (placeholders.iter())
+                .map(|(key, _)| format!("{{{key}}}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(Error::InvalidConfig(format!(
+                "Output file name {template} has an unknown placeholder {{{name}}}, expected \
+                 one of {known}"
+            )));
+        };
+        let Some(value) = value else {
+            return Err(Error::InvalidConfig(format!(
+                "Output file name {template} features {{{name}}}, but no {name} is configured"
+            )));
+        };
+        expanded.push_str(value);
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Generate setters for entries of the configuration
+macro_rules! setters {
+    ($($(#[$doc:meta])* $field:ident: $type:ty,)*) => {
+        impl ConfigurationBuilder {
+            $(
+                $(#[$doc])*
+                pub fn $field(mut self, $field: $type) -> Self {
+                    self.0.$field = $field.into();
+                    self
+                }
+            )*
+        }
+    };
+}
+//
+setters! {
+    /// Set the name of the simulated process
+    process: String,
+
+    /// Set the number of events to be simulated
+    num_events: usize,
+
+    /// Set the collision energy at center of mass (GeV)
+    e_total: Float,
+
+    /// Set the cuts on the angles and energies of generated photons
+    event_cut: EventCut,
+
+    /// Set the fine structure constant
+    alpha: Float,
+
+    /// Set the scheme in which the fine structure constant of the Standard
+    /// Model contribution is evaluated
+    alpha_scheme: AlphaScheme,
+
+    /// Set the fine structure constant at the Z⁰ mass peak
+    alpha_z: Float,
+
+    /// Set the scheme in which the inputs of the electroweak sector are
+    /// specified
+    electroweak_scheme: ElectroweakScheme,
+
+    /// Set the Fermi constant (GeV^-2)
+    g_fermi: Float,
+
+    /// Set the W boson mass (GeV)
+    m_w: Float,
+
+    /// Set the conversion factor from GeV^(-2) to pb
+    gev2_to_picobarn: Float,
+
+    /// Set the Z⁰ boson mass (GeV)
+    m_z0: Float,
+
+    /// Set the Z⁰ boson width (GeV)
+    g_z0: Float,
+
+    /// Set the scheme in which the Z⁰ propagator is evaluated
+    z_lineshape: ZLineshape,
+
+    /// Set the square sine of Weinberg's Theta
+    sin2_weinberg: Float,
+
+    /// Set the branching ratio from Z to e+/e-
+    branching_ep_em: Float,
+
+    /// Set the anomalous coupling Beta +
+    beta_plus: Float,
+
+    /// Set the anomalous coupling Beta -
+    beta_minus: Float,
+
+    /// Set the number of histogram bins
+    num_bins: usize,
+
+    /// Set whether histograms of the photon distributions should be produced
+    plot: bool,
+
+    /// Set the random number generation engine
+    rng: RandomEngine,
+
+    /// Set the luxury level of the ranlux engine
+    ranlux_luxury: u8,
+
+    /// Set the random number generator seed
+    seed: u64,
+
+    /// Set the differential distributions to be computed
+    distributions: Vec<DistributionConfig>,
+
+    /// Set the directory where the output files should be written
+    output_dir: String,
+
+    /// Set the name of the res.data, res.times and res.histos output files
+    output_name: String,
+
+    /// Set the format of the res.data output file
+    data_format: DataFormat,
+
+    /// Set the label of the run
+    tag: String,
+
+    /// Set the file where results should be written in JSON format
+    json_output: String,
+
+    /// Set the file where a summary of the results should be appended in YAML
+    /// format
+    summary_yaml: String,
+
+    /// Set the file where the histograms should be written in YODA format
+    yoda_output: String,
+
+    /// Set up the output of accepted events
+    event_output: EventOutputConfig,
+
+    /// Set up periodic checkpointing of the simulation state
+    checkpoint: CheckpointConfig,
+
+    /// Set the grid of anomalous couplings over which the results should be
+    /// reweighted
+    coupling_grid: CouplingGridConfig,
+
+    /// Set up adaptive importance sampling of the event generator
+    adaptive_sampling: AdaptiveSamplingConfig,
+
+    /// Set the uncertainties on the electroweak parameters which should be
+    /// propagated to the total cross-section
+    systematics: SystematicsConfig,
+
+    /// Set up the resampling of event batches, to estimate the uncertainty of
+    /// derived quantities
+    resampling: ResamplingConfig,
+
+    /// Set up checks of the kinematics of each generated event
+    validation: ValidationConfig,
+
+    /// Set up the recomputation of numerically unstable events with extended
+    /// precision
+    stability_guard: StabilityGuardConfig,
+
+    /// Set up the computation of the angular asymmetries of the photons
+    asymmetries: AsymmetryConfig,
+
+    /// Set the highest order of the Legendre moments of the photon angular
+    /// distribution to be computed
+    legendre_moments: usize,
+
+    /// Set whether the distribution of the event weights should be recorded
+    /// and displayed
+    weight_distribution: bool,
+
+    /// Set whether the spin density matrix of the anomalous contribution
+    /// should be displayed
+    spin_density: bool,
+
+    /// Set the file where the first events whose weight is not finite or
+    /// negative should be written
+    bad_events_output: String,
+
+    /// Set the file where the total cross-section after each batch of events
+    /// should be written
+    convergence_output: String,
+
+    /// Set the directory where the results of simulations are cached
+    cache_dir: String,
+
+    /// Set whether the time spent in each phase of the simulation should be
+    /// measured and displayed
+    timings: bool,
+
+    /// Set the number of simulation threads
+    threads: usize,
+
+    /// Set whether each simulation thread should be pinned to a CPU core
+    pin_threads: bool,
+
+    /// Set whether initial-state radiation should be simulated
+    isr: bool,
+
+    /// Set the standard deviation of the collision energy (GeV)
+    beam_spread: Float,
+
+    /// Set up the simulation of the detector
+    detector: DetectorConfig,
+
+    /// Set the ordering of the outgoing photons
+    photon_ordering: PhotonOrdering,
+
+    /// Set the generator of flat phase space
+    phase_space_generator: PhaseSpaceGenerator,
+
+    /// Set the longitudinal polarization of the electron beam
+    electron_polarization: Float,
+
+    /// Set the longitudinal polarization of the positron beam
+    positron_polarization: Float,
+}
+
 /// A value from the configuration file, tagged with the struct field which it
-/// is supposed to map for error reporting purposes.
+/// is supposed to map and the line where it appears for error reporting
+/// purposes.
 struct ConfigItem<'data> {
     name: &'static str,
+    line: usize,
     data: &'data str,
 }
 //
 impl<'data> ConfigItem<'data> {
-    /// Build a config item from a struct field tag and raw iterator data
-    fn new(name: &'static str, data: &'data str) -> Self {
-        Self { name, data }
+    /// Build a config item from a struct field tag, line number and raw data
+    fn new(name: &'static str, line: usize, data: &'data str) -> Self {
+        Self { name, line, data }
     }
 
     /// Parse this data using Rust's standard parsing logic
@@ -172,10 +1505,10 @@ impl<'data> ConfigItem<'data> {
     where
         <T as FromStr>::Err: ::std::error::Error + Send + Sync + 'static,
     {
-        self.data
-            .parse::<T>()
-            .map_err(Error::new)
-            .context(format!("Could not parse configuration of {}", self.name))
+        self.data.parse::<T>().map_err(|e| Error::ConfigParse {
+            what: format!("configuration of {} on line {}", self.name, self.line),
+            source: Box::new(e),
+        })
     }
 
     /// Parse this data using special logic which handles Fortran's bool syntax
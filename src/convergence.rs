@@ -0,0 +1,93 @@
+//! History of the Monte Carlo convergence of the total cross-section, batch of
+//! events after batch of events
+//!
+//! Each batch of events contributes its sums of event weights and of their
+//! squares, which are recorded in batch order. The cross-section and its
+//! statistical error after each batch are then deduced from the sums over the
+//! batches simulated so far, so that the convergence of the simulation (and
+//! the jumps due to rare events of large weight) can be plotted.
+
+use crate::{numeric::Float, output::compression::OutputFile, Result};
+use prefix_num_ops::real::*;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// Totals of a batch of events
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct BatchTotals {
+    /// Number of generated events
+    pub events: usize,
+
+    /// Sum of the event weights, normalized by the configured number of events
+    pub sigma: Float,
+
+    /// Sum of the squared event weights, with the same normalization
+    pub variance: Float,
+}
+//
+impl BatchTotals {
+    /// Multiply the weights of the events by a certain factor
+    pub fn scale_weights(&mut self, factor: Float) {
+        self.sigma *= factor;
+        self.variance *= factor.powi(2);
+    }
+}
+
+/// Estimate of the total cross-section after some number of events
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct ConvergencePoint {
+    /// Number of generated events
+    pub events: usize,
+
+    /// Total cross-section (pb)
+    pub sigma: Float,
+
+    /// Standard deviation of the total cross-section (pb)
+    pub sigma_stddev: Float,
+}
+
+/// Deduce the convergence history from the totals of each batch of events,
+/// given the configured number of events and the incident flux factor
+pub fn history(
+    batches: &[BatchTotals],
+    num_events: usize,
+    incident_flux: Float,
+) -> Vec<ConvergencePoint> {
+    let (mut events, mut sigma_sum, mut variance_sum) = (0, 0., 0.);
+    (batches.iter())
+        .map(|batch| {
+            events += batch.events;
+            sigma_sum += batch.sigma;
+            variance_sum += batch.variance;
+            let n_ev = events as Float;
+            let variance = (variance_sum - sigma_sum.powi(2) / n_ev) / (n_ev - 1.);
+            let prec = sqrt(variance / n_ev) / abs(sigma_sum / n_ev);
+            let sigma = sigma_sum * (num_events as Float / n_ev) * incident_flux;
+            ConvergencePoint {
+                events,
+                sigma,
+                sigma_stddev: abs(sigma) * prec,
+            }
+        })
+        .collect()
+}
+
+/// Write a convergence history to a file, in the CSV format
+///
+/// The columns are the number of generated events (`events`), the total
+/// cross-section (`sigma`, in pb) and its standard deviation (`sigma_stddev`,
+/// in pb).
+///
+pub fn write_csv(points: &[ConvergencePoint], path: &str) -> Result<()> {
+    let mut file = OutputFile::create(path)?;
+    writeln!(file, "events,sigma,sigma_stddev")?;
+    for point in points {
+        writeln!(
+            file,
+            "{},{:e},{:e}",
+            point.events, point.sigma, point.sigma_stddev
+        )?;
+    }
+    file.finish()?;
+    Ok(())
+}
@@ -1,34 +1,182 @@
 //! Physical couplings used for result computations
+//!
+//! The couplings themselves are computed by the core crate, and re-exported
+//! here, from the parameters which this module derives from the configuration.
 
 use crate::{
     config::Configuration,
-    numeric::{floats::consts::PI, Float},
+    numeric::{
+        floats::consts::{PI, SQRT_2},
+        real::cast,
+        Complex, Float, Real,
+    },
 };
+use clap::ValueEnum;
 use prefix_num_ops::real::*;
+use serde::{Deserialize, Serialize};
 
-/// Set of physical couplings
-pub struct Couplings {
-    /// Standard Model contribution electromagnetic coupling √(4𝜋𝛼)³
-    pub g_a: Float,
+pub use trois_photons_core::coupling::Couplings;
 
-    /// 𝛽₊ anomalous contribution electroweak coupling
-    pub g_beta_p: Float,
+/// Masses of the charged leptons (GeV)
+#[allow(clippy::excessive_precision)]
+const LEPTON_MASSES: [Float; 3] = [0.000_510_998_95, 0.105_658_375_5, 1.776_86];
 
-    /// 𝛽₋ anomalous contribution electroweak coupling
-    pub g_beta_m: Float,
+/// Hadronic contribution to the running of the fine structure constant at the
+/// Z⁰ mass peak
+const DELTA_ALPHA_HAD_Z0: Float = 0.027_66;
+
+/// Sum of the squared charges of the five lightest quarks, times the number of
+/// colors, which drives the hadronic running above the Z⁰ mass peak
+const QUARK_CHARGES_2: Float = 11. / 3.;
+
+/// Schemes in which the fine structure constant of the Standard Model
+/// contribution can be evaluated
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AlphaScheme {
+    /// Configured value of the fine structure constant
+    #[default]
+    Fixed,
+
+    /// Fine structure constant running from its configured (Thomson limit)
+    /// value to the collision energy, through leptonic and hadronic vacuum
+    /// polarization
+    Running,
 }
 //
-impl Couplings {
-    /// Fill in the parameters using data from the configuration file
-    pub fn new(cfg: &Configuration) -> Self {
-        let e2 = 4. * PI * cfg.alpha;
-        let e2_z = 4. * PI * cfg.alpha_z;
-        let cos2_weinberg = 1. - cfg.sin2_weinberg;
-        let g_beta = -sqrt(e2_z / (4. * cos2_weinberg * cfg.sin2_weinberg)) / cfg.m_z0.powi(4);
-        Couplings {
-            g_a: -sqrt(e2).powi(3),
-            g_beta_p: g_beta,
-            g_beta_m: g_beta,
+impl AlphaScheme {
+    /// Name of the scheme, as in configuration files
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Fixed => "fixed",
+            Self::Running => "running",
+        }
+    }
+
+    /// Fine structure constant of the Standard Model contribution
+    ///
+    /// The hadronic vacuum polarization is parametrized by its value at the Z⁰
+    /// mass peak and its leading-logarithmic running with five quark flavors,
+    /// which is sensible for collision energies well above the b quark
+    /// threshold.
+    ///
+    pub fn alpha(self, cfg: &Configuration) -> Float {
+        match self {
+            Self::Fixed => cfg.alpha,
+            Self::Running => {
+                let s = cfg.e_total.powi(2);
+                let log_factor = cfg.alpha / (3. * PI);
+                let delta_lep = log_factor
+                    * LEPTON_MASSES
+                        .iter()
+                        .map(|m_l| (ln(s / m_l.powi(2)) - 5. / 3.).max(0.))
+                        .sum::<Float>();
+                let delta_had =
+                    DELTA_ALPHA_HAD_Z0 + log_factor * QUARK_CHARGES_2 * ln(s / cfg.m_z0.powi(2));
+                cfg.alpha / (1. - delta_lep - delta_had)
+            }
+        }
+    }
+}
+
+/// Schemes in which the inputs of the electroweak sector can be specified
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ElectroweakScheme {
+    /// Configured fine structure constant at the Z⁰ mass peak, squared sine
+    /// of the Weinberg angle and Z⁰ mass
+    #[default]
+    AlphaMz,
+
+    /// Configured Fermi constant, Z⁰ mass and W mass, from which the squared
+    /// sine of the Weinberg angle is deduced at tree level, along with the
+    /// fine structure constant of the G_µ scheme
+    GfMzMw,
+}
+//
+impl ElectroweakScheme {
+    /// Name of the scheme, as in configuration files
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::AlphaMz => "alpha-mz",
+            Self::GfMzMw => "gf-mz-mw",
+        }
+    }
+
+    /// Effective fine structure constant at the Z⁰ mass peak and squared sine
+    /// of the Weinberg angle, which the anomalous contributions depend on
+    ///
+    /// In the (G_F, M_Z, M_W) scheme, sin²θ_W = 1 - M_W²/M_Z² and
+    /// 𝛼 = √2·G_F·M_W²·sin²θ_W/𝜋.
+    ///
+    pub fn parameters(self, cfg: &Configuration) -> (Float, Float) {
+        match self {
+            Self::AlphaMz => (cfg.alpha_z, cfg.sin2_weinberg),
+            Self::GfMzMw => {
+                let m_w2 = cfg.m_w.powi(2);
+                let sin2_weinberg = 1. - m_w2 / cfg.m_z0.powi(2);
+                let alpha_z = SQRT_2 * cfg.g_fermi * m_w2 * sin2_weinberg / PI;
+                (alpha_z, sin2_weinberg)
+            }
+        }
+    }
+}
+
+/// Schemes in which the Z⁰ propagator can be evaluated
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ZLineshape {
+    /// Breit-Wigner propagator with a fixed width, 1/(s - M² + iMΓ)
+    #[default]
+    FixedWidth,
+
+    /// Breit-Wigner propagator with an s-dependent width, 1/(s - M² + isΓ/M),
+    /// as in the LEP definition of the Z⁰ mass and width
+    RunningWidth,
+
+    /// Propagator of the complex mass scheme, 1/(s - μ²), whose complex pole
+    /// μ² is deduced from the configured mass and width as in the
+    /// s-dependent width scheme
+    ComplexMass,
+}
+//
+impl ZLineshape {
+    /// Name of the scheme, as in configuration files
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::FixedWidth => "fixed-width",
+            Self::RunningWidth => "running-width",
+            Self::ComplexMass => "complex-mass",
+        }
+    }
+
+    /// Inverse of the Z⁰ propagator at a certain collision energy, in units of
+    /// MΓ and with a conjugated imaginary part, i.e. 𝛿 - i·w where 𝛿 is the
+    /// distance to the Z⁰ peak and w is 1 for a fixed width
+    pub fn inverse_propagator(self, cfg: &Configuration, e_collision: Float) -> Complex {
+        let relat_width = cfg.g_z0 / cfg.m_z0;
+        let dzeta = (e_collision / cfg.m_z0).powi(2);
+        match self {
+            Self::FixedWidth => Complex::new((dzeta - 1.) / relat_width, -1.),
+            Self::RunningWidth => Complex::new((dzeta - 1.) / relat_width, -dzeta),
+            Self::ComplexMass => {
+                // The pole mass and width are slightly lower than the
+                // configured ones, which follow the s-dependent width scheme
+                let pole_factor = 1. / (1. + relat_width.powi(2));
+                Complex::new((dzeta - pole_factor) / relat_width, -pole_factor)
+            }
         }
     }
 }
+
+impl<R: Real> From<&Configuration> for Couplings<R> {
+    /// Fill in the parameters using data from the configuration file
+    fn from(cfg: &Configuration) -> Self {
+        Couplings::new(
+            cast(cfg.alpha_scheme.alpha(cfg)),
+            cast(cfg.alpha_z),
+            cast(cfg.sin2_weinberg),
+            cast(cfg.m_z0),
+        )
+    }
+}
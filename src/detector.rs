@@ -0,0 +1,141 @@
+//! Fast simulation of the detector, which measures the outgoing photons with
+//! Gaussian energy and angular resolutions and loses those which fall in the
+//! cracks of its acceptance, so that cuts can be applied at detector level
+
+use crate::{
+    error::ensure,
+    event::{Event, NUM_INCOMING, NUM_OUTGOING},
+    momentum::{E, X},
+    numeric::{floats::consts::PI, Float},
+    Result,
+};
+use nalgebra::Vector3;
+use prefix_num_ops::real::*;
+use serde::{Deserialize, Serialize};
+
+/// Number of Gaussian random numbers which are needed to measure an event:
+/// one for the energy and two for the direction of each photon
+const NUM_GAUSSIANS: usize = 3 * NUM_OUTGOING;
+
+/// Number of random numbers which are drawn to measure an event, which the
+/// Box-Muller transform turns into Gaussian random numbers pairwise
+pub const NUM_RANDOM_NUMBERS: usize = NUM_GAUSSIANS.div_ceil(2) * 2;
+
+/// Configuration of the detector simulation (not in the legacy format)
+///
+/// The relative energy resolution of photons is the quadratic sum of a
+/// stochastic term, divided by the square root of the energy in GeV, and of a
+/// constant term. The directions of photons are smeared in polar angle and
+/// azimuth around the beam axis.
+///
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DetectorConfig {
+    /// Stochastic term of the relative energy resolution (GeV^½)
+    pub energy_stochastic: Float,
+
+    /// Constant term of the relative energy resolution
+    pub energy_constant: Float,
+
+    /// Resolution of the polar angle of photons (radians)
+    pub theta_resolution: Float,
+
+    /// Resolution of the azimuthal angle of photons (radians)
+    pub phi_resolution: Float,
+
+    /// Ranges of |cosine| of the (beam, photon) angle in which photons are
+    /// lost, e.g. the gaps between the barrel and the endcaps
+    pub cracks: Vec<[Float; 2]>,
+}
+//
+impl DetectorConfig {
+    /// Check that the configuration is sensible
+    pub fn check(&self) -> Result<()> {
+        for (name, resolution) in [
+            ("energy_stochastic", self.energy_stochastic),
+            ("energy_constant", self.energy_constant),
+            ("theta_resolution", self.theta_resolution),
+            ("phi_resolution", self.phi_resolution),
+        ] {
+            ensure!(
+                resolution >= 0. && resolution.is_finite(),
+                InvalidConfig,
+                "detector.{name} is a resolution, so it must be a non-negative number (got \
+                 {resolution})"
+            );
+        }
+        for &[min, max] in &self.cracks {
+            ensure!(
+                (0. ..=1.).contains(&min) && (0. ..=1.).contains(&max) && min < max,
+                InvalidConfig,
+                "The cracks of the detector must be non-empty ranges of |cosine| within [0, 1] \
+                 (got [{min}, {max}])"
+            );
+        }
+        ensure!(
+            !self.cracks.iter().any(|&[min, max]| min == 0. && max == 1.),
+            InvalidConfig,
+            "A crack of the detector covers all directions, so no event would be detected"
+        );
+        Ok(())
+    }
+
+    /// Measure the outgoing photons of an event, from uniform random numbers
+    /// in [0, 1[, or tell that a photon fell in a crack
+    ///
+    /// The measured event keeps the incoming momenta and the weight factor of
+    /// the generated event.
+    ///
+    pub fn measure(&self, event: &Event, randoms: [Float; NUM_RANDOM_NUMBERS]) -> Option<Event> {
+        // Turn the uniform random numbers into Gaussian ones
+        let mut gaussians = [0.; NUM_RANDOM_NUMBERS];
+        for (pair, uniforms) in gaussians.chunks_exact_mut(2).zip(randoms.chunks_exact(2)) {
+            let radius = sqrt(-2. * ln(1. - uniforms[0]));
+            let angle = 2. * PI * uniforms[1];
+            pair[0] = radius * cos(angle);
+            pair[1] = radius * sin(angle);
+        }
+
+        // Set up an orthonormal basis whose first axis is the beam
+        let beam = event.electron_momentum().xyz().normalize();
+        let other_axis = if abs(beam[X]) < 0.9 {
+            Vector3::x()
+        } else {
+            Vector3::y()
+        };
+        let normal_1 = beam.cross(&other_axis).normalize();
+        let normal_2 = beam.cross(&normal_1);
+
+        // Measure each photon
+        let mut momenta = *event.all_momenta();
+        for (ph, gaussians) in gaussians.chunks_exact(3).take(NUM_OUTGOING).enumerate() {
+            let p = event.outgoing_momentum(ph);
+            let direction = p.xyz().normalize();
+
+            // Smear its direction, and lose it if it falls in a crack
+            let theta =
+                acos(direction.dot(&beam).clamp(-1., 1.)) + self.theta_resolution * gaussians[1];
+            let phi = atan2(direction.dot(&normal_2), direction.dot(&normal_1))
+                + self.phi_resolution * gaussians[2];
+            let abs_cos_theta = abs(cos(theta));
+            if (self.cracks.iter()).any(|[min, max]| (min..=max).contains(&&abs_cos_theta)) {
+                return None;
+            }
+            let direction =
+                beam * cos(theta) + (normal_1 * cos(phi) + normal_2 * sin(phi)) * sin(theta);
+
+            // Smear its energy, which cannot become negative
+            let energy = p[E];
+            let resolution = hypot(self.energy_stochastic / sqrt(energy), self.energy_constant);
+            let energy = (energy * (1. + resolution * gaussians[0])).max(0.);
+
+            // Record the measured 4-momentum
+            let mut measured = momenta.row_mut(NUM_INCOMING + ph);
+            for (coord, component) in direction.iter().enumerate() {
+                measured[coord] = energy * component;
+            }
+            measured[E] = energy;
+        }
+        Some(Event::with_weight_factor(momenta, event.weight_factor()))
+    }
+}
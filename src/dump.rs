@@ -0,0 +1,181 @@
+//! Event display, which pretty-prints the first events that pass the cuts to
+//! show what the cuts and matrix elements are doing
+
+use log::info;
+use nalgebra::Vector3;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use std::borrow::Cow;
+use trois_photons::{
+    config::Configuration,
+    evcut::Cut,
+    event::{Event, NUM_OUTGOING},
+    evgen::EventGenerator,
+    matelems::{MatrixElement, CONTRIBUTIONS},
+    momentum::{E, X, Y, Z},
+    numeric::Float,
+    random::{
+        RandGenerator, RandomEngine, RandomGenerator, RanfGenerator, RanluxGenerator,
+        SobolGenerator, StdRng, XoshiroPlus,
+    },
+    resacc::ResultsAccumulator,
+};
+
+/// Names of the particles, in event matrix order
+const PARTICLES: [&str; 5] = ["e-", "e+", "photon 1", "photon 2", "photon 3"];
+
+/// Display the first events which pass the cuts
+///
+/// The random number generator is seeded as for the simulation, so that these
+/// are the first events that a single-threaded simulation would accept.
+///
+pub fn dump_events(
+    cfg: &Configuration,
+    process: &dyn MatrixElement,
+    evgen: &EventGenerator,
+    num_events: usize,
+) {
+    match cfg.rng {
+        RandomEngine::Ranf => dump::<RanfGenerator>(cfg, process, evgen, num_events),
+        RandomEngine::Ranlux => dump::<RanluxGenerator>(cfg, process, evgen, num_events),
+        RandomEngine::XoshiroPlus => {
+            dump::<RandGenerator<XoshiroPlus>>(cfg, process, evgen, num_events)
+        }
+        RandomEngine::Xoshiro256PlusPlus => {
+            dump::<RandGenerator<Xoshiro256PlusPlus>>(cfg, process, evgen, num_events)
+        }
+        RandomEngine::StdRng => dump::<RandGenerator<StdRng>>(cfg, process, evgen, num_events),
+        RandomEngine::Sobol => dump::<SobolGenerator>(cfg, process, evgen, num_events),
+    }
+}
+
+/// Display the first events which pass the cuts, with a certain random
+/// number generator
+fn dump<R: RandomGenerator>(
+    cfg: &Configuration,
+    process: &dyn MatrixElement,
+    evgen: &EventGenerator,
+    num_events: usize,
+) {
+    let mut rng = R::from_config(cfg);
+    let res_builder = ResultsAccumulator::new(cfg, evgen.event_weight());
+    let mut generated = 0usize;
+    for accepted in 1..=num_events {
+        let (event, measured) = loop {
+            let event = evgen.generate(&mut rng);
+            let measured = evgen.measure(&event, &mut rng).map(Cow::into_owned);
+            generated += 1;
+            if let Some(measured) = measured {
+                if cfg.event_cut.apply(&measured).is_ok() {
+                    break (event, measured);
+                }
+            }
+        };
+        info!("");
+        info!("=== Accepted event {accepted} (generated event {generated}) ===");
+        dump_momenta(&event);
+        dump_photon_angles(&event);
+        if cfg.detector.is_some() {
+            info!("Measured by the detector:");
+            dump_momenta(&measured);
+        }
+        dump_cut_values(cfg, &measured);
+
+        // Display the matrix elements, and what they contribute to the event
+        // weight (in pb, as the weight distribution reports it)
+        let contribs = process.contributions(&event);
+        let m2_sums = contribs.m2_sums();
+        let weights = res_builder.me_sigma_contribs(&event, &contribs) * cfg.num_events as Float;
+        info!(
+            "{:<12} {:>14} {:>14}",
+            "Contribution", "|M|² sum", "Weight (pb)"
+        );
+        for ((name, m2_sum), weight) in CONTRIBUTIONS.iter().zip(&m2_sums).zip(&weights) {
+            info!("{name:<12} {m2_sum:>14.6e} {weight:>14.6e}");
+        }
+        info!("{:<12} {:>14} {:>14.6e}", "Total", "", weights.sum());
+    }
+    info!("");
+}
+
+/// Display the 4-momenta of an event
+fn dump_momenta(event: &Event) {
+    info!(
+        "{:<10} {:>12} {:>12} {:>12} {:>12}",
+        "Particle", "E (GeV)", "px", "py", "pz"
+    );
+    for (name, p) in PARTICLES.iter().zip(event.all_momenta().row_iter()) {
+        info!(
+            "{name:<10} {:>12.6} {:>12.6} {:>12.6} {:>12.6}",
+            p[E], p[X], p[Y], p[Z]
+        );
+    }
+    if event.weight_factor() != 1. {
+        info!("Weight factor: {:.6}", event.weight_factor());
+    }
+}
+
+/// Display the angles between the outgoing photons
+fn dump_photon_angles(event: &Event) {
+    for ph1 in 0..NUM_OUTGOING - 1 {
+        for ph2 in ph1 + 1..NUM_OUTGOING {
+            let (p1, p2) = (event.outgoing_momentum(ph1), event.outgoing_momentum(ph2));
+            let cos = cos_angle(&p1.xyz(), &p2.xyz());
+            info!(
+                "Angle(photon {}, photon {}): {:>8.3}° (cos = {cos:.6})",
+                ph1 + 1,
+                ph2 + 1,
+                cos.clamp(-1., 1.).acos().to_degrees()
+            );
+        }
+    }
+}
+
+/// Display the quantities that the standard cuts are applied to, next to the
+/// configured thresholds
+fn dump_cut_values(cfg: &Configuration, event: &Event) {
+    let cut = &cfg.event_cut;
+    let p_beam = event.electron_momentum().xyz();
+    let photons = || (0..NUM_OUTGOING).map(|ph| event.outgoing_momentum(ph).xyz());
+
+    let min_energy = event.min_photon_energy();
+    let max_cos_beam = photons()
+        .map(|p| cos_angle(&p, &p_beam).abs())
+        .fold(0., Float::max);
+    let p_out: Vec<_> = photons().collect();
+    let max_cos_photons = (0..NUM_OUTGOING - 1)
+        .flat_map(|ph1| (ph1 + 1..NUM_OUTGOING).map(move |ph2| (ph1, ph2)))
+        .map(|(ph1, ph2)| cos_angle(&p_out[ph1], &p_out[ph2]))
+        .fold(-1., Float::max);
+    let cos_normal = cos_angle(&p_out[0].cross(&p_out[1]), &p_beam).abs();
+
+    let values = [
+        (Cut::PhotonEnergy, min_energy, ">=", cut.e_min),
+        (
+            Cut::BeamPhotonAngle,
+            max_cos_beam,
+            "<=",
+            cut.beam_photons_cut,
+        ),
+        (
+            Cut::PhotonPhotonAngle,
+            max_cos_photons,
+            "<=",
+            cut.photon_photon_cut,
+        ),
+        (
+            Cut::BeamPhotonPlaneAngle,
+            cos_normal,
+            ">=",
+            cut.beam_photon_plane_cut,
+        ),
+    ];
+    for (c, value, relation, threshold) in values {
+        let name = cut.cut_name(c);
+        info!("Cut {name:<20} {value:>12.6} {relation} {threshold:.6}");
+    }
+}
+
+/// Cosine of the angle between two 3-vectors
+fn cos_angle(v1: &Vector3<Float>, v2: &Vector3<Float>) -> Float {
+    v1.dot(v2) / (v1.norm() * v2.norm())
+}
@@ -0,0 +1,100 @@
+//! Errors which the simulation can report to its users
+
+use crate::interrupt::PartialResults;
+use std::io;
+use thiserror::Error;
+
+/// Failures of the simulation, sorted by kind so that library users can handle
+/// them differently (e.g. report configuration errors to whoever wrote the
+/// configuration, but retry after I/O errors)
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A configuration entry could not be decoded
+    #[error("Could not parse {what}")]
+    ConfigParse {
+        /// Description of what was being parsed
+        what: String,
+
+        /// Underlying parsing error
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// A configuration entry is missing (legacy format)
+    #[error("Missing configuration of {0}")]
+    MissingConfig(&'static str),
+
+    /// The configuration is well-formed, but does not make sense
+    #[error("{0}")]
+    InvalidConfig(String),
+
+    /// The cuts on generated events do not make sense
+    #[error("{0}")]
+    InvalidCut(String),
+
+    /// A file could not be read or written
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// Results could not be expressed in JSON
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    /// Results could not be expressed in YAML
+    #[error(transparent)]
+    Yaml(#[from] serde_yaml::Error),
+
+    /// A checkpoint could not be saved or restored
+    #[error("{what}")]
+    Checkpoint {
+        /// Description of the operation that failed
+        what: &'static str,
+
+        /// Underlying (de)serialization error
+        #[source]
+        source: bincode::Error,
+    },
+
+    /// The state of a random number generator could not be saved or restored
+    #[error("{what}")]
+    RngState {
+        /// Description of the operation that failed
+        what: &'static str,
+
+        /// Underlying (de)serialization error
+        #[source]
+        source: bincode::Error,
+    },
+
+    /// The checkpoint does not match the current build and configuration
+    #[error("The checkpoint was produced by a different build or configuration")]
+    CheckpointMismatch,
+
+    /// A generated event violates 4-momentum conservation or on-shellness
+    #[error("{0}")]
+    InvalidEvent(String),
+
+    /// The computation cannot be carried out in floating-point arithmetic
+    #[error("{0}")]
+    Numerical(String),
+
+    /// The simulation was interrupted (see the interrupt module), and only
+    /// produced partial results
+    #[error("The simulation was interrupted after {} events", .0.num_events())]
+    Interrupted(Box<PartialResults>),
+}
+
+/// Result type of the simulation's public interface
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Return early with an error of a certain kind if a condition is not met,
+/// mirroring anyhow's ensure!() macro
+macro_rules! ensure {
+    ($cond:expr, $kind:ident, $($msg:tt)+) => {
+        #[allow(clippy::neg_cmp_op_on_partial_ord)]
+        if !$cond {
+            return Err($crate::Error::$kind(format!($($msg)+)));
+        }
+    };
+}
+pub(crate) use ensure;
@@ -1,97 +1,182 @@
-//! Mechanism to apply a cut to generated events
+//! Mechanism to apply a cut to generated events, and to keep track of which
+//! individual cuts rejected them
+//!
+//! The cuts themselves are applied by the core crate, whose definitions are
+//! re-exported here.
 
-use crate::{
-    event::{Event, NUM_OUTGOING},
-    momentum::{E, X},
-    numeric::Float,
-};
-use prefix_num_ops::real::*;
+use crate::{error::ensure, evfilter::FilterExpression, numeric::Float, Result};
+use serde::{Deserialize, Serialize};
 
-/// Cuts on generated events
-pub struct EventCut {
-    /// Cut on maximum cosine of (beam, photons) angle
-    pub beam_photons_cut: Float,
+pub use trois_photons_core::evcut::{AdditionalCut, Cut, EventFilter};
 
-    /// Cut on maximum cosine of (photon, photon) angle
-    pub photon_photon_cut: Float,
+/// Cuts on generated events, as configured
+pub type EventCut = trois_photons_core::evcut::EventCut<Vec<AdditionalCut>, FilterExpression>;
 
-    /// Cut on minimum photon energy
-    pub e_min: Float,
+/// Check that the cuts on generated events are sensible
+pub fn check(event_cut: &EventCut) -> Result<()> {
+    let standard_cuts = [
+        ("beam_photons_cut", event_cut.beam_photons_cut),
+        ("photon_photon_cut", event_cut.photon_photon_cut),
+        ("e_min", event_cut.e_min),
+        ("beam_photon_plane_cut", event_cut.beam_photon_plane_cut),
+    ];
+    for (name, value) in standard_cuts {
+        ensure!(
+            !value.is_nan(),
+            InvalidCut,
+            "The {name} cut must be a number"
+        );
+    }
 
-    /// Cut on minimum cosine of (beam, normal to the photon plane) angle
-    pub beam_photon_plane_cut: Float,
+    // Angular cuts bound cosines, and must let some events through
+    ensure!(
+        event_cut.beam_photons_cut > 0. && event_cut.beam_photons_cut <= 1.,
+        InvalidCut,
+        "event_cut.beam_photons_cut bounds the |cosine| of the (beam, photon) angles, so it \
+         must be in ]0, 1] (got {})",
+        event_cut.beam_photons_cut
+    );
+    ensure!(
+        event_cut.photon_photon_cut > -0.5 && event_cut.photon_photon_cut <= 1.,
+        InvalidCut,
+        "event_cut.photon_photon_cut bounds the cosine of the (photon, photon) angles, so it \
+         must be in ]-0.5, 1] since two of the three photons are always at most 120° apart \
+         (got {})",
+        event_cut.photon_photon_cut
+    );
+    ensure!(
+        event_cut.beam_photon_plane_cut >= 0. && event_cut.beam_photon_plane_cut < 1.,
+        InvalidCut,
+        "event_cut.beam_photon_plane_cut bounds the |cosine| of the (beam, normal to the \
+         photon plane) angle, so it must be in [0, 1[ (got {})",
+        event_cut.beam_photon_plane_cut
+    );
+    ensure!(
+        event_cut.e_min >= 0.,
+        InvalidCut,
+        "event_cut.e_min is a minimal photon energy, so it must not be negative (got {})",
+        event_cut.e_min
+    );
+    for cut in &event_cut.additional {
+        check_additional(cut)?;
+    }
+    Ok(())
 }
-//
-impl EventCut {
-    /// Setup the cuts on generated events
-    pub fn new(
-        beam_photons_cut: Float,
-        photon_photon_cut: Float,
-        e_min: Float,
-        beam_photon_plane_cut: Float,
-    ) -> Self {
-        EventCut {
-            beam_photons_cut,
-            photon_photon_cut,
-            e_min,
-            beam_photon_plane_cut,
+
+/// Check that an additional cut does not reject every event
+fn check_additional(cut: &AdditionalCut) -> Result<()> {
+    let name = cut.name();
+    match *cut {
+        AdditionalCut::MaxPhotonEnergy { max: bound }
+        | AdditionalCut::MaxPhotonRapidity { max: bound } => {
+            ensure!(bound > 0., InvalidCut, "The {name} cut must be positive")
+        }
+        AdditionalCut::MinDiphotonMass { min } => {
+            ensure!(min >= 0., InvalidCut, "The {name} cut must not be negative")
+        }
+        AdditionalCut::PhotonIsolation {
+            min_angle,
+            min_energy,
+        } => {
+            ensure!(
+                (0. ..180.).contains(&min_angle),
+                InvalidCut,
+                "The minimal angle of the {name} cut must be in [0, 180[ degrees (got {min_angle})"
+            );
+            ensure!(
+                min_energy >= 0.,
+                InvalidCut,
+                "The energy threshold of the {name} cut must not be negative"
+            );
         }
     }
+    Ok(())
+}
 
-    /// Decide whether a generated event passes the cut or should be rejected
-    pub fn keep(&self, event: &Event) -> bool {
-        // Check if the outgoing photons pass the energy cut
-        if event.min_photon_energy() < self.e_min {
-            return false;
-        }
+/// Number of generated events which were rejected by each individual cut
+///
+/// Since cuts are applied in order, an event is only accounted for by the first
+/// cut that rejected it. When the detector is simulated, events in which it
+/// lost a photon are rejected before any cut.
+///
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CutFlow {
+    /// Number of rejected events, indexed by position of the cut
+    rejected: Vec<usize>,
 
-        // Get the incoming electron 4-momentum
-        let p_el = event.electron_momentum();
-
-        // Check if the (beam, photon) angles pass the cut
-        {
-            let ps_out = event.outgoing_momenta();
-            let ps_out_xyz = ps_out.fixed_columns::<3>(X);
-            let cos_nums = ps_out_xyz * p_el.xyz();
-            let cos_denoms = ps_out.column(E) * p_el[E];
-            for (&num, denom) in cos_nums.iter().zip(cos_denoms.iter()) {
-                if abs(num) > self.beam_photons_cut * denom {
-                    return false;
-                }
-            }
+    /// Number of events in which the detector lost a photon
+    #[serde(default)]
+    undetected: usize,
+}
+//
+impl CutFlow {
+    /// Prepare to record the events rejected by a certain set of cuts
+    pub fn new(event_cut: &EventCut) -> Self {
+        Self {
+            rejected: vec![0; event_cut.cuts().count()],
+            undetected: 0,
         }
+    }
 
-        // Check if the (photon1, photon{2, 3}) angles pass the cut
-        for ph1 in 0..NUM_OUTGOING - 1 {
-            for ph2 in ph1 + 1..NUM_OUTGOING {
-                let p_ph1 = event.outgoing_momentum(ph1);
-                let p_ph2 = event.outgoing_momentum(ph2);
-                let cos_num = p_ph1.xyz().dot(&p_ph2.xyz());
-                let cos_denom = p_ph1[E] * p_ph2[E];
-                if cos_num > self.photon_photon_cut * cos_denom {
-                    return false;
-                }
-            }
-        }
+    /// Record that an event was rejected by a certain cut
+    pub fn reject(&mut self, cut: Cut) {
+        self.rejected[cut.index()] += 1;
+    }
+
+    /// Record that the detector lost a photon of an event
+    pub fn reject_undetected(&mut self) {
+        self.undetected += 1;
+    }
 
-        // Compute a vector which is normal to the outgoing photon plane
-        // This notion is only valid because we have three output photons
-        assert_eq!(NUM_OUTGOING, 3, "This part assumes 3 outgoing particles");
-        let n_ppp = event
-            .outgoing_momentum(0)
-            .xyz()
-            .cross(&event.outgoing_momentum(1).xyz());
-
-        // Compute the cosine of the angle between the beam and this vector
-        let cos_num = p_el.xyz().dot(&n_ppp);
-        let cos_denom = p_el[E] * n_ppp.norm();
-
-        // Check if the (beam, normal to photon plane) angle passes the cut
-        if abs(cos_num) < self.beam_photon_plane_cut * cos_denom {
-            return false;
+    /// Integrate the statistics of another cut flow
+    pub fn merge(&mut self, other: &Self) {
+        for (dst, src) in self.rejected.iter_mut().zip(&other.rejected) {
+            *dst += src;
         }
+        self.undetected += other.undetected;
+    }
+
+    /// Number of events that were rejected by a certain cut
+    pub fn rejected(&self, cut: Cut) -> usize {
+        self.rejected[cut.index()]
+    }
+
+    /// Number of events in which the detector lost a photon
+    pub fn undetected(&self) -> usize {
+        self.undetected
+    }
+
+    /// Total number of rejected events, including those in which the detector
+    /// lost a photon
+    pub fn total_rejected(&self) -> usize {
+        self.rejected.iter().sum::<usize>() + self.undetected
+    }
+
+    /// Fraction of the generated events in which the detector measured all
+    /// photons
+    pub fn acceptance(&self, num_events: usize) -> Float {
+        1. - (self.undetected as Float) / (num_events as Float)
+    }
 
-        // If all checks passed, we're good
-        true
+    /// Efficiency of each cut, given the number of generated events
+    ///
+    /// The efficiency of a cut is the fraction of the events which reached it
+    /// (i.e. were detected and passed the previous cuts) that passed it.
+    ///
+    pub fn efficiencies(&self, num_events: usize) -> Vec<(Cut, Float)> {
+        let mut reaching = num_events - self.undetected;
+        (0..self.rejected.len())
+            .map(|index| {
+                let cut = Cut::from_index(index);
+                let rejected = self.rejected(cut);
+                let efficiency = if reaching > 0 {
+                    1. - (rejected as Float) / (reaching as Float)
+                } else {
+                    0.
+                };
+                reaching -= rejected;
+                (cut, efficiency)
+            })
+            .collect()
     }
 }
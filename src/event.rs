@@ -1,126 +1,207 @@
 //! This module defines the properties and storage of generated events
+//!
+//! Individual events are defined in the core crate, whose definitions are
+//! re-exported here.
 
 use crate::{
-    momentum::{Momentum, E, MOMENTUM_DIM},
-    numeric::Float,
+    error::ensure,
+    momentum::{E, MOMENTUM_DIM, X, Y, Z},
+    numeric::{real::cast, Float, Real},
+    Result,
 };
-use nalgebra::{Const, MatrixSlice, SMatrix, SVector};
-use std::fmt::Display;
+use nalgebra::{SMatrix, SVector};
+use prefix_num_ops::real::*;
+use serde::{Deserialize, Serialize};
 
-/// Number of incoming particles
-pub const NUM_INCOMING: usize = 2;
-
-/// Number of outgoing particles (replaces original INP)
-pub const NUM_OUTGOING: usize = 3;
-
-/// Number of particles in an event
-pub const NUM_PARTICLES: usize = NUM_INCOMING + NUM_OUTGOING;
-
-/// Vector type whose side is the number of particles in an event
-pub type ParticleVector<T> = SVector<T, NUM_PARTICLES>;
-
-/// Square matrix type whose side is the number of particles in an event
-pub type ParticleMatrix<T> = SMatrix<T, NUM_PARTICLES, NUM_PARTICLES>;
+pub use trois_photons_core::event::{
+    Event, EventMatrix, ParticleMatrix, ParticleVector, INCOMING_E_M, INCOMING_E_P, NUM_INCOMING,
+    NUM_OUTGOING, NUM_PARTICLES, NUM_SPINS,
+};
 
-/// Event data matrix type (columns are 4-coordinates, rows are particles)
-type EventMatrix = SMatrix<Float, NUM_PARTICLES, MOMENTUM_DIM>;
+/// Checks of the kinematics of each generated event (not in the legacy format)
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValidationConfig {
+    /// Tolerance of the checks, relative to the collision energy
+    #[serde(default = "ValidationConfig::default_tolerance")]
+    pub tolerance: Float,
+}
+//
+impl ValidationConfig {
+    /// Default tolerance of the checks, which leaves room for the rounding
+    /// errors of event generation
+    pub const DEFAULT_TOLERANCE: Float = 1000. * Float::EPSILON;
+
+    /// Default value of the tolerance configuration entry
+    fn default_tolerance() -> Float {
+        Self::DEFAULT_TOLERANCE
+    }
+}
 
-/// Slice of the event data matrix containing only outgoing particles
-type OutgoingMomentaSlice<'matrix> = MatrixSlice<
-    'matrix,
-    Float,
-    Const<NUM_OUTGOING>,
-    Const<MOMENTUM_DIM>,
-    Const<1>,
-    Const<NUM_PARTICLES>,
->;
+/// Check that the 4-momentum of an event is conserved and that all particles
+/// are on their mass shell, given the masses of the outgoing particles, within
+/// a tolerance relative to the collision energy
+///
+/// Events below the production threshold of massive outgoing particles, which
+/// the event generator leaves massless with a zero weight factor, are only
+/// checked for 4-momentum conservation.
+///
+pub fn validate(event: &Event, masses: &[Float; NUM_OUTGOING], tolerance: Float) -> Result<()> {
+    let (p_el, p_pos) = (event.electron_momentum(), event.positron_momentum());
+    let scale = p_el[E] + p_pos[E];
+    let momenta = event.all_momenta();
+    let layout = "rows are e-, e+ and photons, columns are X, Y, Z and E";
+
+    let imbalance = p_el + p_pos - event.outgoing_momenta().row_sum().transpose();
+    ensure!(
+        imbalance.amax() <= tolerance * scale,
+        InvalidEvent,
+        "4-momentum is not conserved (imbalance: {:?} GeV) in event ({layout}):{momenta}",
+        imbalance.as_slice()
+    );
+
+    let below_threshold = event.weight_factor() == 0.;
+    for par in 0..NUM_PARTICLES {
+        let p = event.momentum(par);
+        let expected_mass = match par.checked_sub(NUM_INCOMING) {
+            Some(out_par) if !below_threshold => masses[out_par],
+            _ => 0.,
+        };
+        let mass2 = p[E].powi(2) - p.fixed_rows::<3>(X).norm_squared();
+        ensure!(
+            abs(mass2 - expected_mass.powi(2)) <= tolerance * scale.powi(2) && p[E] >= 0.,
+            InvalidEvent,
+            "Particle {par} is off-shell (mass²: {mass2} GeV², expected {} GeV²) in event \
+             ({layout}):{momenta}",
+            expected_mass.powi(2)
+        );
+    }
+    Ok(())
+}
 
-/// Row of the incoming electron in the event data matrix
-pub const INCOMING_E_M: usize = 0;
+/// Storage for a batch of ee -> ppp events, in struct-of-arrays layout
+///
+/// Each coordinate of each outgoing 4-momentum is stored in its own contiguous
+/// array, which is the data layout favored by SIMD and GPU computations. The
+/// incoming 4-momenta only differ from one event to the next by the fraction
+/// of the beam energy that initial-state radiation leaves them, so the nominal
+/// ones are stored once along with these per-event fractions.
+///
+pub struct EventBatch<R: Real = Float> {
+    /// Nominal incoming electron and positron 4-momenta
+    incoming_momenta: SMatrix<R, NUM_INCOMING, MOMENTUM_DIM>,
 
-/// Index of the incoming positron in the 4-momentum array
-pub const INCOMING_E_P: usize = 1;
+    /// Fraction of the nominal energy carried by each incoming particle, for
+    /// each event
+    beam_fractions: [Vec<R>; NUM_INCOMING],
 
-/// Number of possible spin values of the outgoing particles
-pub const NUM_SPINS: usize = 2;
+    /// Coordinates of the outgoing 4-momenta, for each photon and coordinate
+    outgoing_momenta: [[Vec<R>; MOMENTUM_DIM]; NUM_OUTGOING],
 
-/// Storage for ee -> ppp event data
-///
-/// Encapsulates a vector of incoming and outgoing 4-momenta.
-///
-pub struct Event(EventMatrix);
+    /// Weight factor of each event, see Event::weight_factor()
+    weight_factors: Vec<R>,
+}
 //
-impl Event {
-    /// Build an event from an event data matrix
-    pub fn new(matrix: EventMatrix) -> Self {
-        Self(matrix)
+impl<R: Real> EventBatch<R> {
+    /// Prepare to store a certain number of events with common nominal
+    /// incoming 4-momenta
+    pub fn with_capacity(
+        incoming_momenta: SMatrix<R, NUM_INCOMING, MOMENTUM_DIM>,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            incoming_momenta,
+            beam_fractions: std::array::from_fn(|_par| Vec::with_capacity(capacity)),
+            outgoing_momenta: std::array::from_fn(|_par| {
+                std::array::from_fn(|_coord| Vec::with_capacity(capacity))
+            }),
+            weight_factors: Vec::with_capacity(capacity),
+        }
     }
 
-    /// Access the full internal 4-momentum array by reference
-    pub fn all_momenta(&self) -> &EventMatrix {
-        &self.0
+    /// Append an event to the batch
+    pub fn push(&mut self, event: &Event<R>) {
+        let momenta = event.all_momenta();
+        for (par, fractions) in self.beam_fractions.iter_mut().enumerate() {
+            let fraction = momenta[(par, E)] / self.incoming_momenta[(par, E)];
+            debug_assert!(
+                (momenta.row(par) - self.incoming_momenta.row(par) * fraction).norm()
+                    <= cast::<R>(1e-6) * momenta[(par, E)],
+                "All events of a batch should have collinear incoming momenta"
+            );
+            fractions.push(fraction);
+        }
+        let p_out = event.outgoing_momenta();
+        for (par, coords) in self.outgoing_momenta.iter_mut().enumerate() {
+            for (coord, values) in coords.iter_mut().enumerate() {
+                values.push(p_out[(par, coord)]);
+            }
+        }
+        self.weight_factors.push(event.weight_factor());
     }
 
-    /// Extract the 4-momentum of a single particle (internal for now)
-    ///
-    /// We return an owned momentum vector because that's more convenient to
-    /// handle than a slice and the compiler is smart enough to elide the copy.
-    ///
-    fn momentum(&self, par: usize) -> Momentum {
-        Momentum::from_iterator(self.0.row(par).iter().copied())
+    /// Append an event to the batch, given its outgoing energies, 3-momenta,
+    /// weight factor and incoming beam energy fractions
+    pub(crate) fn push_outgoing(
+        &mut self,
+        p_e: &SVector<R, NUM_OUTGOING>,
+        p_xyz: &SMatrix<R, NUM_OUTGOING, 3>,
+        weight_factor: R,
+        beam_fractions: [R; NUM_INCOMING],
+    ) {
+        for (fractions, fraction) in self.beam_fractions.iter_mut().zip(beam_fractions) {
+            fractions.push(fraction);
+        }
+        for (par, coords) in self.outgoing_momenta.iter_mut().enumerate() {
+            let [p_x, p_y, p_z, e] = coords;
+            p_x.push(p_xyz[(par, X)]);
+            p_y.push(p_xyz[(par, Y)]);
+            p_z.push(p_xyz[(par, Z)]);
+            e.push(p_e[par]);
+        }
+        self.weight_factors.push(weight_factor);
     }
 
-    /// Extract the electron 4-momentum
-    pub fn electron_momentum(&self) -> Momentum {
-        self.momentum(INCOMING_E_M)
+    /// Number of events in the batch
+    pub fn len(&self) -> usize {
+        self.weight_factors.len()
     }
 
-    /// Extract the positron 4-momentum
-    #[allow(dead_code)]
-    pub fn positron_momentum(&self) -> Momentum {
-        self.momentum(INCOMING_E_P)
+    /// Truth that the batch contains no event
+    pub fn is_empty(&self) -> bool {
+        self.weight_factors.is_empty()
     }
 
-    /// Extract a photon's 4-momentum
-    pub fn outgoing_momentum(&self, par: usize) -> Momentum {
-        self.momentum(NUM_INCOMING + par)
+    /// Access one coordinate of one outgoing 4-momentum, for all events
+    pub fn outgoing_coordinates(&self, par: usize, coord: usize) -> &[R] {
+        &self.outgoing_momenta[par][coord]
     }
 
-    /// Access the outgoing 4-momenta
-    pub fn outgoing_momenta(&self) -> OutgoingMomentaSlice {
-        self.0.fixed_rows::<NUM_OUTGOING>(NUM_INCOMING)
+    /// Access the weight factors of all events
+    pub fn weight_factors(&self) -> &[R] {
+        &self.weight_factors
     }
 
-    /// Minimal outgoing photon energy
-    pub fn min_photon_energy(&self) -> Float {
-        if cfg!(feature = "no-photon-sorting") {
-            // This somewhat elaborate method (compared to, say, min_by())
-            // allows us to propagate what we know about the matrix' layout and
-            // contents to the compiler, enabling better optimization.
-            let first_out_e = self.outgoing_momentum(0)[E];
-            self.outgoing_momenta()
-                .column(E)
-                .iter()
-                .skip(1)
-                .fold(first_out_e, |e1, &e2| if e1 < e2 { e1 } else { e2 })
-        } else {
-            // Use the fact that photons are sorted by decreasing energy
-            self.outgoing_momentum(NUM_OUTGOING - 1)[E]
-        }
+    /// Access the fraction of the nominal energy carried by one incoming
+    /// particle, for all events
+    pub fn beam_fractions(&self, par: usize) -> &[R] {
+        &self.beam_fractions[par]
     }
-}
 
-impl Display for Event {
-    /// Dump 4-momenta of the 3 outgoing photons
-    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let p_out = self.outgoing_momenta();
-        for coord in 0..4 {
-            write!(fmt, "{coord}\t")?;
-            for part in 0..NUM_OUTGOING {
-                write!(fmt, "{}\t", p_out[(part, coord)])?;
+    /// Extract one event of the batch
+    pub fn event(&self, index: usize) -> Event<R> {
+        let momenta = EventMatrix::from_fn(|par, coord| {
+            if par < NUM_INCOMING {
+                self.beam_fractions[par][index] * self.incoming_momenta[(par, coord)]
+            } else {
+                self.outgoing_momenta[par - NUM_INCOMING][coord][index]
             }
-            writeln!(fmt)?;
-        }
-        Ok(())
+        });
+        Event::with_weight_factor(momenta, self.weight_factors[index])
+    }
+
+    /// Iterate over the events of the batch
+    pub fn iter(&self) -> impl Iterator<Item = Event<R>> + '_ {
+        (0..self.len()).map(|index| self.event(index))
     }
 }
@@ -0,0 +1,479 @@
+//! Event filters, which select generated events beyond the cuts through an
+//! expression written in the configuration
+//!
+//! A filter expression compares observables of the event to numbers, e.g.
+//! `min_photon_energy > 5 && max_cos_theta < 0.95`. Comparisons use the `<`,
+//! `<=`, `>`, `>=`, `==` and `!=` operators, and can be combined with `&&`,
+//! `||`, `!` and parentheses, where `&&` takes precedence over `||` as usual.
+
+use crate::{
+    evcut::EventFilter,
+    event::{Event, NUM_OUTGOING},
+    momentum::E,
+    numeric::Float,
+    Error, Result,
+};
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// Event filter, as configured
+///
+/// Filters are written and read as the expression that they were parsed from.
+///
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct FilterExpression {
+    /// Text of the expression
+    text: String,
+
+    /// Parsed expression
+    root: Node,
+}
+//
+impl FilterExpression {
+    /// Parse a filter expression
+    pub fn new(text: &str) -> Result<Self> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            text,
+            tokens: &tokens,
+            next: 0,
+        };
+        let root = parser.disjunction()?;
+        if let Some(&(token, _)) = parser.tokens.get(parser.next) {
+            return Err(parser.error(format!("unexpected {token}")));
+        }
+        Ok(Self {
+            text: text.to_owned(),
+            root,
+        })
+    }
+}
+//
+impl EventFilter for FilterExpression {
+    fn keep(&self, event: &Event) -> bool {
+        self.root.eval(event)
+    }
+}
+//
+impl Display for FilterExpression {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(&self.text)
+    }
+}
+//
+impl FromStr for FilterExpression {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        Self::new(text)
+    }
+}
+//
+impl TryFrom<String> for FilterExpression {
+    type Error = Error;
+
+    fn try_from(text: String) -> Result<Self> {
+        Self::new(&text)
+    }
+}
+//
+impl From<FilterExpression> for String {
+    fn from(filter: FilterExpression) -> Self {
+        filter.text
+    }
+}
+
+/// Quantities of an event which filters can be applied to
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Observable {
+    /// Energy of the least energetic photon (GeV)
+    MinPhotonEnergy,
+
+    /// Energy of the most energetic photon (GeV)
+    MaxPhotonEnergy,
+
+    /// Maximum |cosine| of the (beam, photon) angles
+    MaxCosTheta,
+
+    /// Maximum cosine of the (photon, photon) angles
+    MaxCosPhotons,
+
+    /// |Cosine| of the (beam, normal to the photon plane) angle
+    CosNormal,
+
+    /// Maximum |rapidity| of the photons with respect to the beam axis
+    MaxPhotonRapidity,
+
+    /// Minimum invariant mass of the photon pairs (GeV)
+    MinDiphotonMass,
+
+    /// Maximum invariant mass of the photon pairs (GeV)
+    MaxDiphotonMass,
+}
+//
+impl Observable {
+    /// All observables, in the order of the documentation
+    const ALL: [Self; 8] = [
+        Self::MinPhotonEnergy,
+        Self::MaxPhotonEnergy,
+        Self::MaxCosTheta,
+        Self::MaxCosPhotons,
+        Self::CosNormal,
+        Self::MaxPhotonRapidity,
+        Self::MinDiphotonMass,
+        Self::MaxDiphotonMass,
+    ];
+
+    /// Name of the observable in filter expressions
+    fn name(self) -> &'static str {
+        match self {
+            Self::MinPhotonEnergy => "min_photon_energy",
+            Self::MaxPhotonEnergy => "max_photon_energy",
+            Self::MaxCosTheta => "max_cos_theta",
+            Self::MaxCosPhotons => "max_cos_photons",
+            Self::CosNormal => "cos_normal",
+            Self::MaxPhotonRapidity => "max_photon_rapidity",
+            Self::MinDiphotonMass => "min_diphoton_mass",
+            Self::MaxDiphotonMass => "max_diphoton_mass",
+        }
+    }
+
+    /// Value of the observable for a certain event
+    fn eval(self, event: &Event) -> Float {
+        let p_beam = event.electron_momentum().xyz();
+        let photons = || (0..NUM_OUTGOING).map(|ph| event.outgoing_momentum(ph));
+        let pairs = || {
+            (0..NUM_OUTGOING - 1)
+                .flat_map(|ph1| (ph1 + 1..NUM_OUTGOING).map(move |ph2| (ph1, ph2)))
+                .map(|(ph1, ph2)| (event.outgoing_momentum(ph1), event.outgoing_momentum(ph2)))
+        };
+        let max_cos_theta = || {
+            photons()
+                .map(|p| cos_angle(&p.xyz(), &p_beam).abs())
+                .fold(0., Float::max)
+        };
+        let diphoton_masses = || {
+            pairs().map(|(p1, p2)| {
+                let p_sum = p1 + p2;
+                (p_sum[E].powi(2) - p_sum.xyz().norm_squared())
+                    .max(0.)
+                    .sqrt()
+            })
+        };
+        match self {
+            Self::MinPhotonEnergy => event.min_photon_energy(),
+            Self::MaxPhotonEnergy => photons().map(|p| p[E]).fold(0., Float::max),
+            Self::MaxCosTheta => max_cos_theta(),
+            Self::MaxCosPhotons => pairs()
+                .map(|(p1, p2)| cos_angle(&p1.xyz(), &p2.xyz()))
+                .fold(-1., Float::max),
+            Self::CosNormal => {
+                let (p1, p2) = (event.outgoing_momentum(0), event.outgoing_momentum(1));
+                cos_angle(&p1.xyz().cross(&p2.xyz()), &p_beam).abs()
+            }
+            // For massless particles, |y| = atanh(|cos(beam, particle)|)
+            Self::MaxPhotonRapidity => max_cos_theta().atanh(),
+            Self::MinDiphotonMass => diphoton_masses().fold(Float::INFINITY, Float::min),
+            Self::MaxDiphotonMass => diphoton_masses().fold(0., Float::max),
+        }
+    }
+}
+
+/// Cosine of the angle between two 3-vectors
+fn cos_angle(v1: &Vector3<Float>, v2: &Vector3<Float>) -> Float {
+    v1.dot(v2) / (v1.norm() * v2.norm())
+}
+
+/// Operand of a comparison
+#[derive(Clone, Copy, Debug)]
+enum Operand {
+    /// Observable of the event
+    Observable(Observable),
+
+    /// Number
+    Number(Float),
+}
+//
+impl Operand {
+    /// Value of the operand for a certain event
+    fn eval(self, event: &Event) -> Float {
+        match self {
+            Self::Observable(observable) => observable.eval(event),
+            Self::Number(number) => number,
+        }
+    }
+}
+
+/// Comparison operator
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Relation {
+    /// `<`
+    Less,
+
+    /// `<=`
+    LessEqual,
+
+    /// `>`
+    Greater,
+
+    /// `>=`
+    GreaterEqual,
+
+    /// `==`
+    Equal,
+
+    /// `!=`
+    NotEqual,
+}
+//
+impl Relation {
+    /// Compare two values
+    fn holds(self, lhs: Float, rhs: Float) -> bool {
+        match self {
+            Self::Less => lhs < rhs,
+            Self::LessEqual => lhs <= rhs,
+            Self::Greater => lhs > rhs,
+            Self::GreaterEqual => lhs >= rhs,
+            Self::Equal => lhs == rhs,
+            Self::NotEqual => lhs != rhs,
+        }
+    }
+}
+
+/// Node of a parsed filter expression
+#[derive(Clone, Debug)]
+enum Node {
+    /// Comparison of two operands
+    Compare(Operand, Relation, Operand),
+
+    /// Negation of an expression
+    Not(Box<Node>),
+
+    /// Conjunction of two expressions
+    And(Box<Node>, Box<Node>),
+
+    /// Disjunction of two expressions
+    Or(Box<Node>, Box<Node>),
+}
+//
+impl Node {
+    /// Truth of the expression for a certain event
+    fn eval(&self, event: &Event) -> bool {
+        match self {
+            Self::Compare(lhs, relation, rhs) => relation.holds(lhs.eval(event), rhs.eval(event)),
+            Self::Not(node) => !node.eval(event),
+            Self::And(lhs, rhs) => lhs.eval(event) && rhs.eval(event),
+            Self::Or(lhs, rhs) => lhs.eval(event) || rhs.eval(event),
+        }
+    }
+}
+
+/// Lexical unit of a filter expression
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token<'text> {
+    /// Name of an observable
+    Identifier(&'text str),
+
+    /// Number
+    Number(Float),
+
+    /// Comparison operator
+    Relation(Relation),
+
+    /// `&&`
+    And,
+
+    /// `||`
+    Or,
+
+    /// `!`
+    Not,
+
+    /// `(`
+    Open,
+
+    /// `)`
+    Close,
+}
+//
+impl Display for Token<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Identifier(name) => write!(fmt, "name {name}"),
+            Self::Number(number) => write!(fmt, "number {number}"),
+            Self::Relation(relation) => {
+                let operator = match relation {
+                    Relation::Less => "<",
+                    Relation::LessEqual => "<=",
+                    Relation::Greater => ">",
+                    Relation::GreaterEqual => ">=",
+                    Relation::Equal => "==",
+                    Relation::NotEqual => "!=",
+                };
+                write!(fmt, "operator {operator}")
+            }
+            Self::And => write!(fmt, "operator &&"),
+            Self::Or => write!(fmt, "operator ||"),
+            Self::Not => write!(fmt, "operator !"),
+            Self::Open => write!(fmt, "opening parenthesis"),
+            Self::Close => write!(fmt, "closing parenthesis"),
+        }
+    }
+}
+
+/// Split a filter expression into tokens, along with their byte position
+fn tokenize(text: &str) -> Result<Vec<(Token<'_>, usize)>> {
+    let mut tokens = Vec::new();
+    let mut rest = text.char_indices().peekable();
+    while let Some((start, c)) = rest.next() {
+        let mut next_is = |expected| rest.next_if(|&(_, c)| c == expected).is_some();
+        let token = match c {
+            _ if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '&' if next_is('&') => Token::And,
+            '|' if next_is('|') => Token::Or,
+            '=' if next_is('=') => Token::Relation(Relation::Equal),
+            '!' if next_is('=') => Token::Relation(Relation::NotEqual),
+            '!' => Token::Not,
+            '<' if next_is('=') => Token::Relation(Relation::LessEqual),
+            '<' => Token::Relation(Relation::Less),
+            '>' if next_is('=') => Token::Relation(Relation::GreaterEqual),
+            '>' => Token::Relation(Relation::Greater),
+            _ if c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' || c == '+' => {
+                // Numbers may have an exponent, whose sign must not be
+                // mistaken for an operator
+                let mut end = start + c.len_utf8();
+                while let Some(&(pos, c)) = rest.peek() {
+                    let exponent_sign = (c == '-' || c == '+')
+                        && text[start..pos].ends_with(['e', 'E'])
+                        && text[start..pos].starts_with(|c: char| c.is_ascii_digit() || c == '.');
+                    if !(c.is_ascii_alphanumeric() || c == '_' || c == '.' || exponent_sign) {
+                        break;
+                    }
+                    end = pos + c.len_utf8();
+                    rest.next();
+                }
+                let word = &text[start..end];
+                if word.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                    Token::Identifier(word)
+                } else {
+                    Token::Number(word.parse().map_err(|_| {
+                        Error::InvalidCut(format!(
+                            "Filter {text:?} has an invalid number {word} at position {start}"
+                        ))
+                    })?)
+                }
+            }
+            _ => {
+                return Err(Error::InvalidCut(format!(
+                    "Filter {text:?} has an unexpected character {c:?} at position {start}"
+                )))
+            }
+        };
+        tokens.push((token, start));
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser of filter expressions
+struct Parser<'text, 'tokens> {
+    /// Text of the expression
+    text: &'text str,
+
+    /// Tokens of the expression
+    tokens: &'tokens [(Token<'text>, usize)],
+
+    /// Index of the next token to be parsed
+    next: usize,
+}
+//
+impl Parser<'_, '_> {
+    /// Parse a disjunction, i.e. `conjunction || conjunction || ...`
+    fn disjunction(&mut self) -> Result<Node> {
+        let mut node = self.conjunction()?;
+        while self.accept(Token::Or) {
+            node = Node::Or(Box::new(node), Box::new(self.conjunction()?));
+        }
+        Ok(node)
+    }
+
+    /// Parse a conjunction, i.e. `negation && negation && ...`
+    fn conjunction(&mut self) -> Result<Node> {
+        let mut node = self.negation()?;
+        while self.accept(Token::And) {
+            node = Node::And(Box::new(node), Box::new(self.negation()?));
+        }
+        Ok(node)
+    }
+
+    /// Parse a possibly negated comparison or parenthesized expression
+    fn negation(&mut self) -> Result<Node> {
+        if self.accept(Token::Not) {
+            return Ok(Node::Not(Box::new(self.negation()?)));
+        }
+        if self.accept(Token::Open) {
+            let node = self.disjunction()?;
+            if !self.accept(Token::Close) {
+                return Err(self.error("expected a closing parenthesis".to_owned()));
+            }
+            return Ok(node);
+        }
+        let lhs = self.operand()?;
+        let relation = match self.tokens.get(self.next) {
+            Some(&(Token::Relation(relation), _)) => relation,
+            _ => return Err(self.error("expected a comparison operator".to_owned())),
+        };
+        self.next += 1;
+        let rhs = self.operand()?;
+        Ok(Node::Compare(lhs, relation, rhs))
+    }
+
+    /// Parse an observable or a number
+    fn operand(&mut self) -> Result<Operand> {
+        let operand = match self.tokens.get(self.next) {
+            Some(&(Token::Number(number), _)) => Operand::Number(number),
+            Some(&(Token::Identifier(name), _)) => {
+                let observable = (Observable::ALL.into_iter())
+                    .find(|observable| observable.name() == name)
+                    .ok_or_else(|| {
+                        let known = (Observable::ALL.iter())
+                            .map(|observable| observable.name())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        self.error(format!(
+                            "unknown observable {name}, expected one of {known}"
+                        ))
+                    })?;
+                Operand::Observable(observable)
+            }
+            _ => return Err(self.error("expected an observable or a number".to_owned())),
+        };
+        self.next += 1;
+        Ok(operand)
+    }
+
+    /// Skip the next token if it is the expected one, and tell if it was
+    fn accept(&mut self, expected: Token<'_>) -> bool {
+        let found = matches!(self.tokens.get(self.next), Some(&(token, _)) if token == expected);
+        self.next += usize::from(found);
+        found
+    }
+
+    /// Report an error at the position of the next token
+    fn error(&self, message: String) -> Error {
+        let position = match self.tokens.get(self.next) {
+            Some(&(_, position)) => format!("position {position}"),
+            None => "the end".to_owned(),
+        };
+        Error::InvalidCut(format!(
+            "Filter {:?} is invalid at {position}: {message}",
+            self.text
+        ))
+    }
+}
@@ -1,33 +1,153 @@
 //! This module provides event generation facilities
 
 use crate::{
-    event::{Event, NUM_INCOMING, NUM_OUTGOING},
+    beam::{self, BeamSpread},
+    config::Configuration,
+    detector::{self, DetectorConfig},
+    event::{Event, EventBatch, NUM_INCOMING, NUM_OUTGOING},
+    isr::{self, StructureFunction},
+    matelems::MatrixElement,
     momentum::{E, MOMENTUM_DIM, X, Y, Z},
-    numeric::{
-        floats::{
-            consts::{FRAC_PI_2, PI},
-            MIN_POSITIVE,
-        },
-        Float,
-    },
-    random::RandomGenerator,
+    numeric::{real::*, Float, Real},
+    rambo,
+    random::{RandGenerator, RandomGenerator},
+    resacc::ResultsAccumulator,
+    vegas::{AdaptiveSamplingConfig, GridBins, GridWeights, VegasGrid, NUM_DIMS},
+    Result,
 };
-use nalgebra::{matrix, SMatrix, SVector};
-use prefix_num_ops::real::*;
+use clap::ValueEnum;
+use log::info;
+use nalgebra::{matrix, SMatrix, SVector, Vector3};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Energies and 3-momenta of the outgoing photons, along with the factor by
+/// which the event weight must be multiplied
+type OutgoingMomenta<R> = (SVector<R, NUM_OUTGOING>, SMatrix<R, NUM_OUTGOING, 3>, R);
+
+/// Outgoing photons as in OutgoingMomenta, boosted to the laboratory frame,
+/// along with the fraction of the nominal energy carried by each incoming
+/// particle
+type LabMomenta<R> = (OutgoingMomenta<R>, [R; NUM_INCOMING]);
+
+/// Orderings of the outgoing photons of generated events
+///
+/// Event outputs and histograms label the photons by their position in this
+/// order, from 1 (the hardest) to 3.
+///
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PhotonOrdering {
+    /// Decreasing energy, as in the original 3photons
+    Energy,
+
+    /// Decreasing momentum transverse to the beam axis
+    TransverseMomentum,
+
+    /// Order in which the phase space generator produced them, which has no
+    /// physical meaning
+    Generation,
+}
+//
+impl PhotonOrdering {
+    /// Name of the ordering, as in configuration files
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Energy => "energy",
+            Self::TransverseMomentum => "transverse-momentum",
+            Self::Generation => "generation",
+        }
+    }
+
+    /// Description of the ordering, as featured in output files
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Energy => "by decreasing energy",
+            Self::TransverseMomentum => "by decreasing transverse momentum",
+            Self::Generation => "in generation order",
+        }
+    }
+}
+//
+impl Default for PhotonOrdering {
+    /// Photons are sorted by decreasing energy, unless the no-photon-sorting
+    /// feature is enabled
+    fn default() -> Self {
+        if cfg!(feature = "no-photon-sorting") {
+            Self::Generation
+        } else {
+            Self::Energy
+        }
+    }
+}
+
+/// Generators of flat massless phase space, which produce the outgoing
+/// photons unless adaptive sampling is enabled
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PhaseSpaceGenerator {
+    /// RAMBO algorithm, as in the original 3photons, whose events all have
+    /// the same weight
+    #[default]
+    Rambo,
+
+    /// Uniform sampling of the Dalitz plot of the photon energies and of the
+    /// orientation of the event, whose events are weighted by the Jacobian of
+    /// this parametrization (as with adaptive sampling, without the grid)
+    Dalitz,
+}
+//
+impl PhaseSpaceGenerator {
+    /// Name of the generator, as in configuration files
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rambo => "rambo",
+            Self::Dalitz => "dalitz",
+        }
+    }
+}
 
 /// Generator of ee -> ppp events
-pub struct EventGenerator {
+///
+/// Events are generated with Float precision unless told otherwise. Beam
+/// effects and the adaptive sampling grid are always sampled with Float
+/// precision, like the random numbers that they are sampled from, and then
+/// converted to the precision of the events.
+///
+pub struct EventGenerator<R: Real = Float> {
     /// Total center-of-mass energy of the collision
-    e_total: Float,
+    e_total: R,
 
     /// Weight of generated events
-    ev_weight: Float,
+    ev_weight: R,
 
     /// Incoming electron and positron momenta
-    incoming_momenta: SMatrix<Float, NUM_INCOMING, MOMENTUM_DIM>,
+    incoming_momenta: SMatrix<R, NUM_INCOMING, MOMENTUM_DIM>,
+
+    /// Adaptive sampling grid of the random parameters (if enabled)
+    grid: Option<VegasGrid>,
+
+    /// Structure function of initial-state radiation (if enabled)
+    isr: Option<StructureFunction>,
+
+    /// Energy spread of the beams (if enabled)
+    spread: Option<BeamSpread>,
+
+    /// Masses of the outgoing particles (if any is nonzero)
+    masses: Option<[R; NUM_OUTGOING]>,
+
+    /// Simulation of the detector (if enabled)
+    detector: Option<DetectorConfig>,
+
+    /// Ordering of the outgoing photons
+    ordering: PhotonOrdering,
+
+    /// Generator of the outgoing photons, without adaptive sampling
+    phase_space: PhaseSpaceGenerator,
 }
 //
-impl EventGenerator {
+impl<R: Real> EventGenerator<R> {
     // ### CONSTRUCTION ###
 
     /// Initialize event generation for a center-of-mass energy of e_total.
@@ -35,92 +155,206 @@ impl EventGenerator {
     /// Combines former functionality of ppp constructor and IBEGIN-based lazy
     /// initialization from the original C++ 3photons code.
     ///
+    /// Fails if the collision energy is so extreme that the phase space weight
+    /// of events cannot be represented.
+    ///
     #[rustfmt::skip]
-    #[allow(clippy::assertions_on_constants)]
-    pub fn new(e_total: Float) -> Self {
-        // Check on the number of particles. The check for N<101 is gone since
-        // unlike the original RAMBO, we don't use arrays of hardcoded size.
-        assert!(NUM_OUTGOING > 1);
-
-        // Factorials for the phase space weight. Replaces the lazy
-        // initialization from the original RAMBO code with less branchy code.
-        println!("IBegin");
-        // Replaces Z[INP-1] in the original 3photons code
-        let mut z_n = ((NUM_OUTGOING - 1) as Float) * ln(FRAC_PI_2);
-        for k in 2..NUM_OUTGOING {
-            z_n -= 2. * ln((k - 1) as Float);
-        }
-        let z_n = z_n - ln((NUM_OUTGOING - 1) as Float);
-
-        // NOTE: The check on total energy is gone, because we only generate
-        //       massless photons and so the total energy will always be enough.
-        //       Counting of nonzero masses is also gone because it was unused.
-
+    pub fn new(e_total: R) -> Result<Self> {
         // All generated events will have the same weight: pre-compute it
-        let ln_weight = (2. * (NUM_OUTGOING as Float) - 4.) * ln(e_total) + z_n;
-        assert!((-180. ..=174.).contains(&ln_weight));
-        let ev_weight = exp(ln_weight);
+        let ev_weight = rambo::weight::<NUM_OUTGOING, R>(e_total)?;
 
         // Compute the incoming particle momenta
-        assert_eq!(NUM_INCOMING, 2);
+        const { assert!(NUM_INCOMING == 2) };
+        let (half_e, zero) = (e_total / cast(2.), R::zero());
         let incoming_momenta = matrix![
-            -e_total / 2., 0., 0., e_total / 2.;
-            e_total / 2.,  0., 0., e_total / 2.
+            -half_e, zero, zero, half_e;
+            half_e,  zero, zero, half_e
         ];
 
         // Construct and return the output data structure
-        EventGenerator {
+        Ok(EventGenerator {
             e_total,
             ev_weight,
             incoming_momenta,
+            grid: None,
+            isr: None,
+            spread: None,
+            masses: None,
+            detector: None,
+            ordering: PhotonOrdering::default(),
+            phase_space: PhaseSpaceGenerator::default(),
+        })
+    }
+
+    /// Initialize event generation as configured, including initial-state
+    /// radiation, beam energy spread, detector simulation and adaptive
+    /// sampling if enabled, for the outgoing particle masses of the simulated
+    /// process and with the configured photon ordering and phase space
+    /// generator
+    pub fn from_config(cfg: &Configuration, process: &dyn MatrixElement) -> Result<Self> {
+        let mut evgen = Self::new(cast(cfg.e_total))?;
+        evgen.set_outgoing_masses(process.outgoing_masses().map(cast))?;
+        evgen.set_photon_ordering(cfg.photon_ordering);
+        evgen.set_phase_space_generator(cfg.phase_space_generator);
+        if cfg.isr {
+            evgen.enable_isr(cfg.alpha);
         }
+        if let Some(sigma) = cfg.beam_spread {
+            evgen.enable_beam_spread(sigma);
+        }
+        if let Some(detector) = &cfg.detector {
+            evgen.enable_detector(detector.clone());
+        }
+        if let Some(adaptive) = &cfg.adaptive_sampling {
+            evgen.adapt_sampling(cfg, process, adaptive);
+        }
+        Ok(evgen)
     }
 
-    // ### EVENT GENERATION ###
+    /// Simulate initial-state radiation, given the fine structure constant
+    ///
+    /// Each event is then generated at a reduced collision energy, sampled
+    /// from the structure function of the incoming electron and positron, and
+    /// boosted back to the laboratory frame. This must be done before adaptive
+    /// sampling, so that the warm-up accounts for it.
+    ///
+    pub fn enable_isr(&mut self, alpha: Float) {
+        self.isr = Some(StructureFunction::new(alpha, cast(self.e_total)));
+    }
+
+    /// Smear the collision energy of each event with a Gaussian distribution
+    /// of a certain standard deviation (GeV)
+    ///
+    /// Both beams are rescaled alike, so that events remain generated in the
+    /// center-of-mass frame. Like initial-state radiation, this must be done
+    /// before adaptive sampling.
+    ///
+    pub fn enable_beam_spread(&mut self, sigma: Float) {
+        self.spread = Some(BeamSpread::new(sigma, cast(self.e_total)));
+    }
+
+    /// Simulate how a detector measures the outgoing photons of each event,
+    /// see measure()
+    ///
+    /// Like beam effects, this must be done before adaptive sampling, so that
+    /// the warm-up applies the cuts to measured events.
+    ///
+    pub fn enable_detector(&mut self, detector: DetectorConfig) {
+        self.detector = Some(detector);
+    }
+
+    /// Give masses (GeV) to the outgoing particles, which are massless unless
+    /// told otherwise
+    ///
+    /// Massive momenta are obtained by rescaling massless ones as in RAMBO,
+    /// which scales the event weight by a per-event factor. Events whose
+    /// collision energy falls below the production threshold because of beam
+    /// effects keep massless momenta, and get a zero weight factor. Massive
+    /// particles are not sorted by energy, since they are told apart by their
+    /// masses. Like beam effects, this must be done before adaptive sampling.
+    ///
+    /// Fails if the nominal collision energy is below the production threshold.
+    ///
+    pub fn set_outgoing_masses(&mut self, masses: [R; NUM_OUTGOING]) -> Result<()> {
+        rambo::check_masses(&masses, self.e_total)?;
+        self.masses = masses.iter().any(|&m| m != R::zero()).then_some(masses);
+        Ok(())
+    }
+
+    /// Choose how the outgoing photons of generated events are ordered, which
+    /// is by decreasing energy unless the no-photon-sorting feature is enabled
+    pub fn set_photon_ordering(&mut self, ordering: PhotonOrdering) {
+        self.ordering = ordering;
+    }
+
+    /// Choose how flat phase space is generated when adaptive sampling is
+    /// disabled, which is by RAMBO unless told otherwise
+    pub fn set_phase_space_generator(&mut self, generator: PhaseSpaceGenerator) {
+        self.phase_space = generator;
+    }
 
+    /// Adapt the sampling of random parameters to the simulated process, by
+    /// simulating several iterations of warm-up events
+    ///
+    /// The warm-up uses its own random number generator, so that the random
+    /// number stream of the actual simulation is unaffected. Its events are
+    /// simulated with Float precision, in which results are accumulated.
+    ///
+    pub fn adapt_sampling(
+        &mut self,
+        cfg: &Configuration,
+        process: &dyn MatrixElement,
+        adaptive: &AdaptiveSamplingConfig,
+    ) {
+        let mut warmup = EventGenerator::<Float> {
+            e_total: cast(self.e_total),
+            ev_weight: cast(self.ev_weight),
+            incoming_momenta: self.incoming_momenta.map(cast),
+            grid: None,
+            isr: self.isr,
+            spread: self.spread,
+            masses: self.masses.map(|masses| masses.map(cast)),
+            detector: self.detector.clone(),
+            ordering: self.ordering,
+            phase_space: self.phase_space,
+        };
+        warmup.warm_up(cfg, process, adaptive);
+        self.grid = warmup.grid;
+    }
+
+    // ### EVENT GENERATION ###
     /// Use a highly specialized version of the RAMBO (RAndom Momenta
     /// Beautifully Organized) algorithm from S.D. Ellis, R. Kleiss and W.J.
-    /// Stirling to generate the 4-momenta of the three outgoing photons.
+    /// Stirling to generate the 4-momenta of the three outgoing photons, or of
+    /// the massive particles that replace them (see set_outgoing_masses()).
+    /// Another flat phase space generator can be chosen instead, see
+    /// set_phase_space_generator().
     ///
-    /// All events have the same weight, it can be queried via event_weight().
+    /// With RAMBO and without adaptive sampling, all events have the same
+    /// weight, it can be queried via event_weight(). Adaptive sampling replaces RAMBO with a
+    /// dedicated parametrization of phase space, and scales the event weight by
+    /// a per-event factor, see Event::weight_factor(). So does initial-state
+    /// radiation, which also lowers the collision energy of each event.
     ///
-    /// The 4-momenta of output photons are sorted by decreasing energy.
+    /// The 4-momenta of output photons are sorted as set by
+    /// set_photon_ordering(), those of massive particles are kept in the order
+    /// of their masses.
     ///
-    pub fn generate(&self, rng: &mut RandomGenerator) -> Event {
-        // Generate massless outgoing 4-momenta in infinite phase space
-        let q = Self::generate_raw(rng);
+    pub fn generate(&self, rng: &mut impl RandomGenerator) -> Event<R> {
+        self.generate_with_bins(rng, &mut GridBins::default())
+    }
 
-        // Calculate the parameters of the conformal transformation
-        let r = q.column_sum();
-        let r_norm_2 = r[E] * r[E] - r.xyz().norm_squared();
-        let alpha = self.e_total / r_norm_2;
-        let r_norm = sqrt(r_norm_2);
-        let beta = 1. / (r_norm + r[E]);
-
-        // Perform the conformal transformation from Q's to output 4-momenta
-        let tr_q = q.transpose();
-        let tr_q_xyz = tr_q.fixed_columns::<3>(X);
-        let rq = tr_q_xyz * r.xyz();
-        let mut p_e = alpha * (r[E] * tr_q.column(E) - rq);
-        let b_rq_e = beta * rq - tr_q.column(E);
-        let mut p_xyz = alpha * (r_norm * tr_q_xyz + b_rq_e * r.xyz().transpose());
-
-        // Sort the output 4-momenta in order of decreasing energy (if enabled)
-        if cfg!(not(feature = "no-photon-sorting")) {
-            for par1 in 0..NUM_OUTGOING - 1 {
-                for par2 in par1 + 1..NUM_OUTGOING {
-                    if p_e[par2] > p_e[par1] {
-                        p_e.swap_rows(par1, par2);
-                        p_xyz.swap_rows(par1, par2);
-                    }
-                }
-            }
+    /// Generate a batch of events, as if generate() were called repeatedly
+    ///
+    /// The events are stored in struct-of-arrays layout, which is suitable for
+    /// vectorized processing. The simulation itself still processes events one
+    /// at a time, which is faster as long as cuts and matrix elements are not
+    /// vectorized, since events then never need to leave CPU registers.
+    ///
+    pub fn generate_batch(
+        &self,
+        rng: &mut impl RandomGenerator,
+        num_events: usize,
+    ) -> EventBatch<R> {
+        let mut batch = EventBatch::with_capacity(self.incoming_momenta, num_events);
+        let mut bins = GridBins::default();
+        for _ in 0..num_events {
+            let ((p_e, p_xyz, weight_factor), beam_fractions) =
+                self.generate_sorted(rng, &mut bins);
+            batch.push_outgoing(&p_e, &p_xyz, weight_factor, beam_fractions);
         }
+        batch
+    }
+
+    /// Like generate(), but also record which adaptive sampling grid bin each
+    /// random parameter fell in (if adaptive sampling is enabled)
+    fn generate_with_bins(&self, rng: &mut impl RandomGenerator, bins: &mut GridBins) -> Event<R> {
+        let ((p_e, p_xyz, weight_factor), beam_fractions) = self.generate_sorted(rng, bins);
 
         // Build the final event: incoming momenta + output 4-momenta
-        Event::new(SMatrix::from_fn(|par, coord| {
+        let momenta = SMatrix::from_fn(|par, coord| {
             if par < NUM_INCOMING {
-                self.incoming_momenta[(par, coord)]
+                beam_fractions[par] * self.incoming_momenta[(par, coord)]
             } else if coord <= Z {
                 p_xyz[(par - NUM_INCOMING, coord)]
             } else if coord == E {
@@ -128,7 +362,204 @@ impl EventGenerator {
             } else {
                 unreachable!()
             }
-        }))
+        });
+        Event::with_weight_factor(momenta, weight_factor)
+    }
+
+    /// Generate the output 4-momenta in the laboratory frame, sorted as
+    /// configured
+    fn generate_sorted(
+        &self,
+        rng: &mut impl RandomGenerator,
+        bins: &mut GridBins,
+    ) -> LabMomenta<R> {
+        // Sample the beam energy spread and the energy fractions left by
+        // initial-state radiation (if enabled), and the associated event weight
+        // factor
+        let spread_factor = match &self.spread {
+            Some(spread) => cast(spread.sample(Self::random_beam_params::<
+                { beam::NUM_RANDOM_NUMBERS },
+            >(rng))),
+            None => R::one(),
+        };
+        let (isr_fractions, isr_weight) = match &self.isr {
+            Some(isr) => Self::sample_isr(rng, isr),
+            None => ([R::one(); NUM_INCOMING], R::one()),
+        };
+        let beam_fractions = isr_fractions.map(|x| spread_factor * x);
+        let [x_el, x_pos] = beam_fractions;
+        let s_ratio = x_el * x_pos;
+
+        // Generate the output 4-momenta in the center-of-mass frame of the
+        // collision, as energies and 3-momenta
+        let e_collision = self.e_total * sqrt(s_ratio);
+        let (mut p_e, mut p_xyz, mut weight_factor) = match (&self.grid, self.phase_space) {
+            (Some(grid), _) => self.generate_dalitz(rng, Some(grid), bins, e_collision),
+            (None, PhaseSpaceGenerator::Dalitz) => {
+                self.generate_dalitz(rng, None, bins, e_collision)
+            }
+            (None, PhaseSpaceGenerator::Rambo) => self.generate_rambo(rng, e_collision),
+        };
+
+        // Give the outgoing particles their masses (if any)
+        if let Some(masses) = &self.masses {
+            match rambo::massive_transform(&p_e, &p_xyz, masses, e_collision) {
+                Some((massive_e, massive_xyz, mass_factor)) => {
+                    (p_e, p_xyz) = (massive_e, massive_xyz);
+                    weight_factor *= mass_factor;
+                }
+                None => weight_factor = R::zero(),
+            }
+        }
+
+        // The phase space volume scales as s^(N-2) and the incident flux as
+        // 1/s, which the weight of events at the nominal energy does not know
+        let weight_factor = weight_factor * isr_weight * powi(s_ratio, NUM_OUTGOING as i32 - 3);
+
+        // Boost the photons to the laboratory frame, along the beam axis where
+        // the positron travels towards +X
+        if x_el != x_pos {
+            let beta = (x_pos - x_el) / (x_pos + x_el);
+            let gamma = (x_pos + x_el) / (cast::<R>(2.) * sqrt(s_ratio));
+            for par in 0..NUM_OUTGOING {
+                let (e, p_x) = (p_e[par], p_xyz[(par, X)]);
+                p_e[par] = gamma * (e + beta * p_x);
+                p_xyz[(par, X)] = gamma * (p_x + beta * e);
+            }
+        }
+
+        // Sort the output 4-momenta in order of decreasing energy or transverse
+        // momentum (if enabled), unless the outgoing particles are told apart
+        // by their masses
+        if self.ordering != PhotonOrdering::Generation && self.masses.is_none() {
+            let hardness =
+                |p_e: &SVector<R, NUM_OUTGOING>, p_xyz: &SMatrix<R, NUM_OUTGOING, 3>, par| {
+                    match self.ordering {
+                        PhotonOrdering::Energy => p_e[par],
+                        PhotonOrdering::TransverseMomentum => {
+                            powi(p_xyz[(par, Y)], 2) + powi(p_xyz[(par, Z)], 2)
+                        }
+                        PhotonOrdering::Generation => unreachable!(),
+                    }
+                };
+            for par1 in 0..NUM_OUTGOING - 1 {
+                for par2 in par1 + 1..NUM_OUTGOING {
+                    if hardness(&p_e, &p_xyz, par2) > hardness(&p_e, &p_xyz, par1) {
+                        p_e.swap_rows(par1, par2);
+                        p_xyz.swap_rows(par1, par2);
+                    }
+                }
+            }
+        }
+        ((p_e, p_xyz, weight_factor), beam_fractions)
+    }
+
+    /// Sample the fractions of the nominal energy which the incoming electron
+    /// and positron keep after initial-state radiation, along with the factor
+    /// by which the event weight must be multiplied
+    fn sample_isr(
+        rng: &mut impl RandomGenerator,
+        isr: &StructureFunction,
+    ) -> ([R; NUM_INCOMING], R) {
+        const { assert!(NUM_INCOMING == isr::NUM_RANDOM_NUMBERS) };
+        let randoms: [Float; isr::NUM_RANDOM_NUMBERS] = Self::random_beam_params(rng);
+        let samples = randoms.map(|random| isr.sample(random));
+        (
+            samples.map(|(x, _weight)| cast(x)),
+            cast(samples.iter().map(|(_x, weight)| weight).product::<Float>()),
+        )
+    }
+
+    /// Draw the random numbers which beam effects and detector measurements are
+    /// sampled from
+    fn random_beam_params<const N: usize>(rng: &mut impl RandomGenerator) -> [Float; N] {
+        if cfg!(feature = "faster-evgen") {
+            rng.random_array()
+        } else {
+            std::array::from_fn(|_| rng.random())
+        }
+    }
+
+    /// Generate the output 4-momenta using RAMBO, with unit weight factor, for
+    /// a certain collision energy
+    fn generate_rambo(&self, rng: &mut impl RandomGenerator, e_total: R) -> OutgoingMomenta<R> {
+        // Generate massless outgoing 4-momenta in infinite phase space, then
+        // map them to the collision's phase space
+        let q = Self::generate_raw(rng);
+        let (p_e, p_xyz) = rambo::conformal_transform(&q, e_total);
+        (p_e, p_xyz, R::one())
+    }
+
+    /// Generate the output 4-momenta from the photon energies and the
+    /// orientation of the event, sampled uniformly or through the adaptive
+    /// sampling grid (if any), for a certain collision energy
+    ///
+    /// Three-body massless phase space is uniform in the energies of two
+    /// photons and in the orientation of the event, so these parameters are
+    /// well-suited to adaptive sampling since cuts and matrix elements depend
+    /// on them in a simple way. Random numbers are drawn like in RAMBO, so that
+    /// multi-threaded runs can keep track of the random number stream.
+    ///
+    fn generate_dalitz(
+        &self,
+        rng: &mut impl RandomGenerator,
+        grid: Option<&VegasGrid>,
+        bins: &mut GridBins,
+        e_total: R,
+    ) -> OutgoingMomenta<R> {
+        // Draw the random parameters, then sample the adapted ones
+        let (mut params, (cos_phi, sin_phi)) = if cfg!(feature = "faster-evgen") {
+            let uniforms = rng.random_array::<9>();
+            let sincos_phi = Self::random_unit_2d_outgoing(rng);
+            let params = std::array::from_fn(|dim| uniforms[dim]);
+            (params, (sincos_phi[(0, Y)], sincos_phi[(0, X)]))
+        } else {
+            let uniforms: [Float; NUM_OUTGOING * 4] = std::array::from_fn(|_| rng.random());
+            let params = std::array::from_fn(|dim| uniforms[dim]);
+            let phi = cast::<R>(2.) * R::PI() * cast(uniforms[NUM_DIMS]);
+            (params, (cos(phi), sin(phi)))
+        };
+        let jacobian = grid.map_or(1., |grid| grid.map(&mut params, bins));
+        let [u_1, u_2, u_cos_theta, u_psi] = params.map(cast::<R>);
+        let (one, two) = (R::one(), cast::<R>(2.));
+
+        // Map the unit square to the Dalitz triangle of the energy fractions
+        // x_i = 2 * E_i / e_total, which satisfy x_1 + x_2 + x_3 = 2
+        let x_1 = u_1;
+        let x_2 = one - x_1 + x_1 * u_2;
+        let x_3 = two - x_1 - x_2;
+        let dalitz_jacobian = two * x_1;
+
+        // Direction of the first photon, with respect to the beam axis
+        let cos_theta = two * u_cos_theta - one;
+        let sin_theta = sqrt(one - powi(cos_theta, 2));
+        let n_1 = Vector3::new(cos_theta, sin_theta * cos_phi, sin_theta * sin_phi);
+
+        // Direction of the second photon, in the plane of the event which is
+        // rotated around the first photon by an angle psi
+        let psi = two * R::PI() * u_psi;
+        let perp_1 = Vector3::new(-sin_theta, cos_theta * cos_phi, cos_theta * sin_phi);
+        let perp_2 = Vector3::new(R::zero(), -sin_phi, cos_phi);
+        let cos_12 = clamp(
+            (powi(x_3, 2) - powi(x_1, 2) - powi(x_2, 2)) / (two * x_1 * x_2),
+            -one,
+            one,
+        );
+        let sin_12 = sqrt(one - powi(cos_12, 2));
+        let n_2 = n_1 * cos_12 + (perp_1 * cos(psi) + perp_2 * sin(psi)) * sin_12;
+
+        // Deduce the output 4-momenta, the third photon balancing the others
+        let half_e = e_total / two;
+        let p_e = SVector::<R, NUM_OUTGOING>::new(x_1, x_2, x_3) * half_e;
+        let p_1 = n_1 * p_e[0];
+        let p_2 = n_2 * p_e[1];
+        let p_3 = -(p_1 + p_2);
+        let p_xyz = SMatrix::<R, NUM_OUTGOING, 3>::from_rows(&[
+            p_1.transpose(),
+            p_2.transpose(),
+            p_3.transpose(),
+        ]);
+        (p_e, p_xyz, cast::<R>(jacobian) * dalitz_jacobian)
     }
 
     /// Generate massless outgoing 4-momenta in infinite phase space
@@ -136,7 +567,7 @@ impl EventGenerator {
     /// The output momenta are provided as a matrix where rows are 4-momentum
     /// components (Px, Py, Pz, E) and columns are particles.
     ///
-    fn generate_raw(rng: &mut RandomGenerator) -> SMatrix<Float, MOMENTUM_DIM, NUM_OUTGOING> {
+    fn generate_raw(rng: &mut impl RandomGenerator) -> SMatrix<R, MOMENTUM_DIM, NUM_OUTGOING> {
         // In all operating modes, random number generation is kept
         // well-separated from computations, as it was observed that it has a
         // harmful interaction with the compiler's loop optimizations.
@@ -146,9 +577,10 @@ impl EventGenerator {
             // original 3photons did. This enables greater performance.
 
             // Generate the basic random parameters of the particles
-            let params =
-                SMatrix::<Float, NUM_OUTGOING, 3>::from_column_slice(&rng.random_array::<9>()[..]);
-            let cos_theta = params.column(0).map(|r| 2. * r - 1.);
+            let params = SMatrix::<R, NUM_OUTGOING, 3>::from_column_slice(
+                &rng.random_array::<9>().map(cast::<R>)[..],
+            );
+            let cos_theta = params.column(0).map(|r| cast::<R>(2.) * r - R::one());
             let exp_min_e = params.column(1).component_mul(&params.column(2));
             let sincos_phi = Self::random_unit_2d_outgoing(rng);
 
@@ -159,51 +591,22 @@ impl EventGenerator {
             //        logarithms. Using a vectorized ln() implementation in the
             //        computation of the energy vector should help.
             //
-            let sin_theta = cos_theta.map(|cos| sqrt(1. - cos.powi(2)));
-            let energy = exp_min_e.map(|e_me| -ln(e_me + MIN_POSITIVE));
+            let sin_theta = cos_theta.map(|cos| sqrt(R::one() - powi(cos, 2)));
+            let energy = exp_min_e.map(|e_me| -ln(e_me + R::min_positive_value()));
             SMatrix::from_fn(|coord, par| {
                 energy[par]
                     * match coord {
                         X => sin_theta[par] * sincos_phi[(par, X)],
                         Y => sin_theta[par] * sincos_phi[(par, Y)],
                         Z => cos_theta[par],
-                        E => 1.,
+                        E => R::one(),
                         _ => unreachable!(),
                     }
             })
         } else {
             // This mode targets maximal reproducibility with respect to the
             // original 3photons program, at the expense of performance.
-
-            // Generate the basic random parameters of the particles
-            const COS_THETA: usize = 0;
-            const PHI: usize = 1;
-            const EXP_MIN_E: usize = 2;
-            let params = SMatrix::<Float, 3, NUM_OUTGOING>::from_fn(|coord, _par| match coord {
-                COS_THETA => 2. * rng.random() - 1.,
-                PHI => 2. * PI * rng.random(),
-                EXP_MIN_E => rng.random() * rng.random(),
-                _ => unreachable!(),
-            });
-            let cos_theta = params.row(COS_THETA);
-            let phi = params.row(PHI);
-            let exp_min_e = params.row(EXP_MIN_E);
-
-            // Compute the outgoing momenta
-            let cos_phi = phi.map(cos);
-            let sin_phi = phi.map(sin);
-            let sin_theta = cos_theta.map(|cos| sqrt(1. - cos.powi(2)));
-            let energy = exp_min_e.map(|e_me| -ln(e_me + MIN_POSITIVE));
-            SMatrix::from_fn(|coord, par| {
-                energy[par]
-                    * match coord {
-                        X => sin_theta[par] * sin_phi[par],
-                        Y => sin_theta[par] * cos_phi[par],
-                        Z => cos_theta[par],
-                        E => 1.,
-                        _ => unreachable!(),
-                    }
-            })
+            rambo::massless_momenta(rng)
         }
     }
 
@@ -212,64 +615,168 @@ impl EventGenerator {
     /// NOTE: Similar techniques may be used to generate a vector on the unit
     ///       sphere, but that benchmarked unfavorably, likely because...
     ///
-    ///       - We perform best in SSE, in which 2 doubles fit better than 3
-    ///       - The phi trig ops are expensive, the random cos of theta isn't
-    ///       - RNG calls disturb compiler optimizations, and the 3D case brings
-    ///         more computations close to them.
-    ///       - Statistics force us to discard more points and call the RNG more
+    ///   - We perform best in SSE, in which 2 doubles fit better than 3
+    ///   - The phi trig ops are expensive, the random cos of theta isn't
+    ///   - RNG calls disturb compiler optimizations, and the 3D case brings
+    ///     more computations close to them.
+    ///   - Statistics force us to discard more points and call the RNG more
     ///
-    fn random_unit_2d_outgoing(rng: &mut RandomGenerator) -> SMatrix<Float, NUM_OUTGOING, 2> {
+    fn random_unit_2d_outgoing(rng: &mut impl RandomGenerator) -> SMatrix<R, NUM_OUTGOING, 2> {
         // Grab three random points on the unit square
-        let mut points = SMatrix::<Float, NUM_OUTGOING, 2>::from_iterator(
-            rng.random_array::<6>().iter().map(|r| 2. * r - 1.),
+        let unit_square = |r: &Float| cast::<R>(2.) * cast(*r) - R::one();
+        let mut points = SMatrix::<R, NUM_OUTGOING, 2>::from_iterator(
+            rng.random_array::<6>().iter().map(unit_square),
         );
 
         // Re-roll each point until it falls on the unit disc, and is not
         // too close to the origin (otherwise we'll get floating-point issues)
-        let mut radii2 = SVector::<Float, NUM_OUTGOING>::from_iterator(
+        let mut radii2 = SVector::<R, NUM_OUTGOING>::from_iterator(
             points.row_iter().map(|row| row.norm_squared()),
         );
         for (point_idx, radius2) in radii2.iter_mut().enumerate() {
-            const MIN_POSITIVE_2: Float = MIN_POSITIVE * MIN_POSITIVE;
-            while *radius2 > 1. || *radius2 < MIN_POSITIVE_2 {
-                let new_point = SVector::<Float, 2>::from_iterator(
-                    rng.random_array::<2>().iter().map(|r| 2. * r - 1.),
-                );
+            let min_positive_2 = R::min_positive_value() * R::min_positive_value();
+            while *radius2 > R::one() || *radius2 < min_positive_2 {
+                let new_point =
+                    SVector::<R, 2>::from_iterator(rng.random_array::<2>().iter().map(unit_square));
                 points.set_row(point_idx, &new_point.transpose());
                 *radius2 = new_point.norm_squared();
             }
         }
 
         // Now you only need to normalize to get points on the unit circle
-        let norms = radii2.map(|r2| 1. / sqrt(r2));
-        for (mut point, norm) in points.row_iter_mut().zip(norms.iter()) {
+        let norms = radii2.map(|r2| R::one() / sqrt(r2));
+        for (mut point, &norm) in points.row_iter_mut().zip(norms.iter()) {
             point.apply(|coord| *coord *= norm);
         }
         points
     }
 
-    /// Simulate the impact of N calls to "generate()" on an RNG
+    /// Simulate the impact of N calls to "generate()", each followed by a call
+    /// to "measure()", on an RNG
     ///
-    /// This function must be kept in sync with the `genrate_raw()`
-    /// implementation. Such is the price to pay for perfect reproducibility
-    /// between single-threaded and multi-threaded runs...
+    /// This function must be kept in sync with the `genrate_raw()`,
+    /// `generate_dalitz()`, `generate_sorted()` and `measure()`
+    /// implementations. Such is the
+    /// price to pay for perfect reproducibility between single-threaded and
+    /// multi-threaded runs...
     ///
-    #[cfg(all(feature = "multi-threading", not(feature = "faster-threading")))]
-    pub fn simulate_event_batch(rng: &mut RandomGenerator, num_events: usize) {
+    #[cfg(any(
+        feature = "mpi",
+        all(feature = "multi-threading", not(feature = "faster-threading"))
+    ))]
+    pub fn simulate_event_batch(&self, rng: &mut impl RandomGenerator, num_events: usize) {
         if cfg!(feature = "faster-evgen") {
             for _ in 0..num_events {
+                if self.spread.is_some() {
+                    rng.skip_array::<{ beam::NUM_RANDOM_NUMBERS }>();
+                }
+                if self.isr.is_some() {
+                    rng.skip_array::<{ isr::NUM_RANDOM_NUMBERS }>();
+                }
                 rng.skip_array::<9>();
                 Self::random_unit_2d_outgoing(rng);
+                if self.detector.is_some() {
+                    rng.skip_array::<{ detector::NUM_RANDOM_NUMBERS }>();
+                }
             }
         } else {
-            rng.skip(num_events * NUM_OUTGOING * 4);
+            let spread_randoms = self.spread.map_or(0, |_| beam::NUM_RANDOM_NUMBERS);
+            let isr_randoms = self.isr.map_or(0, |_| isr::NUM_RANDOM_NUMBERS);
+            let detector_randoms =
+                (self.detector.as_ref()).map_or(0, |_| detector::NUM_RANDOM_NUMBERS);
+            rng.skip(
+                num_events * (NUM_OUTGOING * 4 + spread_randoms + isr_randoms + detector_randoms),
+            );
         }
     }
 
     // ### EVENT PROPERTIES ###
 
     /// Access the event weight (identical for all generated events)
-    pub fn event_weight(&self) -> Float {
+    pub fn event_weight(&self) -> R {
         self.ev_weight
     }
+
+    /// Access the masses of the outgoing particles (GeV)
+    pub fn outgoing_masses(&self) -> [R; NUM_OUTGOING] {
+        self.masses.unwrap_or([R::zero(); NUM_OUTGOING])
+    }
+}
+//
+impl EventGenerator {
+    /// Simulate how the detector measures the outgoing photons of an event, if
+    /// enabled by enable_detector(), or tell that a photon was lost
+    ///
+    /// Cuts apply to the measured event, whereas matrix elements must be
+    /// evaluated on the generated one. When the detector is simulated, this
+    /// must be called right after generating each event, since it draws random
+    /// numbers.
+    ///
+    pub fn measure<'event>(
+        &self,
+        event: &'event Event,
+        rng: &mut impl RandomGenerator,
+    ) -> Option<Cow<'event, Event>> {
+        let Some(detector) = &self.detector else {
+            return Some(Cow::Borrowed(event));
+        };
+        let randoms = Self::random_beam_params::<{ detector::NUM_RANDOM_NUMBERS }>(rng);
+        detector.measure(event, randoms).map(Cow::Owned)
+    }
+
+    /// Simulate the warm-up iterations of adapt_sampling()
+    fn warm_up(
+        &mut self,
+        cfg: &Configuration,
+        process: &dyn MatrixElement,
+        adaptive: &AdaptiveSamplingConfig,
+    ) {
+        /// Offset between the seed of the simulation and that of the warm-up
+        const WARMUP_SEED_OFFSET: u64 = 0x5645_4741_5347_5249;
+        let seed = cfg.rng.effective_seed(cfg.seed) ^ WARMUP_SEED_OFFSET;
+        let mut rng = RandGenerator::<Xoshiro256PlusPlus>::with_seed(seed);
+
+        // Start from a uniform grid
+        self.grid = Some(VegasGrid::new(adaptive.bins));
+        info!("");
+        for iteration in 0..adaptive.iterations {
+            // Simulate the warm-up events, recording their weights
+            let grid = self.grid.as_ref().expect("Grid was just set up");
+            let mut weights2 = GridWeights::new(grid);
+            let mut res_builder = ResultsAccumulator::new(cfg, self.ev_weight);
+            let (mut sum_w, mut sum_w2) = (0., 0.);
+            let mut bins = GridBins::default();
+            for _ in 0..adaptive.events_per_iteration {
+                let event = self.generate_with_bins(&mut rng, &mut bins);
+                let Some(measured) = self.measure(&event, &mut rng) else {
+                    continue;
+                };
+                if cfg.event_cut.apply(&measured).is_ok() {
+                    let res_contrib = process.contributions(&event);
+                    let Some(weight) = res_builder.integrate(&event, &measured, res_contrib) else {
+                        continue;
+                    };
+                    weights2.fill(&bins, weight);
+                    sum_w += weight;
+                    sum_w2 += weight.powi(2);
+                }
+            }
+
+            // Report the cross-section estimate of this iteration
+            let n_ev = adaptive.events_per_iteration as Float;
+            let mean = sum_w / n_ev;
+            let prec = sqrt((sum_w2 / n_ev - mean.powi(2)) / (n_ev - 1.)) / abs(mean);
+            let sigma = mean * (cfg.num_events as Float);
+            info!(
+                "Adaptive sampling iteration {:>3} : sigma = {sigma:e} pb, prec = {prec:e}",
+                iteration + 1
+            );
+
+            // Refine the grid accordingly
+            self.grid
+                .as_mut()
+                .expect("Grid was just set up")
+                .refine(&weights2, adaptive.damping);
+        }
+    }
 }
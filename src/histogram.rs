@@ -0,0 +1,498 @@
+//! Lightweight histogramming of event distributions, replacing the PAW-based
+//! plotting features of the original 3photons code
+
+use crate::{
+    config::Configuration,
+    event::{Event, NUM_OUTGOING},
+    momentum::E,
+    numeric::Float,
+};
+use prefix_num_ops::real::*;
+use serde::{Deserialize, Serialize};
+use std::io::{Result, Write};
+
+/// One-dimensional weighted histogram with underflow and overflow bins
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Histogram {
+    /// Lower bound of the histogram range
+    min: Float,
+
+    /// Upper bound of the histogram range
+    max: Float,
+
+    /// Sum of weights in each bin (underflow first, overflow last)
+    sum_w: Vec<Float>,
+
+    /// Sum of squared weights in each bin (same layout as sum_w)
+    sum_w2: Vec<Float>,
+
+    /// Sum of weighted values in each bin (same layout as sum_w)
+    sum_wx: Vec<Float>,
+
+    /// Sum of weighted squared values in each bin (same layout as sum_w)
+    sum_wx2: Vec<Float>,
+
+    /// Number of values recorded in each bin (same layout as sum_w)
+    num_entries: Vec<u64>,
+}
+//
+impl Histogram {
+    /// Create a histogram with a certain number of bins over [min, max[
+    pub fn new(num_bins: usize, min: Float, max: Float) -> Self {
+        assert!(num_bins > 0, "Histograms must have at least one bin");
+        assert!(min < max, "Histogram range must not be empty");
+        Self {
+            min,
+            max,
+            sum_w: vec![0.; num_bins + 2],
+            sum_w2: vec![0.; num_bins + 2],
+            sum_wx: vec![0.; num_bins + 2],
+            sum_wx2: vec![0.; num_bins + 2],
+            num_entries: vec![0; num_bins + 2],
+        }
+    }
+
+    /// Number of regular bins (excluding underflow and overflow)
+    pub fn num_bins(&self) -> usize {
+        self.sum_w.len() - 2
+    }
+
+    /// Width of the regular bins
+    pub fn bin_width(&self) -> Float {
+        (self.max - self.min) / (self.num_bins() as Float)
+    }
+
+    /// Lower bound, upper bound, sum of weights and sum of squared weights of
+    /// each bin, starting with the underflow bin and ending with the overflow
+    /// bin (whose outer bounds are infinite)
+    pub fn bins(&self) -> impl Iterator<Item = (Float, Float, Float, Float)> + '_ {
+        let num_bins = self.num_bins();
+        let width = self.bin_width();
+        let bound = move |index: usize| match index {
+            0 => -Float::INFINITY,
+            _ if index > num_bins + 1 => Float::INFINITY,
+            _ => self.min + ((index - 1) as Float) * width,
+        };
+        (0..num_bins + 2).map(move |index| {
+            (
+                bound(index),
+                bound(index + 1),
+                self.sum_w[index],
+                self.sum_w2[index],
+            )
+        })
+    }
+
+    /// Record a weighted value
+    pub fn fill(&mut self, x: Float, weight: Float) {
+        let index = if x < self.min {
+            0
+        } else if x >= self.max {
+            self.num_bins() + 1
+        } else {
+            let bin = ((x - self.min) / self.bin_width()) as usize;
+            bin.min(self.num_bins() - 1) + 1
+        };
+        self.sum_w[index] += weight;
+        self.sum_w2[index] += weight * weight;
+        self.sum_wx[index] += weight * x;
+        self.sum_wx2[index] += weight * x * x;
+        self.num_entries[index] += 1;
+    }
+
+    /// Integrate the contents of another histogram with the same binning
+    pub fn merge(&mut self, other: &Self) {
+        assert_eq!(self.sum_w.len(), other.sum_w.len());
+        for (dst, src) in self.sum_w.iter_mut().zip(&other.sum_w) {
+            *dst += src;
+        }
+        for (dst, src) in self.sum_w2.iter_mut().zip(&other.sum_w2) {
+            *dst += src;
+        }
+        for (dst, src) in self.sum_wx.iter_mut().zip(&other.sum_wx) {
+            *dst += src;
+        }
+        for (dst, src) in self.sum_wx2.iter_mut().zip(&other.sum_wx2) {
+            *dst += src;
+        }
+        for (dst, src) in self.num_entries.iter_mut().zip(&other.num_entries) {
+            *dst += src;
+        }
+    }
+
+    /// Multiply the weights of the recorded values by a certain factor
+    pub fn scale_weights(&mut self, factor: Float) {
+        for ((w, w2), (wx, wx2)) in (self.sum_w.iter_mut().zip(&mut self.sum_w2))
+            .zip(self.sum_wx.iter_mut().zip(&mut self.sum_wx2))
+        {
+            *w *= factor;
+            *w2 *= factor.powi(2);
+            *wx *= factor;
+            *wx2 *= factor;
+        }
+    }
+
+    /// Write the histogram as a differential distribution in text form
+    ///
+    /// Each line features the bin bounds, the sum of weights divided by the
+    /// bin width, and the associated statistical uncertainty.
+    ///
+    pub fn write(&self, out: &mut impl Write, title: &str) -> Result<()> {
+        let num_bins = self.num_bins();
+        let width = self.bin_width();
+        writeln!(out, "# {title}")?;
+        writeln!(out, "# Underflow : {}", self.sum_w[0])?;
+        writeln!(out, "# Overflow  : {}", self.sum_w[num_bins + 1])?;
+        writeln!(out, "# x_low x_high value error")?;
+        for bin in 1..=num_bins {
+            let x_low = self.min + ((bin - 1) as Float) * width;
+            writeln!(
+                out,
+                "{} {} {} {}",
+                x_low,
+                x_low + width,
+                self.sum_w[bin] / width,
+                sqrt(self.sum_w2[bin]) / width
+            )?;
+        }
+        writeln!(out)?;
+        writeln!(out)
+    }
+
+    /// Write the histogram as a YODA Histo1D object with a certain path and
+    /// title, for comparisons with experimental data through Rivet
+    pub fn write_yoda(&self, out: &mut impl Write, path: &str, title: &str) -> Result<()> {
+        // Statistics of a bin, in the column order of the YODA format
+        let stats = |index: usize| {
+            (
+                self.sum_w[index],
+                self.sum_w2[index],
+                self.sum_wx[index],
+                self.sum_wx2[index],
+                self.num_entries[index],
+            )
+        };
+        let write_stats = |out: &mut dyn Write, (sum_w, sum_w2, sum_wx, sum_wx2, n)| {
+            writeln!(out, "{sum_w:e}\t{sum_w2:e}\t{sum_wx:e}\t{sum_wx2:e}\t{n:e}")
+        };
+
+        // Header, with the mean and area computed over all bins
+        let num_bins = self.num_bins();
+        let total = (0..num_bins + 2).map(stats).fold(
+            (0., 0., 0., 0., 0),
+            |(w, w2, wx, wx2, n), (bin_w, bin_w2, bin_wx, bin_wx2, bin_n)| {
+                (
+                    w + bin_w,
+                    w2 + bin_w2,
+                    wx + bin_wx,
+                    wx2 + bin_wx2,
+                    n + bin_n,
+                )
+            },
+        );
+        writeln!(out, "BEGIN YODA_HISTO1D_V2 {path}")?;
+        writeln!(out, "Path: {path}")?;
+        writeln!(out, "Title: {title}")?;
+        writeln!(out, "Type: Histo1D")?;
+        writeln!(out, "---")?;
+        writeln!(out, "# Mean: {:e}", total.2 / total.0)?;
+        writeln!(out, "# Area: {:e}", total.0)?;
+
+        // Total and out-of-range statistics
+        writeln!(
+            out,
+            "# ID\t ID\t sumw\t sumw2\t sumwx\t sumwx2\t numEntries"
+        )?;
+        write!(out, "Total   \tTotal   \t")?;
+        write_stats(out, total)?;
+        write!(out, "Underflow\tUnderflow\t")?;
+        write_stats(out, stats(0))?;
+        write!(out, "Overflow\tOverflow\t")?;
+        write_stats(out, stats(num_bins + 1))?;
+
+        // Regular bins, whose edges are computed alike for adjacent bins
+        writeln!(
+            out,
+            "# xlow\t xhigh\t sumw\t sumw2\t sumwx\t sumwx2\t numEntries"
+        )?;
+        let width = self.bin_width();
+        let edge = |index: usize| self.min + (index as Float) * width;
+        for bin in 1..=num_bins {
+            write!(out, "{:e}\t{:e}\t", edge(bin - 1), edge(bin))?;
+            write_stats(out, stats(bin))?;
+        }
+        writeln!(out, "END YODA_HISTO1D_V2")?;
+        writeln!(out)
+    }
+}
+
+/// Observables of which differential distributions can be computed
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Observable {
+    /// Energy of each photon (GeV)
+    PhotonEnergy,
+
+    /// Energy of the least energetic photon (GeV)
+    MinPhotonEnergy,
+
+    /// Energy of the most energetic photon (GeV)
+    MaxPhotonEnergy,
+
+    /// Cosine of the angle between each photon and the electron beam
+    PhotonCosTheta,
+
+    /// Invariant mass of each photon pair (GeV)
+    DiphotonMass,
+
+    /// Cosine of the angle between each photon pair
+    DiphotonCosAngle,
+
+    /// Absolute cosine of the angle between the beam and the normal to the
+    /// photon plane
+    PlaneCosTheta,
+}
+//
+impl Observable {
+    /// Observables whose distributions are computed by default
+    const DEFAULTS: [Self; 3] = [Self::PhotonEnergy, Self::PhotonCosTheta, Self::DiphotonMass];
+
+    /// Name of the observable, as in configuration files
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PhotonEnergy => "photon_energy",
+            Self::MinPhotonEnergy => "min_photon_energy",
+            Self::MaxPhotonEnergy => "max_photon_energy",
+            Self::PhotonCosTheta => "photon_cos_theta",
+            Self::DiphotonMass => "diphoton_mass",
+            Self::DiphotonCosAngle => "diphoton_cos_angle",
+            Self::PlaneCosTheta => "plane_cos_theta",
+        }
+    }
+
+    /// Whether the observable takes one value per photon, so that its
+    /// distribution can be restricted to a photon of a certain label
+    pub fn per_photon(self) -> bool {
+        matches!(self, Self::PhotonEnergy | Self::PhotonCosTheta)
+    }
+
+    /// Natural range of the observable for a certain collision energy
+    fn range(self, e_total: Float) -> (Float, Float) {
+        match self {
+            Self::PhotonEnergy | Self::MinPhotonEnergy | Self::MaxPhotonEnergy => {
+                (0., e_total / 2.)
+            }
+            Self::PhotonCosTheta | Self::DiphotonCosAngle => (-1., 1.),
+            Self::DiphotonMass => (0., e_total),
+            Self::PlaneCosTheta => (0., 1.),
+        }
+    }
+
+    /// Title of the associated differential cross-section
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::PhotonEnergy => "dsigma/dE_photon (pb/GeV)",
+            Self::MinPhotonEnergy => "dsigma/dE_min_photon (pb/GeV)",
+            Self::MaxPhotonEnergy => "dsigma/dE_max_photon (pb/GeV)",
+            Self::PhotonCosTheta => "dsigma/dcos(photon,beam) (pb)",
+            Self::DiphotonMass => "dsigma/dm_photon_photon (pb/GeV)",
+            Self::DiphotonCosAngle => "dsigma/dcos(photon,photon) (pb)",
+            Self::PlaneCosTheta => "dsigma/d|cos(plane normal,beam)| (pb)",
+        }
+    }
+
+    /// Feed the value(s) of this observable for an event into a closure,
+    /// restricting per-photon observables to the photon of a certain label
+    /// (from 1 to 3) if requested
+    fn for_each_value(self, event: &Event, photon: Option<usize>, mut f: impl FnMut(Float)) {
+        let p_el = event.electron_momentum();
+        let photons = || {
+            (0..NUM_OUTGOING)
+                .filter(move |&ph| photon.is_none_or(|label| ph + 1 == label))
+                .map(|ph| event.outgoing_momentum(ph))
+        };
+        let photon_pairs = || {
+            (0..NUM_OUTGOING).flat_map(move |ph1| {
+                (ph1 + 1..NUM_OUTGOING)
+                    .map(move |ph2| (event.outgoing_momentum(ph1), event.outgoing_momentum(ph2)))
+            })
+        };
+        match self {
+            Self::PhotonEnergy => photons().for_each(|p| f(p[E])),
+            Self::MinPhotonEnergy => f(photons().map(|p| p[E]).fold(Float::MAX, Float::min)),
+            Self::MaxPhotonEnergy => f(photons().map(|p| p[E]).fold(0., Float::max)),
+            Self::PhotonCosTheta => {
+                photons().for_each(|p| f(p.xyz().dot(&p_el.xyz()) / (p[E] * p_el[E])))
+            }
+            Self::DiphotonMass => photon_pairs().for_each(|(p1, p2)| {
+                let p_sum = p1 + p2;
+                let m2 = p_sum[E].powi(2) - p_sum.xyz().norm_squared();
+                f(sqrt(m2.max(0.)))
+            }),
+            Self::DiphotonCosAngle => {
+                photon_pairs().for_each(|(p1, p2)| f(p1.xyz().dot(&p2.xyz()) / (p1[E] * p2[E])))
+            }
+            Self::PlaneCosTheta => {
+                const { assert!(NUM_OUTGOING == 3, "This part assumes 3 outgoing particles") };
+                let n_ppp = event
+                    .outgoing_momentum(0)
+                    .xyz()
+                    .cross(&event.outgoing_momentum(1).xyz());
+                f(abs(p_el.xyz().dot(&n_ppp)) / (p_el[E] * n_ppp.norm()))
+            }
+        }
+    }
+}
+
+/// Configuration of a differential distribution (not in the legacy format)
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DistributionConfig {
+    /// Observable to be histogrammed
+    pub observable: Observable,
+
+    /// Label of the photon which a per-photon observable is restricted to,
+    /// from 1 (the hardest in the configured photon ordering) to 3 (defaults
+    /// to all photons)
+    #[serde(default)]
+    pub photon: Option<usize>,
+
+    /// Number of bins (defaults to the num_bins configuration entry)
+    #[serde(default)]
+    pub bins: Option<usize>,
+
+    /// Lower bound of the histogram (defaults to the observable's minimum)
+    #[serde(default)]
+    pub min: Option<Float>,
+
+    /// Upper bound of the histogram (defaults to the observable's maximum)
+    #[serde(default)]
+    pub max: Option<Float>,
+}
+//
+impl DistributionConfig {
+    /// Histogram range for a certain collision energy
+    pub fn range(&self, e_total: Float) -> (Float, Float) {
+        let (min, max) = self.observable.range(e_total);
+        (self.min.unwrap_or(min), self.max.unwrap_or(max))
+    }
+}
+
+/// Differential distributions of the outgoing photons, along with the label
+/// of the photon which each is restricted to (if any)
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Distributions(Vec<(Observable, Option<usize>, Histogram)>);
+//
+impl Distributions {
+    /// Set up the configured distributions, if histogramming is enabled
+    ///
+    /// If histogramming is enabled but no distribution is configured, a
+    /// standard set of distributions is computed.
+    ///
+    pub fn new(cfg: &Configuration) -> Option<Self> {
+        if !cfg.plot && cfg.distributions.is_empty() {
+            return None;
+        }
+        let default_configs;
+        let configs = if cfg.distributions.is_empty() {
+            default_configs = Observable::DEFAULTS.map(|observable| DistributionConfig {
+                observable,
+                photon: None,
+                bins: None,
+                min: None,
+                max: None,
+            });
+            &default_configs[..]
+        } else {
+            &cfg.distributions[..]
+        };
+        Some(Self(
+            configs
+                .iter()
+                .map(|dist| {
+                    let (min, max) = dist.range(cfg.e_total);
+                    let histogram = Histogram::new(dist.bins.unwrap_or(cfg.num_bins), min, max);
+                    (dist.observable, dist.photon, histogram)
+                })
+                .collect(),
+        ))
+    }
+
+    /// Record an event, with a certain weight
+    pub fn fill(&mut self, event: &Event, weight: Float) {
+        for (observable, photon, histogram) in &mut self.0 {
+            observable.for_each_value(event, *photon, |x| histogram.fill(x, weight));
+        }
+    }
+
+    /// Integrate the contents of another set of distributions
+    pub fn merge(&mut self, other: &Self) {
+        for ((_, _, histogram), (_, _, other_histogram)) in self.0.iter_mut().zip(&other.0) {
+            histogram.merge(other_histogram);
+        }
+    }
+
+    /// Multiply the weights of the recorded events by a certain factor
+    pub fn scale_weights(&mut self, factor: Float) {
+        for (_, _, histogram) in &mut self.0 {
+            histogram.scale_weights(factor);
+        }
+    }
+
+    /// Unique names, observables and histograms of the distributions, in
+    /// configuration order
+    ///
+    /// Distributions are named after their observable, followed by the label
+    /// of their photon if they are restricted to one (e.g.
+    /// `photon_energy_gamma1`), with a numeric suffix if an observable is
+    /// histogrammed several times (e.g. `photon_energy`, then
+    /// `photon_energy_2`).
+    ///
+    pub fn iter(&self) -> impl Iterator<Item = (String, Observable, &Histogram)> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(index, (observable, photon, histogram))| {
+                let occurrences = (self.0[..index].iter())
+                    .filter(|(other, other_photon, _)| {
+                        (other, other_photon) == (observable, photon)
+                    })
+                    .count();
+                let mut name = observable.name().to_owned();
+                if let Some(label) = photon {
+                    name = format!("{name}_gamma{label}");
+                }
+                if occurrences > 0 {
+                    name = format!("{name}_{}", occurrences + 1);
+                }
+                (name, *observable, histogram)
+            })
+    }
+
+    /// Title of a distribution, which tells which photon it is restricted to
+    /// (if any)
+    fn title(observable: Observable, photon: Option<usize>) -> String {
+        match photon {
+            Some(label) => format!("{} for gamma{label}", observable.title()),
+            None => observable.title().to_owned(),
+        }
+    }
+
+    /// Write the distributions as differential cross-sections in the YODA
+    /// format, under the /3PHOTONS/ path prefix
+    pub fn write_yoda(&self, out: &mut impl Write) -> Result<()> {
+        for ((name, observable, histogram), (_, photon, _)) in self.iter().zip(&self.0) {
+            let title = Self::title(observable, *photon);
+            histogram.write_yoda(out, &format!("/3PHOTONS/{name}"), &title)?;
+        }
+        Ok(())
+    }
+
+    /// Write the distributions as differential cross-sections in text form
+    pub fn write(&self, out: &mut impl Write) -> Result<()> {
+        for (observable, photon, histogram) in &self.0 {
+            histogram.write(out, &Self::title(*observable, *photon))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+//! Graceful interruption of the simulation (e.g. on Ctrl-C), which stops
+//! simulating events but keeps the results accumulated so far
+//!
+//! Once interruptions are enabled, the simulation checks whether it was asked
+//! to stop between rounds of event batches. If so, it saves a checkpoint (if
+//! checkpointing is set up) and fails with [`Error::Interrupted`], which holds
+//! the partial results.
+
+#[cfg(doc)]
+use crate::Error;
+use crate::{
+    config::Configuration,
+    numeric::Float,
+    resacc::{AccumulatorCheckpoint, ResultsAccumulator},
+    resfin::FinalResults,
+};
+use std::{
+    fmt::{self, Debug},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Whether simulations can be interrupted
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Whether simulations have been asked to stop
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Let simulations be interrupted by request()
+///
+/// Simulations then synchronize their threads periodically, as they do when
+/// checkpointing is enabled, so that they can stop in a consistent state.
+///
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Truth that simulations can be interrupted
+pub(crate) fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Ask running simulations to stop, and tell whether this was already asked
+///
+/// This function only sets a flag, so it may be called from a signal handler.
+///
+pub fn request() -> bool {
+    REQUESTED.swap(true, Ordering::Relaxed)
+}
+
+/// Truth that simulations have been asked to stop
+pub(crate) fn is_requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
+
+/// Results of a simulation which was interrupted before all of its events were
+/// simulated
+pub struct PartialResults {
+    /// Number of events that were simulated
+    num_events: usize,
+
+    /// Number of events that the simulation was configured for
+    configured_events: usize,
+
+    /// Weight of the generated events
+    event_weight: Float,
+
+    /// Results accumulated over the simulated events
+    sums: AccumulatorCheckpoint,
+}
+//
+impl PartialResults {
+    /// Record the results accumulated by an interrupted simulation
+    pub(crate) fn new(
+        num_events: usize,
+        configured_events: usize,
+        event_weight: Float,
+        sums: AccumulatorCheckpoint,
+    ) -> Self {
+        Self {
+            num_events,
+            configured_events,
+            event_weight,
+            sums,
+        }
+    }
+
+    /// Number of events that were simulated before the interruption
+    pub fn num_events(&self) -> usize {
+        self.num_events
+    }
+
+    /// Configuration of a simulation of the events that were simulated, which
+    /// the partial results must be finalized with
+    pub fn configuration(&self, cfg: &Configuration) -> Configuration {
+        let mut cfg = cfg.clone();
+        cfg.num_events = self.num_events;
+        cfg
+    }
+
+    /// Derive results from the simulated events, given the configuration from
+    /// configuration()
+    pub fn finalize(self, cfg: &Configuration) -> FinalResults<'_> {
+        // Event weights are normalized by the configured number of events,
+        // renormalize them to the number of simulated events
+        let mut sums = self.sums;
+        sums.scale_weights(self.configured_events as Float / self.num_events as Float);
+        let mut accumulator = ResultsAccumulator::new(cfg, self.event_weight);
+        accumulator.restore(sums);
+        let mut results = accumulator.finalize();
+        results.partial = true;
+        results
+    }
+}
+//
+impl Debug for PartialResults {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("PartialResults")
+            .field("num_events", &self.num_events)
+            .field("configured_events", &self.configured_events)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,70 @@
+//! Interval check mode, which bounds the rounding errors of the matrix
+//! elements by computing them with interval arithmetic over a sample of
+//! generated events
+
+use crate::cli::IntervalCheckArgs;
+use anyhow::{ensure, Context, Result};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use trois_photons::{
+    config::Configuration,
+    coupling::Couplings,
+    event::Event,
+    interval::Interval,
+    matelems::{MEsContributions, CONTRIBUTIONS, NUM_MAT_ELEMS},
+    numeric::Float,
+    random::{RandGenerator, RandomGenerator},
+    setup_event_generation,
+};
+
+/// Compute the matrix elements of events which pass the cuts with interval
+/// arithmetic, check that the intervals enclose the floating-point results,
+/// and report the worst-case relative width of each contribution
+pub fn run(args: &IntervalCheckArgs) -> Result<()> {
+    // Set up event generation as for a simulation
+    let cfg = Configuration::load_with_overlay(&args.args.config, &args.args.set, |cfg| {
+        args.args.apply_overrides(cfg)
+    })
+    .context("Failed to load the configuration")?;
+    let (process, evgen) =
+        setup_event_generation(&cfg).context("Failed to set up event generation")?;
+    let interval_couplings = Couplings::<Interval>::from(&cfg);
+    let seed = cfg.rng.effective_seed(cfg.seed);
+    let mut rng = RandGenerator::<Xoshiro256PlusPlus>::with_seed(seed);
+
+    // Check the events that pass the cuts
+    let mut checked = 0;
+    let mut worst_widths: [Float; NUM_MAT_ELEMS] = [0.; NUM_MAT_ELEMS];
+    while checked < args.events {
+        let event = evgen.generate(&mut rng);
+        if cfg.event_cut.apply(&event).is_err() {
+            continue;
+        }
+        let interval_event = Event::with_weight_factor(
+            event.all_momenta().map(Interval::from),
+            event.weight_factor().into(),
+        );
+        let m2_sums = process.contributions(&event).m2_sums();
+        let interval_m2_sums =
+            MEsContributions::new(&interval_couplings, &interval_event).m2_sums();
+        for (contrib, (&value, &interval)) in m2_sums.iter().zip(&interval_m2_sums).enumerate() {
+            ensure!(
+                interval.contains(value),
+                "The {} contribution {value} lies outside of its enclosure {interval}",
+                CONTRIBUTIONS[contrib]
+            );
+            if interval.width() > 0. {
+                worst_widths[contrib] = worst_widths[contrib].max(interval.relative_width());
+            }
+        }
+        checked += 1;
+    }
+
+    // Report the outcome
+    println!();
+    println!("Checked events     : {checked}");
+    println!("Worst-case relative width of the enclosures (and in units of epsilon):");
+    for (name, width) in CONTRIBUTIONS.iter().zip(worst_widths) {
+        println!("  {name:<16} : {width:.3e} ({:.0})", width / Float::EPSILON);
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! Initial-state radiation, which lowers the collision energy of events by
+//! letting the incoming electron and positron radiate collinear photons
+
+use crate::numeric::{floats::consts::PI, Float};
+use prefix_num_ops::real::*;
+
+/// Electron mass (GeV)
+pub const M_ELECTRON: Float = 0.000_510_998_95;
+
+/// Number of random numbers which are drawn to sample the energy fractions of
+/// the incoming electron and positron
+pub const NUM_RANDOM_NUMBERS: usize = 2;
+
+/// Electron structure function, in the beta-exponentiated leading-logarithmic
+/// approximation (exponentiated soft part, first order hard part)
+///
+/// For a beam particle of energy E, D(x) is the probability density that the
+/// particle still carries an energy x·E after emitting initial-state photons.
+///
+#[derive(Clone, Copy, Debug)]
+pub struct StructureFunction {
+    /// Large logarithm of the collision, 𝛽 = 2𝛼/π (ln(s/mₑ²) - 1)
+    beta: Float,
+}
+//
+impl StructureFunction {
+    /// Set up the structure function for a certain fine structure constant
+    /// and nominal collision energy
+    pub fn new(alpha: Float, e_total: Float) -> Self {
+        let beta = 2. * alpha / PI * (2. * ln(e_total / M_ELECTRON) - 1.);
+        Self { beta }
+    }
+
+    /// Large logarithm 𝛽 of the collision
+    pub fn beta(&self) -> Float {
+        self.beta
+    }
+
+    /// Probability density D(x) that a beam particle keeps an energy fraction x
+    pub fn density(&self, x: Float) -> Float {
+        let eta = self.beta / 2.;
+        eta * powf(1. - x, eta - 1.) * (1. + 3. / 8. * self.beta) - self.beta / 4. * (1. + x)
+    }
+
+    /// Sample an energy fraction in ]0, 1] from a uniform random number in [0, 1[
+    ///
+    /// The fraction is sampled from the integrable singularity 𝜂 (1-x)^(𝜂-1)
+    /// of the structure function, where 𝜂 = 𝛽/2, and returned along with the
+    /// factor by which the event weight must be multiplied to account for the
+    /// rest of the structure function.
+    ///
+    pub fn sample(&self, random: Float) -> (Float, Float) {
+        let eta = self.beta / 2.;
+        let one_minus_x = powf(random, 1. / eta);
+        let x = 1. - one_minus_x;
+        let weight = 1. + 3. / 8. * self.beta - (1. + x) * powf(one_minus_x, 1. - eta) / 2.;
+        (x, weight)
+    }
+}
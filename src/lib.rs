@@ -0,0 +1,414 @@
+//! 3 photons: A simple Monte Carlo simulation
+//!
+//!
+//! # Introduction (for the physicist)
+//!
+//! This small computational program computes cross-section for the particle
+//! physics process electron + positron gives three photons (e⁺e⁻ → 𝛾𝛾𝛾).
+//!
+//! It distinguishes a classical Standard Model contribution, of purely Quantum
+//! ElectroDynamic origin and an hypothetic, beyond the Standard Model, New
+//! Physics contribution, phenomenologically described by two effective
+//! operators.
+//!
+//! It was designed in the LEP era, so these new interactions occurs between the
+//! Z⁰ boson and the three photons.
+//!
+//! The effective operator can be related to specific models, among which
+//! magnetic monopoles that run in a four points loop. The two operators exhibit
+//! different (???)
+//!
+//!
+//! # Introduction (for the numerical guy)
+//!
+//! The physicist want to compute a (multidimensional) integral, so we chose a
+//! Monte Carlo algorithm
+//!
+//!
+//! # Introduction (for the computer guy)
+//!
+//! this program started in a purely procedural style:
+//!
+//! * read in parameters and initialise counters
+//! * loop over (random) event,
+//!     * determining their geometrical and energy configuration,
+//!     * their phase space weight,
+//!     * their transition probability for each polarisation/helicity
+//!       configuration, depending on coupling strength
+//!     * sum it up
+//! * then display / store the result.
+//!
+//! The use of common (for the original Fortran) or struct (in C) or record
+//! types (in Ada) or classes (in C++) illustrates an object oriented design.
+//!
+//! The fact that we can plug each phase's output as the input of the next phase
+//! lend to a functionnal approach.
+
+#![warn(missing_docs)]
+
+pub mod asymmetry;
+pub mod beam;
+pub mod cache;
+pub mod checkpoint;
+pub mod config;
+pub mod convergence;
+pub mod coupling;
+pub mod detector;
+mod error;
+pub mod evcut;
+pub mod event;
+pub mod evfilter;
+pub mod evgen;
+pub mod histogram;
+pub mod interrupt;
+pub mod isr;
+pub mod moments;
+pub mod numeric;
+pub mod output;
+pub mod process;
+pub mod provenance;
+pub mod rambo;
+pub mod random;
+pub mod resacc;
+pub mod resampling;
+pub mod resfin;
+pub mod resmerge;
+pub mod scheduling;
+pub mod simulation;
+pub mod stability;
+pub mod systematics;
+pub mod timings;
+pub mod vegas;
+pub mod weights;
+
+use crate::{
+    checkpoint::{Checkpoint, Checkpointer},
+    config::Configuration,
+    event::{Event, NUM_OUTGOING},
+    evgen::EventGenerator,
+    matelems::{MEsVector, MatrixElement},
+    numeric::Float,
+    output::events::EventWriter,
+    process::ProcessRegistry,
+    provenance::RunTimer,
+    random::{CountingGenerator, RandomGenerator, WithRandomGenerator},
+    resacc::ResultsAccumulator,
+    resfin::FinalResults,
+    stability::StabilityGuard,
+    timings::{Phase, PhaseTimer},
+};
+
+pub use crate::error::{Error, Result};
+#[cfg(feature = "validate-me")]
+pub use trois_photons_core::alternate;
+#[cfg(feature = "interval-check")]
+pub use trois_photons_core::interval;
+pub use trois_photons_core::{dirac, matelems, momentum, spinor};
+
+/// Set up the matrix elements of the configured process and the event
+/// generator of a simulation (including adaptive sampling warm-up, if
+/// configured)
+pub fn setup_event_generation(
+    cfg: &Configuration,
+) -> Result<(Box<dyn MatrixElement>, EventGenerator)> {
+    let process = ProcessRegistry::new().create(cfg)?;
+    let evgen = EventGenerator::from_config(cfg, &*process)?;
+    Ok((process, evgen))
+}
+
+/// Run the simulation using the configured random number generator
+pub fn simulate<'cfg>(
+    cfg: &'cfg Configuration,
+    process: &dyn MatrixElement,
+    evgen: &EventGenerator,
+    event_writer: Option<&EventWriter>,
+    resume: Option<Checkpoint>,
+    checkpointer: Option<Checkpointer>,
+) -> Result<FinalResults<'cfg>> {
+    simulate_observed(
+        cfg,
+        process,
+        evgen,
+        event_writer,
+        resume,
+        checkpointer,
+        &|_event, _weight| {},
+    )
+}
+
+/// Run the simulation using the configured random number generator, showing
+/// each accepted event and its weight to an observer (see Simulation)
+pub(crate) fn simulate_observed<'cfg>(
+    cfg: &'cfg Configuration,
+    process: &dyn MatrixElement,
+    evgen: &EventGenerator,
+    event_writer: Option<&EventWriter>,
+    resume: Option<Checkpoint>,
+    checkpointer: Option<Checkpointer>,
+    observer: &(impl Fn(&Event, Float) + Sync),
+) -> Result<FinalResults<'cfg>> {
+    let timer = RunTimer::start();
+    let mut results = cfg.rng.dispatch(SimulationRun {
+        cfg,
+        process,
+        evgen,
+        event_writer,
+        resume,
+        checkpointer,
+        observer,
+    })?;
+    timer.stop(&mut results.provenance);
+    Ok(results)
+}
+
+/// Arguments of simulate_with_rng(), which is run with the configured type of
+/// random number generator
+struct SimulationRun<'a, 'cfg, O> {
+    cfg: &'cfg Configuration,
+    process: &'a dyn MatrixElement,
+    evgen: &'a EventGenerator,
+    event_writer: Option<&'a EventWriter>,
+    resume: Option<Checkpoint>,
+    checkpointer: Option<Checkpointer<'a>>,
+    observer: &'a O,
+}
+//
+impl<'cfg, O: Fn(&Event, Float) + Sync> WithRandomGenerator for SimulationRun<'_, 'cfg, O> {
+    type Output = Result<FinalResults<'cfg>>;
+
+    fn run<R: RandomGenerator + 'static>(self) -> Self::Output {
+        simulate_with_rng::<R>(
+            self.cfg,
+            self.process,
+            self.evgen,
+            self.event_writer,
+            self.resume,
+            self.checkpointer,
+            self.observer,
+        )
+    }
+}
+
+/// Run the simulation using a certain kind of random number generator
+fn simulate_with_rng<'cfg, R: RandomGenerator>(
+    cfg: &'cfg Configuration,
+    process: &dyn MatrixElement,
+    evgen: &EventGenerator,
+    event_writer: Option<&EventWriter>,
+    resume: Option<Checkpoint>,
+    checkpointer: Option<Checkpointer>,
+    observer: &(impl Fn(&Event, Float) + Sync),
+) -> Result<FinalResults<'cfg>> {
+    // Set up the recomputation of unstable events, if requested
+    let guard = StabilityGuard::new(cfg);
+    let simulator = EventSimulator::new(cfg, process, evgen, guard.as_ref());
+
+    // This kernel simulates a number of events, given an initial random number
+    // generator state, and return the accumulated intermediary results
+    //
+    // The random number generator counts the random numbers that it draws, so
+    // that their generation can be timed separately from the rest of phase
+    // space sampling if per-phase timings are enabled.
+    let simulate_events = |num_events: usize,
+                           rng: &mut CountingGenerator<R>|
+     -> Result<ResultsAccumulator> {
+        // Setup a results accumulator
+        let mut res_builder = ResultsAccumulator::new(cfg, evgen.event_weight());
+
+        // Setup a buffer for the accepted events, if they are to be written
+        let mut formatted_events = event_writer.map(EventWriter::new_batch).unwrap_or_default();
+
+        // Start timing the simulation phases, if requested
+        let mut timer = PhaseTimer::new(cfg.timings);
+        let initial_rng = timer.enabled().then(|| rng.clone());
+
+        // Simulate the requested number of events
+        for _ in 0..num_events {
+            // Simulate an event, breaking down its contribution to the results
+            // by matrix element if it is to be recorded
+            let simulated = simulator.simulate_event(
+                rng,
+                &mut res_builder,
+                &mut timer,
+                event_writer.is_some(),
+            )?;
+            let Some(sigma_contrib) = simulated.sigma_contrib else {
+                continue;
+            };
+
+            // NOTE: The original code would display the result here
+
+            // Record the event as measured, if requested
+            let measured = (simulated.measured.as_ref()).expect("Accepted events are measured");
+            if let (Some(event_writer), Some(me_contribs)) = (event_writer, &simulated.me_contribs)
+            {
+                event_writer.format_event(
+                    &mut formatted_events,
+                    measured,
+                    sigma_contrib,
+                    me_contribs,
+                );
+            }
+
+            // Show it to the observer
+            observer(measured, sigma_contrib * (cfg.num_events as Float));
+            timer.lap(Phase::Accumulation);
+        }
+
+        // Write down the accepted events, if requested
+        if let Some(event_writer) = event_writer {
+            event_writer.write_batch(formatted_events)?;
+        }
+        timer.lap(Phase::Accumulation);
+
+        // Separate random number generation from phase space sampling, by
+        // drawing the same random numbers again
+        if let Some(initial_rng) = initial_rng {
+            timer.reattribute(rng.redraw_time(&initial_rng), Phase::PhaseSpace, Phase::Rng);
+        }
+        if let Some(timings) = timer.finish() {
+            res_builder.add_timings(&timings);
+        }
+
+        // Return the accumulated results
+        Ok(res_builder)
+    };
+
+    // Run the simulation
+    scheduling::run_simulation(cfg, resume, checkpointer, evgen, simulate_events)
+}
+
+/// Simulation of individual events, which the simulation kernel and the C
+/// interface share so that they generate and select events in the same way
+pub struct EventSimulator<'a> {
+    /// Configuration of the simulation
+    cfg: &'a Configuration,
+
+    /// Matrix elements of the simulated process
+    process: &'a dyn MatrixElement,
+
+    /// Phase space generator
+    evgen: &'a EventGenerator,
+
+    /// Recomputation of unstable events, if enabled
+    guard: Option<&'a StabilityGuard>,
+
+    /// Masses of the outgoing particles, which event validation checks
+    masses: [Float; NUM_OUTGOING],
+}
+//
+impl<'a> EventSimulator<'a> {
+    /// Prepare to simulate events
+    pub fn new(
+        cfg: &'a Configuration,
+        process: &'a dyn MatrixElement,
+        evgen: &'a EventGenerator,
+        guard: Option<&'a StabilityGuard>,
+    ) -> Self {
+        Self {
+            cfg,
+            process,
+            evgen,
+            guard,
+            masses: evgen.outgoing_masses(),
+        }
+    }
+
+    /// Generate an event, measure it as the detector would (if enabled), and
+    /// integrate it into the results if the measurement passes the cuts
+    ///
+    /// The contribution of accepted events is broken down by matrix element if
+    /// `me_contribs` is set, e.g. so that they can be recorded. Fails if event
+    /// validation is enabled and the event is invalid.
+    ///
+    pub fn simulate_event(
+        &self,
+        rng: &mut impl RandomGenerator,
+        res_builder: &mut ResultsAccumulator,
+        timer: &mut PhaseTimer,
+        me_contribs: bool,
+    ) -> Result<SimulatedEvent> {
+        // Generate an event, and measure it as the detector would (if
+        // enabled), which the cuts apply to
+        let event = self.evgen.generate(rng);
+        let measured = self.evgen.measure(&event, rng);
+        timer.lap(Phase::PhaseSpace);
+
+        // Check its kinematics, if requested
+        if let Some(validation) = &self.cfg.validation {
+            event::validate(&event, &self.masses, validation.tolerance)?;
+        }
+
+        // Reject the event if the detector lost a photon
+        let Some(measured) = measured else {
+            res_builder.reject_undetected();
+            timer.lap(Phase::Cuts);
+            return Ok(SimulatedEvent::rejected(event, None));
+        };
+
+        // Otherwise, record which cut rejected it, if any
+        let measured = measured.into_owned();
+        if let Err(cut) = self.cfg.event_cut.apply(&measured) {
+            res_builder.reject(cut);
+            timer.lap(Phase::Cuts);
+            return Ok(SimulatedEvent::rejected(event, Some(measured)));
+        }
+        timer.lap(Phase::Cuts);
+
+        // Compute the total weight, including matrix elements, in extended
+        // precision if the event is unstable
+        let res_contrib = match self.guard {
+            Some(guard) if guard.is_unstable(&event) => {
+                res_builder.count_extended_precision();
+                guard.contributions(&event)
+            }
+            _ => self.process.contributions(&event),
+        };
+        timer.lap(Phase::MatrixElements);
+
+        // Break it down by matrix element, if requested
+        let me_contribs = me_contribs.then(|| res_builder.me_sigma_contribs(&event, &res_contrib));
+
+        // Integrate the event's contribution into the results, unless its
+        // matrix elements are not finite
+        let sigma_contrib = res_builder.integrate(&event, &measured, res_contrib);
+        timer.lap(Phase::Accumulation);
+        Ok(SimulatedEvent {
+            event,
+            measured: Some(measured),
+            sigma_contrib,
+            me_contribs,
+        })
+    }
+}
+
+/// Event simulated by EventSimulator, along with its contribution to the
+/// results if it was accepted
+pub struct SimulatedEvent {
+    /// Event as generated, from which the matrix elements are computed
+    pub event: Event,
+
+    /// Event as measured by the detector (the generated event if it is
+    /// disabled), which the cuts and observables apply to, or None if the
+    /// detector lost a photon
+    pub measured: Option<Event>,
+
+    /// Contribution of the event to the total cross-section (pb), if it passed
+    /// the cuts and its matrix elements are finite
+    pub sigma_contrib: Option<Float>,
+
+    /// Breakdown of this contribution by matrix element, if requested
+    pub me_contribs: Option<MEsVector>,
+}
+//
+impl SimulatedEvent {
+    /// Event which did not pass the cuts
+    fn rejected(event: Event, measured: Option<Event>) -> Self {
+        Self {
+            event,
+            measured,
+            sigma_contrib: None,
+            me_contribs: None,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Console output of the log messages emitted by the simulation
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::{
+    io::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Truth that all log messages go to the standard error, because the standard
+/// output is used for something else (e.g. streaming events)
+static DIVERTED: AtomicBool = AtomicBool::new(false);
+
+/// Logger which displays informational messages on the standard output, as
+/// the original 3photons did, and warnings and errors on the standard error
+struct ConsoleLogger;
+//
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let diverted = DIVERTED.load(Ordering::Relaxed);
+        match record.level() {
+            Level::Error | Level::Warn => eprintln!("{}: {}", record.level(), record.args()),
+            Level::Info if diverted => eprintln!("{}", record.args()),
+            Level::Debug | Level::Trace if diverted => {
+                eprintln!("[{}] {}", record.target(), record.args())
+            }
+            Level::Info => println!("{}", record.args()),
+            Level::Debug | Level::Trace => println!("[{}] {}", record.target(), record.args()),
+        }
+    }
+
+    fn flush(&self) {
+        // Failing to flush the console is not worth reporting
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Display the log messages up to a certain level of verbosity
+pub fn init(level: LevelFilter) {
+    log::set_logger(&ConsoleLogger).expect("The logger should only be set up once");
+    log::set_max_level(level);
+}
+
+/// Send all subsequent log messages to the standard error, leaving the
+/// standard output free for other uses
+pub fn divert_to_stderr() {
+    DIVERTED.store(true, Ordering::Relaxed);
+}
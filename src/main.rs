@@ -1,84 +1,138 @@
-//! 3 photons: A simple Monte Carlo simulation
-//!
-//!
-//! # Introduction (for the physicist)
-//!
-//! This small computational program computes cross-section for the particle
-//! physics process electron + positron gives three photons (e⁺e⁻ → 𝛾𝛾𝛾).
-//!
-//! It distinguishes a classical Standard Model contribution, of purely Quantum
-//! ElectroDynamic origin and an hypothetic, beyond the Standard Model, New
-//! Physics contribution, phenomenologically described by two effective
-//! operators.
-//!
-//! It was designed in the LEP era, so these new interactions occurs between the
-//! Z⁰ boson and the three photons.
-//!
-//! The effective operator can be related to specific models, among which
-//! magnetic monopoles that run in a four points loop. The two operators exhibit
-//! different (???)
-//!
-//!
-//! # Introduction (for the numerical guy)
-//!
-//! The physicist want to compute a (multidimensional) integral, so we chose a
-//! Monte Carlo algorithm
-//!
-//!
-//! # Introduction (for the computer guy)
-//!
-//! this program started in a purely procedural style:
-//!
-//! * read in parameters and initialise counters
-//! * loop over (random) event,
-//!     * determining their geometrical and energy configuration,
-//!     * their phase space weight,
-//!     * their transition probability for each polarisation/helicity
-//!       configuration, depending on coupling strength
-//!     * sum it up
-//! * then display / store the result.
-//!
-//! The use of common (for the original Fortran) or struct (in C) or record
-//! types (in Ada) or classes (in C++) illustrates an object oriented design.
-//!
-//! The fact that we can plug each phase's output as the input of the next phase
-//! lend to a functionnal approach.
+//! Command-line front-end of the 3photons simulation (see the library
+//! documentation for an introduction)
 
 #![warn(missing_docs)]
 
-mod config;
-mod coupling;
-mod evcut;
-mod event;
-mod evgen;
-mod matelems;
-mod momentum;
-mod numeric;
-mod output;
-mod random;
-mod resacc;
-mod resfin;
-mod scheduling;
-mod spinor;
-
-use anyhow::Context;
-
-use crate::{
-    config::Configuration, coupling::Couplings, evgen::EventGenerator, matelems::MEsContributions,
-    random::RandomGenerator, resacc::ResultsAccumulator,
+mod bench;
+mod check;
+mod cli;
+mod compare;
+mod dump;
+#[cfg(feature = "interval-check")]
+mod intervalcheck;
+mod logging;
+mod merge;
+mod phasespacetest;
+mod precisioncheck;
+mod replay;
+mod replicas;
+mod scan;
+mod selftest;
+mod serve;
+mod signals;
+#[cfg(feature = "validate-me")]
+mod validateme;
+
+use anyhow::{bail, ensure, Context, Result};
+use clap::Parser;
+use log::{debug, info, LevelFilter};
+
+use crate::cli::{Args, Cli, Command};
+use trois_photons::{
+    cache::ResultsCache,
+    checkpoint::{Checkpoint, Checkpointer},
+    config::Configuration,
+    interrupt::PartialResults,
+    output::{self, events::EventOutputConfig},
+    resfin::FinalResults,
+    scheduling, setup_event_generation, simulate, Error,
 };
 
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-/// We'll use anyhow's type-erased result type throughout the application
-type Result<T> = anyhow::Result<T>;
+/// Name of the checkpoint file which interrupted simulations save, unless
+/// checkpointing is configured
+const INTERRUPTION_CHECKPOINT: &str = "interrupted.ckpt";
 
 /// This will act as our main function, with suitable error handling
 fn main() -> Result<()> {
+    // Parse the command-line arguments
+    let cli = Cli::parse();
+
+    // Join the other processes of the MPI job, if any, which only communicate
+    // from the main thread of each process
+    #[cfg(feature = "mpi")]
+    let _universe = mpi::initialize_with_threading(mpi::Threading::Funneled);
+
+    // Only let the main process of an MPI job report progress
+    logging::init(if scheduling::is_main_process() {
+        cli.log_level()
+    } else {
+        cli.log_level().min(LevelFilter::Warn)
+    });
+
+    // Other operating modes would run on each process of an MPI job
+    ensure!(
+        (cli.command.is_none() && cli.replicas.is_none()) || scheduling::num_processes() == 1,
+        "Only single simulation runs can be distributed across MPI processes"
+    );
+
+    // Run in the requested operating mode
+    match &cli.command {
+        None => match cli.replicas {
+            Some(num_replicas) => replicas::run(&cli.args, num_replicas),
+            None => run(&cli.args),
+        },
+        Some(Command::Scan(scan_args)) => scan::run(scan_args),
+        Some(Command::SelfTest(self_test_args)) => selftest::run(self_test_args),
+        Some(Command::PhaseSpaceTest(phase_space_test_args)) => {
+            phasespacetest::run(phase_space_test_args)
+        }
+        Some(Command::Replay(replay_args)) => replay::run(replay_args),
+        Some(Command::Serve(serve_args)) => serve::run(serve_args),
+        Some(Command::Compare(compare_args)) => compare::run(compare_args),
+        Some(Command::Bench(bench_args)) => bench::run(bench_args),
+        Some(Command::Check(check_args)) => check::run(check_args),
+        Some(Command::Merge(merge_args)) => merge::run(merge_args),
+        Some(Command::PrecisionCheck(precision_check_args)) => {
+            precisioncheck::run(precision_check_args)
+        }
+        #[cfg(feature = "interval-check")]
+        Some(Command::IntervalCheck(interval_check_args)) => {
+            intervalcheck::run(interval_check_args)
+        }
+        #[cfg(feature = "validate-me")]
+        Some(Command::ValidateMe(validate_me_args)) => validateme::run(validate_me_args),
+    }
+}
+
+/// Run the simulation once, and output the results
+fn run(args: &Args) -> Result<()> {
     // ### CONFIGURATION READOUT ###
 
-    // Load the configuration from its file
-    let cfg = Configuration::load("valeurs").context("Failed to load the configuration")?;
+    // Load the configuration from its file, with command-line overrides, and
+    // keep the standard output clean before the configuration is printed out
+    // if events are streamed there
+    let cfg = Configuration::load_with_overlay(&args.config, &args.set, |cfg| {
+        args.apply_overrides(cfg);
+        if cfg
+            .event_output
+            .as_ref()
+            .is_some_and(EventOutputConfig::streams_to_stdout)
+        {
+            logging::divert_to_stderr();
+        }
+    })
+    .context("Failed to load the configuration")?;
+
+    // Reuse the results of an identical run, if they are cached
+    ensure!(
+        cfg.cache_dir.is_none() || scheduling::num_processes() == 1,
+        "Results cannot be cached when the simulation is distributed across MPI processes"
+    );
+    let cache = ResultsCache::new(&cfg);
+    if let Some(cache) = &cache {
+        let cached = cache
+            .load(&cfg)
+            .context("Failed to load the cached results")?;
+        if let Some((result, elapsed_time)) = cached {
+            return output_results(&cfg, &result, elapsed_time);
+        }
+    }
+
+    // Output partial results and save a checkpoint if the simulation is
+    // interrupted, unless it is distributed across MPI processes
+    let interruptible = scheduling::num_processes() == 1 && signals::trap_interruptions();
 
     // ### SIMULATION INITIALIZATION ###
 
@@ -92,56 +146,166 @@ fn main() -> Result<()> {
     // NOTE: Deleted the original WTEV value. In the C++ code, it was
     //       overwritten by the first RAMBO call w/o having ever been read!
 
-    // Compute physical couplings
-    let couplings = Couplings::new(&cfg);
-
-    // Initialize the event generator
-    let evgen = EventGenerator::new(cfg.e_total);
+    // Set up the matrix elements of the configured process and initialize the
+    // event generator, reporting it like the original code did (this includes
+    // adaptive sampling warm-up, if requested)
+    let (process, evgen) =
+        setup_event_generation(&cfg).context("Failed to set up event generation")?;
+    info!("IBegin");
 
-    // ### SIMULATION EXECUTION ###
-
-    // This kernel simulates a number of events, given an initial random number
-    // generator state, and return the accumulated intermediary results
-    let simulate_events = |num_events: usize, rng: &mut RandomGenerator| -> ResultsAccumulator {
-        // Setup a results accumulator
-        let mut res_builder = ResultsAccumulator::new(&cfg, evgen.event_weight());
+    // Display the first accepted events, if requested
+    if let Some(num_events) = args.dump_events {
+        if scheduling::is_main_process() {
+            dump::dump_events(&cfg, &*process, &evgen, num_events);
+        }
+    }
 
-        // Simulate the requested number of events
-        for _ in 0..num_events {
-            // Generate an event
-            let event = evgen.generate(rng);
+    // Load the checkpoint of an interrupted simulation, if requested
+    let resume = args
+        .resume
+        .as_deref()
+        .map(|path| Checkpoint::load(path, &cfg))
+        .transpose()
+        .context("Failed to load the checkpoint")?;
 
-            // If the event passes the cut...
-            if cfg.event_cut.keep(&event) {
-                // Compute the total weight, including matrix elements
-                let res_contrib = MEsContributions::new(&couplings, &event);
+    // Prepare to write accepted events to disk, if requested
+    let event_writer = cfg
+        .event_output
+        .as_ref()
+        .map(|output| {
+            let resume_events = resume
+                .as_ref()
+                .and_then(|checkpoint| checkpoint.events.as_ref());
+            output.writer(&cfg, &*process, &evgen, resume_events)
+        })
+        .transpose()
+        .context("Failed to set up event output")?;
 
-                // NOTE: The original code would display the result here
+    // Set up periodic checkpointing, if requested, and otherwise save a
+    // checkpoint if the simulation is interrupted (unless events are streamed,
+    // since these cannot be rewound)
+    let interruption_checkpoint = cfg
+        .output_path(INTERRUPTION_CHECKPOINT)
+        .to_string_lossy()
+        .into_owned();
+    let streams_events = cfg
+        .event_output
+        .as_ref()
+        .is_some_and(|output| output.stream);
+    let checkpointer = Checkpointer::new(&cfg, event_writer.as_ref()).or_else(|| {
+        (interruptible && !streams_events).then(|| {
+            Checkpointer::on_interruption(&cfg, &interruption_checkpoint, event_writer.as_ref())
+        })
+    });
+    let checkpoint_path = match &cfg.checkpoint {
+        Some(checkpoint) => Some(&checkpoint.path),
+        None => (!streams_events).then_some(&interruption_checkpoint),
+    };
 
-                // Integrate the event's contribution into the results
-                res_builder.integrate(res_contrib);
+    // ### SIMULATION EXECUTION ###
 
-                // NOTE: The FORTRAN code would fill histograms here
-            }
+    // Run the simulation
+    let result = match simulate(
+        &cfg,
+        &*process,
+        &evgen,
+        event_writer.as_ref(),
+        resume,
+        checkpointer,
+    ) {
+        Err(Error::Interrupted(partial)) => {
+            return output_partial_results(&cfg, *partial, checkpoint_path, start_time.elapsed());
         }
-
-        // Return the accumulated results
-        res_builder
+        result => result.context("Failed to run the simulation")?,
     };
 
-    // Run the simulation
-    let result = scheduling::run_simulation(cfg.num_events, simulate_events);
-
-    // NOTE: This is where the FORTRAN code would normalize histograms
-
     // ### RESULTS DISPLAY AND STORAGE ###
 
+    // If the simulation was distributed across MPI processes, only the main
+    // process outputs the results
+    if !scheduling::is_main_process() {
+        return Ok(());
+    }
+
     // Measure how much time has elapsed
     let elapsed_time = start_time.elapsed();
+    debug!("Simulation took {elapsed_time:?}");
 
-    // Send the results to the standard output and to disk
-    output::dump_results(&cfg, &result, elapsed_time).context("Failed to output the results")?;
+    // Send the results to the standard output and to disk, and cache them
+    output_results(&cfg, &result, elapsed_time)?;
+    if let Some(cache) = &cache {
+        cache
+            .store(&result)
+            .context("Failed to store the results in the cache")?;
+    }
+    if let Some(event_writer) = event_writer {
+        event_writer
+            .finish(&cfg, &result)
+            .context("Failed to write the accepted events")?;
+    }
 
     // ...and we're done
     Ok(())
 }
+
+/// Send the results of an interrupted simulation to the standard output and
+/// to the configured output files, then report the interruption
+fn output_partial_results(
+    cfg: &Configuration,
+    partial: PartialResults,
+    checkpoint_path: Option<&String>,
+    elapsed_time: Duration,
+) -> Result<()> {
+    let partial_cfg = partial.configuration(cfg);
+    let result = partial.finalize(&partial_cfg);
+    output_results(&partial_cfg, &result, elapsed_time)?;
+    let interruption = format!(
+        "The simulation was interrupted after {} of {} events",
+        partial_cfg.num_events, cfg.num_events
+    );
+    match checkpoint_path {
+        Some(path) => bail!("{interruption}, resume it with --resume {path}"),
+        None => bail!("{interruption}"),
+    }
+}
+
+/// Send the results of a simulation to the standard output and to the
+/// configured output files
+fn output_results(
+    cfg: &Configuration,
+    result: &FinalResults,
+    elapsed_time: Duration,
+) -> Result<()> {
+    output::dump_results(cfg, result, elapsed_time).context("Failed to output the results")?;
+    if let Some(path) = &cfg.json_output {
+        result
+            .write_json(path)
+            .context("Failed to write the JSON results")?;
+    }
+    if let Some(path) = &cfg.yoda_output {
+        result
+            .write_yoda(path)
+            .context("Failed to write the YODA histograms")?;
+    }
+    if let Some(path) = &cfg.summary_yaml {
+        result
+            .append_summary_yaml(path)
+            .context("Failed to write the YAML summary")?;
+    }
+    if let Some(path) = &cfg.bad_events_output {
+        (result.weight_issues)
+            .write_events(path)
+            .context("Failed to write the events of pathological weight")?;
+    }
+    if let Some(path) = &cfg.convergence_output {
+        result
+            .write_convergence_csv(path)
+            .context("Failed to write the convergence history")?;
+    }
+    if let Some(grid) = &cfg.coupling_grid {
+        result
+            .write_coupling_grid(grid)
+            .context("Failed to write the anomalous coupling grid")?;
+    }
+    Ok(())
+}
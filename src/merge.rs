@@ -0,0 +1,56 @@
+//! Merge mode, which combines the results of independent simulation runs (e.g.
+//! from different machines) into those of a single, larger run
+
+use crate::cli::MergeArgs;
+use anyhow::{Context, Result};
+use log::info;
+use std::time::Duration;
+use trois_photons::{
+    output,
+    resmerge::{self, RunResults},
+};
+
+/// Read the JSON results of several simulation runs, merge them, then output
+/// the merged results like the simulation would
+pub fn run(args: &MergeArgs) -> Result<()> {
+    // Read the results of each run, and check that they can be merged
+    let runs = (args.inputs.iter())
+        .map(|path| {
+            RunResults::load(path).with_context(|| format!("Failed to read the results of {path}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut cfg = resmerge::merged_configuration(&runs).context("Cannot merge these results")?;
+    cfg.json_output = args.json.clone();
+    cfg.yoda_output = args.yoda.clone();
+
+    // Write the merged results to the working directory, rather than over
+    // those of the first run
+    cfg.output_dir = None;
+    cfg.output_name = None;
+    info!(
+        "Merging the results of {} runs, for a total of {} events",
+        runs.len(),
+        cfg.num_events
+    );
+
+    // The merged results took as long to simulate as all runs together
+    let elapsed_time = (runs.iter())
+        .filter_map(|run| run.provenance.wall_time)
+        .map(Duration::from_secs_f64)
+        .sum();
+
+    // Merge the results, then output them as in the default mode
+    let result = resmerge::merge(&cfg, runs).context("Failed to merge the results")?;
+    output::dump_results(&cfg, &result, elapsed_time).context("Failed to output the results")?;
+    if let Some(path) = &cfg.json_output {
+        result
+            .write_json(path)
+            .context("Failed to write the JSON results")?;
+    }
+    if let Some(path) = &cfg.yoda_output {
+        result
+            .write_yoda(path)
+            .context("Failed to write the YODA histograms")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+//! Legendre moments of the photon angular distribution, which characterize it
+//! without the binning artifacts of histograms
+//!
+//! The moment of order ℓ is the cross-section-weighted mean of P_ℓ(cos θ_γ),
+//! where θ_γ is the angle between a photon and the electron beam and each
+//! photon gets a third of the event weight. The distribution of cos θ_γ,
+//! normalized to unit integral, is then ½ Σ_ℓ (2ℓ + 1) ⟨P_ℓ⟩ P_ℓ(cos θ_γ).
+
+use crate::{
+    config::Configuration,
+    event::{Event, NUM_OUTGOING},
+    momentum::E,
+    numeric::{Float, WeightedMean},
+};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+/// Accumulator of the Legendre moments over events
+#[derive(Clone, Deserialize, Serialize)]
+pub struct MomentAccumulator {
+    /// Weighted means of P_ℓ(cos θ_γ), for ℓ from 1 to the highest order
+    moments: Vec<WeightedMean>,
+}
+//
+impl MomentAccumulator {
+    /// Set up the accumulation of the moments, if they are configured
+    pub fn new(cfg: &Configuration) -> Option<Self> {
+        cfg.legendre_moments.map(|max_order| Self {
+            moments: vec![WeightedMean::default(); max_order],
+        })
+    }
+
+    /// Record an event, with a certain weight
+    pub fn fill(&mut self, event: &Event, weight: Float) {
+        // Average the Legendre polynomials over photons, computing them using
+        // Bonnet's recursion formula
+        let p_el = event.electron_momentum();
+        let mut sums = vec![0.; self.moments.len()];
+        for ph in 0..NUM_OUTGOING {
+            let p = event.outgoing_momentum(ph);
+            let cos_theta = p.xyz().dot(&p_el.xyz()) / (p[E] * p_el[E]);
+            let (mut p_prev, mut p_curr) = (1., cos_theta);
+            for (order, sum) in (1..).zip(&mut sums) {
+                *sum += p_curr;
+                let p_next = ((2 * order + 1) as Float * cos_theta * p_curr
+                    - order as Float * p_prev)
+                    / (order + 1) as Float;
+                (p_prev, p_curr) = (p_curr, p_next);
+            }
+        }
+        for (moment, sum) in self.moments.iter_mut().zip(sums) {
+            moment.add(sum / NUM_OUTGOING as Float, weight);
+        }
+    }
+
+    /// Integrate the contents of another accumulator
+    pub fn merge(&mut self, other: &Self) {
+        for (moment, other_moment) in self.moments.iter_mut().zip(&other.moments) {
+            moment.merge(*other_moment);
+        }
+    }
+
+    /// Multiply the weights of the recorded events by a certain factor
+    pub fn scale_weights(&mut self, factor: Float) {
+        for moment in &mut self.moments {
+            moment.scale_weights(factor);
+        }
+    }
+
+    /// Compute the moments and their statistical errors, given the number of
+    /// generated events
+    pub fn finalize(&self, num_events: usize) -> LegendreMoments {
+        LegendreMoments {
+            values: self.moments.iter().map(WeightedMean::mean).collect(),
+            stddevs: (self.moments.iter())
+                .map(|moment| moment.stddev(num_events))
+                .collect(),
+        }
+    }
+}
+
+/// Legendre moments ⟨P_ℓ(cos θ_γ)⟩ of the photon angular distribution, with
+/// their statistical errors
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LegendreMoments {
+    /// Moments of order 1 to the highest configured order
+    pub values: Vec<Float>,
+
+    /// Standard deviations of the moments
+    pub stddevs: Vec<Float>,
+}
+//
+impl LegendreMoments {
+    /// Display the moments and their statistical errors
+    pub fn print(&self) {
+        info!("Legendre moments   :      Value | Std. dev.");
+        for (order, (value, stddev)) in (1..).zip(self.values.iter().zip(&self.stddevs)) {
+            let name = format!("<P_{order}(cos theta)>");
+            info!("{name:<19}: {value:>+10.6} | {stddev:.6}");
+        }
+        info!("");
+    }
+}
@@ -1,37 +1,161 @@
 //! Basic numerical concepts used throughout the program
 
-#![allow(missing_docs)]
+use serde::{Deserialize, Serialize};
 
-// Floating-point precision is configured here
-#[cfg(feature = "f32")]
-pub type Float = f32;
-#[cfg(feature = "f32")]
-pub use std::f32 as floats;
-#[cfg(not(feature = "f32"))]
-pub type Float = f64;
-#[cfg(not(feature = "f32"))]
-pub use std::f64 as floats;
-pub type Complex = num_complex::Complex<Float>;
+// Floating-point precision is configured in the core crate
+pub use trois_photons_core::numeric::{floats, functions, real, Complex, Float, Real};
 
-/// Mathematical functions
-pub mod functions {
-    /// Compute the conjugate of a Complex number
-    pub fn conj(z: super::Complex) -> super::Complex {
-        z.conj()
+/// Running sum of floating-point numbers, using Neumaier's variant of Kahan
+/// summation to compensate for rounding errors
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct CompensatedSum {
+    /// Naive sum of the inputs
+    sum: Float,
+
+    /// Accumulated rounding error of the naive sum (with opposite sign)
+    compensation: Float,
+}
+//
+impl CompensatedSum {
+    /// Add a number to the sum
+    #[inline]
+    pub fn add(&mut self, x: Float) {
+        let new_sum = self.sum + x;
+        self.compensation += if self.sum.abs() >= x.abs() {
+            (self.sum - new_sum) + x
+        } else {
+            (x - new_sum) + self.sum
+        };
+        self.sum = new_sum;
+    }
+
+    /// Integrate the contents of another sum
+    pub fn merge(&mut self, other: Self) {
+        self.add(other.sum);
+        self.compensation += other.compensation;
+    }
+
+    /// Multiply the sum by a certain factor
+    pub fn scale(&mut self, factor: Float) {
+        self.sum *= factor;
+        self.compensation *= factor;
+    }
+
+    /// Compensated value of the sum
+    pub fn value(&self) -> Float {
+        self.sum + self.compensation
+    }
+
+    /// Estimated rounding error of naive summation, relative to the sum
+    pub fn relative_rounding_error(&self) -> Float {
+        (self.compensation / self.value()).abs()
+    }
+}
+
+/// Weighted mean of a quantity over the simulated events
+///
+/// Since the mean is a ratio of two weighted sums over the same events, its
+/// statistical error is estimated by linearizing the ratio around its value.
+///
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct WeightedMean {
+    /// Accumulated weighted values
+    values: CompensatedSum,
+
+    /// Accumulated squared weighted values
+    value_squares: CompensatedSum,
+
+    /// Accumulated products of the weighted values with the weights
+    products: CompensatedSum,
+
+    /// Accumulated weights
+    weights: CompensatedSum,
+
+    /// Accumulated squared weights
+    weight_squares: CompensatedSum,
+}
+//
+impl WeightedMean {
+    /// Account for the value of the quantity for an event of a certain weight
+    #[inline]
+    pub fn add(&mut self, value: Float, weight: Float) {
+        let weighted_value = value * weight;
+        self.values.add(weighted_value);
+        self.value_squares.add(weighted_value.powi(2));
+        self.products.add(weighted_value * weight);
+        self.weights.add(weight);
+        self.weight_squares.add(weight.powi(2));
+    }
+
+    /// Integrate the contents of another weighted mean
+    pub fn merge(&mut self, other: Self) {
+        self.values.merge(other.values);
+        self.value_squares.merge(other.value_squares);
+        self.products.merge(other.products);
+        self.weights.merge(other.weights);
+        self.weight_squares.merge(other.weight_squares);
+    }
+
+    /// Multiply the weights of the accumulated events by a certain factor
+    pub fn scale_weights(&mut self, factor: Float) {
+        self.values.scale(factor);
+        self.value_squares.scale(factor.powi(2));
+        self.products.scale(factor.powi(2));
+        self.weights.scale(factor);
+        self.weight_squares.scale(factor.powi(2));
+    }
+
+    /// Value of the weighted mean
+    pub fn mean(&self) -> Float {
+        self.values.value() / self.weights.value()
     }
 
-    /// Compute the squared norm of a Complex number
-    pub fn norm_sqr(z: super::Complex) -> super::Float {
-        z.norm_sqr()
+    /// Standard deviation of the weighted mean, given the number of generated
+    /// events (those which were not accounted for have zero weight)
+    pub fn stddev(&self, num_events: usize) -> Float {
+        let n_ev = num_events as Float;
+        let mean = self.mean();
+        let residual_squares = self.value_squares.value() - 2. * mean * self.products.value()
+            + mean.powi(2) * self.weight_squares.value();
+        let variance = n_ev / (n_ev - 1.) * residual_squares / self.weights.value().powi(2);
+        variance.max(0.).sqrt()
     }
+}
 
-    /// Get the real part of of a Complex number
-    pub fn re(z: super::Complex) -> super::Float {
-        z.re
+/// Regularly spaced range of values, including both bounds
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct GridRange {
+    /// First value of the range
+    pub from: Float,
+
+    /// Last value of the range (up to rounding errors)
+    pub to: Float,
+
+    /// Spacing between two consecutive values
+    pub step: Float,
+}
+//
+impl GridRange {
+    /// Check that the range is non-empty and has a positive spacing
+    pub fn check(&self, name: &str) -> crate::Result<()> {
+        crate::error::ensure!(
+            self.from <= self.to,
+            InvalidConfig,
+            "The {name} range must not be empty"
+        );
+        crate::error::ensure!(
+            self.step > 0.,
+            InvalidConfig,
+            "The {name} step must be positive"
+        );
+        Ok(())
     }
 
-    /// Get the imaginary part of of a Complex number
-    pub fn im(z: super::Complex) -> super::Float {
-        z.im
+    /// Values of the range, tolerating rounding errors on the upper bound
+    pub fn values(&self) -> impl Iterator<Item = Float> {
+        let num_points = ((self.to - self.from) / self.step + 1e-6).floor() as usize + 1;
+        let GridRange { from, step, .. } = *self;
+        (0..num_points).map(move |point| from + (point as Float) * step)
     }
 }
@@ -0,0 +1,201 @@
+//! Transparent compression of output files, selected by their extension
+//!
+//! Files whose path ends with `.gz` are compressed with gzip, and files whose
+//! path ends with `.zst` are compressed with Zstandard (both require the
+//! compressed-output feature). Since both formats allow concatenating
+//! compressed streams, compressed files can also be appended to.
+
+#[cfg(not(feature = "compressed-output"))]
+use crate::Error;
+use crate::Result as AnyResult;
+#[cfg(feature = "compressed-output")]
+use flate2::{read::MultiGzDecoder, write::GzEncoder};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Result, Write},
+};
+
+/// Compression of a file, as selected by the extension of its path
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    /// Uncompressed file
+    None,
+
+    /// gzip compression (extension `.gz`)
+    Gzip,
+
+    /// Zstandard compression (extension `.zst`)
+    Zstd,
+}
+//
+impl Compression {
+    /// Compression of the file at a certain path
+    pub fn from_path(path: &str) -> Self {
+        let lowercase = path.to_ascii_lowercase();
+        if lowercase.ends_with(".gz") {
+            Self::Gzip
+        } else if lowercase.ends_with(".zst") {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+
+    /// Path of the file without its compression extension, e.g. for the sake
+    /// of deducing the format of its contents
+    pub fn strip_extension(path: &str) -> &str {
+        match Self::from_path(path) {
+            Self::None => path,
+            Self::Gzip => &path[..path.len() - ".gz".len()],
+            Self::Zstd => &path[..path.len() - ".zst".len()],
+        }
+    }
+
+    /// Check that files can be written to a certain path, i.e. that its
+    /// compression is supported by this build
+    pub fn check_path(path: &str) -> AnyResult<()> {
+        match Self::from_path(path) {
+            Self::None => Ok(()),
+            #[cfg(feature = "compressed-output")]
+            Self::Gzip | Self::Zstd => Ok(()),
+            #[cfg(not(feature = "compressed-output"))]
+            Self::Gzip | Self::Zstd => Err(Error::InvalidConfig(format!(
+                "Writing compressed files such as {path} requires the compressed-output feature"
+            ))),
+        }
+    }
+}
+
+/// Output file, compressed according to the extension of its path
+///
+/// Unlike a BufWriter, which silently ignores errors when it is dropped, an
+/// OutputFile must be finished to write the end of the compressed stream.
+///
+pub struct OutputFile(BufWriter<Encoder>);
+//
+impl OutputFile {
+    /// Create or truncate the file at a certain path
+    pub fn create(path: &str) -> Result<Self> {
+        Self::new(path, Box::new(File::create(path)?))
+    }
+
+    /// Open the file at a certain path for appending, creating it if needed
+    pub fn append(path: &str) -> Result<Self> {
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+        Self::new(path, Box::new(file))
+    }
+
+    /// Write to the standard output, without compression
+    pub fn stdout() -> Self {
+        Self(BufWriter::new(Encoder::Plain(Box::new(io::stdout()))))
+    }
+
+    /// Compress the data sent to some destination according to the
+    /// extension of a path
+    fn new(path: &str, out: Box<dyn Write + Send>) -> Result<Self> {
+        let encoder = match Compression::from_path(path) {
+            Compression::None => Encoder::Plain(out),
+            #[cfg(feature = "compressed-output")]
+            Compression::Gzip => Encoder::Gzip(GzEncoder::new(out, flate2::Compression::default())),
+            #[cfg(feature = "compressed-output")]
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(out, 0)?),
+            #[cfg(not(feature = "compressed-output"))]
+            Compression::Gzip | Compression::Zstd => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("Writing {path} requires the compressed-output feature"),
+                ))
+            }
+        };
+        Ok(Self(BufWriter::new(encoder)))
+    }
+
+    /// Write the end of the compressed stream, if any, and flush the file
+    pub fn finish(self) -> Result<()> {
+        self.0.into_inner().map_err(|e| e.into_error())?.finish()
+    }
+}
+//
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.0.write_all(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Compression stage of an OutputFile
+enum Encoder {
+    /// No compression
+    Plain(Box<dyn Write + Send>),
+
+    /// gzip compression
+    #[cfg(feature = "compressed-output")]
+    Gzip(GzEncoder<Box<dyn Write + Send>>),
+
+    /// Zstandard compression
+    #[cfg(feature = "compressed-output")]
+    Zstd(zstd::Encoder<'static, Box<dyn Write + Send>>),
+}
+//
+impl Encoder {
+    /// Write the end of the compressed stream, if any, and flush the output
+    fn finish(self) -> Result<()> {
+        let mut out = match self {
+            Self::Plain(out) => out,
+            #[cfg(feature = "compressed-output")]
+            Self::Gzip(encoder) => encoder.finish()?,
+            #[cfg(feature = "compressed-output")]
+            Self::Zstd(encoder) => encoder.finish()?,
+        };
+        out.flush()
+    }
+}
+//
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match self {
+            Self::Plain(out) => out.write(buf),
+            #[cfg(feature = "compressed-output")]
+            Self::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "compressed-output")]
+            Self::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    /// Flushing a compressed stream ends the current compressed block, so that
+    /// a reader can decompress all the data written so far
+    fn flush(&mut self) -> Result<()> {
+        match self {
+            Self::Plain(out) => out.flush(),
+            #[cfg(feature = "compressed-output")]
+            Self::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "compressed-output")]
+            Self::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Open a file for reading, decompressing it according to the extension of
+/// its path
+pub fn open(path: &str) -> Result<Box<dyn Read>> {
+    let file = BufReader::new(File::open(path)?);
+    match Compression::from_path(path) {
+        Compression::None => Ok(Box::new(file)),
+        #[cfg(feature = "compressed-output")]
+        Compression::Gzip => Ok(Box::new(MultiGzDecoder::new(file))),
+        #[cfg(feature = "compressed-output")]
+        Compression::Zstd => Ok(Box::new(zstd::Decoder::with_buffer(file)?)),
+        #[cfg(not(feature = "compressed-output"))]
+        Compression::Gzip | Compression::Zstd => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Reading {path} requires the compressed-output feature"),
+        )),
+    }
+}
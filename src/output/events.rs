@@ -0,0 +1,759 @@
+//! Format-agnostic machinery for writing accepted events to disk
+
+#[cfg(feature = "parquet-output")]
+use super::parquet::ParquetFormat;
+#[cfg(feature = "root-output")]
+use super::root::RootFormat;
+use super::{
+    compression::{Compression, OutputFile},
+    hepmc3::HepMC3Format,
+    jsonl::JsonLinesFormat,
+    lhe::LheFormat,
+    npz::NpzFormat,
+    records::RecordsFormat,
+    unweighting::{Unweighter, UnweightingConfig, UnweightingStats},
+};
+use crate::{
+    config::Configuration,
+    error::ensure,
+    event::{
+        Event, EventMatrix, INCOMING_E_M, INCOMING_E_P, NUM_INCOMING, NUM_OUTGOING, NUM_PARTICLES,
+    },
+    evgen::EventGenerator,
+    matelems::{MEsVector, MatrixElement},
+    momentum::{E, X, Y, Z},
+    numeric::Float,
+    resfin::FinalResults,
+    scheduling, Error, Result as AnyResult,
+};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Result, Seek, SeekFrom, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender, SyncSender},
+        Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+/// Configuration of accepted event output (not in the legacy format)
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventOutputConfig {
+    /// File where events should be written ("-" designating the standard
+    /// output when events are streamed)
+    pub path: String,
+
+    /// Format of the output file (deduced from the extension if unspecified)
+    #[serde(default)]
+    pub format: Option<EventFileFormat>,
+
+    /// Write events to the output file as they are generated, rather than at
+    /// the end of the simulation, so that they can be processed online (e.g.
+    /// through a named pipe)
+    #[serde(default)]
+    pub stream: bool,
+
+    /// Unweighting of the events, if any
+    #[serde(default)]
+    pub unweighting: Option<UnweightingConfig>,
+}
+//
+impl EventOutputConfig {
+    /// Path which designates the standard output when events are streamed
+    pub const STDOUT: &'static str = "-";
+
+    /// Check that events can be written following this configuration
+    pub fn check(&self) -> AnyResult<()> {
+        let format = self.format()?;
+        Compression::check_path(&self.path)?;
+        ensure!(
+            format.compressible() || Compression::from_path(&self.path) == Compression::None,
+            InvalidConfig,
+            "Events cannot be compressed in the {format:?} format, which has its own storage layout"
+        );
+        ensure!(
+            !self.stream || format.streamable(),
+            InvalidConfig,
+            "Events cannot be streamed in the {format:?} format, use lhe or jsonl"
+        );
+        ensure!(
+            self.stream || self.path != Self::STDOUT,
+            InvalidConfig,
+            "Events can only be written to the standard output when they are streamed"
+        );
+        Ok(())
+    }
+
+    /// Truth that events are streamed to the standard output
+    pub fn streams_to_stdout(&self) -> bool {
+        self.stream && self.path == Self::STDOUT
+    }
+
+    /// Format of the output file
+    fn format(&self) -> AnyResult<EventFileFormat> {
+        match self.format {
+            Some(format) => Ok(format),
+            None => EventFileFormat::from_path(&self.path),
+        }
+    }
+
+    /// Set up an event writer following this configuration, resuming from a
+    /// checkpoint if one is provided
+    ///
+    /// If unweighting is enabled, this simulates some events in order to
+    /// estimate the maximal event weight.
+    ///
+    pub fn writer(
+        &self,
+        cfg: &Configuration,
+        process: &dyn MatrixElement,
+        evgen: &EventGenerator,
+        resume: Option<&EventWriterCheckpoint>,
+    ) -> AnyResult<EventWriter> {
+        let format: Box<dyn EventFormat> = match self.format()? {
+            EventFileFormat::Lhe => Box::new(LheFormat),
+            EventFileFormat::HepMC3 => Box::new(HepMC3Format),
+            EventFileFormat::JsonLines => Box::new(JsonLinesFormat),
+            EventFileFormat::Records => Box::new(RecordsFormat),
+            EventFileFormat::Npz => Box::new(NpzFormat),
+            #[cfg(feature = "parquet-output")]
+            EventFileFormat::Parquet => Box::new(ParquetFormat),
+            #[cfg(feature = "root-output")]
+            EventFileFormat::Root => Box::new(RootFormat),
+        };
+        let unweighter = self
+            .unweighting
+            .as_ref()
+            .map(|unweighting| Unweighter::new(cfg, unweighting, process, evgen));
+        if self.stream {
+            Ok(EventWriter::new_stream(
+                cfg, format, &self.path, unweighter,
+            )?)
+        } else {
+            Ok(EventWriter::new(
+                cfg, format, &self.path, unweighter, resume,
+            )?)
+        }
+    }
+}
+
+/// Supported event file formats
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+pub enum EventFileFormat {
+    /// Les Houches Event File (LHEF 3.0)
+    #[serde(rename = "lhe")]
+    #[value(name = "lhe")]
+    Lhe,
+
+    /// HepMC3 ASCII
+    #[serde(rename = "hepmc3")]
+    #[value(name = "hepmc3")]
+    HepMC3,
+
+    /// JSON Lines, with one JSON object per event
+    #[serde(rename = "jsonl")]
+    #[value(name = "jsonl")]
+    JsonLines,
+
+    /// Binary records of 3photons, featuring the contribution of each matrix
+    /// element to the event weight (see README.md)
+    #[serde(rename = "records")]
+    #[value(name = "records")]
+    Records,
+
+    /// NumPy archive of event arrays, which can be loaded with numpy.load()
+    #[serde(rename = "npz")]
+    #[value(name = "npz")]
+    Npz,
+
+    /// Parquet file of event columns, for out-of-core analyses with e.g.
+    /// polars, pandas or DuckDB (requires the parquet-output feature)
+    #[cfg(feature = "parquet-output")]
+    #[serde(rename = "parquet")]
+    #[value(name = "parquet")]
+    Parquet,
+
+    /// ROOT file, featuring a tree of the events and the histograms (requires
+    /// the root-output feature)
+    #[cfg(feature = "root-output")]
+    #[serde(rename = "root")]
+    #[value(name = "root")]
+    Root,
+}
+//
+impl EventFileFormat {
+    /// Truth that events can be streamed in this format, i.e. that it does not
+    /// need the final results before the events
+    pub fn streamable(self) -> bool {
+        matches!(self, Self::Lhe | Self::JsonLines)
+    }
+
+    /// Truth that files in this format can be compressed as a whole, i.e.
+    /// that they are not meant to be read out of order
+    fn compressible(self) -> bool {
+        matches!(
+            self,
+            Self::Lhe | Self::HepMC3 | Self::JsonLines | Self::Records
+        )
+    }
+
+    /// Deduce the event file format from a file extension, ignoring that of
+    /// the compression if any (e.g. `events.lhe.gz` is an LHEF file)
+    fn from_path(path: &str) -> AnyResult<Self> {
+        let extension = Path::new(Compression::strip_extension(path))
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("lhe") | Some("lhef") => Ok(Self::Lhe),
+            Some("hepmc") | Some("hepmc3") => Ok(Self::HepMC3),
+            Some("jsonl") => Ok(Self::JsonLines),
+            Some("3pr") => Ok(Self::Records),
+            Some("npz") => Ok(Self::Npz),
+            #[cfg(feature = "parquet-output")]
+            Some("parquet") => Ok(Self::Parquet),
+            #[cfg(not(feature = "parquet-output"))]
+            Some("parquet") => Err(Error::InvalidConfig(format!(
+                "Writing events to {path} requires the parquet-output feature"
+            ))),
+            #[cfg(feature = "root-output")]
+            Some("root") => Ok(Self::Root),
+            #[cfg(not(feature = "root-output"))]
+            Some("root") => Err(Error::InvalidConfig(format!(
+                "Writing events to {path} requires the root-output feature"
+            ))),
+            _ => Err(Error::InvalidConfig(format!(
+                "Cannot deduce the event file format of {path}, please specify it"
+            ))),
+        }
+    }
+}
+
+/// File format in which accepted events can be written
+pub trait EventFormat: Send + Sync {
+    /// Write the beginning of the file, which may feature information that is
+    /// only known at the end of the simulation (e.g. the cross-section)
+    fn write_header(
+        &self,
+        out: &mut dyn Write,
+        cfg: &Configuration,
+        res: &FinalResults,
+        max_weight: Float,
+    ) -> Result<()>;
+
+    /// Write the beginning of a stream of events, which cannot feature the
+    /// information that is only known at the end of the simulation
+    ///
+    /// By default, formats do not support streaming.
+    ///
+    fn write_stream_header(&self, _out: &mut dyn Write, _cfg: &Configuration) -> Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "This event format cannot be streamed",
+        ))
+    }
+
+    /// Write one accepted event, given its number, weight, and the
+    /// contribution of each matrix element to the weight
+    ///
+    /// Event numbers are unique, but not necessarily consecutive in the file.
+    /// Event weights are normalized such that their average over all generated
+    /// events (including those which did not pass the cuts) is the total
+    /// cross-section, in pb.
+    ///
+    fn write_event(
+        &self,
+        out: &mut dyn Write,
+        event_number: usize,
+        event: &Event,
+        weight: Float,
+        me_weights: &MEsVector,
+    ) -> Result<()>;
+
+    /// Write the end of the file
+    fn write_footer(&self, out: &mut dyn Write) -> Result<()>;
+
+    /// Write the end of a stream of events, which may feature the information
+    /// that is only known at the end of the simulation
+    ///
+    /// By default, this is the end of the file.
+    ///
+    fn write_stream_footer(
+        &self,
+        out: &mut dyn Write,
+        _cfg: &Configuration,
+        _res: &FinalResults,
+        _max_weight: Float,
+    ) -> Result<()> {
+        self.write_footer(out)
+    }
+
+    /// Assemble the final output file from the temporary event storage, where
+    /// events were written by write_event()
+    ///
+    /// By default, the events are copied as they are between the header and
+    /// the footer, compressing the output file according to its extension.
+    ///
+    fn assemble(
+        &self,
+        path: &str,
+        tmp_path: &str,
+        cfg: &Configuration,
+        res: &FinalResults,
+        max_weight: Float,
+    ) -> Result<()> {
+        let mut out = OutputFile::create(path)?;
+        self.write_header(&mut out, cfg, res, max_weight)?;
+        io::copy(&mut File::open(tmp_path)?, &mut out)?;
+        self.write_footer(&mut out)?;
+        out.finish()
+    }
+}
+
+/// Writer of accepted events, to be shared between simulation threads
+///
+/// Since some formats require the cross-section before the events, the events
+/// are first written to a temporary file, then assembled with a header into
+/// the final file at the end of the simulation. Alternatively, in the formats
+/// which allow it, events can be streamed to the final file (or the standard
+/// output) as soon as each batch of events has been simulated.
+///
+/// Batches of events are written by a background thread, so that simulation
+/// threads do not wait for I/O. Each simulation thread can queue one batch
+/// for writing while it fills the next one, and the buffers of written batches
+/// are reused. If the writer thread falls further behind, simulation threads
+/// wait for it.
+///
+/// In multi-threaded mode, the events of different batches may be written in
+/// a different order from one run to the next.
+///
+/// If unweighting is enabled, only some of the accepted events are written,
+/// with the same weight (see Unweighter).
+///
+pub struct EventWriter {
+    /// Format of the output file
+    format: Box<dyn EventFormat>,
+
+    /// Path to the final output file
+    path: String,
+
+    /// Factor from per-event cross-section contributions to event weights
+    weight_scale: Float,
+
+    /// Event unweighting mechanism, if enabled
+    unweighter: Option<Unweighter>,
+
+    /// Number of the next event to be formatted
+    next_event_number: AtomicUsize,
+
+    /// Queue of requests to the writer thread
+    requests: SyncSender<WriterRequest>,
+
+    /// Buffers of the batches which the writer thread is done with
+    spare_buffers: Mutex<Receiver<Vec<u8>>>,
+
+    /// Writer thread, which owns the destination of the events and the
+    /// statistics of the events written so far (None once it has failed)
+    writer_thread: Mutex<Option<WriterThread>>,
+}
+//
+impl EventWriter {
+    /// Prepare to write events in a certain format to a certain file
+    ///
+    /// If a checkpoint is provided, the temporary event storage of the
+    /// checkpointed run is reused, minus any event written after the
+    /// checkpoint was taken.
+    ///
+    pub fn new(
+        cfg: &Configuration,
+        format: Box<dyn EventFormat>,
+        path: &str,
+        unweighter: Option<Unweighter>,
+        resume: Option<&EventWriterCheckpoint>,
+    ) -> Result<Self> {
+        let tmp_path = format!("{path}.tmp");
+        let mut tmp_file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(resume.is_none())
+            .open(&tmp_path)?;
+        let (next_event_number, stats) = if let Some(checkpoint) = resume {
+            if tmp_file.metadata()?.len() < checkpoint.spool_len {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("{tmp_path} is shorter than recorded in the checkpoint"),
+                ));
+            }
+            tmp_file.set_len(checkpoint.spool_len)?;
+            tmp_file.seek(SeekFrom::End(0))?;
+            (checkpoint.next_event_number, checkpoint.stats)
+        } else {
+            (0, EventStats::default())
+        };
+        let sink = EventSink::Spool {
+            file: BufWriter::new(tmp_file),
+            path: tmp_path,
+        };
+        Self::with_sink(
+            cfg,
+            format,
+            path,
+            unweighter,
+            next_event_number,
+            sink,
+            stats,
+        )
+    }
+
+    /// Prepare to stream events in a certain format to a certain file, or to
+    /// the standard output if the path is EventOutputConfig::STDOUT
+    pub fn new_stream(
+        cfg: &Configuration,
+        format: Box<dyn EventFormat>,
+        path: &str,
+        unweighter: Option<Unweighter>,
+    ) -> Result<Self> {
+        let mut out = if path == EventOutputConfig::STDOUT {
+            OutputFile::stdout()
+        } else {
+            OutputFile::create(path)?
+        };
+        format.write_stream_header(&mut out, cfg)?;
+        out.flush()?;
+        let sink = EventSink::Stream(out);
+        Self::with_sink(
+            cfg,
+            format,
+            path,
+            unweighter,
+            0,
+            sink,
+            EventStats::default(),
+        )
+    }
+
+    /// Start the writer thread, given the destination of the events and the
+    /// statistics of the events written so far
+    fn with_sink(
+        cfg: &Configuration,
+        format: Box<dyn EventFormat>,
+        path: &str,
+        unweighter: Option<Unweighter>,
+        next_event_number: usize,
+        sink: EventSink,
+        stats: EventStats,
+    ) -> Result<Self> {
+        let (requests, request_queue) = mpsc::sync_channel(scheduling::num_threads(cfg));
+        let (spare_buffers_in, spare_buffers) = mpsc::channel();
+        let writer_thread = thread::Builder::new()
+            .name("event-writer".to_owned())
+            .spawn(move || write_events(sink, stats, request_queue, spare_buffers_in))?;
+        Ok(Self {
+            format,
+            path: path.to_owned(),
+            weight_scale: cfg.num_events as Float,
+            unweighter,
+            next_event_number: AtomicUsize::new(next_event_number),
+            requests,
+            spare_buffers: Mutex::new(spare_buffers),
+            writer_thread: Mutex::new(Some(writer_thread)),
+        })
+    }
+
+    /// Set up an empty batch of events, reusing the buffer of a batch which
+    /// was written if possible
+    pub fn new_batch(&self) -> FormattedEvents {
+        let spare_buffers = self
+            .spare_buffers
+            .lock()
+            .expect("Mutex data should be valid");
+        FormattedEvents {
+            buffer: spare_buffers.try_recv().unwrap_or_default(),
+            stats: EventStats::default(),
+        }
+    }
+
+    /// Serialize one accepted event into a batch, given its contribution to
+    /// the total cross-section and that of each matrix element (unless
+    /// unweighting discards it)
+    pub fn format_event(
+        &self,
+        batch: &mut FormattedEvents,
+        event: &Event,
+        sigma_contrib: Float,
+        me_contribs: &MEsVector,
+    ) {
+        let mut weight = sigma_contrib * self.weight_scale;
+        let mut me_weights = me_contribs * self.weight_scale;
+        if let Some(unweighter) = &self.unweighter {
+            match unweighter.unweight(event, weight, &mut batch.stats.unweighting) {
+                Some(new_weight) => {
+                    me_weights *= new_weight / weight;
+                    weight = new_weight;
+                }
+                None => return,
+            }
+        }
+        let event_number = self.next_event_number.fetch_add(1, Ordering::Relaxed);
+        self.format
+            .write_event(&mut batch.buffer, event_number, event, weight, &me_weights)
+            .expect("Writing to memory should not fail");
+        batch.stats.max_weight = batch.stats.max_weight.max(weight);
+    }
+
+    /// Queue a batch of serialized events for writing to the temporary event
+    /// storage, or to the output if events are streamed
+    ///
+    /// Fails if the writer thread failed to write a previous batch.
+    ///
+    pub fn write_batch(&self, batch: FormattedEvents) -> Result<()> {
+        self.requests
+            .send(WriterRequest::Write(batch))
+            .map_err(|_| self.writer_failure())
+    }
+
+    /// Flush the temporary event storage and describe its state, so that it
+    /// can be recorded in a checkpoint
+    ///
+    /// Must not be called while events are being written.
+    ///
+    pub fn checkpoint(&self) -> Result<EventWriterCheckpoint> {
+        let (reply, answer) = mpsc::sync_channel(1);
+        self.requests
+            .send(WriterRequest::Checkpoint(reply))
+            .map_err(|_| self.writer_failure())?;
+        let (spool_len, stats) = answer.recv().map_err(|_| self.writer_failure())??;
+        Ok(EventWriterCheckpoint {
+            spool_len,
+            next_event_number: self.next_event_number.load(Ordering::Relaxed),
+            stats,
+        })
+    }
+
+    /// Assemble the final output file, or finish the stream of events
+    pub fn finish(self, cfg: &Configuration, res: &FinalResults) -> Result<()> {
+        // Let the writer thread write the remaining batches
+        let Self {
+            format,
+            path,
+            unweighter,
+            requests,
+            writer_thread,
+            ..
+        } = self;
+        drop(requests);
+        let writer_thread = writer_thread
+            .into_inner()
+            .expect("Mutex data should be valid");
+        let (sink, stats) = join_writer_thread(writer_thread)?;
+
+        // Report how well unweighting went, if enabled
+        if let Some(unweighter) = &unweighter {
+            unweighter.report(&stats.unweighting);
+        }
+
+        match sink {
+            // Flush the temporary event storage, write the final file, then
+            // get rid of the temporary one
+            EventSink::Spool {
+                file,
+                path: tmp_path,
+            } => {
+                file.into_inner().map_err(|e| e.into_error())?;
+                format.assemble(&path, &tmp_path, cfg, res, stats.max_weight)?;
+                fs::remove_file(&tmp_path)
+            }
+
+            // Write the end of the stream
+            EventSink::Stream(mut out) => {
+                format.write_stream_footer(&mut out, cfg, res, stats.max_weight)?;
+                out.finish()
+            }
+        }
+    }
+
+    /// Error to be reported when the writer thread stopped prematurely, which
+    /// is that of the writer thread if it was not reported yet
+    fn writer_failure(&self) -> io::Error {
+        let writer_thread = self
+            .writer_thread
+            .lock()
+            .expect("Mutex data should be valid")
+            .take();
+        match join_writer_thread(writer_thread) {
+            Err(error) => error,
+            Ok(_) => io::Error::other("The event writer thread stopped prematurely"),
+        }
+    }
+}
+
+/// Handle to the writer thread of an EventWriter
+type WriterThread = JoinHandle<Result<(EventSink, EventStats)>>;
+
+/// Wait for the writer thread to finish, then return the destination of the
+/// events and the statistics of the events that it wrote (or its error)
+fn join_writer_thread(writer_thread: Option<WriterThread>) -> Result<(EventSink, EventStats)> {
+    writer_thread
+        .ok_or_else(|| io::Error::other("The event writer thread failed earlier"))?
+        .join()
+        .map_err(|_| io::Error::other("The event writer thread panicked"))?
+}
+
+/// Body of the writer thread of an EventWriter, which handles requests until
+/// the EventWriter stops sending them, then returns the destination of the
+/// events and the statistics of the events that it wrote
+fn write_events(
+    mut sink: EventSink,
+    mut stats: EventStats,
+    requests: Receiver<WriterRequest>,
+    spare_buffers: Sender<Vec<u8>>,
+) -> Result<(EventSink, EventStats)> {
+    for request in requests {
+        match request {
+            WriterRequest::Write(mut batch) => {
+                sink.write_batch(&batch.buffer)?;
+                stats.merge(&batch.stats);
+                batch.buffer.clear();
+                // The EventWriter may be gone, and then needs no buffer
+                let _ = spare_buffers.send(batch.buffer);
+            }
+            WriterRequest::Checkpoint(reply) => {
+                // The EventWriter waits for the answer, unless it is gone
+                let _ = reply.send(sink.spool_len().map(|len| (len, stats)));
+            }
+        }
+    }
+    Ok((sink, stats))
+}
+
+/// Request to the writer thread of an EventWriter
+enum WriterRequest {
+    /// Write a batch of events
+    Write(FormattedEvents),
+
+    /// Flush the temporary event storage, then send back its length and the
+    /// statistics of the events written so far
+    Checkpoint(SyncSender<Result<(u64, EventStats)>>),
+}
+
+/// Destination of the events written by an EventWriter
+enum EventSink {
+    /// Temporary event storage, at a certain path
+    Spool { file: BufWriter<File>, path: String },
+
+    /// Stream of events to the output file or the standard output
+    Stream(OutputFile),
+}
+//
+impl EventSink {
+    /// Write a batch of serialized events, flushing them if they are streamed
+    fn write_batch(&mut self, buffer: &[u8]) -> Result<()> {
+        match self {
+            Self::Spool { file, .. } => file.write_all(buffer),
+            Self::Stream(out) => {
+                out.write_all(buffer)?;
+                out.flush()
+            }
+        }
+    }
+
+    /// Flush the temporary event storage and return its length
+    fn spool_len(&mut self) -> Result<u64> {
+        let Self::Spool { file, .. } = self else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Streamed events cannot be checkpointed",
+            ));
+        };
+        file.flush()?;
+        Ok(file.get_ref().metadata()?.len())
+    }
+}
+
+/// State of an EventWriter, as saved in checkpoints
+#[derive(Deserialize, Serialize)]
+pub struct EventWriterCheckpoint {
+    /// Length of the temporary event storage
+    spool_len: u64,
+
+    /// Number of the next event to be formatted
+    next_event_number: usize,
+
+    /// Statistics of the events written so far
+    stats: EventStats,
+}
+
+/// Accepted events of a batch, serialized in memory until they are written to
+/// the temporary event storage
+#[derive(Default)]
+pub struct FormattedEvents {
+    /// Serialized events
+    buffer: Vec<u8>,
+
+    /// Statistics of the events of this batch
+    stats: EventStats,
+}
+
+/// Statistics of the events which were written
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+struct EventStats {
+    /// Largest event weight
+    max_weight: Float,
+
+    /// Statistics of event unweighting
+    unweighting: UnweightingStats,
+}
+//
+impl EventStats {
+    /// Integrate the statistics of another batch of events
+    fn merge(&mut self, other: &Self) {
+        self.max_weight = self.max_weight.max(other.max_weight);
+        self.unweighting.merge(&other.unweighting);
+    }
+}
+
+/// Express the 4-momenta of an event in the standard event record frame
+///
+/// 3photons uses the X axis as the beam axis, whereas event records expect the
+/// incoming electron to travel along +Z. We therefore rotate momenta by 90°
+/// around the Y axis. Output rows are particles, columns are (Px, Py, Pz, E).
+///
+pub fn lab_frame_momenta(event: &Event) -> [[Float; 4]; NUM_PARTICLES] {
+    let ps = event.all_momenta();
+    let mut result = [[0.; 4]; NUM_PARTICLES];
+    for (par, p) in result.iter_mut().enumerate() {
+        *p = [ps[(par, Z)], ps[(par, Y)], -ps[(par, X)], ps[(par, E)]];
+    }
+    result
+}
+
+/// Build the 4-momenta of an event from those of its outgoing particles in
+/// the standard event record frame, given as (Px, Py, Pz, E) rows
+///
+/// This undoes lab_frame_momenta(), the incoming particles being given the
+/// momenta of the event generator at a certain collision energy.
+///
+pub fn from_lab_frame_momenta(
+    e_total: Float,
+    outgoing: &[[Float; 4]; NUM_OUTGOING],
+) -> EventMatrix {
+    let half_e = e_total / 2.;
+    let mut ps = EventMatrix::zeros();
+    for (par, direction) in [(INCOMING_E_M, -1.), (INCOMING_E_P, 1.)] {
+        ps[(par, X)] = direction * half_e;
+        ps[(par, E)] = half_e;
+    }
+    for (par, p) in outgoing.iter().enumerate() {
+        let mut row = ps.row_mut(NUM_INCOMING + par);
+        row[X] = -p[2];
+        row[Y] = p[1];
+        row[Z] = p[0];
+        row[E] = p[3];
+    }
+    ps
+}
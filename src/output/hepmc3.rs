@@ -0,0 +1,90 @@
+//! HepMC3 ASCII event output
+
+use super::events::{lab_frame_momenta, EventFormat};
+use crate::{
+    config::Configuration,
+    event::{Event, NUM_INCOMING, NUM_PARTICLES},
+    matelems::MEsVector,
+    numeric::Float,
+    resfin::FinalResults,
+};
+use std::io::{Result, Write};
+
+/// PDG identifier of the electron
+const PDG_ELECTRON: i32 = 11;
+
+/// PDG identifier of the photon
+const PDG_PHOTON: i32 = 22;
+
+/// HepMC3 status of beam particles
+const STATUS_BEAM: i32 = 4;
+
+/// HepMC3 status of final-state particles
+const STATUS_FINAL: i32 = 1;
+
+/// Writer of events in the HepMC3 ASCII format
+pub struct HepMC3Format;
+//
+impl EventFormat for HepMC3Format {
+    fn write_header(
+        &self,
+        out: &mut dyn Write,
+        _cfg: &Configuration,
+        _res: &FinalResults,
+        _max_weight: Float,
+    ) -> Result<()> {
+        writeln!(out, "HepMC::Version 3.02.05")?;
+        writeln!(out, "HepMC::Asciiv3-START_EVENT_LISTING")?;
+        writeln!(out, "W Default")?;
+        writeln!(
+            out,
+            "T 3photons\\|{}\\|e+e- -> 3 photons Monte Carlo",
+            env!("CARGO_PKG_VERSION")
+        )
+    }
+
+    fn write_event(
+        &self,
+        out: &mut dyn Write,
+        event_number: usize,
+        event: &Event,
+        weight: Float,
+        _me_weights: &MEsVector,
+    ) -> Result<()> {
+        // Event-wide information: one vertex with all particles attached
+        writeln!(out, "E {event_number} 1 {NUM_PARTICLES}")?;
+        writeln!(out, "U GEV MM")?;
+        writeln!(out, "W {weight:.10e}")?;
+
+        // Incoming beam particles, and the vertex where they annihilate
+        let momenta = lab_frame_momenta(event);
+        let write_particle = |out: &mut dyn Write, par: usize, parent, pdg_id, status| {
+            let p = momenta[par];
+            writeln!(
+                out,
+                "P {} {} {} {:.10e} {:.10e} {:.10e} {:.10e} 0 {}",
+                par + 1,
+                parent,
+                pdg_id,
+                p[0],
+                p[1],
+                p[2],
+                p[3],
+                status
+            )
+        };
+        write_particle(out, 0, 0, PDG_ELECTRON, STATUS_BEAM)?;
+        write_particle(out, 1, 0, -PDG_ELECTRON, STATUS_BEAM)?;
+        writeln!(out, "V -1 0 [1,2]")?;
+
+        // Outgoing photons
+        for par in NUM_INCOMING..NUM_PARTICLES {
+            write_particle(out, par, -1, PDG_PHOTON, STATUS_FINAL)?;
+        }
+        Ok(())
+    }
+
+    fn write_footer(&self, out: &mut dyn Write) -> Result<()> {
+        writeln!(out, "HepMC::Asciiv3-END_EVENT_LISTING")
+    }
+}
@@ -0,0 +1,78 @@
+//! JSON Lines event output, which is easy to consume online from any language
+
+use super::events::{lab_frame_momenta, EventFormat};
+use crate::{
+    config::Configuration,
+    event::{Event, NUM_PARTICLES},
+    matelems::MEsVector,
+    numeric::Float,
+    resfin::FinalResults,
+};
+use serde::Serialize;
+use std::io::{self, Result, Write};
+
+/// Writer of events in the JSON Lines format
+///
+/// Each accepted event is a JSON object on its own line, with the fields
+/// `event_number`, `weight`, `momenta` (the 4-momenta (Px, Py, Pz, E) of the
+/// incoming electron and positron and of the outgoing photons, in the frame of
+/// lab_frame_momenta()) and `me_weights` (the contribution of each matrix
+/// element to the event weight, in the order of the matelems module).
+///
+/// There is no header, so that events can be streamed.
+///
+pub struct JsonLinesFormat;
+//
+impl EventFormat for JsonLinesFormat {
+    fn write_header(
+        &self,
+        _out: &mut dyn Write,
+        _cfg: &Configuration,
+        _res: &FinalResults,
+        _max_weight: Float,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_stream_header(&self, _out: &mut dyn Write, _cfg: &Configuration) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_event(
+        &self,
+        out: &mut dyn Write,
+        event_number: usize,
+        event: &Event,
+        weight: Float,
+        me_weights: &MEsVector,
+    ) -> Result<()> {
+        let record = EventRecord {
+            event_number,
+            weight,
+            momenta: lab_frame_momenta(event),
+            me_weights: me_weights.as_slice(),
+        };
+        serde_json::to_writer(&mut *out, &record).map_err(io::Error::from)?;
+        writeln!(out)
+    }
+
+    fn write_footer(&self, _out: &mut dyn Write) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Line of a JSON Lines event file
+#[derive(Serialize)]
+struct EventRecord<'a> {
+    /// Unique number of the event
+    event_number: usize,
+
+    /// Event weight
+    weight: Float,
+
+    /// 4-momenta of the particles, in the frame of lab_frame_momenta()
+    momenta: [[Float; 4]; NUM_PARTICLES],
+
+    /// Contribution of each matrix element to the event weight
+    me_weights: &'a [Float],
+}
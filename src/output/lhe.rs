@@ -0,0 +1,155 @@
+//! Les Houches Event File (LHEF 3.0) output
+
+use super::events::{lab_frame_momenta, EventFormat};
+use crate::{
+    config::Configuration,
+    event::{Event, NUM_INCOMING, NUM_PARTICLES},
+    matelems::MEsVector,
+    numeric::Float,
+    resfin::FinalResults,
+};
+use std::io::{Result, Write};
+
+/// PDG identifier of the electron
+const PDG_ELECTRON: i32 = 11;
+
+/// PDG identifier of the photon
+const PDG_PHOTON: i32 = 22;
+
+/// Identifier of the e⁺e⁻ → 𝛾𝛾𝛾 process in the event file
+const PROCESS_ID: i32 = 1;
+
+/// Writer of events in the Les Houches Event File format
+///
+/// When events are streamed, the cross-section and maximal weight of the init
+/// block are zero, and their final values are given in a comment at the end of
+/// the stream.
+///
+pub struct LheFormat;
+//
+impl EventFormat for LheFormat {
+    fn write_header(
+        &self,
+        out: &mut dyn Write,
+        cfg: &Configuration,
+        res: &FinalResults,
+        max_weight: Float,
+    ) -> Result<()> {
+        write_preamble(out, cfg, Some((res, max_weight)))
+    }
+
+    fn write_stream_header(&self, out: &mut dyn Write, cfg: &Configuration) -> Result<()> {
+        write_preamble(out, cfg, None)
+    }
+
+    fn write_event(
+        &self,
+        out: &mut dyn Write,
+        _event_number: usize,
+        event: &Event,
+        weight: Float,
+        _me_weights: &MEsVector,
+    ) -> Result<()> {
+        // Event-wide information: particle count, process, weight and scale
+        // (αem and αs are left unspecified)
+        let momenta = lab_frame_momenta(event);
+        let scale = momenta[0][3] + momenta[1][3];
+        writeln!(out, "<event>")?;
+        writeln!(
+            out,
+            "{} {} {:.10e} {:.10e} -1 -1",
+            NUM_PARTICLES, PROCESS_ID, weight, scale
+        )?;
+
+        // Particle record: incoming e-, incoming e+, then outgoing photons
+        for (par, p) in momenta.iter().enumerate() {
+            let (pdg_id, status, mothers) = match par {
+                0 => (PDG_ELECTRON, -1, (0, 0)),
+                1 => (-PDG_ELECTRON, -1, (0, 0)),
+                _ => (PDG_PHOTON, 1, (1, NUM_INCOMING)),
+            };
+            writeln!(
+                out,
+                "{} {} {} {} 0 0 {:.10e} {:.10e} {:.10e} {:.10e} 0 0 9",
+                pdg_id, status, mothers.0, mothers.1, p[0], p[1], p[2], p[3]
+            )?;
+        }
+        writeln!(out, "</event>")
+    }
+
+    fn write_footer(&self, out: &mut dyn Write) -> Result<()> {
+        writeln!(out, "</LesHouchesEvents>")
+    }
+
+    fn write_stream_footer(
+        &self,
+        out: &mut dyn Write,
+        _cfg: &Configuration,
+        res: &FinalResults,
+        max_weight: Float,
+    ) -> Result<()> {
+        writeln!(out, "<!--")?;
+        writeln!(out, "Number of accepted events: {}", res.selected_events)?;
+        writeln!(
+            out,
+            "Total cross-section: {:.10e} +- {:.10e} pb",
+            res.sigma,
+            res.sigma * res.prec
+        )?;
+        writeln!(out, "Maximal event weight: {max_weight:.10e}")?;
+        writeln!(out, "-->")?;
+        self.write_footer(out)
+    }
+}
+
+/// Write the beginning of the file, up to the init block, given the final
+/// results and maximal event weight if they are known (i.e. unless events are
+/// streamed)
+fn write_preamble(
+    out: &mut dyn Write,
+    cfg: &Configuration,
+    results: Option<(&FinalResults, Float)>,
+) -> Result<()> {
+    writeln!(out, "<LesHouchesEvents version=\"3.0\">")?;
+    writeln!(out, "<header>")?;
+    writeln!(out, "<!--")?;
+    writeln!(
+        out,
+        "File generated by 3photons v{}",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(out, "Number of generated events: {}", cfg.num_events)?;
+    let ordering = cfg.photon_ordering.description();
+    writeln!(out, "Photons are listed {ordering}, gamma1 first")?;
+    match results {
+        Some((res, _max_weight)) => {
+            writeln!(out, "Number of accepted events: {}", res.selected_events)?
+        }
+        None => writeln!(
+            out,
+            "Events are streamed, see the end of the stream for the cross-section"
+        )?,
+    }
+    writeln!(out, "-->")?;
+    writeln!(out, "</header>")?;
+
+    // Beams are unpolarized e-/e+ without PDF, and events are weighted
+    // such that the average weight is the total cross-section (IDWTUP=4)
+    let e_beam = cfg.e_total / 2.;
+    writeln!(out, "<init>")?;
+    writeln!(
+        out,
+        "{} {} {:.10e} {:.10e} 0 0 0 0 4 1",
+        PDG_ELECTRON, -PDG_ELECTRON, e_beam, e_beam
+    )?;
+    let (sigma, sigma_err, max_weight) = match results {
+        Some((res, max_weight)) => (res.sigma, res.sigma * res.prec, max_weight),
+        None => (0., 0., 0.),
+    };
+    writeln!(
+        out,
+        "{:.10e} {:.10e} {:.10e} {}",
+        sigma, sigma_err, max_weight, PROCESS_ID
+    )?;
+    writeln!(out, "</init>")
+}
@@ -1,18 +1,36 @@
 //! This module is in charge of outputting the final simulation results to the
 //! standard output and various files
 
+pub mod compression;
+pub mod events;
+pub mod hepmc3;
+pub mod jsonl;
+pub mod lhe;
+pub mod npz;
+#[cfg(feature = "parquet-output")]
+pub mod parquet;
+pub mod records;
+pub mod resdata;
+#[cfg(feature = "root-output")]
+pub mod root;
+pub mod unweighting;
+
 use crate::{
     config::Configuration,
+    coupling::AlphaScheme,
     event::NUM_SPINS,
     matelems::{A, B_M, B_P, NUM_MAT_ELEMS, R_MX},
-    numeric::{floats, Float},
+    numeric::Float,
+    output::resdata::DataFormat,
     resfin::FinalResults,
+    systematics::SystematicUncertainty,
 };
+use log::{info, warn};
 use num_traits::clamp_max;
 use prefix_num_ops::real::*;
 use std::{
     fs::{File, OpenOptions},
-    io::{Result, Write},
+    io::{BufWriter, Result, Write},
     time::Duration,
 };
 
@@ -21,14 +39,56 @@ use std::{
 // Must print one less than the actual machine type precision to match the
 // output of the C++ version of 3photons.
 //
-const SIG_DIGITS: usize = (floats::DIGITS - 1) as usize;
+const SIG_DIGITS: usize = (Float::DIGITS - 1) as usize;
 
 /// Output the simulation results to the console and to disk
 #[allow(clippy::cast_lossless)]
 pub fn dump_results(cfg: &Configuration, res: &FinalResults, elapsed_time: Duration) -> Result<()> {
-    // Print out some final results on stdout
+    // Print out some final results on stdout, starting with the fine structure
+    // constant of the Standard Model contribution if it does not use the
+    // configured value as is (so that the legacy output is left unchanged)
+    if res.partial {
+        info!("");
+        warn!(
+            "These results are partial, the simulation was interrupted after {} events",
+            cfg.num_events
+        );
+    }
+    let scheme = cfg.alpha_scheme;
+    if scheme != AlphaScheme::Fixed {
+        info!("");
+        info!(
+            "1/alpha ({} scheme) : {}",
+            scheme.name(),
+            1. / scheme.alpha(cfg)
+        );
+    }
     res.eric();
     res.fawzi();
+    if let Some(spin_density) = &res.spin_density {
+        spin_density.print();
+    }
+    res.cut_flow();
+    res.extended_precision();
+    res.weight_issues.print();
+    if let Some(asymmetries) = &res.asymmetries {
+        asymmetries.print();
+    }
+    if let Some(moments) = &res.legendre_moments {
+        moments.print();
+    }
+    if let Some(weights) = &res.weight_distribution {
+        weights.print();
+    }
+    if let Some(resampling) = &res.resampling {
+        resampling.print();
+    }
+    if let Some(systematics) = &cfg.systematics {
+        SystematicUncertainty::new(res, systematics).print(res);
+    }
+    if let Some(timings) = &res.timings {
+        timings.print(cfg.num_events);
+    }
 
     // Compute a timestamp of when the run ended
     let current_time = chrono::Utc::now();
@@ -37,7 +97,7 @@ pub fn dump_results(cfg: &Configuration, res: &FinalResults, elapsed_time: Durat
     // Write execution timings to a file
     {
         // Prepare to write our timings into a file
-        let mut tim_file = File::create("res.times")?;
+        let mut tim_file = File::create(cfg.results_path("times"))?;
         let tim_file = &mut tim_file;
 
         // Write a timestamp of when the run ended
@@ -54,14 +114,19 @@ pub fn dump_results(cfg: &Configuration, res: &FinalResults, elapsed_time: Durat
         writeln_3p(tim_file, ("Temps ecoule par evenement", secs_per_ev))?;
     }
 
-    // Write main results file. Try to mimick the original C++ format as well as
-    // possible to ease comparisons, even where it makes little sense.
-    {
+    // Write main results file, in the versioned format if requested...
+    if cfg.data_format == DataFormat::V2 {
+        resdata::write_v2(cfg, res, elapsed_time)?;
+    }
+    //
+    // ...otherwise, try to mimick the original C++ format as well as possible
+    // to ease comparisons, even where it makes little sense.
+    else {
         // Shorthands
         let ev_cut = &cfg.event_cut;
 
         // Prepare to write our results into a file
-        let mut dat_file = File::create("res.data")?;
+        let mut dat_file = File::create(cfg.results_path("data"))?;
         let dat_file = &mut dat_file;
 
         // Write the results to the file
@@ -136,17 +201,24 @@ pub fn dump_results(cfg: &Configuration, res: &FinalResults, elapsed_time: Durat
         }
     }
 
+    // Write the histograms of the photon distributions, if enabled
+    if let Some(histograms) = &res.histograms {
+        let mut histo_file = BufWriter::new(File::create(cfg.results_path("histos"))?);
+        histograms.write(&mut histo_file)?;
+        histo_file.flush()?;
+    }
+
     // Append the results of this run to a cumulative file
     //
     // NOTE: This part is completely broken in the C++ version, I did my best
     //       to fix it in this version.
     {
-        assert_eq!(NUM_MAT_ELEMS, 5);
+        const { assert!(NUM_MAT_ELEMS == 5) };
 
         let mut cum_dat_file = OpenOptions::new()
             .append(true)
             .create(true)
-            .open("pil.mc")?;
+            .open(cfg.output_path("pil.mc"))?;
 
         writeln!(cum_dat_file, "{timestamp}")?;
 
@@ -0,0 +1,408 @@
+//! NumPy archives of event arrays, which Python analyses can load efficiently
+//! with `numpy.load()` instead of parsing text event records
+
+use super::{
+    events::EventFormat,
+    records::{write_numbered_record, NumberedRecords, NUMBERED_RECORD_SIZE},
+};
+use crate::{
+    config::Configuration,
+    event::{Event, NUM_PARTICLES},
+    matelems::{MEsVector, NUM_MAT_ELEMS},
+    momentum::MOMENTUM_DIM,
+    numeric::Float,
+    resfin::FinalResults,
+};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Result, Seek, SeekFrom, Write},
+    ops::Range,
+};
+
+/// Range of the numbered record columns holding the event weight
+const WEIGHT_COLUMNS: Range<usize> = 1..2;
+
+/// Range of the numbered record columns holding the 4-momenta
+const MOMENTA_COLUMNS: Range<usize> = 2..2 + NUM_PARTICLES * MOMENTUM_DIM;
+
+/// Range of the numbered record columns holding the matrix element weights
+const ME_WEIGHT_COLUMNS: Range<usize> = MOMENTA_COLUMNS.end..MOMENTA_COLUMNS.end + NUM_MAT_ELEMS;
+
+/// Writer of NumPy archives (`.npz`)
+///
+/// The archive features the following arrays, where N is the number of events
+/// in the file:
+///
+/// - `event_number`: event numbers (u64, shape N)
+/// - `weight`: event weights (f64, shape N)
+/// - `momenta`: 4-momenta (Px, Py, Pz, E) of the incoming electron and
+///   positron and of the outgoing photons, in the frame of lab_frame_momenta()
+///   (f64, shape N × 5 × 4)
+/// - `me_weights`: contribution of each matrix element to the event weight, in
+///   the order of the matelems module (f64, shape N × 5)
+/// - `num_events`: number of generated events (u64 scalar)
+/// - `cross_section`: total cross-section in pb (f64 scalar)
+///
+/// Event weights are normalized such that their sum divided by `num_events` is
+/// the total cross-section. The archive is not compressed, and uses the ZIP64
+/// extensions so that it can hold more than 4 GiB of events.
+///
+pub struct NpzFormat;
+//
+impl EventFormat for NpzFormat {
+    fn write_header(
+        &self,
+        _out: &mut dyn Write,
+        _cfg: &Configuration,
+        _res: &FinalResults,
+        _max_weight: Float,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_event(
+        &self,
+        out: &mut dyn Write,
+        event_number: usize,
+        event: &Event,
+        weight: Float,
+        me_weights: &MEsVector,
+    ) -> Result<()> {
+        write_numbered_record(out, event_number, event, weight, me_weights)
+    }
+
+    fn write_footer(&self, _out: &mut dyn Write) -> Result<()> {
+        Ok(())
+    }
+
+    #[allow(clippy::useless_conversion)]
+    fn assemble(
+        &self,
+        path: &str,
+        tmp_path: &str,
+        cfg: &Configuration,
+        res: &FinalResults,
+        _max_weight: Float,
+    ) -> Result<()> {
+        let mut archive = ZipWriter::new(BufWriter::new(File::create(path)?));
+
+        // Write the event arrays, reading the temporary event storage once per
+        // array
+        let num_events = NumberedRecords::open(tmp_path)?.num_events();
+        let arrays = [
+            ("event_number", "<u8", vec![num_events], 0..1),
+            ("weight", "<f8", vec![num_events], WEIGHT_COLUMNS),
+            (
+                "momenta",
+                "<f8",
+                vec![num_events, NUM_PARTICLES, MOMENTUM_DIM],
+                MOMENTA_COLUMNS,
+            ),
+            (
+                "me_weights",
+                "<f8",
+                vec![num_events, NUM_MAT_ELEMS],
+                ME_WEIGHT_COLUMNS,
+            ),
+        ];
+        for (name, dtype, shape, columns) in arrays {
+            let header = npy_header(dtype, &shape);
+            let bytes = columns.start * 8..columns.end * 8;
+            let data_len = (num_events * bytes.len()) as u64;
+            archive.start_entry(&format!("{name}.npy"), header.len() as u64 + data_len)?;
+            archive.write_all(&header)?;
+            NumberedRecords::open(tmp_path)?.try_for_each(
+                |record: &[u8; NUMBERED_RECORD_SIZE]| archive.write_all(&record[bytes.clone()]),
+            )?;
+            archive.finish_entry()?;
+        }
+
+        // Write the metadata
+        let scalars = [
+            ("num_events", "<u8", (cfg.num_events as u64).to_le_bytes()),
+            ("cross_section", "<f8", f64::from(res.sigma).to_le_bytes()),
+        ];
+        for (name, dtype, value) in scalars {
+            let header = npy_header(dtype, &[]);
+            archive.start_entry(&format!("{name}.npy"), (header.len() + value.len()) as u64)?;
+            archive.write_all(&header)?;
+            archive.write_all(&value)?;
+            archive.finish_entry()?;
+        }
+        archive.finish()
+    }
+}
+
+/// Header of a C-ordered NPY array (format version 1.0) with a certain dtype
+/// and shape, padded so that the data is 64-byte aligned
+fn npy_header(dtype: &str, shape: &[usize]) -> Vec<u8> {
+    let shape = match shape {
+        [len] => format!("({len},)"),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut dict = format!("{{'descr': '{dtype}', 'fortran_order': False, 'shape': {shape}, }}");
+    const PREAMBLE_LEN: usize = 10;
+    let padded_len = (PREAMBLE_LEN + dict.len() + 1).next_multiple_of(64) - PREAMBLE_LEN;
+    dict.extend(std::iter::repeat_n(' ', padded_len - dict.len() - 1));
+    dict.push('\n');
+
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend((dict.len() as u16).to_le_bytes());
+    header.extend(dict.bytes());
+    header
+}
+
+/// Version of the ZIP specification needed to read ZIP64 archives
+const ZIP_VERSION: u16 = 45;
+
+/// DOS date of the entries (1980-01-01, as the date is irrelevant here)
+const ZIP_DATE: u16 = (1 << 5) | 1;
+
+/// Placeholder for 32-bit fields whose value is in ZIP64 extra fields
+const ZIP64_PLACEHOLDER: u32 = u32::MAX;
+
+/// Header ID of the ZIP64 extended information extra field
+const ZIP64_EXTRA_ID: u16 = 1;
+
+/// Offset of the CRC-32 in the local file header
+const LOCAL_HEADER_CRC_OFFSET: u64 = 14;
+
+/// Entry of a ZIP archive
+struct ZipEntry {
+    /// File name
+    name: String,
+
+    /// Offset of the local file header in the archive
+    offset: u64,
+
+    /// Announced size of the data
+    size: u64,
+
+    /// Size of the data written so far
+    written: u64,
+
+    /// CRC-32 of the data written so far
+    crc: Crc32,
+}
+
+/// Minimal writer of uncompressed ZIP64 archives, whose entries are written
+/// one after the other with a size that is known in advance
+struct ZipWriter<W: Write + Seek> {
+    /// Underlying output
+    out: W,
+
+    /// Entries written so far, and the one being written (if any)
+    entries: Vec<ZipEntry>,
+
+    /// Whether an entry is being written
+    in_entry: bool,
+
+    /// Number of bytes written so far
+    offset: u64,
+}
+//
+impl<W: Write + Seek> ZipWriter<W> {
+    /// Start writing an archive
+    fn new(out: W) -> Self {
+        Self {
+            out,
+            entries: Vec::new(),
+            in_entry: false,
+            offset: 0,
+        }
+    }
+
+    /// Start writing an entry with a certain name and data size
+    fn start_entry(&mut self, name: &str, size: u64) -> Result<()> {
+        assert!(!self.in_entry, "The previous entry must be finished first");
+        let mut header = Vec::new();
+        header.extend(0x0403_4b50_u32.to_le_bytes());
+        header.extend(ZIP_VERSION.to_le_bytes());
+        header.extend(0_u16.to_le_bytes()); // Flags
+        header.extend(0_u16.to_le_bytes()); // Compression method (stored)
+        header.extend(0_u16.to_le_bytes()); // Time
+        header.extend(ZIP_DATE.to_le_bytes());
+        header.extend(0_u32.to_le_bytes()); // CRC-32, patched at the end
+        header.extend(ZIP64_PLACEHOLDER.to_le_bytes()); // Compressed size
+        header.extend(ZIP64_PLACEHOLDER.to_le_bytes()); // Uncompressed size
+        header.extend((name.len() as u16).to_le_bytes());
+        header.extend(20_u16.to_le_bytes()); // Extra field length
+        header.extend(name.bytes());
+        header.extend(ZIP64_EXTRA_ID.to_le_bytes());
+        header.extend(16_u16.to_le_bytes());
+        header.extend(size.to_le_bytes()); // Uncompressed size
+        header.extend(size.to_le_bytes()); // Compressed size
+        self.out.write_all(&header)?;
+
+        self.entries.push(ZipEntry {
+            name: name.to_owned(),
+            offset: self.offset,
+            size,
+            written: 0,
+            crc: Crc32::new(),
+        });
+        self.in_entry = true;
+        self.offset += header.len() as u64;
+        Ok(())
+    }
+
+    /// Finish writing the current entry, checking that its announced size was
+    /// honored and recording its CRC-32 in its local file header
+    fn finish_entry(&mut self) -> Result<()> {
+        let entry = self
+            .entries
+            .last()
+            .filter(|_| self.in_entry)
+            .expect("An entry must have been started");
+        if entry.written != entry.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Entry {} does not have its announced size", entry.name),
+            ));
+        }
+        self.out
+            .seek(SeekFrom::Start(entry.offset + LOCAL_HEADER_CRC_OFFSET))?;
+        self.out.write_all(&entry.crc.value().to_le_bytes())?;
+        self.out.seek(SeekFrom::Start(self.offset))?;
+        self.in_entry = false;
+        Ok(())
+    }
+
+    /// Write the central directory, which ends the archive
+    fn finish(mut self) -> Result<()> {
+        assert!(!self.in_entry, "The last entry must be finished first");
+
+        // Central directory headers
+        let central_dir_offset = self.offset;
+        let mut central_dir = Vec::new();
+        for entry in &self.entries {
+            central_dir.extend(0x0201_4b50_u32.to_le_bytes());
+            central_dir.extend(ZIP_VERSION.to_le_bytes()); // Version made by
+            central_dir.extend(ZIP_VERSION.to_le_bytes()); // Version needed
+            central_dir.extend(0_u16.to_le_bytes()); // Flags
+            central_dir.extend(0_u16.to_le_bytes()); // Compression method
+            central_dir.extend(0_u16.to_le_bytes()); // Time
+            central_dir.extend(ZIP_DATE.to_le_bytes());
+            central_dir.extend(entry.crc.value().to_le_bytes());
+            central_dir.extend(ZIP64_PLACEHOLDER.to_le_bytes()); // Compressed size
+            central_dir.extend(ZIP64_PLACEHOLDER.to_le_bytes()); // Uncompressed size
+            central_dir.extend((entry.name.len() as u16).to_le_bytes());
+            central_dir.extend(28_u16.to_le_bytes()); // Extra field length
+            central_dir.extend(0_u16.to_le_bytes()); // Comment length
+            central_dir.extend(0_u16.to_le_bytes()); // Disk number
+            central_dir.extend(0_u16.to_le_bytes()); // Internal attributes
+            central_dir.extend(0_u32.to_le_bytes()); // External attributes
+            central_dir.extend(ZIP64_PLACEHOLDER.to_le_bytes()); // Header offset
+            central_dir.extend(entry.name.bytes());
+            central_dir.extend(ZIP64_EXTRA_ID.to_le_bytes());
+            central_dir.extend(24_u16.to_le_bytes());
+            central_dir.extend(entry.size.to_le_bytes()); // Uncompressed size
+            central_dir.extend(entry.size.to_le_bytes()); // Compressed size
+            central_dir.extend(entry.offset.to_le_bytes());
+        }
+        let num_entries = self.entries.len() as u64;
+        let central_dir_len = central_dir.len() as u64;
+
+        // ZIP64 end of central directory record and locator
+        let mut end = Vec::new();
+        let zip64_end_offset = central_dir_offset + central_dir_len;
+        end.extend(0x0606_4b50_u32.to_le_bytes());
+        end.extend(44_u64.to_le_bytes()); // Size of the rest of the record
+        end.extend(ZIP_VERSION.to_le_bytes()); // Version made by
+        end.extend(ZIP_VERSION.to_le_bytes()); // Version needed
+        end.extend(0_u32.to_le_bytes()); // Disk number
+        end.extend(0_u32.to_le_bytes()); // Disk of the central directory
+        end.extend(num_entries.to_le_bytes()); // Entries on this disk
+        end.extend(num_entries.to_le_bytes()); // Total number of entries
+        end.extend(central_dir_len.to_le_bytes());
+        end.extend(central_dir_offset.to_le_bytes());
+        end.extend(0x0706_4b50_u32.to_le_bytes());
+        end.extend(0_u32.to_le_bytes()); // Disk of the ZIP64 end record
+        end.extend(zip64_end_offset.to_le_bytes());
+        end.extend(1_u32.to_le_bytes()); // Total number of disks
+
+        // Legacy end of central directory record
+        end.extend(0x0605_4b50_u32.to_le_bytes());
+        end.extend(0_u16.to_le_bytes()); // Disk number
+        end.extend(0_u16.to_le_bytes()); // Disk of the central directory
+        end.extend(u16::MAX.to_le_bytes()); // Entries on this disk
+        end.extend(u16::MAX.to_le_bytes()); // Total number of entries
+        end.extend(ZIP64_PLACEHOLDER.to_le_bytes()); // Central directory size
+        end.extend(ZIP64_PLACEHOLDER.to_le_bytes()); // Central directory offset
+        end.extend(0_u16.to_le_bytes()); // Comment length
+
+        self.out.write_all(&central_dir)?;
+        self.out.write_all(&end)?;
+        self.out.flush()
+    }
+}
+//
+impl<W: Write + Seek> Write for ZipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let entry = self
+            .entries
+            .last_mut()
+            .filter(|_| self.in_entry)
+            .expect("An entry must have been started");
+        let len = self.out.write(buf)?;
+        entry.crc.update(&buf[..len]);
+        entry.written += len as u64;
+        self.offset += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.out.flush()
+    }
+}
+
+/// CRC-32 checksum, as used by the ZIP format
+#[derive(Clone, Copy)]
+struct Crc32(u32);
+//
+impl Crc32 {
+    /// Lookup table of the CRC of each byte value
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut byte = 0;
+        while byte < 256 {
+            let mut crc = byte as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                };
+                bit += 1;
+            }
+            table[byte] = crc;
+            byte += 1;
+        }
+        table
+    };
+
+    /// Start computing a checksum
+    fn new() -> Self {
+        Self(u32::MAX)
+    }
+
+    /// Account for some data
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 = Self::TABLE[((self.0 ^ u32::from(byte)) & 0xff) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    /// Checksum of the data seen so far
+    fn value(self) -> u32 {
+        !self.0
+    }
+}
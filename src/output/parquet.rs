@@ -0,0 +1,155 @@
+//! Parquet files, which hold the accepted events as columns for out-of-core
+//! analyses with e.g. polars, pandas or DuckDB
+
+use super::{
+    events::EventFormat,
+    records::{column_names, write_numbered_record, NumberedRecords},
+};
+use crate::{
+    config::Configuration, event::Event, matelems::MEsVector, numeric::Float, resfin::FinalResults,
+};
+use ::parquet::{
+    basic::Compression,
+    data_type::{DoubleType, Int64Type},
+    errors::ParquetError,
+    file::{metadata::KeyValue, properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+use std::{
+    fs::File,
+    io::{self, Result, Write},
+    sync::Arc,
+};
+
+/// Number of events per row group, which bounds the memory footprint of the
+/// assembly of a Parquet file (about 60 MB per row group)
+const ROW_GROUP_SIZE: usize = 1 << 18;
+
+/// Writer of Parquet files
+///
+/// Each accepted event is a row, with one column per column of the numbered
+/// records (see column_names()), the 4-momenta being given in the frame of
+/// lab_frame_momenta(). The `event_number` column is an unsigned 64-bit
+/// integer, and all other columns are doubles. Columns are Snappy-compressed.
+///
+/// The number of generated events and the total cross-section in pb are stored
+/// in the key-value metadata of the file, as `num_events` and `cross_section`.
+///
+pub struct ParquetFormat;
+//
+impl EventFormat for ParquetFormat {
+    fn write_header(
+        &self,
+        _out: &mut dyn Write,
+        _cfg: &Configuration,
+        _res: &FinalResults,
+        _max_weight: Float,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_event(
+        &self,
+        out: &mut dyn Write,
+        event_number: usize,
+        event: &Event,
+        weight: Float,
+        me_weights: &MEsVector,
+    ) -> Result<()> {
+        write_numbered_record(out, event_number, event, weight, me_weights)
+    }
+
+    fn write_footer(&self, _out: &mut dyn Write) -> Result<()> {
+        Ok(())
+    }
+
+    fn assemble(
+        &self,
+        path: &str,
+        tmp_path: &str,
+        cfg: &Configuration,
+        res: &FinalResults,
+        _max_weight: Float,
+    ) -> Result<()> {
+        // Describe the columns and the global results
+        let mut columns = column_names();
+        let mut schema = format!(
+            "message events {{ required int64 {} (INTEGER(64, false));",
+            columns.next().expect("There should be an event number")
+        );
+        for name in columns {
+            schema.push_str(&format!(" required double {name};"));
+        }
+        schema.push_str(" }");
+        let schema = Arc::new(parse_message_type(&schema).map_err(io::Error::other)?);
+        #[allow(clippy::useless_conversion)]
+        let metadata = vec![
+            KeyValue::new("num_events".to_owned(), cfg.num_events.to_string()),
+            KeyValue::new("cross_section".to_owned(), f64::from(res.sigma).to_string()),
+        ];
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(ROW_GROUP_SIZE)
+            .set_key_value_metadata(Some(metadata))
+            .build();
+        let mut writer = SerializedFileWriter::new(File::create(path)?, schema, properties.into())
+            .map_err(io::Error::other)?;
+
+        // Transpose the temporary event storage into row groups
+        let mut event_numbers = Vec::with_capacity(ROW_GROUP_SIZE);
+        let mut values = vec![Vec::with_capacity(ROW_GROUP_SIZE); column_names().count() - 1];
+        let mut flush = |event_numbers: &mut Vec<i64>, values: &mut [Vec<f64>]| -> Result<()> {
+            write_row_group(&mut writer, event_numbers, values).map_err(io::Error::other)?;
+            event_numbers.clear();
+            values.iter_mut().for_each(Vec::clear);
+            Ok(())
+        };
+        NumberedRecords::open(tmp_path)?.try_for_each(|record| {
+            let mut words = record
+                .chunks_exact(8)
+                .map(|word| word.try_into().expect("Columns should be 8 bytes long"));
+            let event_number =
+                u64::from_le_bytes(words.next().expect("There should be an event number"));
+            event_numbers.push(event_number as i64);
+            for (column, word) in values.iter_mut().zip(words) {
+                column.push(f64::from_le_bytes(word));
+            }
+            if event_numbers.len() == ROW_GROUP_SIZE {
+                flush(&mut event_numbers, &mut values)?;
+            }
+            Ok(())
+        })?;
+        if !event_numbers.is_empty() {
+            flush(&mut event_numbers, &mut values)?;
+        }
+        writer.close().map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+/// Write a row group made of the event numbers and the other columns
+fn write_row_group(
+    writer: &mut SerializedFileWriter<File>,
+    event_numbers: &[i64],
+    values: &[Vec<f64>],
+) -> std::result::Result<(), ParquetError> {
+    let mut row_group = writer.next_row_group()?;
+    let mut column = row_group
+        .next_column()?
+        .expect("There should be an event number column");
+    column
+        .typed::<Int64Type>()
+        .write_batch(event_numbers, None, None)?;
+    column.close()?;
+    for values in values {
+        let mut column = row_group
+            .next_column()?
+            .expect("There should be one column per value");
+        column
+            .typed::<DoubleType>()
+            .write_batch(values, None, None)?;
+        column.close()?;
+    }
+    row_group.close()?;
+    Ok(())
+}
@@ -0,0 +1,206 @@
+//! Binary per-event records, for offline analyses which need more than the
+//! standard event formats provide
+
+use super::events::{lab_frame_momenta, EventFormat};
+use crate::{
+    config::Configuration,
+    event::{Event, NUM_PARTICLES},
+    matelems::{MEsVector, NUM_MAT_ELEMS},
+    momentum::MOMENTUM_DIM,
+    numeric::Float,
+    resfin::FinalResults,
+};
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Result, Write},
+};
+
+/// Magic bytes at the start of a record file
+const MAGIC: &[u8; 8] = b"3PHOTREC";
+
+/// Version of the record file layout
+const VERSION: u32 = 1;
+
+/// Number of values in each record: weight, 4-momenta, matrix element weights
+const RECORD_LEN: usize = 1 + NUM_PARTICLES * MOMENTUM_DIM + NUM_MAT_ELEMS;
+
+/// Writer of per-event binary records
+///
+/// The file starts with a header made of the `3PHOTREC` magic bytes, the
+/// layout version and the number of values per record (as little-endian u32),
+/// the number of generated events (as little-endian u64) and the total
+/// cross-section in pb (as little-endian f64).
+///
+/// Each accepted event is then stored as a record of little-endian f64 values:
+/// the event weight, the 4-momenta (Px, Py, Pz, E) of the incoming electron and
+/// positron and of the outgoing photons in the frame of lab_frame_momenta(),
+/// and the contribution of each matrix element to the event weight, in the
+/// order of the matelems module.
+///
+pub struct RecordsFormat;
+//
+impl EventFormat for RecordsFormat {
+    fn write_header(
+        &self,
+        out: &mut dyn Write,
+        cfg: &Configuration,
+        res: &FinalResults,
+        _max_weight: Float,
+    ) -> Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&(RECORD_LEN as u32).to_le_bytes())?;
+        out.write_all(&(cfg.num_events as u64).to_le_bytes())?;
+        write_value(out, res.sigma)
+    }
+
+    fn write_event(
+        &self,
+        out: &mut dyn Write,
+        _event_number: usize,
+        event: &Event,
+        weight: Float,
+        me_weights: &MEsVector,
+    ) -> Result<()> {
+        write_record(out, event, weight, me_weights)
+    }
+
+    fn write_footer(&self, _out: &mut dyn Write) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Write the record of an event
+fn write_record(
+    out: &mut dyn Write,
+    event: &Event,
+    weight: Float,
+    me_weights: &MEsVector,
+) -> Result<()> {
+    write_value(out, weight)?;
+    for &coord in lab_frame_momenta(event).iter().flatten() {
+        write_value(out, coord)?;
+    }
+    for &me_weight in me_weights.iter() {
+        write_value(out, me_weight)?;
+    }
+    Ok(())
+}
+
+/// Write a value as a little-endian f64, whatever the floating-point precision
+#[allow(clippy::useless_conversion)]
+fn write_value(out: &mut dyn Write, value: Float) -> Result<()> {
+    out.write_all(&f64::from(value).to_le_bytes())
+}
+
+/// Size of a numbered record: the event number as a little-endian u64,
+/// followed by the record of the event
+pub(super) const NUMBERED_RECORD_SIZE: usize = (1 + RECORD_LEN) * 8;
+
+/// Write the numbered record of an event, for the formats which are assembled
+/// from the temporary event storage column by column
+pub(super) fn write_numbered_record(
+    out: &mut dyn Write,
+    event_number: usize,
+    event: &Event,
+    weight: Float,
+    me_weights: &MEsVector,
+) -> Result<()> {
+    out.write_all(&(event_number as u64).to_le_bytes())?;
+    write_record(out, event, weight, me_weights)
+}
+
+/// Names of the particles of an event, in the order of its momenta
+#[cfg(any(feature = "parquet-output", feature = "root-output"))]
+const PARTICLE_NAMES: [&str; NUM_PARTICLES] =
+    ["electron", "positron", "photon1", "photon2", "photon3"];
+
+/// Names of the 4-momentum components, in the order of lab_frame_momenta()
+#[cfg(any(feature = "parquet-output", feature = "root-output"))]
+const COMPONENT_NAMES: [&str; MOMENTUM_DIM] = ["px", "py", "pz", "e"];
+
+/// Names of the matrix elements, in the order of the matelems module
+#[cfg(any(feature = "parquet-output", feature = "root-output"))]
+const MAT_ELEM_NAMES: [&str; NUM_MAT_ELEMS] = ["a", "b_p", "b_m", "r_mx", "i_mx"];
+
+/// Names of the columns of the numbered records, for the columnar formats:
+/// `event_number`, `weight`, `<particle>_<component>` for the 4-momenta (e.g.
+/// `photon1_px`, `electron_e`, photons being labelled in the configured
+/// photon ordering) and `weight_<matrix element>` for the contribution of each
+/// matrix element to the event weight (e.g. `weight_b_p`)
+#[cfg(any(feature = "parquet-output", feature = "root-output"))]
+pub(super) fn column_names() -> impl Iterator<Item = String> {
+    ["event_number".to_owned(), "weight".to_owned()]
+        .into_iter()
+        .chain(PARTICLE_NAMES.iter().flat_map(|particle| {
+            COMPONENT_NAMES
+                .iter()
+                .map(move |component| format!("{particle}_{component}"))
+        }))
+        .chain(MAT_ELEM_NAMES.iter().map(|me| format!("weight_{me}")))
+}
+
+/// Reader of a temporary event storage made of numbered records
+pub(super) struct NumberedRecords {
+    /// Reader of the temporary event storage
+    reader: BufReader<File>,
+
+    /// Number of events in the temporary event storage
+    num_events: usize,
+}
+//
+impl NumberedRecords {
+    /// Open a temporary event storage, checking that it is made of whole
+    /// numbered records
+    pub fn open(tmp_path: &str) -> Result<Self> {
+        let file = File::open(tmp_path)?;
+        let len = file.metadata()?.len() as usize;
+        if len % NUMBERED_RECORD_SIZE != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{tmp_path} does not contain a whole number of events"),
+            ));
+        }
+        Ok(Self {
+            reader: BufReader::new(file),
+            num_events: len / NUMBERED_RECORD_SIZE,
+        })
+    }
+
+    /// Number of events in the temporary event storage
+    pub fn num_events(&self) -> usize {
+        self.num_events
+    }
+
+    /// Feed each numbered record into a closure, as raw bytes
+    pub fn try_for_each(
+        mut self,
+        mut f: impl FnMut(&[u8; NUMBERED_RECORD_SIZE]) -> Result<()>,
+    ) -> Result<()> {
+        let mut record = [0; NUMBERED_RECORD_SIZE];
+        for _ in 0..self.num_events {
+            self.reader.read_exact(&mut record)?;
+            f(&record)?;
+        }
+        Ok(())
+    }
+
+    /// Iterate over one 8-byte column of the numbered records (0 being the
+    /// event number and the next ones being the values of the record)
+    ///
+    /// Since the iterator cannot report I/O errors, it panics if the temporary
+    /// event storage cannot be read.
+    ///
+    #[cfg(feature = "root-output")]
+    pub fn into_column(mut self, column: usize) -> impl Iterator<Item = [u8; 8]> {
+        let mut record = [0; NUMBERED_RECORD_SIZE];
+        (0..self.num_events).map(move |_| {
+            self.reader
+                .read_exact(&mut record)
+                .expect("The temporary event storage should hold the expected events");
+            record[8 * column..8 * (column + 1)]
+                .try_into()
+                .expect("Columns should be 8 bytes long")
+        })
+    }
+}
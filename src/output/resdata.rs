@@ -0,0 +1,149 @@
+//! Versioned format of the res.data results file, which features everything
+//! that the simulation computes, unlike the legacy format of 3photons
+//!
+//! A v2 file starts with a `3photons-res.data 2` line, followed by sections
+//! whose `[name]` headers stand on their own line. Each line of a section is
+//! made of whitespace-separated fields, the first of which names the value(s)
+//! that follow. Lines starting with `#` are comments. Numbers are written with
+//! enough digits to be read back exactly.
+
+use crate::{
+    checkpoint,
+    config::Configuration,
+    event::NUM_SPINS,
+    matelems::{CONTRIBUTIONS, NUM_MAT_ELEMS},
+    numeric::Float,
+    resfin::FinalResults,
+};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufWriter, Result, Write},
+    time::Duration,
+};
+
+/// Version of the format, which is bumped when its layout changes
+const VERSION: u32 = 2;
+
+/// Formats of the res.data file
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DataFormat {
+    /// Format of the original 3photons program
+    #[default]
+    Legacy,
+
+    /// Versioned format, which adds the metadata of the run, the cut flow
+    /// and the full per-spin results
+    V2,
+}
+
+/// Write the res.data file in the v2 format
+pub(super) fn write_v2(
+    cfg: &Configuration,
+    res: &FinalResults,
+    elapsed_time: Duration,
+) -> Result<()> {
+    let mut file = BufWriter::new(File::create(cfg.results_path("data"))?);
+    let out = &mut file;
+    writeln!(out, "3photons-res.data {VERSION}")?;
+
+    // How the results were produced
+    let provenance = &res.provenance;
+    section(out, "metadata")?;
+    entry(out, "version", &provenance.version)?;
+    entry(out, "git_commit", &provenance.git_commit)?;
+    entry(out, "build_profile", &provenance.build_profile)?;
+    let features = provenance.features.join(",");
+    let features = if features.is_empty() { "-" } else { &features };
+    entry(out, "features", features)?;
+    entry(out, "config_hash", checkpoint::config_hash(cfg))?;
+    let rng = (provenance.rng.to_possible_value()).expect("Engines should have a name");
+    entry(out, "rng", rng.get_name())?;
+    entry(out, "seed", provenance.seed)?;
+    let start_time = provenance.start_time.as_deref().unwrap_or("-");
+    entry(out, "start_time", start_time)?;
+    entry(out, "wall_time", elapsed_time.as_secs_f64())?;
+    entry(out, "partial", res.partial)?;
+
+    // Configuration entries of the original 3photons
+    let ev_cut = &cfg.event_cut;
+    section(out, "configuration")?;
+    entry(out, "process", &cfg.process)?;
+    entry(out, "num_events", cfg.num_events)?;
+    number(out, "e_total", cfg.e_total)?;
+    number(out, "beam_photons_cut", ev_cut.beam_photons_cut)?;
+    number(out, "photon_photon_cut", ev_cut.photon_photon_cut)?;
+    number(out, "e_min", ev_cut.e_min)?;
+    number(out, "beam_photon_plane_cut", ev_cut.beam_photon_plane_cut)?;
+    number(out, "alpha", cfg.alpha)?;
+    number(out, "alpha_z", cfg.alpha_z)?;
+    number(out, "gev2_to_picobarn", cfg.gev2_to_picobarn)?;
+    number(out, "m_z0", cfg.m_z0)?;
+    number(out, "g_z0", cfg.g_z0)?;
+    number(out, "sin2_weinberg", cfg.sin2_weinberg)?;
+    number(out, "branching_ep_em", cfg.branching_ep_em)?;
+    number(out, "beta_plus", cfg.beta_plus)?;
+    number(out, "beta_minus", cfg.beta_minus)?;
+
+    // Total cross-section and statistical significances
+    section(out, "results")?;
+    entry(out, "selected_events", res.selected_events)?;
+    entry(out, "non_finite_events", res.weight_issues.non_finite())?;
+    entry(out, "negative_weight_events", res.weight_issues.negative())?;
+    number(out, "sigma", res.sigma)?;
+    number(out, "sigma_error", res.sigma * res.prec)?;
+    number(out, "prec", res.prec)?;
+    number(out, "beta_min", res.beta_min)?;
+    number(out, "ss_p", res.ss_p)?;
+    number(out, "ss_p_error", res.ss_p * res.inc_ss_p)?;
+    number(out, "ss_m", res.ss_m)?;
+    number(out, "ss_m_error", res.ss_m * res.inc_ss_m)?;
+
+    // Statistics of each cut, in order of application, with the name of the
+    // cut last since it may contain spaces
+    section(out, "cut_flow")?;
+    writeln!(out, "# rejected efficiency cut")?;
+    if cfg.detector.is_some() {
+        let undetected = res.cut_flow.undetected();
+        let acceptance = res.cut_flow.acceptance(cfg.num_events);
+        writeln!(out, "{undetected} {acceptance:e} acceptance")?;
+    }
+    for (cut, efficiency) in res.cut_flow.efficiencies(cfg.num_events) {
+        let rejected = res.cut_flow.rejected(cut);
+        let name = ev_cut.cut_name(cut);
+        writeln!(out, "{rejected} {efficiency:e} {name}")?;
+    }
+
+    // Cross-section and relative statistical error of each contribution, for
+    // each spin
+    for (name, matrix) in [("spm2", &res.spm2), ("vars", &res.vars)] {
+        section(out, name)?;
+        writeln!(out, "# spin {}", CONTRIBUTIONS.join(" "))?;
+        for spin in 0..NUM_SPINS {
+            write!(out, "{}", spin + 1)?;
+            for contrib in 0..NUM_MAT_ELEMS {
+                write!(out, " {:e}", matrix[(spin, contrib)])?;
+            }
+            writeln!(out)?;
+        }
+    }
+    file.flush()
+}
+
+/// Start a section of the file
+fn section(out: &mut impl Write, name: &str) -> Result<()> {
+    writeln!(out, "[{name}]")
+}
+
+/// Write a named value
+fn entry(out: &mut impl Write, name: &str, value: impl Display) -> Result<()> {
+    writeln!(out, "{name} {value}")
+}
+
+/// Write a named number, with enough digits to be read back exactly
+fn number(out: &mut impl Write, name: &str, value: Float) -> Result<()> {
+    writeln!(out, "{name} {value:e}")
+}
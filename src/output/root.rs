@@ -0,0 +1,107 @@
+//! ROOT files, which hold the accepted events as a tree of flat branches along
+//! with the histograms, for ROOT-based downstream analyses
+
+use super::{
+    events::EventFormat,
+    records::{column_names, write_numbered_record, NumberedRecords},
+};
+use crate::{
+    config::Configuration, event::Event, matelems::MEsVector, numeric::Float, resfin::FinalResults,
+};
+use oxyroot::{RootFile, WriterTree};
+use std::io::{self, Result, Write};
+
+/// Name of the tree of events
+const EVENTS_TREE: &str = "events";
+
+/// Writer of ROOT files
+///
+/// The `events` tree has one entry per accepted event, with one branch per
+/// column of the numbered records (see column_names()), the 4-momenta being
+/// given in the frame of lab_frame_momenta(). The `event_number` branch is a
+/// u64, and all other branches are f64.
+///
+/// Since ROOT histograms cannot be written yet, each histogram is stored as a
+/// tree named after its observable (with a numeric suffix if an observable is
+/// histogrammed several times), with one entry per bin including the underflow
+/// and overflow bins, and the branches `x_low`, `x_high`, `sum_w` and `sum_w2`.
+/// The differential cross-section in a bin is `sum_w / (x_high - x_low)`, and
+/// its statistical error is `sqrt(sum_w2) / (x_high - x_low)`.
+///
+pub struct RootFormat;
+//
+impl EventFormat for RootFormat {
+    fn write_header(
+        &self,
+        _out: &mut dyn Write,
+        _cfg: &Configuration,
+        _res: &FinalResults,
+        _max_weight: Float,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_event(
+        &self,
+        out: &mut dyn Write,
+        event_number: usize,
+        event: &Event,
+        weight: Float,
+        me_weights: &MEsVector,
+    ) -> Result<()> {
+        write_numbered_record(out, event_number, event, weight, me_weights)
+    }
+
+    fn write_footer(&self, _out: &mut dyn Write) -> Result<()> {
+        Ok(())
+    }
+
+    fn assemble(
+        &self,
+        path: &str,
+        tmp_path: &str,
+        _cfg: &Configuration,
+        res: &FinalResults,
+        _max_weight: Float,
+    ) -> Result<()> {
+        // Write the tree of events, reading the temporary event storage once
+        // per branch
+        let mut file = RootFile::create(path).map_err(io::Error::other)?;
+        let mut tree = WriterTree::new(EVENTS_TREE);
+        let mut columns = column_names().enumerate();
+        let (_, name) = columns.next().expect("There should be an event number");
+        tree.new_branch(
+            name,
+            NumberedRecords::open(tmp_path)?
+                .into_column(0)
+                .map(u64::from_le_bytes),
+        );
+        for (column, name) in columns {
+            tree.new_branch(
+                name,
+                NumberedRecords::open(tmp_path)?
+                    .into_column(column)
+                    .map(f64::from_le_bytes),
+            );
+        }
+        tree.write(&mut file).map_err(io::Error::other)?;
+
+        // Write one tree per histogram
+        if let Some(histograms) = &res.histograms {
+            for (name, _observable, histogram) in histograms.iter() {
+                let mut tree = WriterTree::new(name);
+                let bins = histogram.bins().collect::<Vec<_>>();
+                #[allow(clippy::useless_conversion)]
+                let column = |f: fn(&(Float, Float, Float, Float)) -> Float| {
+                    bins.iter().map(f).map(f64::from).collect::<Vec<_>>()
+                };
+                tree.new_branch("x_low", column(|bin| bin.0).into_iter());
+                tree.new_branch("x_high", column(|bin| bin.1).into_iter());
+                tree.new_branch("sum_w", column(|bin| bin.2).into_iter());
+                tree.new_branch("sum_w2", column(|bin| bin.3).into_iter());
+                tree.write(&mut file).map_err(io::Error::other)?;
+            }
+        }
+        file.close().map_err(io::Error::other)
+    }
+}
@@ -0,0 +1,189 @@
+//! Conversion of weighted events into unit-weight events, suitable for
+//! detector simulation, using the hit-or-miss method
+
+use crate::{
+    config::Configuration,
+    event::Event,
+    evgen::EventGenerator,
+    matelems::MatrixElement,
+    numeric::Float,
+    random::{RandGenerator, RandomGenerator},
+    resacc::ResultsAccumulator,
+};
+use log::info;
+use prefix_num_ops::real::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Serialize};
+
+/// Offset between the seed of the simulation and that of the warm-up phase,
+/// which ensures that the warm-up does not replay the first simulated events
+const WARMUP_SEED_OFFSET: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Configuration of event unweighting (not in the legacy format)
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnweightingConfig {
+    /// Number of events which are simulated, before the actual simulation, in
+    /// order to estimate the maximal event weight
+    pub warmup_events: usize,
+
+    /// Factor by which the estimated maximal event weight is multiplied, in
+    /// order to reduce the odds of encountering larger event weights
+    pub safety_factor: Float,
+}
+//
+impl Default for UnweightingConfig {
+    fn default() -> Self {
+        Self {
+            warmup_events: 100_000,
+            safety_factor: 1.1,
+        }
+    }
+}
+
+/// Hit-or-miss event unweighting
+pub struct Unweighter {
+    /// Reference event weight, which unweighted events are given
+    max_weight: Float,
+}
+//
+impl Unweighter {
+    /// Estimate the maximal event weight by simulating some events
+    ///
+    /// Event weights are normalized like those of the EventWriter, i.e. such
+    /// that their average over all generated events is the cross-section.
+    ///
+    pub fn new(
+        cfg: &Configuration,
+        unweighting: &UnweightingConfig,
+        process: &dyn MatrixElement,
+        evgen: &EventGenerator,
+    ) -> Self {
+        // The warm-up uses its own random number generator, so that the
+        // results of the actual simulation do not depend on it
+        let seed = cfg.rng.effective_seed(cfg.seed);
+        let mut rng = RandGenerator::<Xoshiro256PlusPlus>::with_seed(seed ^ WARMUP_SEED_OFFSET);
+
+        // Simulate the warm-up events, keeping track of the largest weight
+        let mut res_builder = ResultsAccumulator::new(cfg, evgen.event_weight());
+        let weight_scale = cfg.num_events as Float;
+        let mut max_weight: Float = 0.;
+        for _ in 0..unweighting.warmup_events {
+            let event = evgen.generate(&mut rng);
+            let Some(measured) = evgen.measure(&event, &mut rng) else {
+                continue;
+            };
+            if cfg.event_cut.apply(&measured).is_ok() {
+                let res_contrib = process.contributions(&event);
+                if let Some(sigma_contrib) = res_builder.integrate(&event, &measured, res_contrib) {
+                    max_weight = max_weight.max(abs(sigma_contrib * weight_scale));
+                }
+            }
+        }
+        Self {
+            max_weight: max_weight * unweighting.safety_factor,
+        }
+    }
+
+    /// Reference event weight, which unweighted events are given
+    pub fn max_weight(&self) -> Float {
+        self.max_weight
+    }
+
+    /// Decide whether a weighted event should be kept, and if so return its
+    /// new weight
+    ///
+    /// Events are kept with a probability proportional to their weight, and
+    /// given the reference weight. Events whose weight exceeds the reference
+    /// weight are always kept with their original weight, which preserves the
+    /// normalization of the weights but is reported as a violation.
+    ///
+    pub fn unweight(
+        &self,
+        event: &Event,
+        weight: Float,
+        stats: &mut UnweightingStats,
+    ) -> Option<Float> {
+        stats.candidates += 1;
+        let ratio = abs(weight) / self.max_weight;
+        if ratio > 1. {
+            stats.violations += 1;
+            stats.max_violation_ratio = stats.max_violation_ratio.max(ratio);
+            stats.written += 1;
+            return Some(weight);
+        }
+        (event_uniform(event) < ratio).then(|| {
+            stats.written += 1;
+            self.max_weight.copysign(weight)
+        })
+    }
+
+    /// Report how well event unweighting went on stdout
+    pub fn report(&self, stats: &UnweightingStats) {
+        let efficiency = (stats.written as Float) / (stats.candidates as Float);
+        info!("Unweighting        :");
+        info!("Max. weight (pb)   : {:e}", self.max_weight);
+        info!("Written events     : {}", stats.written);
+        info!("Efficiency         : {efficiency:.6}");
+        info!("Violations         : {}", stats.violations);
+        if stats.violations > 0 {
+            info!("Max. weight ratio  : {:.6}", stats.max_violation_ratio);
+        }
+        info!("");
+    }
+}
+
+/// Statistics of event unweighting
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct UnweightingStats {
+    /// Number of weighted events which were considered for unweighting
+    candidates: usize,
+
+    /// Number of events which were kept
+    written: usize,
+
+    /// Number of events whose weight exceeded the reference weight
+    violations: usize,
+
+    /// Largest ratio of an event weight to the reference weight, among
+    /// violations
+    max_violation_ratio: Float,
+}
+//
+impl UnweightingStats {
+    /// Integrate the statistics of another batch of events
+    pub fn merge(&mut self, other: &Self) {
+        self.candidates += other.candidates;
+        self.written += other.written;
+        self.violations += other.violations;
+        self.max_violation_ratio = self.max_violation_ratio.max(other.max_violation_ratio);
+    }
+}
+
+/// Pseudo-random number uniformly distributed in [0, 1[, derived from the
+/// outgoing momenta of an event
+///
+/// Using this rather than a random number generator makes the hit-or-miss
+/// decision independent of the order in which events are unweighted, so that
+/// multi-threaded and resumed simulations write out the same events, and it
+/// leaves the random number stream of the simulation untouched.
+///
+fn event_uniform(event: &Event) -> Float {
+    let mut hash = 0u64;
+    for &coord in event.outgoing_momenta().iter() {
+        // The conversion is only needed in single precision
+        #[allow(clippy::useless_conversion)]
+        let bits = u64::from(coord.to_bits());
+        hash = splitmix64(hash ^ bits);
+    }
+    let mantissa_bits = Float::MANTISSA_DIGITS;
+    ((hash >> (64 - mantissa_bits)) as Float) / ((1u64 << mantissa_bits) as Float)
+}
+
+/// SplitMix64 output function, which thoroughly mixes the bits of its input
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
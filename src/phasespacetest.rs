@@ -0,0 +1,134 @@
+//! Phase space test mode, which integrates test functions of known integral
+//! over the events of each flat phase space generator, to detect errors in
+//! their normalization
+
+use crate::cli::PhaseSpaceTestArgs;
+use anyhow::{ensure, Context, Result};
+use clap::ValueEnum;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use trois_photons::{
+    config::Configuration,
+    event::{Event, NUM_OUTGOING},
+    evgen::{EventGenerator, PhaseSpaceGenerator},
+    momentum::{E, X},
+    numeric::{floats::consts::PI, Float},
+    random::{RandGenerator, RandomGenerator},
+};
+
+/// Test function, along with its average over massless 3-body phase space
+struct TestFunction {
+    /// Name of the function, as displayed in the report
+    name: &'static str,
+
+    /// Value of the function for an event
+    value: fn(&Event) -> Float,
+
+    /// Average of the function over phase space
+    average: Float,
+}
+
+/// Test functions which the generators are checked against
+///
+/// The unit function checks the volume of phase space, and the others its
+/// flatness: by rotational invariance, each photon direction is isotropic, so
+/// the squared cosines of the (beam, photon) angles average to 1/3 each, and
+/// the energy fractions x_i = 2·E_i/√s are uniformly distributed over the
+/// Dalitz triangle, where x₁·x₂·x₃ averages to 7/30.
+///
+const TEST_FUNCTIONS: [TestFunction; 3] = [
+    TestFunction {
+        name: "unit",
+        value: |_event| 1.,
+        average: 1.,
+    },
+    TestFunction {
+        name: "sum of cos^2(beam,photon)",
+        value: |event| {
+            (0..NUM_OUTGOING)
+                .map(|ph| {
+                    let p = event.outgoing_momentum(ph);
+                    (p[X] / p[E]).powi(2)
+                })
+                .sum()
+        },
+        average: 1.,
+    },
+    TestFunction {
+        name: "x1*x2*x3",
+        value: |event| {
+            let e_total = event.electron_momentum()[E] + event.positron_momentum()[E];
+            (0..NUM_OUTGOING)
+                .map(|ph| 2. * event.outgoing_momentum(ph)[E] / e_total)
+                .product()
+        },
+        average: 7. / 30.,
+    },
+];
+
+/// Integrate the test functions over the phase space of each flat generator,
+/// failing if any integral deviates from its analytical value beyond the
+/// tolerance
+///
+/// Beam effects, the detector and cuts are left out, so that the events cover
+/// the massless 3-body phase space at the configured collision energy, whose
+/// volume is 𝜋²·s/8 in the normalization of RAMBO.
+///
+pub fn run(args: &PhaseSpaceTestArgs) -> Result<()> {
+    // Load the configuration, for the collision energy and random seed
+    let cfg = Configuration::load_with_overlay(&args.args.config, &args.args.set, |cfg| {
+        args.args.apply_overrides(cfg)
+    })
+    .context("Failed to load the configuration")?;
+    let volume = PI.powi(2) * cfg.e_total.powi(2) / 8.;
+    let seed = cfg.rng.effective_seed(cfg.seed);
+    println!();
+    println!("Phase space volume : {volume:e} (analytical)");
+
+    // Integrate the test functions with each generator
+    let mut failures = 0;
+    for &generator in PhaseSpaceGenerator::value_variants() {
+        let mut evgen =
+            EventGenerator::new(cfg.e_total).context("Failed to set up event generation")?;
+        evgen.set_phase_space_generator(generator);
+        let mut rng = RandGenerator::<Xoshiro256PlusPlus>::with_seed(seed);
+        let mut sums = [(0., 0.); TEST_FUNCTIONS.len()];
+        for _ in 0..args.events {
+            let event = evgen.generate(&mut rng);
+            let weight = evgen.event_weight() * event.weight_factor();
+            for (test, (sum, sum2)) in TEST_FUNCTIONS.iter().zip(&mut sums) {
+                let contrib = weight * (test.value)(&event);
+                *sum += contrib;
+                *sum2 += contrib.powi(2);
+            }
+        }
+
+        // Compare the integrals with their analytical values, allowing for
+        // the statistical error and for rounding errors
+        println!("{} generator :", generator.name());
+        let num_events = args.events as Float;
+        for (test, (sum, sum2)) in TEST_FUNCTIONS.iter().zip(sums) {
+            let integral = sum / num_events;
+            let stddev = ((sum2 / num_events - integral.powi(2)).max(0.) / num_events).sqrt();
+            let expected = volume * test.average;
+            let deviation = (integral - expected).abs();
+            let rounding = num_events.sqrt() * Float::EPSILON * expected;
+            let passed = deviation <= args.tolerance * stddev + rounding;
+            println!(
+                "  {:<26}: {:.8} +- {:.8} of the analytical value{}",
+                test.name,
+                integral / expected,
+                stddev / expected,
+                if passed { "" } else { " (FAILED)" }
+            );
+            failures += usize::from(!passed);
+        }
+    }
+    ensure!(
+        failures == 0,
+        "{failures} integrals deviate from their analytical value by more than {} standard \
+         deviations",
+        args.tolerance
+    );
+    println!("Phase space test passed");
+    Ok(())
+}
@@ -0,0 +1,92 @@
+//! Extended-precision check mode, which validates the matrix elements and
+//! their accumulation against a reference computed with the DoubleFloat type
+//! over a sample of generated events
+
+use crate::cli::PrecisionCheckArgs;
+use anyhow::{ensure, Context, Result};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use trois_photons::{
+    config::Configuration,
+    coupling::Couplings,
+    event::Event,
+    matelems::{MEsContributions, CONTRIBUTIONS, NUM_MAT_ELEMS},
+    numeric::{CompensatedSum, Float},
+    random::{RandGenerator, RandomGenerator},
+    setup_event_generation,
+};
+use trois_photons_core::extended::DoubleFloat;
+
+/// Compute the matrix elements of generated events both in the precision of
+/// Float and in extended precision, and report the worst-case relative
+/// deviation of each contribution, then that of their sums over all events
+pub fn run(args: &PrecisionCheckArgs) -> Result<()> {
+    // Set up event generation as for a simulation
+    ensure!(args.events > 0, "At least one event must be checked");
+    let cfg = Configuration::load_with_overlay(&args.args.config, &args.args.set, |cfg| {
+        args.args.apply_overrides(cfg)
+    })
+    .context("Failed to load the configuration")?;
+    let (process, evgen) =
+        setup_event_generation(&cfg).context("Failed to set up event generation")?;
+    let extended_couplings = Couplings::<DoubleFloat>::from(&cfg);
+    let seed = cfg.rng.effective_seed(cfg.seed);
+    let mut rng = RandGenerator::<Xoshiro256PlusPlus>::with_seed(seed);
+
+    // Check the events, summing their matrix elements as the simulation does
+    // on one side, and in extended precision on the other side
+    let mut checked = 0;
+    let mut worst_deviations: [Float; NUM_MAT_ELEMS] = [0.; NUM_MAT_ELEMS];
+    let mut sums = [CompensatedSum::default(); NUM_MAT_ELEMS];
+    let mut extended_sums = [DoubleFloat::from(0.); NUM_MAT_ELEMS];
+    while checked < args.events {
+        let event = evgen.generate(&mut rng);
+        if !args.no_cuts && cfg.event_cut.apply(&event).is_err() {
+            continue;
+        }
+        let extended_event = Event::with_weight_factor(
+            event.all_momenta().map(DoubleFloat::from),
+            event.weight_factor().into(),
+        );
+        let m2_sums = process.contributions(&event).m2_sums();
+        let extended_m2_sums =
+            MEsContributions::new(&extended_couplings, &extended_event).m2_sums();
+        for (contrib, (&value, &reference)) in m2_sums.iter().zip(&extended_m2_sums).enumerate() {
+            let deviation = relative_deviation(value, reference);
+            worst_deviations[contrib] = worst_deviations[contrib].max(deviation);
+            sums[contrib].add(value);
+            extended_sums[contrib] += reference;
+        }
+        checked += 1;
+    }
+
+    // Report the outcome
+    println!();
+    println!("Checked events     : {checked}");
+    println!("Worst-case relative deviation from extended precision (and in units of epsilon):");
+    for (name, deviation) in CONTRIBUTIONS.iter().zip(worst_deviations) {
+        println!(
+            "  {name:<16} : {deviation:.3e} ({:.0})",
+            deviation / Float::EPSILON
+        );
+    }
+    println!("Relative deviation of the sums over all events (and in units of epsilon):");
+    for (name, (sum, reference)) in CONTRIBUTIONS.iter().zip(sums.iter().zip(extended_sums)) {
+        let deviation = relative_deviation(sum.value(), reference);
+        println!(
+            "  {name:<16} : {deviation:.3e} ({:.0})",
+            deviation / Float::EPSILON
+        );
+    }
+    Ok(())
+}
+
+/// Deviation of a number from its extended-precision reference, relative to
+/// the magnitude of the reference (or zero if both are zero)
+fn relative_deviation(value: Float, reference: DoubleFloat) -> Float {
+    let deviation = (DoubleFloat::from(value) - reference).value().abs();
+    if deviation == 0. {
+        0.
+    } else {
+        deviation / reference.value().abs()
+    }
+}
@@ -0,0 +1,81 @@
+//! Registry of the processes whose matrix elements can be simulated
+//!
+//! The simulated process is selected by name via the `process` configuration
+//! entry. Library users can register their own implementations of the
+//! MatrixElement trait under new names, so that they are selected like the
+//! built-in process, or pass them to simulate() directly.
+
+use crate::{
+    config::Configuration, coupling::Couplings, error::ensure, matelems::MatrixElement, Error,
+    Result,
+};
+
+/// Name of the built-in e+e- -> ppp process of 3photons
+pub const DEFAULT_PROCESS: &str = "ee-3photons";
+
+/// Set up the matrix elements of a process for a certain configuration
+pub type ProcessConstructor =
+    Box<dyn Fn(&Configuration) -> Result<Box<dyn MatrixElement>> + Send + Sync>;
+
+/// Processes which can be simulated, by name
+pub struct ProcessRegistry {
+    /// Registered processes, in registration order
+    processes: Vec<(String, ProcessConstructor)>,
+}
+//
+impl ProcessRegistry {
+    /// Registry which only features the built-in process
+    pub fn new() -> Self {
+        let mut registry = Self {
+            processes: Vec::new(),
+        };
+        registry
+            .register(DEFAULT_PROCESS, |cfg| Ok(Box::new(Couplings::from(cfg))))
+            .expect("The registry was empty");
+        registry
+    }
+
+    /// Register a new process, whose name must not be taken
+    pub fn register(
+        &mut self,
+        name: &str,
+        constructor: impl Fn(&Configuration) -> Result<Box<dyn MatrixElement>> + Send + Sync + 'static,
+    ) -> Result<()> {
+        ensure!(
+            self.names().all(|registered| registered != name),
+            InvalidConfig,
+            "A process named {name:?} is already registered"
+        );
+        self.processes
+            .push((name.to_owned(), Box::new(constructor)));
+        Ok(())
+    }
+
+    /// Names of the registered processes, in registration order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.processes.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Set up the matrix elements of the configured process
+    pub fn create(&self, cfg: &Configuration) -> Result<Box<dyn MatrixElement>> {
+        let (_, constructor) = (self.processes.iter())
+            .find(|(name, _)| *name == cfg.process)
+            .ok_or_else(|| {
+                Error::InvalidConfig(format!(
+                    "Unknown process {:?}, expected one of {}",
+                    cfg.process,
+                    self.names()
+                        .map(|name| format!("{name:?}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })?;
+        constructor(cfg)
+    }
+}
+//
+impl Default for ProcessRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,127 @@
+//! Record of how simulation results were produced, so that old cross-section
+//! numbers can be reproduced
+
+use crate::{config::Configuration, numeric::Float, random::RandomEngine};
+use serde::{Deserialize, Serialize};
+use std::{env, fs, time::Instant};
+
+/// Provenance of simulation results
+///
+/// The configuration of the simulation is not featured here, since it is
+/// already part of the results.
+///
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Provenance {
+    /// Version of 3photons
+    pub version: String,
+
+    /// Git commit which 3photons was built from
+    pub git_commit: String,
+
+    /// Build profile ("release" or "debug")
+    pub build_profile: String,
+
+    /// Enabled cargo features
+    pub features: Vec<String>,
+
+    /// Random number generation engine
+    pub rng: RandomEngine,
+
+    /// Random number generator seed
+    pub seed: u64,
+
+    /// Standard deviation of the collision energy (GeV), if the energy spread
+    /// of the beams was simulated
+    pub beam_spread: Option<Float>,
+
+    /// Name of the machine which ran the simulation, if known
+    pub hostname: Option<String>,
+
+    /// Date and time at which the simulation started (RFC 3339), if known
+    pub start_time: Option<String>,
+
+    /// Time taken by the simulation (seconds), if known
+    pub wall_time: Option<f64>,
+}
+//
+impl Provenance {
+    /// Describe the current build and machine, and the random number
+    /// generation setup of a configuration
+    pub fn new(cfg: &Configuration) -> Self {
+        let features = [
+            ("compressed-output", cfg!(feature = "compressed-output")),
+            ("f32", cfg!(feature = "f32")),
+            ("faster-evgen", cfg!(feature = "faster-evgen")),
+            ("faster-threading", cfg!(feature = "faster-threading")),
+            ("fma", cfg!(feature = "fma")),
+            ("interval-check", cfg!(feature = "interval-check")),
+            ("mpi", cfg!(feature = "mpi")),
+            ("multi-threading", cfg!(feature = "multi-threading")),
+            ("no-photon-sorting", cfg!(feature = "no-photon-sorting")),
+            ("parquet-output", cfg!(feature = "parquet-output")),
+            ("root-output", cfg!(feature = "root-output")),
+            ("standard-random", cfg!(feature = "standard-random")),
+        ];
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            git_commit: env!("GIT_COMMIT").to_owned(),
+            build_profile: if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            }
+            .to_owned(),
+            features: features
+                .into_iter()
+                .filter(|&(_name, enabled)| enabled)
+                .map(|(name, _enabled)| name.to_owned())
+                .collect(),
+            rng: cfg.rng,
+            seed: cfg.rng.effective_seed(cfg.seed),
+            beam_spread: cfg.beam_spread,
+            hostname: hostname(),
+            start_time: None,
+            wall_time: None,
+        }
+    }
+}
+
+/// Stopwatch measuring the wall time of a simulation
+///
+/// WebAssembly in the browser has no clock that the standard library can use,
+/// so no time is measured there.
+///
+pub(crate) struct RunTimer(Option<(Instant, String)>);
+//
+impl RunTimer {
+    /// Start measuring time
+    pub fn start() -> Self {
+        Self(
+            (!cfg!(all(target_arch = "wasm32", target_os = "unknown"))).then(|| {
+                (
+                    Instant::now(),
+                    chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+                )
+            }),
+        )
+    }
+
+    /// Record the start time and elapsed time into a provenance record
+    pub fn stop(self, provenance: &mut Provenance) {
+        if let Some((start, start_time)) = self.0 {
+            provenance.start_time = Some(start_time);
+            provenance.wall_time = Some(start.elapsed().as_secs_f64());
+        }
+    }
+}
+
+/// Name of the current machine, if it can be found out
+fn hostname() -> Option<String> {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .into_iter()
+        .find_map(|var| env::var(var).ok())
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+}
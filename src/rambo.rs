@@ -0,0 +1,250 @@
+//! Generation of particles in flat N-body phase space, using the RAMBO
+//! (RAndom Momenta Beautifully Organized) algorithm from S.D. Ellis, R. Kleiss
+//! and W.J. Stirling
+//!
+//! Massless particles are generated with a constant weight. Massive particles
+//! are obtained by rescaling massless momenta as in the original RAMBO, at the
+//! expense of a per-event weight factor.
+//!
+//! The event generator uses it for three outgoing photons, but it works for
+//! any number N of outgoing particles, as groundwork for processes with more
+//! photons. Events themselves keep a fixed number of particles for now, since
+//! stable Rust cannot yet size matrices by NUM_INCOMING + N.
+
+use crate::{
+    error::ensure,
+    momentum::{E, MOMENTUM_DIM, X, Y, Z},
+    numeric::{real::*, Float, Real},
+    random::RandomGenerator,
+    Result,
+};
+use nalgebra::{SMatrix, SVector};
+
+/// Flat phase space of N outgoing particles
+#[derive(Clone, Copy, Debug)]
+pub struct PhaseSpace<const N: usize, R: Real = Float> {
+    /// Total center-of-mass energy of the collision
+    e_total: R,
+
+    /// Weight of massless events
+    weight: R,
+
+    /// Masses of the outgoing particles, if any is nonzero
+    masses: Option<[R; N]>,
+}
+//
+impl<const N: usize, R: Real> PhaseSpace<N, R> {
+    /// Prepare to generate massless particles at a center-of-mass energy of
+    /// e_total
+    ///
+    /// Fails if the collision energy is so extreme that the phase space weight
+    /// of events cannot be represented.
+    ///
+    pub fn new(e_total: R) -> Result<Self> {
+        Ok(Self {
+            e_total,
+            weight: weight::<N, R>(e_total)?,
+            masses: None,
+        })
+    }
+
+    /// Prepare to generate particles of certain masses at a center-of-mass
+    /// energy of e_total
+    ///
+    /// Fails if the collision energy is below the production threshold, or so
+    /// extreme that the phase space weight of events cannot be represented.
+    ///
+    pub fn with_masses(e_total: R, masses: [R; N]) -> Result<Self> {
+        check_masses(&masses, e_total)?;
+        Ok(Self {
+            masses: masses.iter().any(|&m| m != R::zero()).then_some(masses),
+            ..Self::new(e_total)?
+        })
+    }
+
+    /// Weight of massless events, which is identical for all events and which
+    /// the weight of massive events is a multiple of
+    pub fn weight(&self) -> R {
+        self.weight
+    }
+
+    /// Generate the energies and 3-momenta of the outgoing particles, along
+    /// with the weight of the event
+    pub fn generate(&self, rng: &mut impl RandomGenerator) -> (SVector<R, N>, SMatrix<R, N, 3>, R) {
+        let (p_e, p_xyz) = conformal_transform(&massless_momenta::<N, R>(rng), self.e_total);
+        match &self.masses {
+            Some(masses) => {
+                let (p_e, p_xyz, factor) = massive_transform(&p_e, &p_xyz, masses, self.e_total)
+                    .expect("Collision energy was checked to be above threshold");
+                (p_e, p_xyz, self.weight * factor)
+            }
+            None => (p_e, p_xyz, self.weight),
+        }
+    }
+}
+
+/// Check that particles of certain masses can be produced at a center-of-mass
+/// energy of e_total
+pub fn check_masses<const N: usize, R: Real>(masses: &[R; N], e_total: R) -> Result<()> {
+    ensure!(
+        masses.iter().all(|&m| m >= R::zero()),
+        InvalidConfig,
+        "Particle masses must be nonnegative, but they are {masses:?} GeV"
+    );
+    let total_mass = masses.iter().fold(R::zero(), |acc, &m| acc + m);
+    ensure!(
+        total_mass < e_total,
+        InvalidConfig,
+        "Particles of total mass {total_mass} GeV cannot be produced at {e_total} GeV"
+    );
+    Ok(())
+}
+
+/// Weight of the events of N-body massless phase space at a center-of-mass
+/// energy of e_total, which is identical for all events
+///
+/// Fails if the collision energy is so extreme that this weight cannot be
+/// represented.
+///
+#[rustfmt::skip]
+pub fn weight<const N: usize, R: Real>(e_total: R) -> Result<R> {
+    // Check on the number of particles. The check for N<101 is gone since
+    // unlike the original RAMBO, we don't use arrays of hardcoded size.
+    assert!(N > 1);
+
+    // Factorials for the phase space weight. Replaces the lazy
+    // initialization from the original RAMBO code with less branchy code.
+    // Replaces Z[INP-1] in the original 3photons code
+    let mut z_n = cast::<R>(N - 1) * ln(R::FRAC_PI_2());
+    for k in 2..N {
+        z_n -= cast::<R>(2.) * ln(cast::<R>(k - 1));
+    }
+    let z_n = z_n - ln(cast::<R>(N - 1));
+
+    // NOTE: The check on total energy is gone, because we only generate
+    //       massless particles and so the total energy will always be enough.
+    //       Counting of nonzero masses is also gone because it was unused.
+
+    // All generated events will have the same weight: pre-compute it
+    let ln_weight = cast::<R>(2 * N - 4) * ln(e_total) + z_n;
+    ensure!(
+        (cast(-180.)..=cast(174.)).contains(&ln_weight),
+        Numerical,
+        "The phase space weight at {e_total} GeV does not fit in floating-point numbers"
+    );
+    Ok(exp(ln_weight))
+}
+
+/// Generate N massless 4-momenta in infinite phase space
+///
+/// The output momenta are provided as a matrix where rows are 4-momentum
+/// components (Px, Py, Pz, E) and columns are particles. Random numbers are
+/// drawn one at a time in particle order, as the original 3photons did.
+///
+pub fn massless_momenta<const N: usize, R: Real>(
+    rng: &mut impl RandomGenerator,
+) -> SMatrix<R, MOMENTUM_DIM, N> {
+    // Generate the basic random parameters of the particles
+    const COS_THETA: usize = 0;
+    const PHI: usize = 1;
+    const EXP_MIN_E: usize = 2;
+    let params = SMatrix::<R, 3, N>::from_fn(|coord, _par| match coord {
+        COS_THETA => cast::<R>(2.) * cast(rng.random()) - R::one(),
+        PHI => cast::<R>(2.) * R::PI() * cast(rng.random()),
+        EXP_MIN_E => cast::<R>(rng.random()) * cast(rng.random()),
+        _ => unreachable!(),
+    });
+    let cos_theta = params.row(COS_THETA);
+    let phi = params.row(PHI);
+    let exp_min_e = params.row(EXP_MIN_E);
+
+    // Compute the momenta
+    let cos_phi = phi.map(cos);
+    let sin_phi = phi.map(sin);
+    let sin_theta = cos_theta.map(|cos| sqrt(R::one() - powi(cos, 2)));
+    let energy = exp_min_e.map(|e_me| -ln(e_me + R::min_positive_value()));
+    SMatrix::from_fn(|coord, par| {
+        energy[par]
+            * match coord {
+                X => sin_theta[par] * sin_phi[par],
+                Y => sin_theta[par] * cos_phi[par],
+                Z => cos_theta[par],
+                E => R::one(),
+                _ => unreachable!(),
+            }
+    })
+}
+
+/// Map massless 4-momenta from infinite phase space (laid out as in the output
+/// of massless_momenta()) to the energies and 3-momenta of particles whose
+/// total 4-momentum is (0, 0, 0, e_total)
+pub fn conformal_transform<const N: usize, R: Real>(
+    q: &SMatrix<R, MOMENTUM_DIM, N>,
+    e_total: R,
+) -> (SVector<R, N>, SMatrix<R, N, 3>) {
+    // Calculate the parameters of the conformal transformation
+    let r = q.column_sum();
+    let r_norm_2 = r[E] * r[E] - r.xyz().norm_squared();
+    let alpha = e_total / r_norm_2;
+    let r_norm = sqrt(r_norm_2);
+    let beta = R::one() / (r_norm + r[E]);
+
+    // Perform the conformal transformation from Q's to output 4-momenta
+    let tr_q = q.transpose();
+    let tr_q_xyz = tr_q.fixed_columns::<3>(X);
+    let rq = tr_q_xyz * r.xyz();
+    let p_e = (tr_q.column(E) * r[E] - rq) * alpha;
+    let b_rq_e = rq * beta - tr_q.column(E);
+    let p_xyz = (tr_q_xyz * r_norm + b_rq_e * r.xyz().transpose()) * alpha;
+    (p_e, p_xyz)
+}
+
+/// Give masses to the particles of massless momenta whose total 4-momentum is
+/// (0, 0, 0, e_total), laid out as in the output of conformal_transform()
+///
+/// As in the original RAMBO, all 3-momenta are rescaled by a common factor,
+/// which is solved for by Newton iteration so that energy is still conserved.
+/// The weight of massless events must then be multiplied by the returned
+/// factor. Returns None if the collision energy is below the production
+/// threshold of these particles.
+///
+pub fn massive_transform<const N: usize, R: Real>(
+    p_e: &SVector<R, N>,
+    p_xyz: &SMatrix<R, N, 3>,
+    masses: &[R; N],
+    e_total: R,
+) -> Option<(SVector<R, N>, SMatrix<R, N, 3>, R)> {
+    // Maximal number of Newton iterations (the original RAMBO stopped at 6,
+    // which is enough in double precision unless masses are extreme)
+    const MAX_ITERATIONS: usize = 16;
+
+    // Check the production threshold
+    let total_mass = masses.iter().fold(R::zero(), |acc, &m| acc + m);
+    if total_mass >= e_total {
+        return None;
+    }
+
+    // Solve for the rescaling factor x, such that the energies of the massive
+    // particles add up to e_total, starting from its upper bound
+    let masses2 = SVector::<R, N>::from_fn(|par, _| powi(masses[par], 2));
+    let p_e2 = p_e.map(|e| powi(e, 2));
+    let accuracy = cast::<R>(4 * N) * R::epsilon() * e_total;
+    let mut x = sqrt(R::one() - powi(total_mass / e_total, 2));
+    let mut energies = p_e.clone_owned();
+    for _ in 0..MAX_ITERATIONS {
+        energies = masses2.zip_map(&p_e2, |m2, e2| sqrt(m2 + powi(x, 2) * e2));
+        let energy_excess = energies.sum() - e_total;
+        if abs(energy_excess) <= accuracy {
+            break;
+        }
+        let derivative = p_e2.component_div(&energies).sum();
+        x -= energy_excess / (x * derivative);
+    }
+
+    // Rescale the 3-momenta and compute the mass-effect weight factor
+    let p_norms = p_e * x;
+    let norm_ratios = p_norms.component_div(&energies).product();
+    let energy_derivative = p_norms.zip_map(&energies, |p, e| powi(p, 2) / e).sum();
+    let weight_factor = powi(x, 2 * N as i32 - 3) * norm_ratios * e_total / energy_derivative;
+    Some((energies, p_xyz * x, weight_factor))
+}
@@ -0,0 +1,95 @@
+//! Wrapper around a random number generator which counts the random numbers
+//! that were drawn from it, so that their generation can be timed separately
+
+use super::RandomGenerator;
+use crate::{config::Configuration, numeric::Float};
+use serde::{Deserialize, Serialize};
+use std::{hint::black_box, time::Duration, time::Instant};
+
+/// Random number generator which counts the random numbers drawn from it
+///
+/// Only the state of the underlying generator is serialized, so that
+/// checkpoints do not depend on whether the generator was wrapped. Skipped
+/// random numbers are not counted, since they were not actually drawn.
+///
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct CountingGenerator<R> {
+    /// Underlying random number generator
+    inner: R,
+
+    /// Number of random numbers that were drawn so far
+    #[serde(skip)]
+    count: usize,
+}
+//
+impl<R: RandomGenerator> CountingGenerator<R> {
+    /// Measure how long the underlying generator takes to draw again the
+    /// random numbers which were drawn since an earlier state of this one
+    pub fn redraw_time(&self, earlier: &Self) -> Duration {
+        let mut rng = earlier.inner.clone();
+        let start = Instant::now();
+        for _ in earlier.count..self.count {
+            black_box(rng.random());
+        }
+        start.elapsed()
+    }
+}
+//
+impl<R: RandomGenerator> RandomGenerator for CountingGenerator<R> {
+    const DEFAULT_SEED: u64 = R::DEFAULT_SEED;
+
+    fn with_seed(seed: u64) -> Self {
+        Self {
+            inner: R::with_seed(seed),
+            count: 0,
+        }
+    }
+
+    fn new() -> Self {
+        Self {
+            inner: R::new(),
+            count: 0,
+        }
+    }
+
+    fn from_config(cfg: &Configuration) -> Self {
+        Self {
+            inner: R::from_config(cfg),
+            count: 0,
+        }
+    }
+
+    #[inline]
+    fn random(&mut self) -> Float {
+        self.count += 1;
+        self.inner.random()
+    }
+
+    #[inline]
+    fn random_array<const N: usize>(&mut self) -> [Float; N] {
+        self.count += N;
+        self.inner.random_array()
+    }
+
+    #[cfg(any(
+        feature = "mpi",
+        all(feature = "multi-threading", not(feature = "faster-threading"))
+    ))]
+    fn skip(&mut self, iterations: usize) {
+        self.inner.skip(iterations);
+    }
+
+    #[cfg(any(
+        feature = "mpi",
+        all(feature = "multi-threading", not(feature = "faster-threading"))
+    ))]
+    fn skip_array<const N: usize>(&mut self) {
+        self.inner.skip_array::<N>();
+    }
+
+    #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
+    fn jump(&mut self) {
+        self.inner.jump();
+    }
+}
@@ -1,16 +1,238 @@
-//! Random number generation module. Uses either a port of 3photon's ranf random
-//! number generator or the "rand" crate that is the Rust standard for RNGs.
+//! Random number generation module. Provides a common interface to a port of
+//! 3photon's ranf random number generator, to the engines of the "rand"
+//! crate that is the Rust standard for RNGs, and to a quasi-random generator.
 
-// Build the ranf module if it's going to be used
-#[cfg(not(feature = "standard-random"))]
+mod counting;
 mod ranf;
-
-// Else mimick the interface of RanfGenerator on top of the rand crate
-#[cfg(feature = "standard-random")]
+mod ranlux;
+mod sobol;
 mod standard;
 
-/// Random Number Generator implementation in use
-#[cfg(not(feature = "standard-random"))]
-pub use self::ranf::RanfGenerator as RandomGenerator;
-#[cfg(feature = "standard-random")]
-pub use self::standard::RandGenerator as RandomGenerator;
+use crate::{config::Configuration, error::ensure, numeric::Float, Error, Result};
+use bincode::Options;
+use clap::ValueEnum;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+pub use self::{
+    counting::CountingGenerator,
+    ranf::RanfGenerator,
+    ranlux::RanluxGenerator,
+    sobol::SobolGenerator,
+    standard::{RandGenerator, StdRng, XoshiroPlus},
+};
+
+/// Interface shared by all supported random number generators
+///
+/// Generators can be serialized, so that their state can be saved to and
+/// restored from simulation checkpoints (see save_state()).
+///
+pub trait RandomGenerator: Clone + DeserializeOwned + Send + Serialize {
+    /// Seed which is used when the user does not specify one
+    const DEFAULT_SEED: u64;
+
+    /// Create a new generator with a certain seed
+    ///
+    /// The seed must have been validated using `RandomEngine::check_seed()`.
+    ///
+    fn with_seed(seed: u64) -> Self;
+
+    /// Create a new generator, in a well-defined initial state
+    fn new() -> Self {
+        Self::with_seed(Self::DEFAULT_SEED)
+    }
+
+    /// Create a new generator following the simulation configuration
+    ///
+    /// By default, this uses the configured seed, if any, and the default seed
+    /// otherwise. The configuration must have been checked.
+    ///
+    fn from_config(cfg: &Configuration) -> Self {
+        cfg.seed.map_or_else(Self::new, Self::with_seed)
+    }
+
+    /// Save the state of the generator, so that restoring it later with
+    /// restore_state() yields the same random numbers as this generator
+    fn save_state(&self) -> Result<Vec<u8>> {
+        state_encoding()
+            .serialize(self)
+            .map_err(|source| Error::RngState {
+                what: "Failed to save the RNG state",
+                source,
+            })
+    }
+
+    /// Restore a state which was saved by save_state()
+    ///
+    /// The state must have been saved by the same kind of generator. This is
+    /// partially checked, by rejecting states which do not have the expected
+    /// length.
+    ///
+    fn restore_state(state: &[u8]) -> Result<Self> {
+        state_encoding()
+            .deserialize(state)
+            .map_err(|source| Error::RngState {
+                what: "Failed to restore the RNG state",
+                source,
+            })
+    }
+
+    /// Generate a random number between 0 and 1
+    fn random(&mut self) -> Float;
+
+    /// Generate an array of random numbers between 0 and 1
+    fn random_array<const N: usize>(&mut self) -> [Float; N];
+
+    /// Advance state as if random() had been called "iteration" times
+    #[cfg(any(
+        feature = "mpi",
+        all(feature = "multi-threading", not(feature = "faster-threading"))
+    ))]
+    fn skip(&mut self, iterations: usize) {
+        for _ in 0..iterations {
+            self.random();
+        }
+    }
+
+    /// Advance state as if random_array::<N>() had been called
+    #[cfg(any(
+        feature = "mpi",
+        all(feature = "multi-threading", not(feature = "faster-threading"))
+    ))]
+    fn skip_array<const N: usize>(&mut self) {
+        self.random_array::<N>();
+    }
+
+    /// Switch to another state in an arbitrary but maximally fast way
+    ///
+    /// The new state must only depend on the current one, so that a sequence
+    /// of jumps from a given seed yields reproducible random number streams.
+    ///
+    #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
+    fn jump(&mut self);
+}
+
+/// Binary encoding of saved generator states, which matches that of
+/// bincode::serialize() but rejects trailing bytes on deserialization
+fn state_encoding() -> impl Options {
+    bincode::DefaultOptions::new().with_fixint_encoding()
+}
+
+/// Random number generation engines which can be selected at run time
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+pub enum RandomEngine {
+    /// Port of the ranf generator used by the original 3photons code
+    #[serde(rename = "ranf")]
+    #[value(name = "ranf")]
+    Ranf,
+
+    /// Port of the RANLUX generator of CERNLIB, at the configured luxury level
+    #[serde(rename = "ranlux")]
+    #[value(name = "ranlux")]
+    Ranlux,
+
+    /// xoshiro256+ (or xoshiro128+ in single-precision mode)
+    #[serde(rename = "xoshiro+")]
+    #[value(name = "xoshiro+")]
+    XoshiroPlus,
+
+    /// xoshiro256++
+    #[serde(rename = "xoshiro256++")]
+    #[value(name = "xoshiro256++")]
+    Xoshiro256PlusPlus,
+
+    /// Standard generator of the rand crate
+    #[serde(rename = "std")]
+    #[value(name = "std")]
+    StdRng,
+
+    /// Sobol quasi-random sequence, randomized by a seed-dependent shift
+    #[serde(rename = "sobol")]
+    #[value(name = "sobol")]
+    Sobol,
+}
+//
+impl Default for RandomEngine {
+    /// The default engine is selected by the "standard-random" feature
+    fn default() -> Self {
+        if cfg!(feature = "standard-random") {
+            Self::XoshiroPlus
+        } else {
+            Self::Ranf
+        }
+    }
+}
+//
+impl RandomEngine {
+    /// Seed which is effectively used, given the configured one (if any)
+    pub fn effective_seed(self, seed: Option<u64>) -> u64 {
+        seed.unwrap_or(match self {
+            Self::Ranf => RanfGenerator::DEFAULT_SEED,
+            Self::Ranlux => RanluxGenerator::DEFAULT_SEED,
+            Self::XoshiroPlus => RandGenerator::<XoshiroPlus>::DEFAULT_SEED,
+            Self::Xoshiro256PlusPlus => RandGenerator::<Xoshiro256PlusPlus>::DEFAULT_SEED,
+            Self::StdRng => RandGenerator::<StdRng>::DEFAULT_SEED,
+            Self::Sobol => SobolGenerator::DEFAULT_SEED,
+        })
+    }
+
+    /// Check that a seed is suitable for this random number generation engine
+    pub fn check_seed(self, seed: u64) -> Result<()> {
+        if self == Self::Ranf {
+            ensure!(
+                seed <= RanfGenerator::MAX_SEED,
+                InvalidConfig,
+                "The ranf generator only accepts seeds up to {}",
+                RanfGenerator::MAX_SEED
+            );
+        }
+        if self == Self::Ranlux {
+            ensure!(
+                seed <= RanluxGenerator::MAX_SEED,
+                InvalidConfig,
+                "The ranlux generator only accepts seeds up to {}",
+                RanluxGenerator::MAX_SEED
+            );
+        }
+        Ok(())
+    }
+
+    /// Check that this random number generation engine is usable in the
+    /// current build configuration
+    pub fn check_build(self) -> Result<()> {
+        ensure!(
+            !(self == Self::Sobol && cfg!(feature = "faster-evgen")),
+            InvalidConfig,
+            "The sobol engine needs a fixed amount of random numbers per event, which the \
+             faster-evgen feature does not provide"
+        );
+        Ok(())
+    }
+
+    /// Run a computation with the type of random number generator of this
+    /// engine
+    pub fn dispatch<C: WithRandomGenerator>(self, computation: C) -> C::Output {
+        match self {
+            Self::Ranf => computation.run::<RanfGenerator>(),
+            Self::Ranlux => computation.run::<RanluxGenerator>(),
+            Self::XoshiroPlus => computation.run::<RandGenerator<XoshiroPlus>>(),
+            Self::Xoshiro256PlusPlus => computation.run::<RandGenerator<Xoshiro256PlusPlus>>(),
+            Self::StdRng => computation.run::<RandGenerator<StdRng>>(),
+            Self::Sobol => computation.run::<SobolGenerator>(),
+        }
+    }
+}
+
+/// Computation which is generic over the type of random number generator, so
+/// that it can be run with the engine selected at run time
+///
+/// The RandomGenerator trait is not object-safe, so RandomEngine::dispatch()
+/// selects the generator type instead.
+///
+pub trait WithRandomGenerator {
+    /// Result of the computation
+    type Output;
+
+    /// Run the computation with a certain type of random number generator
+    fn run<R: RandomGenerator + 'static>(self) -> Self::Output;
+}
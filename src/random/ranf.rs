@@ -1,6 +1,9 @@
 //! Random number generation, from Knuth's ranf (in Seminumerical Algorithm)
 
+use super::RandomGenerator;
 use crate::numeric::Float;
+use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
 
 // Generated random numbers will have a granularity of 1/MODULO
 type RanfInt = i32;
@@ -8,13 +11,14 @@ const MODULO: RanfInt = 1_000_000_000;
 const INV_MODULO: Float = 1e-9;
 
 /// Random number generator
-#[derive(Clone)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct RanfGenerator {
     /// Seed which this generator was initialized with
     #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
     seed: RanfInt,
 
     /// Current set of random numbers, maps to IA in original code
+    #[serde(with = "BigArray")]
     numbers: [RanfInt; 56],
 
     /// Index of the current random number, maps to MCALL in original code
@@ -24,12 +28,12 @@ pub struct RanfGenerator {
 impl RanfGenerator {
     // ### CONSTRUCTION ###
 
-    /// Create a new generator, with state faithful to original 3photons code
-    pub fn new() -> RanfGenerator {
-        // TODO: Would be nice to figure out the seed constraints of seeded_new
-        //       and publicize that interface too.
-        Self::seeded_new(234_612_947)
-    }
+    /// Largest seed that this generator accepts
+    ///
+    /// The IN55 initialization algorithm expects the seed to be a valid member
+    /// of the random number sequence, i.e. to be smaller than MODULO.
+    ///
+    pub const MAX_SEED: u64 = (MODULO - 1) as u64;
 
     /// Create a new generator with an arbitrary seed.
     /// This roughly maps to the IN55 method in the original code.
@@ -65,17 +69,43 @@ impl RanfGenerator {
         result
     }
 
-    // ### RANDOM NUMBER GENERATION ###
+    /// Generate 55 new random numbers between 0 and 1/FMODUL
+    /// This roughly maps to the IRN55 method in the original code.
+    fn reset(&mut self) {
+        for i in 1..25 {
+            self.numbers[i] -= self.numbers[i + 31];
+            if self.numbers[i] < 0 {
+                self.numbers[i] += MODULO
+            }
+        }
+        for i in 25..56 {
+            self.numbers[i] -= self.numbers[i - 24];
+            if self.numbers[i] < 0 {
+                self.numbers[i] += MODULO
+            }
+        }
+    }
+}
+
+impl RandomGenerator for RanfGenerator {
+    /// Default seed, faithful to the original 3photons code
+    const DEFAULT_SEED: u64 = 234_612_947;
+
+    /// Create a new generator with an arbitrary seed (up to MAX_SEED)
+    fn with_seed(seed: u64) -> RanfGenerator {
+        assert!(seed <= Self::MAX_SEED, "Invalid ranf seed");
+        Self::seeded_new(seed as RanfInt)
+    }
 
     /// Generate a random number between 0 and 1, with INV_MODULO granularity
     /// Roughly maps to the RN() method in the original code.
-    pub fn random(&mut self) -> Float {
+    fn random(&mut self) -> Float {
         self.random_array::<1>()[0]
     }
 
     /// Generate an array of random numbers
     #[allow(clippy::cast_lossless)]
-    pub fn random_array<const N: usize>(&mut self) -> [Float; N] {
+    fn random_array<const N: usize>(&mut self) -> [Float; N] {
         // Assuming that we will never need more than a round of numbers at a
         // time allows us to take implementation and performance shortcuts.
         let round_size = self.numbers.len() - 1;
@@ -101,41 +131,10 @@ impl RanfGenerator {
         result
     }
 
-    /// Generate 55 new random numbers between 0 and 1/FMODUL
-    /// This roughly maps to the IRN55 method in the original code.
-    fn reset(&mut self) {
-        for i in 1..25 {
-            self.numbers[i] -= self.numbers[i + 31];
-            if self.numbers[i] < 0 {
-                self.numbers[i] += MODULO
-            }
-        }
-        for i in 25..56 {
-            self.numbers[i] -= self.numbers[i - 24];
-            if self.numbers[i] < 0 {
-                self.numbers[i] += MODULO
-            }
-        }
-    }
-
-    // Advance state as if random() had been called "iteration" times
-    #[cfg(all(feature = "multi-threading", not(feature = "faster-threading")))]
-    pub fn skip(&mut self, iterations: usize) {
-        for _ in 0..iterations {
-            self.random();
-        }
-    }
-
-    // Advance state as if random_array() had been called
-    #[cfg(all(feature = "multi-threading", not(feature = "faster-threading")))]
-    pub fn skip_array<const N: usize>(&mut self) {
-        self.random_array::<N>();
-    }
-
     // Just switch to another state as fast as we can
     #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
-    pub fn jump(&mut self) {
-        let new_seed = self.seed + 123456;
+    fn jump(&mut self) {
+        let new_seed = (self.seed + 123456) % MODULO;
         *self = Self::seeded_new(new_seed);
     }
 }
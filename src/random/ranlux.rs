@@ -0,0 +1,192 @@
+//! Random number generation, from F. James' RANLUX (CERNLIB V115), which is
+//! M. Lüscher's refinement of the Marsaglia-Zaman subtract-with-borrow
+//! generator, with a configurable luxury level
+//!
+//! The arithmetic of the original single-precision code is reproduced exactly,
+//! so that the same seed and luxury level yield the same random numbers.
+
+use super::RandomGenerator;
+use crate::{config::Configuration, numeric::Float};
+use serde::{Deserialize, Serialize};
+
+/// Number of 24-bit integers in the generator state
+const STATE_LEN: usize = 24;
+
+/// Modulus of the subtract-with-borrow recurrence
+const MODULO: i32 = 1 << 24;
+
+/// 2⁻²⁴, the granularity of the generated numbers (TWOM24 in original code)
+const INV_MODULO: f32 = 1. / MODULO as f32;
+
+/// Generated numbers below 2⁻¹² are padded with lower-order bits
+const PADDING_THRESHOLD: i32 = 1 << 12;
+
+/// Initial indices of the two lagged state entries (I24 and J24 in original
+/// code, which counts from 1)
+const INITIAL_LAGS: (usize, usize) = (23, 9);
+
+/// Numbers which are discarded after each group of 24 delivered numbers, for
+/// each luxury level (NDSKIP in original code)
+const SKIPPED_NUMBERS: [usize; 5] = [0, 24, 73, 199, 365];
+
+/// Seed which the original code uses when none is specified (JSDFLT)
+const ORIGINAL_DEFAULT_SEED: i32 = 314_159_265;
+
+/// Random number generator
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RanluxGenerator {
+    /// Seed which this generator was initialized with
+    #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
+    seed: i32,
+
+    /// Luxury level
+    luxury: u8,
+
+    /// Current state, in units of 2⁻²⁴ (SEEDS in original code)
+    numbers: [i32; STATE_LEN],
+
+    /// Indices of the two lagged state entries (I24 and J24 in original code)
+    lags: (usize, usize),
+
+    /// Borrow of the last subtraction, in units of 2⁻²⁴ (CARRY in original
+    /// code)
+    carry: i32,
+
+    /// Number of numbers which were delivered in the current group of 24
+    /// (IN24 in original code)
+    delivered: usize,
+}
+//
+impl RanluxGenerator {
+    // ### CONSTRUCTION ###
+
+    /// Largest seed that this generator accepts, which is the largest value of
+    /// a Fortran INTEGER
+    pub const MAX_SEED: u64 = i32::MAX as u64;
+
+    /// Highest luxury level
+    pub const MAX_LUXURY: u8 = (SKIPPED_NUMBERS.len() - 1) as u8;
+
+    /// Luxury level which is used when none is specified (LXDFLT in original
+    /// code)
+    pub const DEFAULT_LUXURY: u8 = 3;
+
+    /// Create a new generator with a certain luxury level (up to MAX_LUXURY)
+    /// and seed (up to MAX_SEED, 0 designating the original default seed)
+    ///
+    /// This maps to the RLUXGO subroutine of the original code, without the
+    /// restarting feature.
+    ///
+    pub fn with_luxury(luxury: u8, seed: u64) -> Self {
+        assert!(luxury <= Self::MAX_LUXURY, "Invalid ranlux luxury level");
+        assert!(seed <= Self::MAX_SEED, "Invalid ranlux seed");
+        let seed = match seed as i32 {
+            0 => ORIGINAL_DEFAULT_SEED,
+            seed => seed,
+        };
+
+        // Fill the state using a multiplicative congruential generator,
+        // computed with Schrage's method to avoid integer overflow
+        let mut numbers = [0; STATE_LEN];
+        let mut jseed = seed;
+        for number in &mut numbers {
+            let k = jseed / 53668;
+            jseed = 40014 * (jseed - k * 53668) - k * 12211;
+            if jseed < 0 {
+                jseed += 2_147_483_563;
+            }
+            *number = jseed % MODULO;
+        }
+        let carry = (numbers[STATE_LEN - 1] == 0) as i32;
+        Self {
+            #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
+            seed,
+            luxury,
+            numbers,
+            lags: INITIAL_LAGS,
+            carry,
+            delivered: 0,
+        }
+    }
+
+    // ### GENERATION ###
+
+    /// Advance the subtract-with-borrow recurrence by one step, returning the
+    /// new number in units of 2⁻²⁴
+    #[inline]
+    fn step(&mut self) -> i32 {
+        let (i, j) = self.lags;
+        let mut uni = self.numbers[j] - self.numbers[i] - self.carry;
+        if uni < 0 {
+            uni += MODULO;
+            self.carry = 1;
+        } else {
+            self.carry = 0;
+        }
+        self.numbers[i] = uni;
+        let previous = |index: usize| index.checked_sub(1).unwrap_or(STATE_LEN - 1);
+        self.lags = (previous(i), previous(j));
+        uni
+    }
+
+    /// Generate a random number in ]0, 1[ in single precision, following the
+    /// RANLUX subroutine of the original code
+    #[inline]
+    fn next_f32(&mut self) -> f32 {
+        // Draw a number, padding small numbers with lower-order bits and
+        // avoiding zero as the original code does
+        let uni = self.step();
+        let mut result = uni as f32 * INV_MODULO;
+        if uni < PADDING_THRESHOLD {
+            result += INV_MODULO * (self.numbers[self.lags.1] as f32 * INV_MODULO);
+            if result == 0. {
+                result = INV_MODULO * INV_MODULO;
+            }
+        }
+
+        // Discard some numbers after each group of 24, as the luxury level
+        // mandates, to break the correlations of the recurrence
+        self.delivered += 1;
+        if self.delivered == STATE_LEN {
+            self.delivered = 0;
+            for _ in 0..SKIPPED_NUMBERS[self.luxury as usize] {
+                self.step();
+            }
+        }
+        result
+    }
+}
+
+impl RandomGenerator for RanluxGenerator {
+    /// Default seed of the original RANLUX code
+    const DEFAULT_SEED: u64 = ORIGINAL_DEFAULT_SEED as u64;
+
+    /// Create a new generator with an arbitrary seed (up to MAX_SEED), at the
+    /// default luxury level
+    fn with_seed(seed: u64) -> Self {
+        Self::with_luxury(Self::DEFAULT_LUXURY, seed)
+    }
+
+    /// Create a new generator with the configured luxury level and seed
+    fn from_config(cfg: &Configuration) -> Self {
+        Self::with_luxury(cfg.ranlux_luxury, cfg.seed.unwrap_or(Self::DEFAULT_SEED))
+    }
+
+    /// Generate a random number between 0 and 1, with 2⁻²⁴ granularity
+    #[allow(clippy::useless_conversion)]
+    fn random(&mut self) -> Float {
+        Float::from(self.next_f32())
+    }
+
+    /// Generate an array of random numbers
+    fn random_array<const N: usize>(&mut self) -> [Float; N] {
+        std::array::from_fn(|_| self.random())
+    }
+
+    // Just switch to another state as fast as we can
+    #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
+    fn jump(&mut self) {
+        let new_seed = (self.seed as u64 + 123456) % (Self::MAX_SEED + 1);
+        *self = Self::with_luxury(self.luxury, new_seed);
+    }
+}
@@ -0,0 +1,184 @@
+//! Quasi-random number generation, from the Sobol low-discrepancy sequence
+//!
+//! Unlike pseudo-random numbers, quasi-random points cover the unit hypercube
+//! as evenly as possible, which speeds up the convergence of Monte Carlo
+//! integration for smooth integrands.
+
+use super::RandomGenerator;
+use crate::{event::NUM_OUTGOING, numeric::Float};
+use rand::{RngCore, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Serialize};
+
+/// Dimension of the quasi-random points, i.e. number of random numbers that the
+/// event generator draws per event
+///
+/// Random numbers are handed out as the successive coordinates of successive
+/// points, so event generation must draw exactly this amount of random numbers
+/// per event, which the rejection sampling of "faster-evgen" does not do.
+///
+const DIMENSIONS: usize = NUM_OUTGOING * 4;
+
+/// Number of bits of the integer coordinates of the quasi-random points
+const BITS: usize = u64::BITS as usize;
+
+/// Parameters of the Sobol sequence beyond the first dimension: degree and
+/// inner coefficients of the primitive polynomial, and initial direction
+/// numbers, as tabulated by S. Joe and F.Y. Kuo (new-joe-kuo-6.21201)
+const PARAMETERS: [(usize, u64, &[u64]); DIMENSIONS - 1] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+];
+
+/// Direction numbers of each dimension, left-aligned in 64-bit integers
+const DIRECTIONS: [[u64; BITS]; DIMENSIONS] = directions();
+
+/// Compute the direction numbers of the Sobol sequence
+const fn directions() -> [[u64; BITS]; DIMENSIONS] {
+    let mut result = [[0; BITS]; DIMENSIONS];
+
+    // The first dimension is the van der Corput sequence
+    let mut bit = 0;
+    while bit < BITS {
+        result[0][bit] = 1 << (BITS - 1 - bit);
+        bit += 1;
+    }
+
+    // Other dimensions follow the recurrence of their primitive polynomial
+    let mut dim = 1;
+    while dim < DIMENSIONS {
+        let (degree, coeffs, initial) = PARAMETERS[dim - 1];
+        let directions = &mut result[dim];
+        let mut bit = 0;
+        while bit < BITS {
+            directions[bit] = if bit < degree {
+                initial[bit] << (BITS - 1 - bit)
+            } else {
+                let mut direction = directions[bit - degree] ^ (directions[bit - degree] >> degree);
+                let mut k = 1;
+                while k < degree {
+                    if (coeffs >> (degree - 1 - k)) & 1 == 1 {
+                        direction ^= directions[bit - k];
+                    }
+                    k += 1;
+                }
+                direction
+            };
+            bit += 1;
+        }
+        dim += 1;
+    }
+    result
+}
+
+/// Number of points which a jump moves forward by, at least
+///
+/// Should be larger than the number of events in a batch, so that the batches
+/// of a multi-threaded simulation use disjoint parts of the sequence.
+///
+#[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
+const JUMP_POINTS: u64 = 1 << 14;
+
+/// Quasi-random number generator, randomized by a seed-dependent digital shift
+///
+/// Randomization makes the results of simulations with different seeds
+/// statistically independent, so that their spread can be used to estimate
+/// the integration error.
+///
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SobolGenerator {
+    /// Digital shift of each coordinate, derived from the seed
+    shift: [u64; DIMENSIONS],
+
+    /// Integer coordinates of the current point, before the shift
+    point: [u64; DIMENSIONS],
+
+    /// Index of the current point in the Sobol sequence (in Gray code order)
+    index: u64,
+
+    /// Next coordinate of the current point to be handed out
+    dim: usize,
+}
+//
+impl SobolGenerator {
+    /// Move to a certain point of the sequence
+    #[cfg(any(feature = "mpi", feature = "multi-threading"))]
+    fn set_index(&mut self, index: u64) {
+        let gray = index ^ (index >> 1);
+        self.point = std::array::from_fn(|dim| {
+            (0..BITS)
+                .filter(|bit| (gray >> bit) & 1 == 1)
+                .fold(0, |coord, bit| coord ^ DIRECTIONS[dim][bit])
+        });
+        self.index = index;
+    }
+
+    /// Move to the next point of the sequence
+    fn next_point(&mut self) {
+        self.index += 1;
+        let bit = self.index.trailing_zeros() as usize;
+        for (coord, directions) in self.point.iter_mut().zip(&DIRECTIONS) {
+            *coord ^= directions[bit];
+        }
+        self.dim = 0;
+    }
+}
+//
+impl RandomGenerator for SobolGenerator {
+    // Arbitrary seed, for consistency with other generators
+    const DEFAULT_SEED: u64 = 12345;
+
+    // Start from the first point of the sequence, with a seeded shift
+    fn with_seed(seed: u64) -> Self {
+        let mut shift_rng = Xoshiro256PlusPlus::seed_from_u64(seed);
+        Self {
+            shift: std::array::from_fn(|_dim| shift_rng.next_u64()),
+            point: [0; DIMENSIONS],
+            index: 0,
+            dim: 0,
+        }
+    }
+
+    // Hand out the next coordinate, moving to the next point if need be
+    fn random(&mut self) -> Float {
+        if self.dim == DIMENSIONS {
+            self.next_point();
+        }
+        let coord = self.point[self.dim] ^ self.shift[self.dim];
+        self.dim += 1;
+        let mantissa_bits = Float::MANTISSA_DIGITS;
+        ((coord >> (BITS as u32 - mantissa_bits)) as Float) / ((1u64 << mantissa_bits) as Float)
+    }
+
+    // Coordinates are handed out one by one
+    fn random_array<const N: usize>(&mut self) -> [Float; N] {
+        std::array::from_fn(|_| self.random())
+    }
+
+    // Points can be computed directly from their index
+    #[cfg(any(
+        feature = "mpi",
+        all(feature = "multi-threading", not(feature = "faster-threading"))
+    ))]
+    fn skip(&mut self, iterations: usize) {
+        let pos = self.dim + iterations;
+        self.set_index(self.index + (pos / DIMENSIONS) as u64);
+        self.dim = pos % DIMENSIONS;
+    }
+
+    // Move to the start of the next block of JUMP_POINTS points
+    #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
+    fn jump(&mut self) {
+        self.set_index((self.index / JUMP_POINTS + 1) * JUMP_POINTS);
+        self.dim = 0;
+    }
+}
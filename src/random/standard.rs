@@ -1,56 +1,94 @@
-//! This module mimicks the interface of RanfGenerator on top of the
-//! abstractions of the standard "random" crate.
+//! This module implements the RandomGenerator interface on top of the
+//! abstractions of the standard "rand" crate.
 
+use super::RandomGenerator;
 use crate::numeric::Float;
 use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use rand_xoshiro::{Xoshiro128Plus, Xoshiro256Plus, Xoshiro256PlusPlus};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-// Select random number generation engine in use
+/// xoshiro+ flavor that is best suited to the floating-point precision in use
 #[cfg(feature = "f32")]
-type Engine = rand_xoshiro::Xoshiro128Plus;
+pub type XoshiroPlus = Xoshiro128Plus;
+/// xoshiro+ flavor that is best suited to the floating-point precision in use
 #[cfg(not(feature = "f32"))]
-type Engine = rand_xoshiro::Xoshiro256Plus;
+pub type XoshiroPlus = Xoshiro256Plus;
 
-/// Facade which makes the rand crate look like RanfGenerator
-#[derive(Clone)]
-pub struct RandGenerator {
-    rng: Engine,
+/// Standard generator of the rand crate
+///
+/// This is the algorithm behind rand::rngs::StdRng, which we use directly
+/// because unlike StdRng, it can be serialized into checkpoints.
+///
+pub type StdRng = ChaCha12Rng;
+
+/// Random number generation engine from the rand ecosystem
+pub trait Engine: Clone + DeserializeOwned + Rng + SeedableRng + Send + Serialize {
+    /// Switch to another state in an arbitrary but maximally fast way
+    #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
+    fn jump(&mut self);
+}
+//
+impl Engine for Xoshiro128Plus {
+    #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
+    fn jump(&mut self) {
+        Xoshiro128Plus::jump(self)
+    }
+}
+//
+impl Engine for Xoshiro256Plus {
+    #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
+    fn jump(&mut self) {
+        Xoshiro256Plus::jump(self)
+    }
+}
+//
+impl Engine for Xoshiro256PlusPlus {
+    #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
+    fn jump(&mut self) {
+        Xoshiro256PlusPlus::jump(self)
+    }
+}
+//
+impl Engine for ChaCha12Rng {
+    // StdRng has no jump-ahead facility, so we reseed it from its own output
+    #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
+    fn jump(&mut self) {
+        *self = Self::from_rng(&mut *self).expect("StdRng reseeding should not fail");
+    }
+}
+
+/// Facade which makes a rand engine look like RanfGenerator
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(bound = "")]
+pub struct RandGenerator<E: Engine> {
+    rng: E,
 }
 //
-impl RandGenerator {
+impl<E: Engine> RandomGenerator for RandGenerator<E> {
+    // Seed which was historically used by this generator
+    const DEFAULT_SEED: u64 = 12345;
+
     // Spawn a new random number generator
-    pub fn new() -> Self {
+    fn with_seed(seed: u64) -> Self {
         Self {
-            rng: Engine::seed_from_u64(12345),
+            rng: E::seed_from_u64(seed),
         }
     }
 
     // Generate a random floating-point number between 0 and 1
-    pub fn random(&mut self) -> Float {
+    fn random(&mut self) -> Float {
         self.rng.gen()
     }
 
-    /// Generate an array of random numbers
-    pub fn random_array<const N: usize>(&mut self) -> [Float; N] {
+    // Generate an array of random numbers
+    fn random_array<const N: usize>(&mut self) -> [Float; N] {
         self.rng.gen()
     }
 
-    // Advance state as if random() had been called "iteration" times
-    #[cfg(all(feature = "multi-threading", not(feature = "faster-threading")))]
-    pub fn skip(&mut self, iterations: usize) {
-        for _ in 0..iterations {
-            self.random();
-        }
-    }
-
-    // Advance state as if random_array::<N>() had been called
-    #[cfg(all(feature = "multi-threading", not(feature = "faster-threading")))]
-    pub fn skip_array<const N: usize>(&mut self) {
-        self.random_array::<N>();
-    }
-
     // Advance state in an arbitrary but maximally fast way
     #[cfg(all(feature = "multi-threading", feature = "faster-threading"))]
-    pub fn jump(&mut self) {
+    fn jump(&mut self) {
         self.rng.jump();
     }
 }
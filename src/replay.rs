@@ -0,0 +1,150 @@
+//! Replay mode, which evaluates the cuts and matrix elements over phase space
+//! points read from a file instead of generated ones, so that results can be
+//! cross-validated with other codes on identical points
+
+use crate::cli::ReplayArgs;
+use anyhow::{bail, ensure, Context, Result};
+use log::info;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    time::Instant,
+};
+use trois_photons::{
+    config::Configuration,
+    event::{self, Event, NUM_OUTGOING},
+    evgen::EventGenerator,
+    numeric::Float,
+    output::events::from_lab_frame_momenta,
+    process::ProcessRegistry,
+    resacc::ResultsAccumulator,
+    stability::StabilityGuard,
+};
+
+/// Number of values on each line of a phase space file: the weight of the
+/// point, then the (Px, Py, Pz, E) 4-momentum of each outgoing particle
+const NUM_VALUES: usize = 1 + 4 * NUM_OUTGOING;
+
+/// Evaluate the configured simulation over the phase space points of a file,
+/// and output the results as a simulation run would
+///
+/// Each non-empty line of the file which does not start with `#` describes a
+/// point as whitespace-separated numbers: its phase space weight, in the
+/// normalization of RAMBO (where flat points all weigh 𝜋²·s/8 for massless
+/// particles), then the 4-momentum of each outgoing particle in the frame of
+/// event records, where the incoming electron travels along +Z at half the
+/// configured collision energy. Particles are kept in file order, which
+/// per-photon observables follow.
+///
+pub fn run(args: &ReplayArgs) -> Result<()> {
+    // Load the configuration, which must not randomize events since points
+    // are given as they should be evaluated
+    let mut cfg = Configuration::load_with_overlay(&args.args.config, &args.args.set, |cfg| {
+        args.args.apply_overrides(cfg)
+    })
+    .context("Failed to load the configuration")?;
+    ensure!(
+        !cfg.isr && cfg.beam_spread.is_none() && cfg.detector.is_none(),
+        "Beam effects and detector simulation cannot be applied to phase space points read \
+         from a file"
+    );
+    ensure!(
+        cfg.event_output.is_none(),
+        "Events cannot be written when phase space points are read from a file"
+    );
+    let start_time = Instant::now();
+
+    // Set up the matrix elements, and the nominal event generator which
+    // provides the weight of flat points
+    let process = ProcessRegistry::new()
+        .create(&cfg)
+        .context("Failed to set up the simulated process")?;
+    let evgen = EventGenerator::new(cfg.e_total).context("Failed to set up event generation")?;
+    let masses = process.outgoing_masses();
+    let guard = StabilityGuard::new(&cfg);
+
+    // Read the phase space points, checking their kinematics
+    let flat_weight = evgen.event_weight();
+    let points = read_points(
+        &args.points,
+        cfg.e_total,
+        flat_weight,
+        &masses,
+        args.tolerance,
+    )
+    .with_context(|| format!("Failed to read the phase space points of {}", args.points))?;
+    ensure!(
+        !points.is_empty(),
+        "{} contains no phase space point",
+        args.points
+    );
+    info!(
+        "Read {} phase space points from {}",
+        points.len(),
+        args.points
+    );
+
+    // Evaluate each point as the simulation would evaluate a generated event,
+    // averaging over the points of the file
+    cfg.num_events = points.len();
+    let mut res_builder = ResultsAccumulator::new(&cfg, flat_weight);
+    for event in &points {
+        match cfg.event_cut.apply(event) {
+            Ok(()) => {
+                let res_contrib = match &guard {
+                    Some(guard) if guard.is_unstable(event) => {
+                        res_builder.count_extended_precision();
+                        guard.contributions(event)
+                    }
+                    _ => process.contributions(event),
+                };
+                res_builder.integrate(event, event, res_contrib);
+            }
+            Err(cut) => res_builder.reject(cut),
+        }
+    }
+    let result = res_builder.finalize();
+
+    // Output the results like the simulation does
+    crate::output_results(&cfg, &result, start_time.elapsed())
+}
+
+/// Read the phase space points of a file as events at a certain collision
+/// energy, whose weight factor relates their weight to that of flat events
+fn read_points(
+    path: &str,
+    e_total: Float,
+    flat_weight: Float,
+    masses: &[Float; NUM_OUTGOING],
+    tolerance: Float,
+) -> Result<Vec<Event>> {
+    let file = BufReader::new(File::open(path)?);
+    let mut points = Vec::new();
+    for (index, line) in file.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let event = parse_point(line, e_total, flat_weight)
+            .and_then(|event| Ok(event::validate(&event, masses, tolerance).map(|()| event)?))
+            .with_context(|| format!("Invalid phase space point on line {}", index + 1))?;
+        points.push(event);
+    }
+    Ok(points)
+}
+
+/// Parse a phase space point (see run() for the format)
+fn parse_point(line: &str, e_total: Float, flat_weight: Float) -> Result<Event> {
+    let values = line
+        .split_whitespace()
+        .map(|value| value.parse::<Float>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let Ok([weight, momenta @ ..]) = <[Float; NUM_VALUES]>::try_from(values.as_slice()) else {
+        bail!("Expected {NUM_VALUES} numbers, found {}", values.len());
+    };
+    let momenta: [[Float; 4]; NUM_OUTGOING] =
+        std::array::from_fn(|par| std::array::from_fn(|coord| momenta[4 * par + coord]));
+    let matrix = from_lab_frame_momenta(e_total, &momenta);
+    Ok(Event::with_weight_factor(matrix, weight / flat_weight))
+}
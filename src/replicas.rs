@@ -0,0 +1,123 @@
+//! Replica mode, which runs the simulation several times with different seeds
+//! to check that the spread of the results matches their claimed uncertainty
+
+use crate::cli::Args;
+use anyhow::{ensure, Context, Result};
+use trois_photons::{
+    config::Configuration, numeric::Float, resfin::FinalResults, setup_event_generation, simulate,
+};
+
+/// Run statistically independent replicas of the simulation, and compare the
+/// spread of their cross-sections with the statistical error of each run
+///
+/// Replica number i uses the configured seed plus i. When multi-threading is
+/// enabled, replicas are run concurrently.
+///
+pub fn run(args: &Args, num_replicas: usize) -> Result<()> {
+    ensure!(num_replicas >= 2, "At least two replicas are needed");
+
+    // Load the configuration that is shared by all replicas
+    let cfg =
+        Configuration::load_with_overlay(&args.config, &args.set, |cfg| args.apply_overrides(cfg))
+            .context("Failed to load the configuration")?;
+    ensure!(
+        cfg.event_output.is_none()
+            && cfg.json_output.is_none()
+            && cfg.yoda_output.is_none()
+            && cfg.checkpoint.is_none(),
+        "Event output, JSON and YODA output and checkpointing are not supported in replica mode"
+    );
+    ensure!(
+        cfg.coupling_grid.is_none() && cfg.systematics.is_none(),
+        "Anomalous coupling grids and systematics are not supported in replica mode"
+    );
+    ensure!(
+        !cfg.plot && cfg.distributions.is_empty(),
+        "Histograms are not supported in replica mode"
+    );
+
+    // Give each replica its own seed
+    let base_seed = cfg.rng.effective_seed(cfg.seed);
+    let configs = (0..num_replicas as u64)
+        .map(|idx| {
+            let seed = (base_seed.checked_add(idx)).context("Replica seed overflow")?;
+            cfg.rng.check_seed(seed)?;
+            let mut replica_cfg = cfg.clone();
+            replica_cfg.seed = Some(seed);
+            Ok(replica_cfg)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Run the replicas
+    #[cfg(feature = "multi-threading")]
+    let results = {
+        use rayon::prelude::*;
+        configs
+            .par_iter()
+            .map(run_replica)
+            .collect::<Result<Vec<_>>>()?
+    };
+    #[cfg(not(feature = "multi-threading"))]
+    let results = configs
+        .iter()
+        .map(run_replica)
+        .collect::<Result<Vec<_>>>()?;
+
+    // Display the results of each replica
+    println!();
+    println!(
+        "{:>12} {:>12} {:>20} {:>20}",
+        "Seed", "Selected", "Sigma (pb)", "Std. dev. (pb)"
+    );
+    for res in &results {
+        println!(
+            "{:>12} {:>12} {:>20.12e} {:>20.12e}",
+            res.provenance.seed,
+            res.selected_events,
+            res.sigma,
+            res.sigma * res.prec
+        );
+        if let Some(path) = &cfg.summary_yaml {
+            res.append_summary_yaml(path)
+                .context("Failed to write the YAML summary")?;
+        }
+    }
+
+    // Compare the spread of the cross-sections with their claimed error
+    let n = num_replicas as Float;
+    let sigmas = results.iter().map(|res| res.sigma);
+    let mean = sigmas.clone().sum::<Float>() / n;
+    let spread = (sigmas.map(|sigma| (sigma - mean).powi(2)).sum::<Float>() / (n - 1.)).sqrt();
+    let claimed = (results.iter())
+        .map(|res| (res.sigma * res.prec).powi(2))
+        .sum::<Float>()
+        / n;
+    let claimed = claimed.sqrt();
+    let chi2 = (results.iter())
+        .map(|res| ((res.sigma - mean) / (res.sigma * res.prec)).powi(2))
+        .sum::<Float>();
+    println!();
+    println!("Replicas           : {num_replicas}");
+    println!(
+        "Mean sigma (pb)    : {mean:.6e} +/- {:.2e}",
+        spread / n.sqrt()
+    );
+    println!("Spread (pb)        : {spread:.6e}");
+    println!("Claimed error (pb) : {claimed:.6e}");
+    println!(
+        "Spread / claimed   : {:.4} +/- {:.4}",
+        spread / claimed,
+        spread / claimed / (2. * (n - 1.)).sqrt()
+    );
+    println!("Chi2 / ndf         : {:.4}", chi2 / (n - 1.));
+    Ok(())
+}
+
+/// Run one replica of the simulation
+fn run_replica(cfg: &Configuration) -> Result<FinalResults<'_>> {
+    let seed = cfg.seed.expect("Replica seeds are always set");
+    let (process, evgen) = setup_event_generation(cfg)
+        .with_context(|| format!("Failed to set up event generation with seed {seed}"))?;
+    simulate(cfg, &*process, &evgen, None, None, None)
+        .with_context(|| format!("Failed to run the simulation with seed {seed}"))
+}
@@ -1,14 +1,24 @@
-///! This module allows integrating simulation results across generated events
+//! This module allows integrating simulation results across generated events
 use crate::{
+    asymmetry::AsymmetryAccumulator,
     config::Configuration,
-    event::{NUM_OUTGOING, NUM_SPINS},
-    matelems::{MEsContributions, MEsVector, A, B_M, B_P, NUM_MAT_ELEMS, R_MX},
-    numeric::{floats::consts::PI, Float},
-    resfin::{FinalResults, PerSpinMEs},
+    convergence::{self, BatchTotals},
+    evcut::{Cut, CutFlow},
+    event::{Event, NUM_OUTGOING, NUM_SPINS},
+    histogram::Distributions,
+    matelems::{MEsContributions, MEsMatrix, MEsVector, A, B_M, B_P, I_MX, NUM_MAT_ELEMS, R_MX},
+    moments::MomentAccumulator,
+    numeric::{floats::consts::PI, functions::*, CompensatedSum, Complex, Float},
+    provenance::Provenance,
+    resampling::{self, DerivedQuantities},
+    resfin::{FinalResults, PerSpinMEs, SpinDensityMatrix, SP_M, SP_P},
+    timings::{Phase, PhaseTimings},
+    weights::{WeightAccumulator, WeightIssues},
 };
 use nalgebra::{vector, SVector};
-use num_traits::Zero;
 use prefix_num_ops::real::*;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, time::Instant};
 
 /// This struct will accumulate intermediary results during integration, and
 /// ultimately compute the final results (see FinalResults below).
@@ -18,19 +28,66 @@ pub struct ResultsAccumulator<'cfg> {
     selected_events: usize,
 
     /// Accumulated cross-section for each contribution
-    spm2: MEsVector,
+    spm2: [CompensatedSum; NUM_MAT_ELEMS],
 
-    /// Accumulated variance for each contribution
-    vars: MEsVector,
+    /// Accumulated products of each pair of contributions (only the upper
+    /// triangle is filled, the diagonal yields the variance of each one, and
+    /// the rest is only needed for reweighting to a coupling grid)
+    moments: [[CompensatedSum; NUM_MAT_ELEMS]; NUM_MAT_ELEMS],
+
+    /// Accumulated cross-section for each contribution, for each merged batch
+    /// of events (empty until another batch is merged into this one)
+    batch_sums: Vec<MEsVector>,
+
+    /// Totals of each merged batch of events, if the convergence history is
+    /// recorded (empty until another batch is merged into this one)
+    batch_totals: Option<Vec<BatchTotals>>,
 
     /// Impact of each contribution on the cross-section
     sigma_contribs: MEsVector,
 
+    /// Impact of each contribution on the cross-section, for unit anomalous
+    /// couplings
+    unit_contribs: MEsVector,
+
     /// Accumulated total cross-section
-    sigma: Float,
+    sigma: CompensatedSum,
 
     /// Accumulated total variance
-    variance: Float,
+    variance: CompensatedSum,
+
+    /// Accumulated interference between the anomalous amplitudes of the two
+    /// electron helicities (real and imaginary parts), if the spin density
+    /// matrix is requested
+    spin_interference: [CompensatedSum; 2],
+
+    /// Accumulated squares of the real and imaginary parts of the interference
+    spin_interference_moments: [CompensatedSum; 2],
+
+    /// Number of events rejected by each cut
+    cut_flow: CutFlow,
+
+    /// Events whose weight is not finite or negative
+    weight_issues: WeightIssues,
+
+    /// Number of events whose matrix elements were recomputed with extended
+    /// precision by the stability guard
+    extended_precision_events: usize,
+
+    /// Histograms of the photon distributions (if enabled)
+    histograms: Option<Distributions>,
+
+    /// Angular asymmetries of the photons (if enabled)
+    asymmetries: Option<AsymmetryAccumulator>,
+
+    /// Legendre moments of the photon angular distribution (if enabled)
+    legendre_moments: Option<MomentAccumulator>,
+
+    /// Distribution of the event weights (if enabled)
+    weights: Option<WeightAccumulator>,
+
+    /// Time spent in each phase of the simulation (if enabled)
+    timings: Option<PhaseTimings>,
 
     // ### PHYSICAL CONSTANTS (CACHED FOR FINALIZATION) ###
     /// Configuration of the simulation
@@ -46,37 +103,22 @@ pub struct ResultsAccumulator<'cfg> {
     /// Event weight, with total phase space normalization
     norm_weight: Float,
 
+    /// Incident flux factor (=1/2s for 2 initial massless particles)
+    incident_flux: Float,
+
     /// Z° propagator
     propagator: Float,
 
-    /// Distance to Z° peak (???)
-    delta_with_z0_peak: Float,
+    /// Inverse Z° propagator 𝛿 - i·w, where 𝛿 is the distance to the Z° peak
+    /// and w depends on the lineshape scheme (1 for a fixed width)
+    inv_propagator: Complex,
 }
 //
 impl<'cfg> ResultsAccumulator<'cfg> {
     /// Prepare for results integration
     pub fn new(cfg: &'cfg Configuration, event_weight: Float) -> Self {
         // This code depends on some aspects of the problem definition
-        assert_eq!(NUM_MAT_ELEMS, 5);
-
-        // Common factor (see definition and remarks above)
-        let fact_com = 1. / 6. * cfg.gev2_to_picobarn;
-        let relat_width = cfg.g_z0 / cfg.m_z0;
-
-        // Sum over polarisations factors
-        let p_aa = 2.;
-        let p_ab = 1. - 4. * cfg.sin2_weinberg;
-        let p_bb = p_ab + 8. * cfg.sin2_weinberg.powi(2);
-
-        // Homogeneity coefficient
-        let c_aa = fact_com * p_aa;
-        let c_ab = fact_com * p_ab / cfg.m_z0.powi(2);
-        let c_bb = fact_com * p_bb / cfg.m_z0.powi(4);
-
-        // Switch to dimensionless variable
-        let dzeta = (cfg.e_total / cfg.m_z0).powi(2);
-        let delta_with_z0_peak = (dzeta - 1.) / relat_width;
-        let propagator = 1. / (1. + delta_with_z0_peak.powi(2));
+        const { assert!(NUM_MAT_ELEMS == 5) };
 
         // Apply total phase space normalization to the event weight
         let n_ev = cfg.num_events as Float;
@@ -86,62 +128,309 @@ impl<'cfg> ResultsAccumulator<'cfg> {
 
         // Compute how much each result contribution adds to the cross-section.
         // Again, this avoids duplicate work in the integration loop.
-        let com_contrib = norm_weight / 4.;
-        let aa_contrib = com_contrib * c_aa;
-        let bb_contrib = com_contrib * c_bb * propagator / relat_width.powi(2);
-        let ab_contrib = com_contrib * c_ab * 2. * cfg.beta_plus * propagator / relat_width;
-        let sigma_contribs = vector![
-            aa_contrib,                          // A
-            bb_contrib * cfg.beta_plus.powi(2),  // B_P
-            bb_contrib * cfg.beta_minus.powi(2), // B_M
-            ab_contrib * delta_with_z0_peak,     // R_MX
-            -ab_contrib                          // I_MX
-        ];
+        let Contributions {
+            fact_com,
+            propagator,
+            inv_propagator,
+            sigma_contribs,
+            unit_contribs,
+        } = Contributions::new(cfg, norm_weight);
+
+        // Incident flux factor (=1/2s for 2 initial massless particles)
+        let incident_flux = 1. / (2. * cfg.e_total.powi(2));
 
         // Return a complete results builder
         ResultsAccumulator {
             selected_events: 0,
-            spm2: MEsVector::zero(),
-            vars: MEsVector::zero(),
+            spm2: Default::default(),
+            moments: Default::default(),
+            batch_sums: Vec::new(),
+            batch_totals: cfg.convergence_output.as_ref().map(|_path| Vec::new()),
             sigma_contribs,
-            sigma: 0.,
-            variance: 0.,
+            unit_contribs,
+            sigma: CompensatedSum::default(),
+            variance: CompensatedSum::default(),
+            spin_interference: Default::default(),
+            spin_interference_moments: Default::default(),
+            cut_flow: CutFlow::new(&cfg.event_cut),
+            weight_issues: WeightIssues::new(cfg),
+            extended_precision_events: 0,
+            histograms: Distributions::new(cfg),
+            asymmetries: AsymmetryAccumulator::new(cfg),
+            legendre_moments: MomentAccumulator::new(cfg),
+            weights: WeightAccumulator::new(cfg),
+            timings: None,
 
             cfg,
             fact_com,
             norm_weight,
+            incident_flux,
             propagator,
-            delta_with_z0_peak,
+            inv_propagator,
         }
     }
 
     /// Integrate one intermediary result into the simulation results
+    ///
+    /// The weight of the event is computed from the generated event, while the
+    /// observables (histograms, asymmetries and Legendre moments) are filled
+    /// with the event as measured by the detector, which is the generated
+    /// event if the detector is disabled.
+    ///
+    /// Returns the contribution of this event to the total cross-section (pb),
+    /// or None if its matrix elements are not finite, in which case it is left
+    /// out of the results (see WeightIssues)
+    ///
     #[allow(clippy::needless_pass_by_value)]
-    pub fn integrate(&mut self, result: MEsContributions) {
-        self.selected_events += 1;
-        let spm2_dif = result.m2_sums();
-        self.spm2 += spm2_dif;
-        self.vars += spm2_dif.map(|x| x.powi(2));
+    pub fn integrate(
+        &mut self,
+        event: &Event,
+        measured: &Event,
+        result: MEsContributions,
+    ) -> Option<Float> {
+        let spm2_dif = self.weighted_m2_sums(event, &result);
         let weight = spm2_dif.dot(&self.sigma_contribs);
-        self.sigma += weight;
-        self.variance += weight.powi(2);
+        let sigma_contrib = weight * self.incident_flux;
+        let event_weight = sigma_contrib * self.cfg.num_events as Float;
+        if !(self.weight_issues).check(event, &result.m2_sums(), event_weight) {
+            return None;
+        }
+        self.selected_events += 1;
+        let covariance = self.cfg.coupling_grid.is_some();
+        for (k, (spm2, moments)) in self.spm2.iter_mut().zip(&mut self.moments).enumerate() {
+            let dif = spm2_dif[k];
+            spm2.add(dif);
+            moments[k].add(dif.powi(2));
+            if covariance {
+                for (moment, &other_dif) in moments.iter_mut().zip(&spm2_dif).skip(k + 1) {
+                    moment.add(dif * other_dif);
+                }
+            }
+        }
+        self.sigma.add(weight);
+        self.variance.add(weight.powi(2));
+        if self.cfg.spin_density {
+            let interference = self.weighted_spin_interference(event, &result);
+            for (k, part) in [re(interference), im(interference)].into_iter().enumerate() {
+                self.spin_interference[k].add(part);
+                self.spin_interference_moments[k].add(part.powi(2));
+            }
+        }
+        if let Some(histograms) = &mut self.histograms {
+            histograms.fill(measured, sigma_contrib);
+        }
+        if let Some(asymmetries) = &mut self.asymmetries {
+            asymmetries.fill(measured, sigma_contrib);
+        }
+        if let Some(moments) = &mut self.legendre_moments {
+            moments.fill(measured, sigma_contrib);
+        }
+        if let Some(weights) = &mut self.weights {
+            weights.fill(event_weight);
+        }
+        Some(sigma_contrib)
+    }
+
+    /// Contribution of each matrix element to the cross-section contribution
+    /// that integrate() would compute for an event
+    ///
+    /// These add up to the value returned by integrate(), up to rounding
+    /// errors.
+    ///
+    pub fn me_sigma_contribs(&self, event: &Event, result: &MEsContributions) -> MEsVector {
+        self.weighted_m2_sums(event, result)
+            .component_mul(&self.sigma_contribs)
+            * self.incident_flux
+    }
+
+    /// Sums of the squared matrix elements of an event for each contribution,
+    /// multiplied by the event's weight factor
+    ///
+    /// With initial-state radiation or beam energy spread, the Z⁰ propagator
+    /// must be evaluated at the collision energy of each event, rather than at
+    /// the nominal one. The
+    /// anomalous contributions are rescaled accordingly, so that they can keep
+    /// being integrated with the nominal propagator.
+    ///
+    fn weighted_m2_sums(&self, event: &Event, result: &MEsContributions) -> MEsVector {
+        let mut m2_sums = result.m2_sums() * event.weight_factor();
+        if self.cfg.varying_collision_energy() {
+            // The Z⁰ propagator is 1/(𝛿 - i·w), where the real part of its
+            // product with the mixed contribution R_MX + i·I_MX is integrated
+            let inv_propagator =
+                (self.cfg.z_lineshape).inverse_propagator(self.cfg, event.collision_energy());
+            let ratio = self.inv_propagator / inv_propagator;
+            m2_sums[B_P] *= norm_sqr(ratio);
+            m2_sums[B_M] *= norm_sqr(ratio);
+            let mixed = Complex::new(m2_sums[R_MX], m2_sums[I_MX]) * ratio;
+            m2_sums[R_MX] = re(mixed);
+            m2_sums[I_MX] = im(mixed);
+        }
+        m2_sums
+    }
+
+    /// Interference between the anomalous amplitudes of the two electron
+    /// helicities for an event, weighted by the anomalous couplings and like
+    /// the anomalous contributions of weighted_m2_sums()
+    fn weighted_spin_interference(&self, event: &Event, result: &MEsContributions) -> Complex {
+        let cfg = self.cfg;
+        let [b_p, b_m] = result.spin_interference();
+        let interference =
+            (b_p * cfg.beta_plus.powi(2) + b_m * cfg.beta_minus.powi(2)) * event.weight_factor();
+        if cfg.varying_collision_energy() {
+            let inv_propagator =
+                (cfg.z_lineshape).inverse_propagator(cfg, event.collision_energy());
+            interference * norm_sqr(self.inv_propagator / inv_propagator)
+        } else {
+            interference
+        }
+    }
+
+    /// Record that a generated event was rejected by a certain cut
+    pub fn reject(&mut self, cut: Cut) {
+        self.cut_flow.reject(cut);
+    }
+
+    /// Record that the detector lost a photon of a generated event
+    pub fn reject_undetected(&mut self) {
+        self.cut_flow.reject_undetected();
+    }
+
+    /// Record that the matrix elements of an event were recomputed with
+    /// extended precision by the stability guard
+    pub fn count_extended_precision(&mut self) {
+        self.extended_precision_events += 1;
+    }
+
+    /// Account for the time spent in each phase of the simulation
+    pub fn add_timings(&mut self, timings: &PhaseTimings) {
+        self.timings
+            .get_or_insert_with(PhaseTimings::default)
+            .merge(timings);
     }
 
     /// Integrate simulation results from another ResultsAccumulator
     #[allow(clippy::needless_pass_by_value)]
     pub fn merge(&mut self, other: Self) {
+        let (own_totals, other_totals) = (self.totals(), other.totals());
+        if self.batch_sums.is_empty() {
+            self.batch_sums.push(self.me_sums());
+        }
+        if other.batch_sums.is_empty() {
+            self.batch_sums.push(other.me_sums());
+        } else {
+            self.batch_sums.extend(other.batch_sums);
+        }
+        if let Some(batch_totals) = &mut self.batch_totals {
+            if batch_totals.is_empty() {
+                batch_totals.push(own_totals);
+            }
+            match other.batch_totals {
+                Some(other_batch_totals) if !other_batch_totals.is_empty() => {
+                    batch_totals.extend(other_batch_totals);
+                }
+                _ => batch_totals.push(other_totals),
+            }
+        }
         self.selected_events += other.selected_events;
-        self.spm2 += other.spm2;
-        self.vars += other.vars;
-        self.sigma += other.sigma;
-        self.variance += other.variance;
+        for (spm2, other_spm2) in self.spm2.iter_mut().zip(other.spm2) {
+            spm2.merge(other_spm2);
+        }
+        for (moments, other_moments) in self.moments.iter_mut().zip(other.moments) {
+            for (moment, other_moment) in moments.iter_mut().zip(other_moments) {
+                moment.merge(other_moment);
+            }
+        }
+        self.sigma.merge(other.sigma);
+        self.variance.merge(other.variance);
+        for (sum, other_sum) in self
+            .spin_interference
+            .iter_mut()
+            .zip(other.spin_interference)
+        {
+            sum.merge(other_sum);
+        }
+        for (moment, other_moment) in
+            (self.spin_interference_moments.iter_mut()).zip(other.spin_interference_moments)
+        {
+            moment.merge(other_moment);
+        }
+        self.cut_flow.merge(&other.cut_flow);
+        self.weight_issues.merge(&other.weight_issues);
+        self.extended_precision_events += other.extended_precision_events;
+        if let (Some(histograms), Some(other_histograms)) =
+            (&mut self.histograms, &other.histograms)
+        {
+            histograms.merge(other_histograms);
+        }
+        if let (Some(asymmetries), Some(other_asymmetries)) =
+            (&mut self.asymmetries, &other.asymmetries)
+        {
+            asymmetries.merge(other_asymmetries);
+        }
+        if let (Some(moments), Some(other_moments)) =
+            (&mut self.legendre_moments, &other.legendre_moments)
+        {
+            moments.merge(other_moments);
+        }
+        if let (Some(weights), Some(other_weights)) = (&mut self.weights, &other.weights) {
+            weights.merge(other_weights);
+        }
+        if let Some(other_timings) = &other.timings {
+            self.add_timings(other_timings);
+        }
+    }
+
+    /// Extract the results accumulated so far, so they can be checkpointed
+    pub fn checkpoint(&self) -> AccumulatorCheckpoint {
+        AccumulatorCheckpoint {
+            selected_events: self.selected_events,
+            spm2: self.spm2,
+            moments: self.moments,
+            batch_sums: self.batch_sums.clone(),
+            batch_totals: self.batch_totals.clone(),
+            sigma: self.sigma,
+            variance: self.variance,
+            spin_interference: self.spin_interference,
+            spin_interference_moments: self.spin_interference_moments,
+            cut_flow: self.cut_flow.clone(),
+            weight_issues: self.weight_issues.clone(),
+            extended_precision_events: self.extended_precision_events,
+            histograms: self.histograms.clone(),
+            asymmetries: self.asymmetries.clone(),
+            legendre_moments: self.legendre_moments.clone(),
+            weights: self.weights.clone(),
+        }
+    }
+
+    /// Replace the accumulated results with those of a checkpoint
+    pub fn restore(&mut self, checkpoint: AccumulatorCheckpoint) {
+        self.selected_events = checkpoint.selected_events;
+        self.spm2 = checkpoint.spm2;
+        self.moments = checkpoint.moments;
+        self.batch_sums = checkpoint.batch_sums;
+        self.batch_totals = checkpoint.batch_totals;
+        self.sigma = checkpoint.sigma;
+        self.variance = checkpoint.variance;
+        self.spin_interference = checkpoint.spin_interference;
+        self.spin_interference_moments = checkpoint.spin_interference_moments;
+        self.cut_flow = checkpoint.cut_flow;
+        self.weight_issues.restore(checkpoint.weight_issues);
+        self.extended_precision_events = checkpoint.extended_precision_events;
+        self.histograms = checkpoint.histograms;
+        self.asymmetries = checkpoint.asymmetries;
+        self.legendre_moments = checkpoint.legendre_moments;
+        self.weights = checkpoint.weights;
     }
 
     /// Turn integrated simulation data into finalized results
-    pub fn finalize(mut self) -> FinalResults<'cfg> {
+    pub fn finalize(self) -> FinalResults<'cfg> {
+        // Time the finalization, if the simulation phases are being timed
+        let start_time = Instant::now();
+
         // This code depends on some aspects of the problem definition
-        assert_eq!(NUM_SPINS, 2);
-        assert_eq!(NUM_MAT_ELEMS, 5);
+        const { assert!(NUM_SPINS == 2) };
+        const { assert!(NUM_MAT_ELEMS == 5) };
 
         // Simulation configuration shorthand
         let cfg = self.cfg;
@@ -149,21 +438,147 @@ impl<'cfg> ResultsAccumulator<'cfg> {
         // Keep around a floating-point version of the total event count
         let n_ev = cfg.num_events as Float;
 
+        // Extract the compensated sums
+        let spm2_sums = self.me_sums();
+        let me_moments =
+            MEsMatrix::from_fn(|row, col| self.moments[row.min(col)][row.max(col)].value());
+        let mut vars_sums = me_moments.diagonal();
+        let sigma_sum = self.sigma.value();
+        let variance_sum = self.variance.value();
+
         // Compute the relative uncertainties for one spin
-        for (&v_spm2, v_var) in self.spm2.iter().zip(self.vars.iter_mut()) {
+        for (&v_spm2, v_var) in spm2_sums.iter().zip(vars_sums.iter_mut()) {
             *v_var = (*v_var - v_spm2.powi(2) / n_ev) / (n_ev - 1.);
             *v_var = sqrt(*v_var / n_ev) / abs(v_spm2 / n_ev);
         }
 
-        // Copy for the opposite spin
-        let mut spm2 = PerSpinMEs::from_fn(|_spin, res| self.spm2[res]);
-        let vars = PerSpinMEs::from_fn(|_spin, res| self.vars[res]);
+        // Copy for the opposite spin, apply physical coefficients
+        let spm2 = self.per_spin_m2(&spm2_sums);
+        let vars = PerSpinMEs::from_fn(|_spin, res| vars_sums[res]);
+        let incident_flux = self.incident_flux;
+
+        // Compute other parts of the result
+        let variance = (variance_sum - sigma_sum.powi(2) / n_ev) / (n_ev - 1.);
+        let prec = sqrt(variance / n_ev) / abs(sigma_sum / n_ev);
+        let sigma = sigma_sum * incident_flux;
+        let sigma_rounding_error = self.sigma.relative_rounding_error();
+
+        let derived = DerivedQuantities::new(cfg, &spm2, sigma);
+        let DerivedQuantities {
+            beta_min,
+            ss_p,
+            ss_m,
+            ..
+        } = derived;
+
+        let ss_denom = spm2.column(A).sum();
+        let inc_num = |col| spm2.column(col).component_mul(&vars.column(col)).norm();
+        let inc_ss_common = inc_num(A) / (2. * abs(ss_denom));
+        let inc = |col| inc_num(col) / abs(spm2.column(col).sum()) + inc_ss_common;
+        let inc_ss_p = inc(B_P);
+        let inc_ss_m = inc(B_M);
+        let spin_density = cfg.spin_density.then(|| self.spin_density(&spm2));
+
+        // Estimate the uncertainty of the derived quantities, if configured
+        let resampling = cfg.resampling.as_ref().map(|resampling| {
+            let batch_sums = if self.batch_sums.is_empty() {
+                std::slice::from_ref(&spm2_sums)
+            } else {
+                &self.batch_sums[..]
+            };
+            resampling::resample(cfg, resampling, &derived, batch_sums, |sums| {
+                let sigma = sums.dot(&self.sigma_contribs) * incident_flux;
+                DerivedQuantities::new(cfg, &self.per_spin_m2(sums), sigma)
+            })
+        });
+
+        // Deduce the convergence history of the cross-section, if recorded
+        let convergence = self.batch_totals.as_ref().map(|batch_totals| {
+            let own_totals = [self.totals()];
+            let batch_totals = if batch_totals.is_empty() {
+                &own_totals[..]
+            } else {
+                &batch_totals[..]
+            };
+            convergence::history(batch_totals, cfg.num_events, incident_flux)
+        });
+
+        // Keep the accumulated results around, so that they can be merged with
+        // those of other simulation runs
+        let sums = self.checkpoint();
+
+        // Account for the finalization time
+        let timings = self.timings.map(|mut timings| {
+            timings.add(Phase::Finalization, start_time.elapsed());
+            timings
+        });
+
+        // Return the final results
+        FinalResults {
+            selected_events: self.selected_events,
+            spm2,
+            vars,
+            sigma,
+            variance,
+            beta_min,
+            prec,
+            sigma_rounding_error,
+            ss_p,
+            inc_ss_p,
+            ss_m,
+            inc_ss_m,
+            spin_density,
+            cut_flow: self.cut_flow,
+            weight_issues: self.weight_issues,
+            extended_precision_events: self.extended_precision_events,
+            histograms: self.histograms,
+            asymmetries: (self.asymmetries.as_ref())
+                .map(|asymmetries| asymmetries.finalize(cfg.num_events)),
+            legendre_moments: (self.legendre_moments.as_ref())
+                .map(|moments| moments.finalize(cfg.num_events)),
+            weight_distribution: self.weights.as_ref().map(WeightAccumulator::finalize),
+            resampling,
+            convergence,
+            timings,
+            partial: false,
+            me_sums: spm2_sums,
+            me_moments,
+            unit_contribs: self.unit_contribs,
+            incident_flux,
+            sums,
+            provenance: Provenance::new(cfg),
+            cfg: Cow::Borrowed(cfg),
+        }
+    }
+
+    /// Number of generated events, and totals of their weights
+    fn totals(&self) -> BatchTotals {
+        BatchTotals {
+            events: self.selected_events
+                + self.cut_flow.total_rejected()
+                + self.weight_issues.non_finite(),
+            sigma: self.sigma.value(),
+            variance: self.variance.value(),
+        }
+    }
+
+    /// Accumulated cross-section for each contribution
+    fn me_sums(&self) -> MEsVector {
+        MEsVector::from_fn(|res, _| self.spm2[res].value())
+    }
+
+    /// Per-spin cross-section contributions, given the sums of the squared
+    /// matrix elements of each contribution over events
+    fn per_spin_m2(&self, spm2_sums: &MEsVector) -> PerSpinMEs {
+        let cfg = self.cfg;
+
+        // Copy for both spins, weighting by the beam polarizations
+        let hel_weights = helicity_weights(cfg);
+        let mut spm2 = PerSpinMEs::from_fn(|spin, res| hel_weights[spin] * spm2_sums[res]);
 
         // Electroweak polarisations factors for the 𝛽₊/𝛽₋ anomalous
         // contribution
-        let polar_p = -2. * cfg.sin2_weinberg;
-        let polar_m = 1. + polar_p;
-        let polars = SVector::<Float, NUM_SPINS>::new(polar_m, polar_p);
+        let polars = electroweak_polarizations(cfg);
 
         // Take polarisations into account
         spm2.fixed_columns_mut::<4>(B_P)
@@ -173,51 +588,238 @@ impl<'cfg> ResultsAccumulator<'cfg> {
             .column_iter_mut()
             .for_each(|mut col| col.component_mul_assign(&polars));
 
-        // Incident flux factor (=1/2s for 2 initial massless particles)
-        let incident_flux = 1. / (2. * cfg.e_total.powi(2));
-
         // Apply physical coefficients and Z⁰ propagator to each spin
-        spm2 *= self.fact_com * incident_flux * self.norm_weight;
+        spm2 *= self.fact_com * self.incident_flux * self.norm_weight;
         let gm_z0 = cfg.g_z0 * cfg.m_z0;
         spm2.fixed_columns_mut::<4>(B_P)
             .apply(|x| *x *= self.propagator / gm_z0);
         spm2.fixed_columns_mut::<2>(B_P).apply(|x| *x /= gm_z0);
         spm2.column_mut(R_MX)
-            .apply(|x| *x *= self.delta_with_z0_peak);
+            .apply(|x| *x *= re(self.inv_propagator));
+        spm2.column_mut(I_MX)
+            .apply(|x| *x *= -im(self.inv_propagator));
 
-        // Compute other parts of the result
-        let beta_min = sqrt(spm2.column(A).sum() / spm2.column(B_P).sum());
+        spm2
+    }
 
-        let ss_denom = spm2.column(A).sum();
-        let ss_norm = 1. / (2. * sqrt(ss_denom));
+    /// Spin density matrix of the anomalous (Z⁰) contribution in the basis of
+    /// the incoming electron helicities, given the per-spin cross-section
+    /// contributions
+    fn spin_density(&self, spm2: &PerSpinMEs) -> SpinDensityMatrix {
+        let cfg = self.cfg;
+        let n_ev = cfg.num_events as Float;
 
-        let ss_p = spm2.column(B_P).sum() * ss_norm;
-        let ss_m = spm2.column(B_M).sum() * ss_norm;
+        // The diagonal elements are the per-spin anomalous cross-sections,
+        // averaged over the incoming spins like the total cross-section
+        let diagonal = [SP_M, SP_P].map(|spin| {
+            (cfg.beta_plus.powi(2) * spm2[(spin, B_P)] + cfg.beta_minus.powi(2) * spm2[(spin, B_M)])
+                / 4.
+        });
+
+        // The off-diagonal element gets the geometric mean of the factors
+        // that per_spin_m2() applies to each spin
+        let hel_weights = helicity_weights(cfg);
+        let polars = electroweak_polarizations(cfg);
+        let factor = sqrt(hel_weights[SP_M] * hel_weights[SP_P])
+            * polars[SP_M]
+            * polars[SP_P]
+            * self.fact_com
+            * self.incident_flux
+            * self.norm_weight
+            * self.propagator
+            / (cfg.g_z0 * cfg.m_z0).powi(2)
+            / 4.;
+        let off_diagonal = self.spin_interference.map(|sum| sum.value() * factor);
+        let mut off_diagonal_stddev = [0.; 2];
+        for (k, stddev) in off_diagonal_stddev.iter_mut().enumerate() {
+            let sum = self.spin_interference[k].value();
+            let variance =
+                (self.spin_interference_moments[k].value() - sum.powi(2) / n_ev) / (n_ev - 1.);
+            *stddev = sqrt(n_ev * variance) * abs(factor);
+        }
 
-        let inc_num = |col| spm2.column(col).component_mul(&vars.column(col)).norm();
-        let inc_ss_common = inc_num(A) / (2. * abs(ss_denom));
-        let inc = |col| inc_num(col) / abs(spm2.column(col).sum()) + inc_ss_common;
-        let inc_ss_p = inc(B_P);
-        let inc_ss_m = inc(B_M);
+        SpinDensityMatrix {
+            diagonal,
+            off_diagonal,
+            off_diagonal_stddev,
+        }
+    }
+}
 
-        let variance = (self.variance - self.sigma.powi(2) / n_ev) / (n_ev - 1.);
-        let prec = sqrt(variance / n_ev) / abs(self.sigma / n_ev);
-        let sigma = self.sigma * incident_flux;
+/// Weight of each electron helicity (as in PerSpinMEs rows), given the
+/// longitudinal polarizations of the beams
+///
+/// The positron must have the opposite helicity, and unpolarized beams give
+/// unit weights.
+///
+fn helicity_weights(cfg: &Configuration) -> SVector<Float, NUM_SPINS> {
+    let (pol_e_m, pol_e_p) = (cfg.electron_polarization, cfg.positron_polarization);
+    SVector::<Float, NUM_SPINS>::new(
+        (1. - pol_e_m) * (1. + pol_e_p),
+        (1. + pol_e_m) * (1. - pol_e_p),
+    )
+}
 
-        // Return the final results
-        FinalResults {
-            selected_events: self.selected_events,
-            spm2,
-            vars,
-            sigma,
-            variance,
-            beta_min,
-            prec,
-            ss_p,
-            inc_ss_p,
-            ss_m,
-            inc_ss_m,
-            cfg,
+/// Electroweak polarization factors of the anomalous amplitudes for each
+/// electron helicity (as in PerSpinMEs rows)
+fn electroweak_polarizations(cfg: &Configuration) -> SVector<Float, NUM_SPINS> {
+    let polar_p = -2. * cfg.sin2_weinberg;
+    let polar_m = 1. + polar_p;
+    SVector::<Float, NUM_SPINS>::new(polar_m, polar_p)
+}
+
+/// Conversion factors from the sums of squared matrix elements to the
+/// cross-section, for a given set of physical parameters
+pub(crate) struct Contributions {
+    /// Common factor, non-averaged over spins
+    pub fact_com: Float,
+
+    /// Z° propagator
+    pub propagator: Float,
+
+    /// Inverse Z° propagator 𝛿 - i·w
+    pub inv_propagator: Complex,
+
+    /// Impact of each contribution on the cross-section
+    pub sigma_contribs: MEsVector,
+
+    /// Impact of each contribution on the cross-section, for unit anomalous
+    /// couplings
+    pub unit_contribs: MEsVector,
+}
+//
+impl Contributions {
+    /// Compute the conversion factors for a certain configuration and
+    /// normalized event weight
+    pub fn new(cfg: &Configuration, norm_weight: Float) -> Self {
+        // Common factor (see ResultsAccumulator::fact_com)
+        let fact_com = 1. / 6. * cfg.gev2_to_picobarn;
+        let relat_width = cfg.g_z0 / cfg.m_z0;
+
+        // Sum over polarisations factors, weighted by the beam polarizations
+        let [w_l, w_r] = helicity_weights(cfg).into();
+        let p_aa = w_l + w_r;
+        let p_ab = w_l - 2. * cfg.sin2_weinberg * p_aa;
+        let p_bb = w_l * (1. - 4. * cfg.sin2_weinberg) + 4. * cfg.sin2_weinberg.powi(2) * p_aa;
+
+        // Homogeneity coefficient
+        let c_aa = fact_com * p_aa;
+        let c_ab = fact_com * p_ab / cfg.m_z0.powi(2);
+        let c_bb = fact_com * p_bb / cfg.m_z0.powi(4);
+
+        // Switch to dimensionless variable
+        let inv_propagator = cfg.z_lineshape.inverse_propagator(cfg, cfg.e_total);
+        let delta_with_z0_peak = re(inv_propagator);
+        let width_factor = -im(inv_propagator);
+        let propagator = 1. / (width_factor.powi(2) + delta_with_z0_peak.powi(2));
+
+        // Compute how much each result contribution adds to the cross-section
+        let com_contrib = norm_weight / 4.;
+        let aa_contrib = com_contrib * c_aa;
+        let bb_contrib = com_contrib * c_bb * propagator / relat_width.powi(2);
+        let ab_contrib = com_contrib * c_ab * 2. * cfg.beta_plus * propagator / relat_width;
+        let sigma_contribs = vector![
+            aa_contrib,                          // A
+            bb_contrib * cfg.beta_plus.powi(2),  // B_P
+            bb_contrib * cfg.beta_minus.powi(2), // B_M
+            ab_contrib * delta_with_z0_peak,     // R_MX
+            -ab_contrib * width_factor           // I_MX
+        ];
+        let unit_ab_contrib = com_contrib * c_ab * 2. * propagator / relat_width;
+        let unit_contribs = vector![
+            aa_contrib,
+            bb_contrib,
+            bb_contrib,
+            unit_ab_contrib * delta_with_z0_peak,
+            -unit_ab_contrib * width_factor
+        ];
+
+        Contributions {
+            fact_com,
+            propagator,
+            inv_propagator,
+            sigma_contribs,
+            unit_contribs,
+        }
+    }
+}
+
+/// Results accumulated by a ResultsAccumulator, as saved in checkpoints and
+/// alongside the final results
+#[derive(Clone, Deserialize, Serialize)]
+pub struct AccumulatorCheckpoint {
+    /// Number of integrated events
+    selected_events: usize,
+
+    /// Accumulated cross-section for each contribution
+    spm2: [CompensatedSum; NUM_MAT_ELEMS],
+
+    /// Accumulated products of each pair of contributions
+    moments: [[CompensatedSum; NUM_MAT_ELEMS]; NUM_MAT_ELEMS],
+
+    /// Accumulated cross-section for each contribution, for each batch
+    batch_sums: Vec<MEsVector>,
+
+    /// Totals of each batch, if the convergence history is recorded
+    batch_totals: Option<Vec<BatchTotals>>,
+
+    /// Accumulated total cross-section
+    sigma: CompensatedSum,
+
+    /// Accumulated total variance
+    variance: CompensatedSum,
+
+    /// Accumulated interference between the anomalous amplitudes of the two
+    /// electron helicities (real and imaginary parts)
+    spin_interference: [CompensatedSum; 2],
+
+    /// Accumulated squares of the real and imaginary parts of the interference
+    spin_interference_moments: [CompensatedSum; 2],
+
+    /// Number of events rejected by each cut
+    cut_flow: CutFlow,
+
+    /// Events whose weight is not finite or negative
+    weight_issues: WeightIssues,
+
+    /// Number of events recomputed with extended precision
+    #[serde(default)]
+    extended_precision_events: usize,
+
+    /// Histograms of the photon distributions (if enabled)
+    histograms: Option<Distributions>,
+
+    /// Angular asymmetries of the photons (if enabled)
+    asymmetries: Option<AsymmetryAccumulator>,
+
+    /// Legendre moments of the photon angular distribution (if enabled)
+    legendre_moments: Option<MomentAccumulator>,
+
+    /// Distribution of the event weights (if enabled)
+    weights: Option<WeightAccumulator>,
+}
+//
+impl AccumulatorCheckpoint {
+    /// Multiply the weights of the accumulated events by a certain factor
+    ///
+    /// Event weights are normalized by the configured number of events, so
+    /// this must be done before the results of simulation runs with different
+    /// numbers of events can be merged.
+    ///
+    pub fn scale_weights(&mut self, factor: Float) {
+        self.sigma.scale(factor);
+        self.variance.scale(factor.powi(2));
+        for batch_totals in self.batch_totals.iter_mut().flatten() {
+            batch_totals.scale_weights(factor);
+        }
+        if let Some(histograms) = &mut self.histograms {
+            histograms.scale_weights(factor);
+        }
+        if let Some(asymmetries) = &mut self.asymmetries {
+            asymmetries.scale_weights(factor);
+        }
+        if let Some(moments) = &mut self.legendre_moments {
+            moments.scale_weights(factor);
         }
     }
 }
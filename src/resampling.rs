@@ -0,0 +1,273 @@
+//! This module estimates the statistical uncertainty of quantities which are
+//! derived from the simulation results, by resampling batches of events
+//!
+//! The analytical variance of the results only covers the cross-section of
+//! each contribution. Quantities like the statistical significances or the
+//! ratios displayed by `FinalResults::eric()` are nonlinear functions of these
+//! cross-sections, so their uncertainties are instead estimated by recomputing
+//! them over resampled sets of event batches.
+
+use crate::{
+    config::Configuration,
+    error::ensure,
+    event::NUM_SPINS,
+    matelems::{MEsVector, A, B_M, B_P},
+    numeric::{floats::consts::PI, Float},
+    random::{RandGenerator, RandomGenerator},
+    resfin::{PerSpinMEs, SP_M, SP_P},
+    Result,
+};
+use log::info;
+use prefix_num_ops::real::*;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Serialize};
+
+/// Resampling methods which can be used to estimate uncertainties
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResamplingMethod {
+    /// Draw batches with replacement, then use the spread of the replicas
+    #[default]
+    Bootstrap,
+
+    /// Leave out one batch at a time
+    Jackknife,
+}
+
+/// Configuration of the resampling of event batches
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResamplingConfig {
+    /// Resampling method
+    pub method: ResamplingMethod,
+
+    /// Number of bootstrap replicas (unused by the jackknife)
+    pub replicas: usize,
+}
+//
+impl ResamplingConfig {
+    /// Check that the configuration is sensible
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            self.method != ResamplingMethod::Bootstrap || self.replicas >= 2,
+            InvalidConfig,
+            "The bootstrap needs at least two replicas"
+        );
+        Ok(())
+    }
+}
+//
+impl Default for ResamplingConfig {
+    fn default() -> Self {
+        Self {
+            method: ResamplingMethod::default(),
+            replicas: 1000,
+        }
+    }
+}
+
+/// Number of derived quantities
+const NUM_DERIVED: usize = 7;
+
+/// Display names of the derived quantities, in the order of to_array()
+const DERIVED_NAMES: [&str; NUM_DERIVED] = [
+    "sigma (pb)",
+    "beta min",
+    "ss B+ (pb-1/2)",
+    "ss B- (pb-1/2)",
+    "mu/lamb -",
+    "mu/lamb +",
+    "rapport",
+];
+
+/// Quantities derived from the per-spin results
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct DerivedQuantities<T> {
+    /// Total cross-section (pb)
+    pub sigma: T,
+
+    /// Beta minimum (???)
+    pub beta_min: T,
+
+    /// Statistical significance B+(pb-1/2) (???)
+    pub ss_p: T,
+
+    /// Statistical significance B-(pb-1/2) (???)
+    pub ss_m: T,
+
+    /// Ratio of the mu and lambda parameters of Eric's parametrization, for
+    /// each spin
+    pub mu_lambda: [T; NUM_SPINS],
+
+    /// Ratio of the numerical and theoretical mu parameters
+    pub mu_ratio: T,
+}
+//
+impl DerivedQuantities<Float> {
+    /// Derive the quantities from the per-spin results and total cross-section
+    pub fn new(cfg: &Configuration, spm2: &PerSpinMEs, sigma: Float) -> Self {
+        let beta_min = sqrt(spm2.column(A).sum() / spm2.column(B_P).sum());
+
+        let ss_denom = spm2.column(A).sum();
+        let ss_norm = 1. / (2. * sqrt(ss_denom));
+
+        let ss_p = spm2.column(B_P).sum() * ss_norm;
+        let ss_m = spm2.column(B_M).sum() * ss_norm;
+
+        let mu_th = cfg.branching_ep_em * cfg.gev2_to_picobarn
+            / (8. * 9. * 5. * PI.powi(2) * cfg.m_z0 * cfg.g_z0);
+        let lambda0 = (spm2.column(B_M) - spm2.column(B_P)) / 2.;
+        let mu0 = (spm2.column(B_M) + spm2.column(B_P)) / 2.;
+        let mu_num = spm2.fixed_columns::<2>(B_P).sum() / 4.;
+
+        DerivedQuantities {
+            sigma,
+            beta_min,
+            ss_p,
+            ss_m,
+            mu_lambda: [mu0[SP_M] / lambda0[SP_M], mu0[SP_P] / lambda0[SP_P]],
+            mu_ratio: mu_num / mu_th,
+        }
+    }
+}
+//
+impl<T: Copy> DerivedQuantities<T> {
+    /// List the derived quantities
+    fn to_array(self) -> [T; NUM_DERIVED] {
+        [
+            self.sigma,
+            self.beta_min,
+            self.ss_p,
+            self.ss_m,
+            self.mu_lambda[SP_M],
+            self.mu_lambda[SP_P],
+            self.mu_ratio,
+        ]
+    }
+
+    /// Build the derived quantities from a list, in the order of to_array()
+    fn from_array(values: [T; NUM_DERIVED]) -> Self {
+        let [sigma, beta_min, ss_p, ss_m, mu_lambda_m, mu_lambda_p, mu_ratio] = values;
+        DerivedQuantities {
+            sigma,
+            beta_min,
+            ss_p,
+            ss_m,
+            mu_lambda: [mu_lambda_m, mu_lambda_p],
+            mu_ratio,
+        }
+    }
+}
+
+/// Statistical uncertainty of a derived quantity
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct ConfidenceInterval {
+    /// Value of the quantity, derived from all events
+    pub value: Float,
+
+    /// Standard deviation of the quantity
+    pub stddev: Float,
+
+    /// Lower bound of the 68% confidence interval
+    pub lower: Float,
+
+    /// Upper bound of the 68% confidence interval
+    pub upper: Float,
+}
+
+/// Fraction of the resampled values which should lie below the lower bound of
+/// a 68% confidence interval (and above its upper bound)
+#[allow(clippy::excessive_precision)]
+const TAIL_FRACTION: Float = 0.158_655_253_931_457_05;
+
+/// Estimate the uncertainty of the derived quantities by resampling batches of
+/// events, given their value for all events, the sums of the squared matrix
+/// elements of each batch, and a function which derives the quantities from
+/// such sums over as many events as were simulated
+pub fn resample(
+    cfg: &Configuration,
+    resampling: &ResamplingConfig,
+    nominal: &DerivedQuantities<Float>,
+    batch_sums: &[MEsVector],
+    derive: impl Fn(&MEsVector) -> DerivedQuantities<Float>,
+) -> DerivedQuantities<ConfidenceInterval> {
+    let num_batches = batch_sums.len();
+
+    // Derive the quantities from each resampled set of batches
+    let replicas = match resampling.method {
+        ResamplingMethod::Bootstrap => {
+            /// Offset between the seed of the simulation and that of the bootstrap
+            const BOOTSTRAP_SEED_OFFSET: u64 = 0x424f_4f54_5354_5250;
+            let seed = cfg.rng.effective_seed(cfg.seed) ^ BOOTSTRAP_SEED_OFFSET;
+            let mut rng = RandGenerator::<Xoshiro256PlusPlus>::with_seed(seed);
+            (0..resampling.replicas)
+                .map(|_| {
+                    let sums = (0..num_batches)
+                        .map(|_| {
+                            let batch = (rng.random() * num_batches as Float) as usize;
+                            batch_sums[batch.min(num_batches - 1)]
+                        })
+                        .sum::<MEsVector>();
+                    derive(&sums)
+                })
+                .collect::<Vec<_>>()
+        }
+        ResamplingMethod::Jackknife => {
+            // Leaving out a batch loses its share of the events, which the
+            // remaining ones must make up for
+            let total_sums = batch_sums.iter().sum::<MEsVector>();
+            let scale = num_batches as Float / (num_batches - 1) as Float;
+            (batch_sums.iter())
+                .map(|batch| derive(&((total_sums - batch) * scale)))
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // Estimate the uncertainty of each quantity from the spread of its values
+    let interval = |value: Float, mut samples: Vec<Float>| {
+        let n = samples.len() as Float;
+        let mean = samples.iter().sum::<Float>() / n;
+        let sum_sq = samples.iter().map(|x| (x - mean).powi(2)).sum::<Float>();
+        match resampling.method {
+            ResamplingMethod::Bootstrap => {
+                samples.sort_by(Float::total_cmp);
+                let quantile = |q: Float| samples[(q * (n - 1.)).round() as usize];
+                ConfidenceInterval {
+                    value,
+                    stddev: sqrt(sum_sq / (n - 1.)),
+                    lower: quantile(TAIL_FRACTION),
+                    upper: quantile(1. - TAIL_FRACTION),
+                }
+            }
+            ResamplingMethod::Jackknife => {
+                let stddev = sqrt(sum_sq * (n - 1.) / n);
+                ConfidenceInterval {
+                    value,
+                    stddev,
+                    lower: value - stddev,
+                    upper: value + stddev,
+                }
+            }
+        }
+    };
+    let values = nominal.to_array();
+    DerivedQuantities::from_array(std::array::from_fn(|idx| {
+        let samples = replicas.iter().map(|replica| replica.to_array()[idx]);
+        interval(values[idx], samples.collect())
+    }))
+}
+
+//
+impl DerivedQuantities<ConfidenceInterval> {
+    /// Display the statistical uncertainties of the derived quantities
+    pub fn print(&self) {
+        info!("Resampled          :        Value |      Std dev | 68% interval");
+        for (name, interval) in DERIVED_NAMES.into_iter().zip(self.to_array()) {
+            info!(
+                "{name:<19}: {:>12.5e} | {:>12.5e} | [{:.5e}, {:.5e}]",
+                interval.value, interval.stddev, interval.lower, interval.upper
+            );
+        }
+        info!("");
+    }
+}
@@ -2,13 +2,31 @@
 //! analyze the final results: differential cross-section, sum & variance
 
 use crate::{
+    asymmetry::Asymmetries,
+    checkpoint,
     config::Configuration,
+    convergence::{self, ConvergencePoint},
+    coupling::ZLineshape,
+    evcut::CutFlow,
     event::NUM_SPINS,
-    matelems::{A, B_M, B_P, I_MX, NUM_MAT_ELEMS, R_MX},
-    numeric::{floats::consts::PI, functions::*, Complex, Float},
+    histogram::Distributions,
+    matelems::{MEsMatrix, MEsVector, A, B_M, B_P, I_MX, NUM_MAT_ELEMS, R_MX},
+    moments::LegendreMoments,
+    numeric::{floats::consts::PI, functions::*, Float, GridRange},
+    output::compression::OutputFile,
+    provenance::Provenance,
+    resacc::AccumulatorCheckpoint,
+    resampling::{ConfidenceInterval, DerivedQuantities},
+    systematics::SystematicUncertainty,
+    timings::PhaseTimings,
+    weights::{WeightDistribution, WeightIssues},
+    Error, Result,
 };
+use log::info;
 use nalgebra::SMatrix;
 use prefix_num_ops::real::*;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, io::Write};
 
 /// Matrix of per-spin result contributions
 ///
@@ -22,7 +40,26 @@ pub const SP_M: usize = 0;
 /// Index of positive spin data
 pub const SP_P: usize = 1;
 
+/// Grid of anomalous couplings over which the results should be reweighted
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CouplingGridConfig {
+    /// File where the table of reweighted results should be written
+    pub path: String,
+
+    /// Values of 𝛽₊
+    pub beta_plus: GridRange,
+
+    /// Values of 𝛽₋
+    pub beta_minus: GridRange,
+}
+
 /// Final results of the simulation
+///
+/// They can be serialized along with the configuration that produced them, and
+/// deserialized into FinalResults<'static> which owns its configuration.
+///
+#[derive(Clone, Deserialize, Serialize)]
 pub struct FinalResults<'cfg> {
     /// Number of integrated events
     pub selected_events: usize,
@@ -39,6 +76,10 @@ pub struct FinalResults<'cfg> {
     /// Relative precision
     pub prec: Float,
 
+    /// Estimated relative rounding error that naive summation would have made
+    /// on the total cross-section (which is compensated for)
+    pub sigma_rounding_error: Float,
+
     /// Total variance
     pub variance: Float,
 
@@ -57,18 +98,310 @@ pub struct FinalResults<'cfg> {
     /// Incertitude associated with ss_m
     pub inc_ss_m: Float,
 
+    /// Spin density matrix of the anomalous (Z⁰) contribution, if requested
+    pub spin_density: Option<SpinDensityMatrix>,
+
+    /// Number of generated events rejected by each cut
+    pub cut_flow: CutFlow,
+
+    /// Events whose weight is not finite or negative
+    pub weight_issues: WeightIssues,
+
+    /// Number of events whose matrix elements were recomputed with extended
+    /// precision by the stability guard
+    #[serde(default)]
+    pub extended_precision_events: usize,
+
+    /// Differential cross-sections of the photon distributions (if enabled)
+    pub histograms: Option<Distributions>,
+
+    /// Angular asymmetries of the photons (if enabled)
+    pub asymmetries: Option<Asymmetries>,
+
+    /// Legendre moments of the photon angular distribution (if enabled)
+    pub legendre_moments: Option<LegendreMoments>,
+
+    /// Distribution of the event weights (if enabled)
+    pub weight_distribution: Option<WeightDistribution>,
+
+    /// Uncertainties of derived quantities, estimated by resampling batches of
+    /// events (if enabled)
+    pub resampling: Option<DerivedQuantities<ConfidenceInterval>>,
+
+    /// Total cross-section after each batch of events (if enabled)
+    pub convergence: Option<Vec<ConvergencePoint>>,
+
+    /// Time spent in each phase of the simulation (if enabled)
+    pub timings: Option<PhaseTimings>,
+
+    /// Whether the simulation was interrupted, so that the results only cover
+    /// the events that were simulated before (which the configuration counts)
+    #[serde(default)]
+    pub partial: bool,
+
+    /// Sum of each matrix element contribution over events
+    pub me_sums: MEsVector,
+
+    /// Sum of the product of each pair of contributions over events (only
+    /// the diagonal is accumulated, unless a coupling grid is requested)
+    pub me_moments: MEsMatrix,
+
+    /// Impact of each contribution on the cross-section, for unit anomalous
+    /// couplings
+    pub unit_contribs: MEsVector,
+
+    /// Incident flux factor (=1/2s for 2 initial massless particles)
+    pub incident_flux: Float,
+
+    /// Accumulated sums which the results derive from, for merging with the
+    /// results of other simulation runs
+    pub sums: AccumulatorCheckpoint,
+
+    /// Record of how the results were produced
+    pub provenance: Provenance,
+
     /// Configuration of the simulation (for further derivation)
-    pub cfg: &'cfg Configuration,
+    pub cfg: Cow<'cfg, Configuration>,
 }
 //
 impl<'cfg> FinalResults<'cfg> {
+    /// Write the results in a machine-readable JSON file, along with metadata
+    /// describing how they were produced
+    pub fn write_json(&self, path: &str) -> Result<()> {
+        let mut json_file = OutputFile::create(path)?;
+        writeln!(json_file, "{}", self.to_json()?)?;
+        json_file.finish()?;
+        Ok(())
+    }
+
+    /// Write the histograms in a YODA file, for comparisons with experimental
+    /// data through Rivet
+    ///
+    /// Fails if histogramming was not enabled.
+    ///
+    pub fn write_yoda(&self, path: &str) -> Result<()> {
+        let histograms = self
+            .histograms
+            .as_ref()
+            .ok_or_else(|| Error::InvalidConfig("YODA output requires histograms".to_owned()))?;
+        let mut yoda_file = OutputFile::create(path)?;
+        histograms.write_yoda(&mut yoda_file)?;
+        yoda_file.finish()?;
+        Ok(())
+    }
+
+    /// Write the total cross-section after each batch of events in a CSV
+    /// file, to plot the convergence of the simulation
+    ///
+    /// Fails if the convergence history was not recorded.
+    ///
+    pub fn write_convergence_csv(&self, path: &str) -> Result<()> {
+        let convergence = self.convergence.as_ref().ok_or_else(|| {
+            Error::InvalidConfig("The convergence history was not recorded".to_owned())
+        })?;
+        convergence::write_csv(convergence, path)
+    }
+
+    /// Express the results in machine-readable JSON, along with metadata
+    /// describing how they were produced
+    pub fn to_json(&self) -> Result<String> {
+        // Metadata and configuration of the simulation
+        let cfg = &*self.cfg;
+        let metadata = &self.provenance;
+
+        // Per-spin results, as one row of matrix elements per spin
+        let per_spin = |mat: &PerSpinMEs| -> [[Float; NUM_MAT_ELEMS]; NUM_SPINS] {
+            let mut rows = [[0.; NUM_MAT_ELEMS]; NUM_SPINS];
+            for (spin, row) in rows.iter_mut().enumerate() {
+                for (elem, value) in row.iter_mut().enumerate() {
+                    *value = mat[(spin, elem)];
+                }
+            }
+            rows
+        };
+
+        // Cut flow, starting with the acceptance of the detector (if simulated)
+        let acceptance = cfg.detector.as_ref().map(|_| JsonCut {
+            cut: "acceptance",
+            rejected: self.cut_flow.undetected(),
+            efficiency: self.cut_flow.acceptance(cfg.num_events),
+        });
+        let cuts =
+            (self.cut_flow.efficiencies(cfg.num_events).into_iter()).map(|(cut, efficiency)| {
+                JsonCut {
+                    cut: cfg.event_cut.cut_name(cut),
+                    rejected: self.cut_flow.rejected(cut),
+                    efficiency,
+                }
+            });
+        let cut_flow = acceptance.into_iter().chain(cuts).collect();
+
+        let results = JsonResults {
+            partial: self.partial,
+            selected_events: self.selected_events,
+            sigma: self.sigma,
+            sigma_stddev: self.sigma * self.prec,
+            prec: self.prec,
+            sigma_rounding_error: self.sigma_rounding_error,
+            variance: self.variance,
+            beta_min: self.beta_min,
+            ss_p: self.ss_p,
+            inc_ss_p: self.inc_ss_p,
+            ss_m: self.ss_m,
+            inc_ss_m: self.inc_ss_m,
+            spm2: per_spin(&self.spm2),
+            vars: per_spin(&self.vars),
+            spin_density: self.spin_density,
+            cut_flow,
+            non_finite_events: self.weight_issues.non_finite(),
+            negative_weight_events: self.weight_issues.negative(),
+            extended_precision_events: (cfg.stability_guard.as_ref())
+                .map(|_guard| self.extended_precision_events),
+            asymmetries: self.asymmetries,
+            legendre_moments: self.legendre_moments.clone(),
+            weight_distribution: self.weight_distribution.clone(),
+            resampling: self.resampling,
+            systematics: (cfg.systematics.as_ref())
+                .map(|systematics| SystematicUncertainty::new(self, systematics)),
+        };
+
+        // Serialize everything
+        Ok(serde_json::to_string_pretty(&JsonOutput {
+            metadata,
+            configuration: cfg,
+            results,
+            sums: &self.sums,
+        })?)
+    }
+
+    /// Append a summary of the results to a YAML file, as a new document
+    ///
+    /// Unlike the JSON output, the file is not overwritten, so that the runs of
+    /// a campaign (e.g. a scan over some configuration entries) accumulate
+    /// their results in a single file.
+    ///
+    pub fn append_summary_yaml(&self, path: &str) -> Result<()> {
+        let mut yaml_file = OutputFile::append(path)?;
+        write!(yaml_file, "---\n{}", self.to_summary_yaml()?)?;
+        yaml_file.finish()?;
+        Ok(())
+    }
+
+    /// Summarize the results in YAML, along with the configuration entries
+    /// which are most commonly varied from one run to another
+    pub fn to_summary_yaml(&self) -> Result<String> {
+        let cfg = &*self.cfg;
+        Ok(serde_yaml::to_string(&YamlSummary {
+            config_hash: checkpoint::config_hash(cfg),
+            version: &self.provenance.version,
+            git_commit: &self.provenance.git_commit,
+            start_time: self.provenance.start_time.as_deref(),
+            wall_time: self.provenance.wall_time,
+            seed: self.provenance.seed,
+            num_events: cfg.num_events,
+            e_total: cfg.e_total,
+            beta_plus: cfg.beta_plus,
+            beta_minus: cfg.beta_minus,
+            selected_events: self.selected_events,
+            sigma: self.sigma,
+            sigma_stddev: self.sigma * self.prec,
+            prec: self.prec,
+            beta_min: self.beta_min,
+            ss_p: self.ss_p,
+            inc_ss_p: self.inc_ss_p,
+            ss_m: self.ss_m,
+            inc_ss_m: self.inc_ss_m,
+        })?)
+    }
+
+    /// Recompute the results for other values of the anomalous couplings and
+    /// of the fine structure constant at the Z⁰ mass peak (which must be
+    /// positive), without simulating again
+    ///
+    /// This is possible because the matrix elements are quadratic in the
+    /// anomalous couplings, so their contributions can be reweighted. The
+    /// uncertainty is only meaningful if a coupling grid was requested, as
+    /// the covariance of the contributions is not accumulated otherwise.
+    ///
+    pub fn reweight(
+        &self,
+        beta_plus: Float,
+        beta_minus: Float,
+        alpha_z: Float,
+    ) -> ReweightedResults {
+        // Keep around a floating-point version of the total event count
+        let n_ev = self.cfg.num_events as Float;
+
+        // The anomalous amplitudes scale as the square root of alpha_z
+        let alpha_z_ratio = alpha_z / self.cfg.alpha_z;
+        let mut contribs = self.unit_contribs;
+        contribs[B_P] *= beta_plus.powi(2) * alpha_z_ratio;
+        contribs[B_M] *= beta_minus.powi(2) * alpha_z_ratio;
+        let mixed_factor = beta_plus * sqrt(alpha_z_ratio);
+        contribs[R_MX] *= mixed_factor;
+        contribs[I_MX] *= mixed_factor;
+
+        // Recompute the cross-section and its variance from the contributions
+        let sigma_sum = self.me_sums.dot(&contribs);
+        let variance_sum = contribs.dot(&(self.me_moments * contribs));
+        let variance = (variance_sum - sigma_sum.powi(2) / n_ev) / (n_ev - 1.);
+        let prec = sqrt(variance / n_ev) / abs(sigma_sum / n_ev);
+        let sigma = sigma_sum * self.incident_flux;
+
+        // Compare the anomalous contribution to the Standard Model one
+        let sigma_sm = self.me_sums[A] * self.unit_contribs[A] * self.incident_flux;
+        let significance = (sigma - sigma_sm) / sqrt(sigma_sm);
+
+        ReweightedResults {
+            sigma,
+            variance,
+            prec,
+            significance,
+            ss_p: self.ss_p * alpha_z_ratio,
+            ss_m: self.ss_m * alpha_z_ratio,
+        }
+    }
+
+    /// Write a table of the cross-section and statistical significance of the
+    /// anomalous contribution over a grid of anomalous couplings
+    ///
+    /// The significance is normalized to an integrated luminosity of 1 pb⁻¹,
+    /// like the B+ and B- significances of the main results.
+    ///
+    pub fn write_coupling_grid(&self, grid: &CouplingGridConfig) -> Result<()> {
+        let mut table = OutputFile::create(&grid.path)?;
+        writeln!(
+            table,
+            "#{:>19} {:>20} {:>20} {:>20} {:>20}",
+            "Beta plus", "Beta moins", "Sigma (pb)", "Std. dev. (pb)", "Signif. (pb-1/2)"
+        )?;
+        for beta_plus in grid.beta_plus.values() {
+            for beta_minus in grid.beta_minus.values() {
+                let res = self.reweight(beta_plus, beta_minus, self.cfg.alpha_z);
+                writeln!(
+                    table,
+                    " {:>19} {:>20} {:>20.12e} {:>20.12e} {:>20.12e}",
+                    beta_plus,
+                    beta_minus,
+                    res.sigma,
+                    res.sigma * res.prec,
+                    res.significance
+                )?;
+            }
+            // Blank lines separate rows of the grid, as gnuplot expects
+            writeln!(table)?;
+        }
+        table.finish()?;
+        Ok(())
+    }
+
     /// Display results using Eric's (???) parametrization
     pub fn eric(&self) {
-        assert_eq!(NUM_SPINS, 2);
-        assert_eq!(NUM_MAT_ELEMS, 5);
+        const { assert!(NUM_SPINS == 2) };
+        const { assert!(NUM_MAT_ELEMS == 5) };
 
         let spm2 = &self.spm2;
-        let cfg = self.cfg;
+        let cfg = &*self.cfg;
 
         let mu_th = cfg.branching_ep_em * cfg.gev2_to_picobarn
             / (8. * 9. * 5. * PI.powi(2) * cfg.m_z0 * cfg.g_z0);
@@ -79,38 +412,73 @@ impl<'cfg> FinalResults<'cfg> {
         let mu0 = (spm2.column(B_M) + spm2.column(B_P)) / 2.;
         let mu_num = spm2.fixed_columns::<2>(B_P).sum() / 4.;
 
-        println!();
-        println!("       :        -          +");
-        println!("sigma0  : {:.6} | {:.6}", sigma0[SP_M], sigma0[SP_P]);
-        println!("alpha0  : {:.5e} | {:.4e}", alpha0[SP_M], alpha0[SP_P]);
-        println!("beta0   : {:} | {:}", beta0[SP_M], beta0[SP_P]);
-        println!("lambda0 : {:.4} | {:.4}", lambda0[SP_M], lambda0[SP_P]);
-        println!("mu0     : {:.4} | {:.5}", mu0[SP_M], mu0[SP_P]);
-        println!(
+        info!("");
+        info!("       :        -          +");
+        info!("sigma0  : {:.6} | {:.6}", sigma0[SP_M], sigma0[SP_P]);
+        info!("alpha0  : {:.5e} | {:.4e}", alpha0[SP_M], alpha0[SP_P]);
+        info!("beta0   : {:} | {:}", beta0[SP_M], beta0[SP_P]);
+        info!("lambda0 : {:.4} | {:.4}", lambda0[SP_M], lambda0[SP_P]);
+        info!("mu0     : {:.4} | {:.5}", mu0[SP_M], mu0[SP_P]);
+        info!(
             "mu/lamb : {:.5} | {:.5}",
             mu0[SP_M] / lambda0[SP_M],
             mu0[SP_P] / lambda0[SP_P]
         );
-        println!("mu (num): {mu_num:.4}");
-        println!("rapport : {:.6}", mu_num / mu_th);
-        println!("mu (th) : {mu_th:.4}");
+        info!("mu (num): {mu_num:.4}");
+        info!("rapport : {:.6}", mu_num / mu_th);
+        info!("mu (th) : {mu_th:.4}");
+    }
+
+    /// Display how many events were rejected by each cut, and the efficiency
+    /// of each cut with respect to the events which passed the previous ones
+    pub fn cut_flow(&self) {
+        let num_events = self.cfg.num_events;
+        info!("Cut flow           :   Rejected | Efficiency");
+        if self.cfg.detector.is_some() {
+            let undetected = self.cut_flow.undetected();
+            let acceptance = self.cut_flow.acceptance(num_events);
+            info!(
+                "{:<19}: {undetected:>10} | {acceptance:.6}",
+                "Detector acceptance"
+            );
+        }
+        for (cut, efficiency) in self.cut_flow.efficiencies(num_events) {
+            let rejected = self.cut_flow.rejected(cut);
+            let name = self.cfg.event_cut.cut_name(cut);
+            info!("{name:<19}: {rejected:>10} | {efficiency:.6}");
+        }
+        let selected = self.selected_events;
+        let efficiency = (selected as Float) / (num_events as Float);
+        info!("{:<19}: {selected:>10} | {efficiency:.6}", "Selected");
+        info!("");
+    }
+
+    /// Display how often the stability guard recomputed the matrix elements
+    /// of selected events with extended precision, if it is enabled
+    pub fn extended_precision(&self) {
+        if self.cfg.stability_guard.is_none() {
+            return;
+        }
+        let recomputed = self.extended_precision_events;
+        let fraction = (recomputed as Float) / (self.selected_events.max(1) as Float);
+        info!("Extended precision : {recomputed} events ({fraction:.3e} of selected)");
+        info!("");
     }
 
     /// Display Fawzi's (???) analytical results and compare them to the Monte
     /// Carlo results that we have computed
     pub fn fawzi(&self) {
-        assert_eq!(NUM_SPINS, 2);
-        assert_eq!(NUM_MAT_ELEMS, 5);
+        const { assert!(NUM_SPINS == 2) };
+        const { assert!(NUM_MAT_ELEMS == 5) };
 
-        let cfg = self.cfg;
+        let cfg = &*self.cfg;
         let ev_cut = &cfg.event_cut;
         let spm2 = &self.spm2;
         let vars = &self.vars;
 
-        let mre = cfg.m_z0 / cfg.e_total;
+        // Z⁰ propagator in units of 1/s, in the configured lineshape scheme
         let gre = cfg.g_z0 * cfg.m_z0 / cfg.e_total.powi(2);
-        let x = 1. - mre.powi(2);
-        let sdz = Complex::new(x, -gre) / (x.powi(2) + gre.powi(2));
+        let sdz = 1. / (cfg.z_lineshape.inverse_propagator(cfg, cfg.e_total) * gre);
         let del = (1. - ev_cut.photon_photon_cut) / 2.;
         let eps = 2. * ev_cut.e_min / cfg.e_total;
         let bra = cfg.m_z0 / (3. * 6. * PI.powi(3) * 16. * 120.);
@@ -158,38 +526,287 @@ impl<'cfg> FinalResults<'cfg> {
         let incr_p = incr(B_P);
         let incr_m = incr(B_M);
 
-        println!();
-        println!("s (pb) :   Sig_cut_Th    Sig_Th      Rapport");
-        println!("       :   Sig_Num");
-        println!("       :   Ecart_relatif  Incertitude");
-        println!();
-        println!(
+        info!("");
+        if cfg.z_lineshape != ZLineshape::FixedWidth {
+            info!("Z0 lineshape : {}", cfg.z_lineshape.name());
+        }
+        info!("s (pb) :   Sig_cut_Th    Sig_Th      Rapport");
+        info!("       :   Sig_Num");
+        info!("       :   Ecart_relatif  Incertitude");
+        info!("");
+        info!(
             "s+(pb) : {:.5} | {:.5} | {:.6}",
             sig_p,
             sig * 3.,
             sig_p / (3. * sig)
         );
-        println!("       : {:.5}", mc_p);
-        println!(
+        info!("       : {:.5}", mc_p);
+        info!(
             "       : {:.6} | {:.8} | {:.2}",
             mc_p / sig_p - 1.,
             incr_p,
             (mc_p / sig_p - 1.) / incr_p
         );
-        println!();
-        println!(
+        info!("");
+        info!(
             "s-(pb) : {:.5} | {:.4} | {:.6}",
             sig_m,
             sig * 5.,
             sig_m / (5. * sig)
         );
-        println!("       : {:.5}", mc_m);
-        println!(
+        info!("       : {:.5}", mc_m);
+        info!(
             "       : {:.6} | {:.9} | {:.2}",
             mc_m / sig_m - 1.,
             incr_m,
             (mc_m / sig_m - 1.) / incr_m
         );
-        println!();
+        info!("");
     }
 }
+
+/// Spin density matrix of the anomalous (Z⁰) contribution to the
+/// cross-section, in the basis of the incoming electron helicities (negative
+/// first, as in PerSpinMEs rows)
+///
+/// The diagonal elements are the per-spin cross-sections of the anomalous
+/// contribution, for the configured anomalous couplings. The off-diagonal
+/// element describes the interference of the two spin states in the photon
+/// final state. It vanishes once integrated over the azimuth of the photons
+/// around the beam axis, unless the cuts break that symmetry, so spin
+/// correlation studies must weight events by their azimuth. Its phase depends
+/// on the spinor phase conventions.
+///
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct SpinDensityMatrix {
+    /// Diagonal elements ρ₋₋ and ρ₊₊ (pb), whose sum is the anomalous
+    /// cross-section
+    pub diagonal: [Float; NUM_SPINS],
+
+    /// Real and imaginary parts of the off-diagonal element ρ₋₊ (pb), the
+    /// other one being its complex conjugate
+    pub off_diagonal: [Float; 2],
+
+    /// Standard deviations of the real and imaginary parts of ρ₋₊ (pb)
+    pub off_diagonal_stddev: [Float; 2],
+}
+//
+impl SpinDensityMatrix {
+    /// Display the spin density matrix, normalized to unit trace
+    pub fn print(&self) {
+        let trace = self.diagonal.iter().sum::<Float>();
+        if trace == 0. {
+            info!("Z0 spin density    : no anomalous contribution");
+            info!("");
+            return;
+        }
+        info!("Z0 spin density    : trace {trace:.6e} pb");
+        info!("rho(-,-)           : {:.6}", self.diagonal[SP_M] / trace);
+        info!("rho(+,+)           : {:.6}", self.diagonal[SP_P] / trace);
+        for (part, (value, stddev)) in ["Re", "Im"]
+            .into_iter()
+            .zip(self.off_diagonal.into_iter().zip(self.off_diagonal_stddev))
+        {
+            info!(
+                "{part} rho(-,+)        : {:+.4e} +/- {:.4e}",
+                value / trace,
+                stddev / abs(trace)
+            );
+        }
+        info!("");
+    }
+}
+
+/// Results of the simulation, recomputed for other values of the couplings
+#[derive(Clone, Copy, Debug)]
+pub struct ReweightedResults {
+    /// Total cross-section
+    pub sigma: Float,
+
+    /// Total variance
+    pub variance: Float,
+
+    /// Relative precision
+    pub prec: Float,
+
+    /// Statistical significance of the anomalous contribution with respect to
+    /// the Standard Model cross-section (pb-1/2)
+    pub significance: Float,
+
+    /// Statistical significance B+(pb-1/2) (???)
+    pub ss_p: Float,
+
+    /// Statistical significance B-(pb-1/2) (???)
+    pub ss_m: Float,
+}
+
+/// Layout of the JSON results file
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    /// How the results were produced
+    metadata: &'a Provenance,
+
+    /// Configuration of the simulation
+    configuration: &'a Configuration,
+
+    /// Results of the simulation
+    results: JsonResults,
+
+    /// Accumulated sums which the results derive from, so that the results of
+    /// several simulation runs can be merged (see the resmerge module)
+    sums: &'a AccumulatorCheckpoint,
+}
+
+/// Final results of the simulation, in a JSON-friendly layout
+#[derive(Serialize)]
+struct JsonResults {
+    /// Whether the simulation was interrupted before all of its events were
+    /// simulated
+    partial: bool,
+
+    /// Number of integrated events
+    selected_events: usize,
+
+    /// Total cross-section (pb)
+    sigma: Float,
+
+    /// Standard deviation of the total cross-section (pb)
+    sigma_stddev: Float,
+
+    /// Relative precision
+    prec: Float,
+
+    /// Estimated relative rounding error of naive summation (compensated for)
+    sigma_rounding_error: Float,
+
+    /// Total variance
+    variance: Float,
+
+    /// Beta minimum
+    beta_min: Float,
+
+    /// Statistical significance B+(pb-1/2)
+    ss_p: Float,
+
+    /// Incertitude associated with ss_p
+    inc_ss_p: Float,
+
+    /// Statistical significance B-(pb-1/2)
+    ss_m: Float,
+
+    /// Incertitude associated with ss_m
+    inc_ss_m: Float,
+
+    /// Cross-section for each spin (rows) and matrix element (columns)
+    spm2: [[Float; NUM_MAT_ELEMS]; NUM_SPINS],
+
+    /// Variance for each spin (rows) and matrix element (columns)
+    vars: [[Float; NUM_MAT_ELEMS]; NUM_SPINS],
+
+    /// Spin density matrix of the anomalous (Z⁰) contribution, if requested
+    spin_density: Option<SpinDensityMatrix>,
+
+    /// Statistics of each cut, in order of application, preceded by the
+    /// acceptance of the detector if it is simulated
+    cut_flow: Vec<JsonCut>,
+
+    /// Number of events whose matrix elements are not finite, which were left
+    /// out of the results
+    non_finite_events: usize,
+
+    /// Number of events of negative weight
+    negative_weight_events: usize,
+
+    /// Number of events recomputed with extended precision (if the stability
+    /// guard is enabled)
+    extended_precision_events: Option<usize>,
+
+    /// Angular asymmetries of the photons (if enabled)
+    asymmetries: Option<Asymmetries>,
+
+    /// Legendre moments of the photon angular distribution (if enabled)
+    legendre_moments: Option<LegendreMoments>,
+
+    /// Distribution of the event weights (if enabled)
+    weight_distribution: Option<WeightDistribution>,
+
+    /// Uncertainties of derived quantities from resampling (if configured)
+    resampling: Option<DerivedQuantities<ConfidenceInterval>>,
+
+    /// Systematic uncertainty band of the total cross-section (if configured)
+    systematics: Option<SystematicUncertainty>,
+}
+
+/// Statistics of an individual cut
+#[derive(Serialize)]
+struct JsonCut {
+    /// Cut which these statistics are about
+    cut: &'static str,
+
+    /// Number of generated events which were rejected by this cut
+    rejected: usize,
+
+    /// Fraction of the events which passed the previous cuts that passed it
+    efficiency: Float,
+}
+
+/// Summary of a simulation run, in the layout of the YAML summary file
+#[derive(Serialize)]
+struct YamlSummary<'a> {
+    /// Hash of the build and configuration entries which affect the results
+    config_hash: String,
+
+    /// Version of 3photons
+    version: &'a str,
+
+    /// Git commit which 3photons was built from
+    git_commit: &'a str,
+
+    /// Date and time at which the simulation started (RFC 3339), if known
+    start_time: Option<&'a str>,
+
+    /// Time taken by the simulation (seconds), if known
+    wall_time: Option<f64>,
+
+    /// Random number generator seed
+    seed: u64,
+
+    /// Number of generated events
+    num_events: usize,
+
+    /// Collision energy at center of mass (GeV)
+    e_total: Float,
+
+    /// Anomalous coupling 𝛽₊
+    beta_plus: Float,
+
+    /// Anomalous coupling 𝛽₋
+    beta_minus: Float,
+
+    /// Number of integrated events
+    selected_events: usize,
+
+    /// Total cross-section (pb)
+    sigma: Float,
+
+    /// Standard deviation of the total cross-section (pb)
+    sigma_stddev: Float,
+
+    /// Relative precision
+    prec: Float,
+
+    /// Beta minimum
+    beta_min: Float,
+
+    /// Statistical significance B+(pb-1/2)
+    ss_p: Float,
+
+    /// Incertitude associated with ss_p
+    inc_ss_p: Float,
+
+    /// Statistical significance B-(pb-1/2)
+    ss_m: Float,
+
+    /// Incertitude associated with ss_m
+    inc_ss_m: Float,
+}
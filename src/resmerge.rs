@@ -0,0 +1,154 @@
+//! Merging of the results of independent simulation runs, which only differ by
+//! their random number generator seed and number of events
+//!
+//! Each run must have written its results in JSON format, which includes the
+//! sums that the results derive from. These sums are combined as if a single
+//! run had simulated the events of all runs, then the merged results are
+//! derived from them.
+
+use crate::{
+    checkpoint,
+    config::Configuration,
+    error::ensure,
+    evgen::EventGenerator,
+    numeric::Float,
+    output::compression,
+    provenance::Provenance,
+    resacc::{AccumulatorCheckpoint, ResultsAccumulator},
+    resfin::FinalResults,
+    Error, Result,
+};
+use serde::Deserialize;
+use std::io::BufReader;
+
+/// Results of a simulation run, as read back from its JSON results file
+pub struct RunResults {
+    /// Path of the JSON results file
+    pub path: String,
+
+    /// Configuration of the run
+    pub cfg: Configuration,
+
+    /// Record of how the results were produced
+    pub provenance: Provenance,
+
+    /// Accumulated sums which the results derive from
+    sums: AccumulatorCheckpoint,
+}
+//
+impl RunResults {
+    /// Read the results of a simulation run from a JSON results file, which
+    /// may be compressed
+    pub fn load(path: &str) -> Result<Self> {
+        let JsonInput {
+            metadata,
+            configuration,
+            sums,
+        } = serde_json::from_reader(BufReader::new(compression::open(path)?))?;
+        let sums = sums.ok_or_else(|| {
+            Error::InvalidConfig(format!(
+                "{path} does not feature the sums that merging requires (it was \
+                written by an older version of 3photons)"
+            ))
+        })?;
+        Ok(Self {
+            path: path.to_owned(),
+            cfg: configuration,
+            provenance: metadata,
+            sums,
+        })
+    }
+}
+
+/// Check that simulation runs can be merged, and return the configuration of
+/// the merged results
+///
+/// Runs can be merged if their configurations only differ by the random number
+/// generator seed, the number of events and the entries which do not affect
+/// the results (e.g. output files), and if they used different seeds. The
+/// merged configuration is that of the first run, with the total number of
+/// events of all runs.
+///
+pub fn merged_configuration(runs: &[RunResults]) -> Result<Configuration> {
+    let (first, others) = runs.split_first().ok_or_else(|| {
+        Error::InvalidConfig("Please provide the results of at least one run".to_owned())
+    })?;
+
+    // Compare the configuration entries which affect the results
+    let fingerprint = |run: &RunResults| {
+        let mut cfg = run.cfg.clone();
+        cfg.seed = None;
+        cfg.num_events = 0;
+        checkpoint::fingerprint(&cfg)
+    };
+    let first_fingerprint = fingerprint(first);
+    for run in others {
+        ensure!(
+            fingerprint(run) == first_fingerprint,
+            InvalidConfig,
+            "The configuration of {} does not match that of {}, other than the seed and \
+            number of events",
+            run.path,
+            first.path
+        );
+    }
+
+    // Runs which used the same seed simulated the same events
+    for (idx, run) in runs.iter().enumerate() {
+        if let Some(previous) =
+            (runs[..idx].iter()).find(|previous| previous.provenance.seed == run.provenance.seed)
+        {
+            return Err(Error::InvalidConfig(format!(
+                "{} and {} were simulated with the same seed ({})",
+                previous.path, run.path, run.provenance.seed
+            )));
+        }
+    }
+
+    // Count the events of all runs
+    let mut cfg = first.cfg.clone();
+    cfg.num_events = (runs.iter())
+        .try_fold(0usize, |total, run| total.checked_add(run.cfg.num_events))
+        .ok_or_else(|| Error::InvalidConfig("Total number of events overflows".to_owned()))?;
+    Ok(cfg)
+}
+
+/// Merge the results of several simulation runs, given the configuration from
+/// merged_configuration()
+///
+/// Runs are integrated in order, as if their events had been simulated one
+/// after the other.
+///
+pub fn merge(cfg: &Configuration, runs: Vec<RunResults>) -> Result<FinalResults<'_>> {
+    let event_weight = EventGenerator::new(cfg.e_total)?.event_weight();
+    let mut merged: Option<ResultsAccumulator> = None;
+    for run in runs {
+        // Event weights are normalized by the number of events of their run,
+        // renormalize them to the total number of events
+        let mut sums = run.sums;
+        sums.scale_weights(run.cfg.num_events as Float / cfg.num_events as Float);
+        let mut accumulator = ResultsAccumulator::new(cfg, event_weight);
+        accumulator.restore(sums);
+        match &mut merged {
+            Some(merged) => merged.merge(accumulator),
+            None => merged = Some(accumulator),
+        }
+    }
+    Ok(merged
+        .expect("Runs should have been checked by merged_configuration()")
+        .finalize())
+}
+
+/// Parts of a JSON results file which merging uses
+#[derive(Deserialize)]
+struct JsonInput {
+    /// How the results were produced
+    metadata: Provenance,
+
+    /// Configuration of the simulation
+    configuration: Configuration,
+
+    /// Accumulated sums which the results derive from (missing from the
+    /// results files of older versions)
+    sums: Option<AccumulatorCheckpoint>,
+}
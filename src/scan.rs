@@ -0,0 +1,83 @@
+//! Collision energy scan mode, which runs the simulation over a grid of
+//! collision energies and tabulates the resulting cross-sections
+
+use crate::cli::ScanArgs;
+use anyhow::{ensure, Context, Result};
+use std::io::Write;
+use trois_photons::{
+    config::Configuration, numeric::GridRange, output::compression::OutputFile,
+    setup_event_generation, simulate,
+};
+
+/// Run the simulation at each collision energy of the scan, and output a table
+/// of the resulting cross-sections
+///
+/// All simulations use the same random number generator seed, so the results
+/// at neighbouring energies are correlated and σ(E) varies smoothly.
+///
+pub fn run(args: &ScanArgs) -> Result<()> {
+    // Check the scan parameters
+    let energies = GridRange {
+        from: args.from,
+        to: args.to,
+        step: args.step,
+    };
+    ensure!(energies.from > 0., "The collision energy must be positive");
+    energies.check("collision energy")?;
+
+    // Load the configuration that is shared by all simulations
+    let mut cfg = Configuration::load_with_overlay(&args.args.config, &args.args.set, |cfg| {
+        args.args.apply_overrides(cfg)
+    })
+    .context("Failed to load the configuration")?;
+    ensure!(
+        cfg.event_output.is_none()
+            && cfg.json_output.is_none()
+            && cfg.yoda_output.is_none()
+            && cfg.checkpoint.is_none(),
+        "Event output, JSON and YODA output and checkpointing are not supported in scan mode"
+    );
+    ensure!(
+        cfg.coupling_grid.is_none() && cfg.systematics.is_none(),
+        "Anomalous coupling grids and systematics are not supported in scan mode"
+    );
+    ensure!(
+        !cfg.plot && cfg.distributions.is_empty(),
+        "Histograms are not supported in scan mode"
+    );
+
+    // Prepare to write the table of cross-sections
+    let mut table = OutputFile::create(&args.output)?;
+    let header = format!(
+        "{:>12} {:>12} {:>20} {:>20} {:>20}",
+        "E_tot (GeV)", "Selected", "Sigma (pb)", "Std. dev. (pb)", "Rel. precision"
+    );
+    println!();
+    println!("{header}");
+    writeln!(table, "#{header}")?;
+
+    // Run the simulation at each collision energy
+    for e_total in energies.values() {
+        cfg.e_total = e_total;
+        let (process, evgen) = setup_event_generation(&cfg)
+            .with_context(|| format!("Failed to set up event generation at {} GeV", cfg.e_total))?;
+        let res = simulate(&cfg, &*process, &evgen, None, None, None)
+            .with_context(|| format!("Failed to run the simulation at {} GeV", cfg.e_total))?;
+        let row = format!(
+            "{:>12} {:>12} {:>20.12e} {:>20.12e} {:>20.12e}",
+            cfg.e_total,
+            res.selected_events,
+            res.sigma,
+            res.sigma * res.prec,
+            res.prec
+        );
+        println!("{row}");
+        writeln!(table, " {row}")?;
+        if let Some(path) = &cfg.summary_yaml {
+            res.append_summary_yaml(path)
+                .context("Failed to write the YAML summary")?;
+        }
+    }
+    table.finish()?;
+    Ok(())
+}
@@ -0,0 +1,107 @@
+//! Distribution of the simulation across the processes of an MPI job
+//!
+//! Each process simulates a contiguous share of the event batches, then the
+//! main process gathers the accumulated results of the others and integrates
+//! them in process order, so that batches are still integrated in order.
+
+use crate::{resacc::ResultsAccumulator, scheduling::accumulate};
+use mpi::{topology::SimpleCommunicator, traits::*, Rank};
+use std::ops::Range;
+
+/// Rank of the process which merges the results of the other processes
+const MAIN_RANK: Rank = 0;
+
+/// Processes of an MPI job which share the simulation work
+pub struct Processes {
+    /// Communicator spanning all processes of the job
+    world: SimpleCommunicator,
+}
+//
+impl Processes {
+    /// Join the other processes of the MPI job, if MPI was initialized and
+    /// the job has more than one process
+    pub fn join() -> Option<Self> {
+        if !mpi::is_initialized() || mpi::is_finalized() {
+            return None;
+        }
+        let world = SimpleCommunicator::world();
+        (world.size() > 1).then_some(Self { world })
+    }
+
+    /// Number of processes in the MPI job
+    pub fn count(&self) -> usize {
+        self.world.size() as usize
+    }
+
+    /// Truth that this is the main process, which outputs the results
+    pub fn is_main(&self) -> bool {
+        self.world.rank() == MAIN_RANK
+    }
+
+    /// Share of a range of event batches that this process should simulate
+    pub fn share(&self, batches: Range<usize>) -> Range<usize> {
+        let num_batches = batches.len();
+        let num_processes = self.world.size() as usize;
+        let rank = self.world.rank() as usize;
+        let boundary = |rank: usize| batches.start + num_batches * rank / num_processes;
+        boundary(rank)..boundary(rank + 1)
+    }
+
+    /// Integrate the results of all processes in process order, and give
+    /// every process a copy of the merged results
+    ///
+    /// The accumulator of a process is None if its share of the batches was
+    /// empty. The new_accumulator callback creates an empty accumulator, into
+    /// which results from other processes can be restored.
+    ///
+    /// Only the accumulated results are sent across processes, so the merged
+    /// per-phase timings (if enabled) only cover the main process.
+    ///
+    pub fn merge_results<'cfg>(
+        &self,
+        mut accumulator: Option<ResultsAccumulator<'cfg>>,
+        new_accumulator: impl Fn() -> ResultsAccumulator<'cfg>,
+    ) -> ResultsAccumulator<'cfg> {
+        let main_process = self.world.process_at_rank(MAIN_RANK);
+        let serialize = |accumulator: &ResultsAccumulator| {
+            bincode::serialize(&accumulator.checkpoint()).expect("Results should be serializable")
+        };
+        let deserialize = |bytes: &[u8]| {
+            let mut accumulator = new_accumulator();
+            accumulator.restore(
+                bincode::deserialize(bytes).expect("Results of other processes should be valid"),
+            );
+            accumulator
+        };
+
+        // Gather the results of the other processes in the main process
+        let mut merged_bytes = if self.is_main() {
+            for rank in (MAIN_RANK + 1)..self.world.size() {
+                let (bytes, _status) = self.world.process_at_rank(rank).receive_vec::<u8>();
+                if !bytes.is_empty() {
+                    accumulate(&mut accumulator, deserialize(&bytes));
+                }
+            }
+            serialize(
+                accumulator
+                    .as_ref()
+                    .expect("Must simulate at least one batch"),
+            )
+        } else {
+            let bytes = accumulator.as_ref().map_or_else(Vec::new, serialize);
+            main_process.send(&bytes[..]);
+            Vec::new()
+        };
+
+        // Send the merged results back to the other processes
+        let mut merged_len = merged_bytes.len() as u64;
+        main_process.broadcast_into(&mut merged_len);
+        merged_bytes.resize(merged_len as usize, 0);
+        main_process.broadcast_into(&mut merged_bytes[..]);
+        if self.is_main() {
+            accumulator.expect("Must simulate at least one batch")
+        } else {
+            deserialize(&merged_bytes)
+        }
+    }
+}
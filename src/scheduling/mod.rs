@@ -1,12 +1,29 @@
 //! This module takes care of scheduling the simulation work, encapsulating use
 //! of multiple threads and anything else that will come in the future
 
+#[cfg(feature = "mpi")]
+mod distributed;
 #[cfg(feature = "multi-threading")]
 mod multi_threading;
 #[cfg(not(feature = "multi-threading"))]
 mod sequential;
 
-use crate::{random::RandomGenerator, resacc::ResultsAccumulator, resfin::FinalResults};
+#[cfg(feature = "multi-threading")]
+use self::multi_threading as backend;
+#[cfg(not(feature = "multi-threading"))]
+use self::sequential as backend;
+#[cfg(feature = "mpi")]
+use crate::error::ensure;
+use crate::{
+    checkpoint::{Checkpoint, Checkpointer},
+    config::Configuration,
+    evgen::EventGenerator,
+    interrupt::{self, PartialResults},
+    random::RandomGenerator,
+    resacc::ResultsAccumulator,
+    resfin::FinalResults,
+    Error, Result,
+};
 
 /// Size of the simulated event batches
 ///
@@ -18,42 +35,181 @@ use crate::{random::RandomGenerator, resacc::ResultsAccumulator, resfin::FinalRe
 /// synchronization overhead changes. But the rate of such change is expected to
 /// be low enough for hard-coding of this constant to be reasonable.
 ///
-const EVENT_BATCH_SIZE: usize = 10_000;
+pub(crate) const EVENT_BATCH_SIZE: usize = 10_000;
+
+/// Number of processes which share the simulation work, which is only greater
+/// than one if the simulation is distributed across the processes of an MPI job
+pub fn num_processes() -> usize {
+    #[cfg(feature = "mpi")]
+    if let Some(processes) = distributed::Processes::join() {
+        return processes.count();
+    }
+    1
+}
+
+/// Number of threads which simulate events
+pub fn num_threads(cfg: &Configuration) -> usize {
+    backend::num_threads(cfg)
+}
+
+/// Truth that this process should output the simulation results
+///
+/// This is always the case, unless the simulation is distributed across the
+/// processes of an MPI job, in which case only the first process should.
+///
+pub fn is_main_process() -> bool {
+    #[cfg(feature = "mpi")]
+    if let Some(processes) = distributed::Processes::join() {
+        return processes.is_main();
+    }
+    true
+}
 
 /// Run the simulation in the manner that was configured at build time.
 ///
-/// Takes as parameters the total number of events to be simulated, and a
-/// simulation kernel that simulates a certain number of events given an initial
-/// random number generator state.
+/// Takes as parameters the configuration (which notably sets the number of
+/// events to be simulated and the random number generator), the checkpoint to
+/// resume from and the checkpointing mechanism (if any), the event generator
+/// (to keep track of the random numbers that it draws), and a simulation
+/// kernel that simulates a certain number of events given an initial random
+/// number generator state, or fails (e.g. if accepted events cannot be
+/// written).
+///
+/// If the mpi feature is enabled and MPI was initialized, the events are
+/// shared between the processes of the MPI job, and every process gets the
+/// merged results.
 ///
 /// Returns the finalized simulation results
 ///
-pub fn run_simulation<'cfg>(
-    num_events: usize,
-    simulate_events: impl Send + Sync + Fn(usize, &mut RandomGenerator) -> ResultsAccumulator<'cfg>,
-) -> FinalResults<'cfg> {
+pub fn run_simulation<'cfg, R: RandomGenerator>(
+    cfg: &'cfg Configuration,
+    resume: Option<Checkpoint>,
+    mut checkpointer: Option<Checkpointer>,
+    evgen: &EventGenerator,
+    simulate_events: impl Send + Sync + Fn(usize, &mut R) -> Result<ResultsAccumulator<'cfg>>,
+) -> Result<FinalResults<'cfg>> {
     // Check that the user is being reasonable (should have already been checked
     // at configuration time, but bugs can happen...)
+    let num_events = cfg.num_events;
     assert!(num_events > 0, "Must simulate at least one event");
 
-    // Initialize the random number generator
-    let rng = RandomGenerator::new();
+    // We know in advance how many batches of event we will process
+    let num_batches = num_events.div_ceil(EVENT_BATCH_SIZE);
+    let batch_size =
+        |batch_id: usize| (num_events - batch_id * EVENT_BATCH_SIZE).min(EVENT_BATCH_SIZE);
 
-    // Integrate simulation results...
-    let results_builder = {
-        // ...in sequential mode
-        #[cfg(not(feature = "multi-threading"))]
-        {
-            sequential::run_simulation_impl(num_events, rng, simulate_events)
+    // Initialize the random number generator and the simulation results,
+    // either from scratch or from a checkpoint
+    #[cfg(feature = "mpi")]
+    let resumed = resume.is_some();
+    let (mut rng, mut accumulator, mut batches_done) = match resume {
+        Some(checkpoint) => {
+            let mut rng = checkpoint.rng::<R>()?;
+            let mut accumulator = simulate_events(0, &mut rng)?;
+            accumulator.restore(checkpoint.results);
+            (rng, Some(accumulator), checkpoint.batches_done)
         }
+        None => (R::from_config(cfg), None, 0),
+    };
+    assert!(
+        batches_done < num_batches,
+        "Checkpoint should match configuration"
+    );
+
+    // If the simulation is distributed across several processes, only simulate
+    // this process' share of the batches
+    #[cfg_attr(not(feature = "mpi"), allow(unused_mut))]
+    let mut batches_end = num_batches;
+    #[cfg(feature = "mpi")]
+    let processes = distributed::Processes::join();
+    #[cfg(feature = "mpi")]
+    if let Some(processes) = &processes {
+        ensure!(
+            checkpointer.is_none() && !resumed,
+            InvalidConfig,
+            "Checkpoints are not supported when the simulation is distributed across processes"
+        );
+        ensure!(
+            cfg.event_output.is_none(),
+            InvalidConfig,
+            "Events cannot be written when the simulation is distributed across processes"
+        );
+        let share = processes.share(batches_done..num_batches);
+        backend::skip_batches((batches_done..share.start).map(batch_size), &mut rng, evgen);
+        (batches_done, batches_end) = (share.start, share.end);
+    }
 
-        // ...in multi-threaded mode
-        #[cfg(feature = "multi-threading")]
-        {
-            multi_threading::run_simulation_impl(num_events, rng, simulate_events)
+    // Simulate the remaining batches of events, in rounds between which
+    // checkpoints can be taken if checkpointing is enabled (and the simulation
+    // can stop if interruptions are enabled), using the configured threads (which do not affect the results, since batches are
+    // integrated in order whatever thread simulated them)
+    let accumulator = backend::with_threads(cfg, || -> Result<_> {
+        let round_size = if checkpointer.is_some() || interrupt::is_enabled() {
+            backend::checkpoint_round_size()
+        } else {
+            batches_end
+        };
+        while batches_done < batches_end {
+            let round_end = (batches_done + round_size).min(batches_end);
+            let batch_sizes = (batches_done..round_end).map(batch_size);
+            let round_accumulator = backend::simulate_batches(
+                batch_sizes,
+                &mut rng,
+                accumulator,
+                evgen,
+                &simulate_events,
+            )?;
+            batches_done = round_end;
+            if batches_done < batches_end && interrupt::is_requested() {
+                // If asked to, stop here, saving a checkpoint which the
+                // simulation can be resumed from
+                if let Some(checkpointer) = &mut checkpointer {
+                    checkpointer.save(batches_done, &rng, &round_accumulator)?;
+                }
+                let events_done = (0..batches_done).map(batch_size).sum();
+                return Err(Error::Interrupted(Box::new(PartialResults::new(
+                    events_done,
+                    num_events,
+                    evgen.event_weight(),
+                    round_accumulator.checkpoint(),
+                ))));
+            }
+            if let Some(checkpointer) = &mut checkpointer {
+                if batches_done < num_batches && checkpointer.is_due() {
+                    checkpointer.save(batches_done, &rng, &round_accumulator)?;
+                }
+            }
+            accumulator = Some(round_accumulator);
         }
-    };
+        Ok(accumulator)
+    })??;
+
+    // Merge the results of all processes, if the simulation is distributed
+    #[cfg(feature = "mpi")]
+    if let Some(processes) = &processes {
+        let new_accumulator = || ResultsAccumulator::new(cfg, evgen.event_weight());
+        return Ok(processes
+            .merge_results(accumulator, new_accumulator)
+            .finalize());
+    }
 
     // Finalize the results
-    results_builder.finalize()
+    Ok(accumulator
+        .expect("Must simulate at least one batch")
+        .finalize())
+}
+
+/// Integrate the results of a batch of events into those of previous batches
+///
+/// Batch results must be integrated in batch order for the simulation results
+/// to be reproducible.
+///
+fn accumulate<'cfg>(
+    accumulator: &mut Option<ResultsAccumulator<'cfg>>,
+    batch_result: ResultsAccumulator<'cfg>,
+) {
+    match accumulator {
+        Some(accumulator) => accumulator.merge(batch_result),
+        None => *accumulator = Some(batch_result),
+    }
 }
@@ -1,59 +1,125 @@
 //! Multi-threaded back-end of the simulation
 
-#[cfg(feature = "faster-threading")]
-use std::sync::atomic::{AtomicBool, Ordering};
+use crate::{
+    config::Configuration,
+    evgen::EventGenerator,
+    random::RandomGenerator,
+    resacc::ResultsAccumulator,
+    scheduling::{accumulate, EVENT_BATCH_SIZE},
+    Result,
+};
+use log::{debug, warn};
+use rayon::ThreadPoolBuilder;
+use std::{io, sync::Mutex};
+
+/// Run some simulation work in the configured threads
+///
+/// If neither the number of threads nor their pinning to CPU cores is
+/// configured, the work runs in rayon's global thread pool, which has one
+/// thread per CPU core. Otherwise, a dedicated thread pool is set up.
+///
+pub fn with_threads<T: Send>(cfg: &Configuration, work: impl FnOnce() -> T + Send) -> Result<T> {
+    // Use the global thread pool by default
+    if cfg.threads.is_none() && !cfg.pin_threads {
+        return Ok(work());
+    }
 
-use crate::{random::RandomGenerator, resacc::ResultsAccumulator, scheduling::EVENT_BATCH_SIZE};
+    // Set up a dedicated thread pool otherwise
+    let mut builder = ThreadPoolBuilder::new().thread_name(|index| format!("simulation-{index}"));
+    if let Some(threads) = cfg.threads {
+        builder = builder.num_threads(threads);
+    }
+    if cfg.pin_threads {
+        let core_ids = core_affinity::get_core_ids()
+            .filter(|core_ids| !core_ids.is_empty())
+            .ok_or_else(|| io::Error::other("Failed to enumerate the CPU cores"))?;
+        builder = builder.start_handler(move |index| {
+            let core_id = core_ids[index % core_ids.len()];
+            if core_affinity::set_for_current(core_id) {
+                debug!(
+                    "Pinned simulation thread {index} to CPU core {}",
+                    core_id.id
+                );
+            } else {
+                warn!("Failed to pin simulation thread {index} to a CPU core");
+            }
+        });
+    }
+    let pool = builder.build().map_err(io::Error::other)?;
+    debug!(
+        "Simulating events using {} threads",
+        pool.current_num_threads()
+    );
+    Ok(pool.install(work))
+}
 
-use std::sync::Mutex;
+/// Number of threads which simulate events
+pub fn num_threads(cfg: &Configuration) -> usize {
+    cfg.threads.unwrap_or_else(rayon::current_num_threads)
+}
 
-/// Simulate events in multi-threaded mode
+/// Number of event batches to be simulated between two checkpoint opportunities
 ///
-/// Takes the random number generator state as input, and does not finalize
-/// the output results, so should be readily amenable to extra layers of
-/// parallelization (such as distribution across multiple compute nodes).
+/// Every thread must wait for the others at the end of a round, so rounds must
+/// be long enough for the resulting load imbalance to be negligible.
 ///
-pub fn run_simulation_impl<'cfg>(
-    mut num_events: usize,
-    mut rng: RandomGenerator,
-    simulate_events: impl Send + Sync + Fn(usize, &mut RandomGenerator) -> ResultsAccumulator<'cfg>,
-) -> ResultsAccumulator<'cfg> {
-    // Some double-checking cannot hurt...
-    assert!(num_events > 0, "Must simulate at least one event");
-
-    // We know in advance how many batches of event we will process
-    let num_batches = num_events / EVENT_BATCH_SIZE + (num_events % EVENT_BATCH_SIZE != 0) as usize;
+pub fn checkpoint_round_size() -> usize {
+    8 * rayon::current_num_threads()
+}
 
-    // The results of parallel tasks will be aggregated...
-    let accumulator = {
-        // ...in a way that is optimized for numerical reproduciblity
+/// Advance the random number generator past batches of events which are
+/// simulated by other processes, as simulate_batches() would
+#[cfg(feature = "mpi")]
+#[cfg_attr(feature = "faster-threading", allow(unused_variables))]
+pub fn skip_batches<R: RandomGenerator>(
+    batch_sizes: impl Iterator<Item = usize>,
+    rng: &mut R,
+    evgen: &EventGenerator,
+) {
+    for batch_size in batch_sizes {
         #[cfg(not(feature = "faster-threading"))]
-        {
-            ReproducibleAccumulator::new(num_batches)
-        }
-
-        // ...in a way that is optimized for computational performance
+        evgen.simulate_event_batch(rng, batch_size);
         #[cfg(feature = "faster-threading")]
-        {
-            FastAccumulator::new(num_batches)
-        }
-    };
+        rng.jump();
+    }
+}
+
+/// Simulate batches of events in multi-threaded mode, integrating their
+/// results into those of previous batches (if any)
+///
+/// Takes the random number generator state as input, and does not finalize
+/// the output results, so should be readily amenable to extra layers of
+/// parallelization (such as distribution across multiple compute nodes).
+///
+/// If some batches fail, the error of the first one (in batch order) is
+/// returned once all batches are done.
+///
+#[cfg_attr(feature = "faster-threading", allow(unused_variables))]
+pub fn simulate_batches<'cfg, R: RandomGenerator>(
+    batch_sizes: impl ExactSizeIterator<Item = usize> + Send,
+    rng: &mut R,
+    accumulator: Option<ResultsAccumulator<'cfg>>,
+    evgen: &EventGenerator,
+    simulate_events: &(impl Sync + Fn(usize, &mut R) -> Result<ResultsAccumulator<'cfg>>),
+) -> Result<ResultsAccumulator<'cfg>> {
+    // The results of parallel tasks will be aggregated in batch order, so that
+    // the outcome does not depend on the number of threads or on the order in
+    // which tasks complete.
+    let batch_results = ReproducibleAccumulator::new(batch_sizes.len());
 
     // This function is a synchronization scope: it will only return
     // once all inner tasks have been executed
     rayon::scope(|scope| {
         // For each requested batch of events...
-        for batch_id in 0..num_batches {
-            let batch_size = ::std::cmp::min(num_events, EVENT_BATCH_SIZE);
-            num_events -= batch_size;
+        for (batch_id, batch_size) in batch_sizes.enumerate() {
+            debug_assert!(batch_size <= EVENT_BATCH_SIZE);
 
             // Spawn a task which is responsible for simulating them
             let mut task_rng = rng.clone();
-            let accumulator_ref = &accumulator;
-            let simulate_events_ref = &simulate_events;
+            let batch_results_ref = &batch_results;
             scope.spawn(move |_| {
-                let result = simulate_events_ref(batch_size, &mut task_rng);
-                accumulator_ref.set_task_result(batch_id, result);
+                let result = simulate_events(batch_size, &mut task_rng);
+                batch_results_ref.set_task_result(batch_id, result);
             });
 
             // By default, we rigorously simulate the impact of each
@@ -61,27 +127,28 @@ pub fn run_simulation_impl<'cfg>(
             // results reproducible, but slows down the scheduling
             // thread and may thus reduce performance and scalability.
             #[cfg(not(feature = "faster-threading"))]
-            crate::evgen::EventGenerator::simulate_event_batch(&mut rng, batch_size);
+            evgen.simulate_event_batch(rng, batch_size);
 
-            // In non-reproducible mode, we instead ask the RNG to
-            // switch to a wildly different state as quickly as it can.
+            // In faster mode, we instead ask the RNG to switch to a wildly
+            // different state as quickly as it can. This gives each batch an
+            // independent random number stream which only depends on the
+            // batch index, so results remain reproducible from one run to the
+            // next, but differ from those of sequential runs.
             #[cfg(feature = "faster-threading")]
             rng.jump();
         }
     });
 
-    // Extract the results from the accumulator
-    accumulator.get_merged_result()
+    // Integrate the results of the batches into the previous ones
+    batch_results.merge_into(accumulator)
 }
 
 /// Reproducibility-optimized results accumulation mechanism
-#[cfg(not(feature = "faster-threading"))]
 struct ReproducibleAccumulator<'cfg> {
     /// Storage for the intermediary simulation results of parallel tasks
-    results: Box<[Mutex<Option<ResultsAccumulator<'cfg>>>]>,
+    results: Box<[Mutex<Option<Result<ResultsAccumulator<'cfg>>>>]>,
 }
 //
-#[cfg(not(feature = "faster-threading"))]
 impl<'cfg> ReproducibleAccumulator<'cfg> {
     /// Set up results storage for N parallel tasks
     fn new(num_tasks: usize) -> Self {
@@ -95,7 +162,7 @@ impl<'cfg> ReproducibleAccumulator<'cfg> {
     }
 
     /// Integrate the results of the n-th simulation task
-    fn set_task_result(&self, task_id: usize, result: ResultsAccumulator<'cfg>) {
+    fn set_task_result(&self, task_id: usize, result: Result<ResultsAccumulator<'cfg>>) {
         let mut lock = self.results[task_id]
             .lock()
             .expect("Mutex data should be valid");
@@ -103,88 +170,19 @@ impl<'cfg> ReproducibleAccumulator<'cfg> {
         *lock = Some(result);
     }
 
-    /// Aggregate the results in a reproducible fashion
-    fn get_merged_result(self) -> ResultsAccumulator<'cfg> {
-        // Start iterating over the task results
-        let mut results_iter = self.results.into_vec().into_iter().map(|entry| {
-            entry
+    /// Integrate the task results, in order, into those of previous tasks,
+    /// or return the error of the first task which failed
+    fn merge_into(
+        self,
+        mut accumulator: Option<ResultsAccumulator<'cfg>>,
+    ) -> Result<ResultsAccumulator<'cfg>> {
+        for entry in self.results.into_vec() {
+            let result = entry
                 .into_inner()
                 .expect("Mutex data should be valid")
-                .expect("Result should be ready")
-        });
-
-        // Initialize results storage with the result of the first task
-        let first_result = results_iter
-            .next()
-            .expect("There should be at least one task");
-
-        // Merge the results of the other tasks
-        results_iter.fold(first_result, |mut r1, r2| {
-            r1.merge(r2);
-            r1
-        })
-    }
-}
-
-/// Speed-optimized results accumulation mechanism
-#[cfg(feature = "faster-threading")]
-struct FastAccumulator<'cfg> {
-    /// Storage location in which results will be merged out of order
-    merged_result: Mutex<Option<ResultsAccumulator<'cfg>>>,
-
-    /// Truth that each task has reported its results
-    task_finished: Box<[AtomicBool]>,
-}
-//
-#[cfg(feature = "faster-threading")]
-impl<'cfg> FastAccumulator<'cfg> {
-    /// Set up results storage for N parallel tasks
-    fn new(num_tasks: usize) -> Self {
-        assert!(num_tasks > 0, "There should be at least one task");
-        Self {
-            merged_result: Mutex::new(None),
-            task_finished: (0..num_tasks)
-                .map(|_| AtomicBool::new(false))
-                .collect::<Vec<_>>()
-                .into_boxed_slice(),
-        }
-    }
-
-    /// Integrate the results of the n-th simulation task
-    #[allow(unknown_lints, clippy::significant_drop_in_scrutinee)]
-    fn set_task_result(&self, task_id: usize, result: ResultsAccumulator<'cfg>) {
-        // Initialize the accumulator or merge the task result into it
-        match *self
-            .merged_result
-            .lock()
-            .expect("Mutex data should be valid")
-        {
-            // If we are the first, initialize the accumulator
-            ref mut storage @ None => *storage = Some(result),
-
-            // Otherwise, merge our results with those that are already here
-            Some(ref mut accumulator) => accumulator.merge(result),
-        }
-
-        // Remember that this task has completed its work
-        let was_finished = self.task_finished[task_id].swap(true, Ordering::Relaxed);
-        assert!(!was_finished, "Tasks should not set their result twice");
-    }
-
-    /// Aggregate the results in a reproducible fashion
-    fn get_merged_result(self) -> ResultsAccumulator<'cfg> {
-        // Check that all tasks have completed their work
-        for ready in self.task_finished.into_vec().into_iter() {
-            assert!(
-                ready.load(Ordering::Relaxed),
-                "All tasks should have completed their work"
-            );
+                .expect("Result should be ready");
+            accumulate(&mut accumulator, result?);
         }
-
-        // Collect the merged result
-        self.merged_result
-            .into_inner()
-            .expect("Mutex data should be valid")
-            .expect("Result should be ready")
+        Ok(accumulator.expect("There should be at least one task"))
     }
 }
@@ -1,8 +1,45 @@
 //! Sequential back-end of the simulation
 
-use crate::{random::RandomGenerator, resacc::ResultsAccumulator, scheduling::EVENT_BATCH_SIZE};
+use crate::{
+    config::Configuration, evgen::EventGenerator, random::RandomGenerator,
+    resacc::ResultsAccumulator, scheduling::accumulate, Result,
+};
+use log::warn;
 
-/// Simulate events in sequential mode
+/// Run some simulation work on the current thread, warning about any thread
+/// configuration since it cannot be honored
+pub fn with_threads<T>(cfg: &Configuration, work: impl FnOnce() -> T) -> Result<T> {
+    if cfg.threads.is_some_and(|threads| threads > 1) || cfg.pin_threads {
+        warn!("Ignoring the thread configuration, since this build is not multi-threaded");
+    }
+    Ok(work())
+}
+
+/// Number of threads which simulate events
+pub fn num_threads(_cfg: &Configuration) -> usize {
+    1
+}
+
+/// Number of event batches to be simulated between two checkpoint opportunities
+pub fn checkpoint_round_size() -> usize {
+    1
+}
+
+/// Advance the random number generator past batches of events which are
+/// simulated by other processes
+#[cfg(feature = "mpi")]
+pub fn skip_batches<R: RandomGenerator>(
+    batch_sizes: impl Iterator<Item = usize>,
+    rng: &mut R,
+    evgen: &EventGenerator,
+) {
+    for batch_size in batch_sizes {
+        evgen.simulate_event_batch(rng, batch_size);
+    }
+}
+
+/// Simulate batches of events in sequential mode, integrating their results
+/// into those of previous batches (if any)
 ///
 /// We use batched logic even in sequential mode, in order to achieve
 /// reproducibility with respect to multi-threaded runs.
@@ -12,29 +49,18 @@ use crate::{random::RandomGenerator, resacc::ResultsAccumulator, scheduling::EVE
 /// larger than the accumulated values and numerical accumulation errors
 /// will start to blow up.
 ///
-pub fn run_simulation_impl<'cfg>(
-    mut num_events: usize,
-    mut rng: RandomGenerator,
-    simulate_events: impl Send + Sync + Fn(usize, &mut RandomGenerator) -> ResultsAccumulator<'cfg>,
-) -> ResultsAccumulator<'cfg> {
-    // Some double-checking cannot hurt...
-    assert!(num_events > 0, "Must simulate at least one event");
-
-    // Initialize the accumulator with the first batch of events
-    let first_batch_size = ::std::cmp::min(EVENT_BATCH_SIZE, num_events);
-    num_events -= first_batch_size;
-    let mut accumulator = simulate_events(first_batch_size, &mut rng);
-
-    // Simulate and integrate complete batches of events (if any)
-    let num_full_batches = num_events / EVENT_BATCH_SIZE;
-    for _ in 0..num_full_batches {
-        accumulator.merge(simulate_events(EVENT_BATCH_SIZE, &mut rng));
+pub fn simulate_batches<'cfg, R: RandomGenerator>(
+    batch_sizes: impl Iterator<Item = usize>,
+    rng: &mut R,
+    mut accumulator: Option<ResultsAccumulator<'cfg>>,
+    _evgen: &EventGenerator,
+    simulate_events: &impl Fn(usize, &mut R) -> Result<ResultsAccumulator<'cfg>>,
+) -> Result<ResultsAccumulator<'cfg>> {
+    // Simulate and integrate the batches of events in order
+    for batch_size in batch_sizes {
+        accumulate(&mut accumulator, simulate_events(batch_size, rng)?);
     }
-    num_events %= EVENT_BATCH_SIZE;
-
-    // Integrate the remaining events
-    accumulator.merge(simulate_events(num_events, &mut rng));
 
-    // Return the final accumulated results
-    accumulator
+    // Return the accumulated results
+    Ok(accumulator.expect("Must simulate at least one batch"))
 }
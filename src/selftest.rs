@@ -0,0 +1,93 @@
+//! Self-test mode, which checks the gauge invariance of the standard
+//! amplitudes and the algebraic identities of the spinor products over a
+//! sample of generated events
+
+use crate::cli::SelfTestArgs;
+use anyhow::{ensure, Context, Result};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use trois_photons::{
+    config::Configuration,
+    matelems::GaugeInvarianceCheck,
+    numeric::Float,
+    random::{RandGenerator, RandomGenerator},
+    setup_event_generation,
+    spinor::SpinorIdentityCheck,
+};
+
+/// Check the Ward identity, the consistency of the spinor product formulas and
+/// the identities of the spinor products over events which pass the cuts,
+/// failing if any check exceeds the tolerance
+pub fn run(args: &SelfTestArgs) -> Result<()> {
+    // Set up event generation as for a simulation
+    let cfg = Configuration::load_with_overlay(&args.args.config, &args.args.set, |cfg| {
+        args.args.apply_overrides(cfg)
+    })
+    .context("Failed to load the configuration")?;
+    let (_process, evgen) =
+        setup_event_generation(&cfg).context("Failed to set up event generation")?;
+    let seed = cfg.rng.effective_seed(cfg.seed);
+    let mut rng = RandGenerator::<Xoshiro256PlusPlus>::with_seed(seed);
+
+    // Check the events that pass the cuts
+    let mut checked = 0;
+    let mut ward_violation: Float = 0.;
+    let (mut min_ratio, mut max_ratio) = (Float::INFINITY, 0. as Float);
+    let mut identities: [(&str, Float); 4] = [
+        ("antisymmetry", 0.),
+        ("invariant masses", 0.),
+        ("Schouten identity", 0.),
+        ("momentum conservation", 0.),
+    ];
+    while checked < args.events {
+        let event = evgen.generate(&mut rng);
+        if cfg.event_cut.apply(&event).is_err() {
+            continue;
+        }
+        let check = GaugeInvarianceCheck::new(&event);
+        ward_violation = ward_violation.max(check.ward_violation);
+        min_ratio = min_ratio.min(check.spinor_ratio);
+        max_ratio = max_ratio.max(check.spinor_ratio);
+        let check = SpinorIdentityCheck::new(&event);
+        let deviations = [
+            check.antisymmetry,
+            check.invariant_mass,
+            check.schouten,
+            check.momentum_conservation,
+        ];
+        for ((_, max_deviation), deviation) in identities.iter_mut().zip(deviations) {
+            *max_deviation = max_deviation.max(deviation);
+        }
+        checked += 1;
+    }
+    let ratio_spread = (max_ratio - min_ratio) / min_ratio;
+
+    // Report the outcome
+    println!();
+    println!("Checked events     : {checked}");
+    println!("Ward identity      : {ward_violation:.3e}");
+    println!("Spinor products    : {ratio_spread:.3e}");
+    println!("Spinor identities  :");
+    for (name, deviation) in identities {
+        println!("  {name:<22}: {deviation:.3e}");
+    }
+    ensure!(
+        ward_violation <= args.tolerance,
+        "The Ward identity is violated beyond the tolerance of {}",
+        args.tolerance
+    );
+    ensure!(
+        ratio_spread <= args.tolerance,
+        "The spinor product formulas disagree with the Feynman diagrams beyond the tolerance \
+         of {}",
+        args.tolerance
+    );
+    for (name, deviation) in identities {
+        ensure!(
+            deviation <= args.tolerance,
+            "The spinor products violate {name} beyond the tolerance of {}",
+            args.tolerance
+        );
+    }
+    println!("Self-test passed");
+    Ok(())
+}
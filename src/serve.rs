@@ -0,0 +1,114 @@
+//! Matrix element service mode, which evaluates the matrix elements of phase
+//! space points sent on the standard input, so that other generators can
+//! reuse those of 3photons (e.g. for reweighting)
+//!
+//! The protocol is line-based JSON. Each request line gives the 4-momenta of
+//! the outgoing photons in the frame of event records, where the incoming
+//! electron travels along +Z, as (Px, Py, Pz, E) rows:
+//!
+//! ```text
+//! {"momenta": [[px1, py1, pz1, e1], [px2, py2, pz2, e2], [px3, py3, pz3, e3]]}
+//! ```
+//!
+//! Photons must have positive energies and be in the center-of-mass frame,
+//! whose collision energy is the sum of their energies. Each request is answered by a line giving the five
+//! squared matrix element contributions (A, B+, B-, Re(M+M-*) and Im(M+M-*))
+//! for the simulated electron helicity, summed over the photon helicities, or
+//! an error message if the request is invalid or its matrix elements are not
+//! finite:
+//!
+//! ```text
+//! {"m2": [a, b_p, b_m, re_mx, im_mx]}
+//! {"error": "..."}
+//! ```
+
+use crate::{cli::ServeArgs, logging};
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Write};
+use trois_photons::{
+    config::Configuration,
+    event::{self, Event, NUM_OUTGOING},
+    matelems::{MatrixElement, NUM_MAT_ELEMS},
+    numeric::Float,
+    output::events::from_lab_frame_momenta,
+    process::ProcessRegistry,
+};
+
+/// Request for the matrix elements of a phase space point
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Request {
+    /// (Px, Py, Pz, E) 4-momenta of the outgoing photons (GeV), in the frame
+    /// of event records
+    momenta: [[Float; 4]; NUM_OUTGOING],
+}
+
+/// Answer to a request
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Response {
+    /// Squared matrix element contributions, in result vector order
+    M2([Float; NUM_MAT_ELEMS]),
+
+    /// Why the request could not be answered
+    Error(String),
+}
+
+/// Answer the requests of the standard input until it is closed
+pub fn run(args: &ServeArgs) -> Result<()> {
+    // Load the configuration, keeping the standard output for answers
+    let cfg = Configuration::load_with_overlay(&args.args.config, &args.args.set, |cfg| {
+        args.args.apply_overrides(cfg);
+        logging::divert_to_stderr();
+    })
+    .context("Failed to load the configuration")?;
+    let process = ProcessRegistry::new()
+        .create(&cfg)
+        .context("Failed to set up the simulated process")?;
+
+    // Answer each request on its own line, as soon as it is received
+    let mut out = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line.context("Failed to read a request")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match evaluate(&line, &*process, args.tolerance) {
+            Ok(m2) => Response::M2(m2),
+            Err(error) => Response::Error(format!("{error:#}")),
+        };
+        serde_json::to_writer(&mut out, &response).context("Failed to write an answer")?;
+        writeln!(out)
+            .and_then(|()| out.flush())
+            .context("Failed to write an answer")?;
+    }
+    Ok(())
+}
+
+/// Evaluate the matrix elements of a request, checking its kinematics within
+/// a tolerance relative to the collision energy
+fn evaluate(
+    line: &str,
+    process: &dyn MatrixElement,
+    tolerance: Float,
+) -> Result<[Float; NUM_MAT_ELEMS]> {
+    let request: Request = serde_json::from_str(line).context("Invalid request")?;
+
+    // Soft photons make the matrix elements diverge, and the kinematics of
+    // an event without energy would pass any check relative to it
+    ensure!(
+        request.momenta.iter().all(|p| p[3] > 0.),
+        "Photon energies must be positive"
+    );
+    let e_total = request.momenta.iter().map(|p| p[3]).sum();
+    let event = Event::new(from_lab_frame_momenta(e_total, &request.momenta));
+    event::validate(&event, &process.outgoing_masses(), tolerance)?;
+
+    let m2: [Float; NUM_MAT_ELEMS] = process.contributions(&event).m2_sums().into();
+    ensure!(
+        m2.iter().all(|m2| m2.is_finite()),
+        "Matrix elements are not finite at this phase space point: {m2:?}"
+    );
+    Ok(m2)
+}
@@ -0,0 +1,50 @@
+//! Trapping of interruption signals (Ctrl-C and SIGTERM), so that interrupted
+//! simulations output their partial results and a checkpoint instead of dying
+//!
+//! A second interruption terminates the program immediately.
+
+use trois_photons::interrupt;
+
+/// Ask the simulation to stop when the program is interrupted, and tell
+/// whether this is supported on this platform
+pub fn trap_interruptions() -> bool {
+    #[cfg(unix)]
+    {
+        unix::trap_interruptions();
+        interrupt::enable();
+        true
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::interrupt;
+
+    /// Message displayed on the first interruption
+    const MESSAGE: &[u8] =
+        b"\nInterrupted, finishing the current events (interrupt again to abort)...\n";
+
+    /// Install the interruption handler
+    pub fn trap_interruptions() {
+        let handler = handle_interruption as extern "C" fn(libc::c_int);
+        for signal in [libc::SIGINT, libc::SIGTERM] {
+            // SAFETY: The handler only calls async-signal-safe functions
+            unsafe { libc::signal(signal, handler as libc::sighandler_t) };
+        }
+    }
+
+    /// Ask the simulation to stop on the first interruption, and terminate
+    /// the program on the second one
+    extern "C" fn handle_interruption(_signal: libc::c_int) {
+        if interrupt::request() {
+            // SAFETY: _exit() is async-signal-safe, unlike exit()
+            unsafe { libc::_exit(130) };
+        }
+        // SAFETY: write() is async-signal-safe, and the message outlives it
+        unsafe { libc::write(libc::STDERR_FILENO, MESSAGE.as_ptr().cast(), MESSAGE.len()) };
+    }
+}
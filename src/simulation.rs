@@ -0,0 +1,92 @@
+//! Convenience interface for library users who want to run simulations and
+//! analyze the generated events themselves
+
+use crate::{
+    config::Configuration, event::Event, evgen::EventGenerator, matelems::MatrixElement,
+    numeric::Float, resfin::FinalResults, Result,
+};
+
+/// Simulation of a certain configuration, ready to run
+///
+/// This takes care of setting up the matrix elements of the configured process
+/// and the event generator, including beam effects and adaptive sampling if
+/// configured. Event output and checkpointing are ignored, use simulate() for
+/// those.
+///
+pub struct Simulation<'cfg> {
+    /// Configuration of the simulation
+    cfg: &'cfg Configuration,
+
+    /// Matrix elements of the simulated process
+    process: Box<dyn MatrixElement>,
+
+    /// Phase space generator
+    evgen: EventGenerator,
+}
+//
+impl<'cfg> Simulation<'cfg> {
+    /// Prepare to simulate a certain configuration, whose process must be
+    /// built into the program
+    pub fn new(cfg: &'cfg Configuration) -> Result<Self> {
+        let (process, evgen) = crate::setup_event_generation(cfg)?;
+        Ok(Self {
+            cfg,
+            process,
+            evgen,
+        })
+    }
+
+    /// Prepare to simulate a certain configuration, with the matrix elements
+    /// of some process (e.g. one created by a custom ProcessRegistry)
+    pub fn with_process(cfg: &'cfg Configuration, process: Box<dyn MatrixElement>) -> Result<Self> {
+        let evgen = EventGenerator::from_config(cfg, &*process)?;
+        Ok(Self {
+            cfg,
+            process,
+            evgen,
+        })
+    }
+
+    /// Matrix elements of the simulated process
+    pub fn process(&self) -> &dyn MatrixElement {
+        &*self.process
+    }
+
+    /// Phase space generator of the simulation
+    pub fn event_generator(&self) -> &EventGenerator {
+        &self.evgen
+    }
+
+    /// Run the simulation
+    pub fn run(&self) -> Result<FinalResults<'cfg>> {
+        self.run_with_observer(|_event, _weight| {})
+    }
+
+    /// Run the simulation, calling an observer on each event which passes the
+    /// cuts, along with its weight
+    ///
+    /// If the detector is simulated, the observer sees events as measured by
+    /// the detector, like the cuts and the event files.
+    ///
+    /// Event weights are normalized such that their average over all generated
+    /// events (including those which did not pass the cuts) is the total
+    /// cross-section, in pb, as in event files.
+    ///
+    /// In multi-threaded builds, the observer is called from several threads
+    /// at once, so it must synchronize any state that it modifies.
+    ///
+    pub fn run_with_observer(
+        &self,
+        observer: impl Fn(&Event, Float) + Sync,
+    ) -> Result<FinalResults<'cfg>> {
+        crate::simulate_observed(
+            self.cfg,
+            &*self.process,
+            &self.evgen,
+            None,
+            None,
+            None,
+            &observer,
+        )
+    }
+}
@@ -0,0 +1,96 @@
+//! Numerical stability guard, which recomputes the matrix elements of events
+//! in nearly collinear or soft regions with extended precision
+//!
+//! The spinor products of two particles vanish as they become collinear, and
+//! those of a photon as it becomes soft, so that the matrix elements of such
+//! events are computed from small differences of large numbers, which lose
+//! most of the precision of Float. The cuts keep the photons away from these
+//! regions, but they may be loose enough to let some events in. The guard
+//! spots them by their smallest pair invariant, and recomputes their matrix
+//! elements with the DoubleFloat type of the extended module.
+
+use crate::{
+    config::Configuration,
+    coupling::Couplings,
+    event::{Event, NUM_PARTICLES},
+    matelems::MEsContributions,
+    momentum::{E, X, Y, Z},
+    numeric::{Complex, Float},
+};
+use serde::{Deserialize, Serialize};
+use trois_photons_core::extended::DoubleFloat;
+
+/// Recomputation of numerically unstable events with extended precision (not
+/// in the legacy format)
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct StabilityGuardConfig {
+    /// Invariant mass 2·pᵢ·pⱼ of a pair of particles, relative to the squared
+    /// collision energy, below which events are recomputed
+    #[serde(default = "StabilityGuardConfig::default_threshold")]
+    pub threshold: Float,
+}
+//
+impl StabilityGuardConfig {
+    /// Default threshold, below which a pair invariant computed in double
+    /// precision loses at least 6 of its 16 significant digits
+    pub const DEFAULT_THRESHOLD: Float = 1e-6;
+
+    /// Default value of the threshold configuration entry
+    fn default_threshold() -> Float {
+        Self::DEFAULT_THRESHOLD
+    }
+}
+
+/// Guard which recomputes the matrix elements of unstable events
+pub struct StabilityGuard {
+    /// Smallest relative pair invariant of stable events
+    threshold: Float,
+
+    /// Couplings of the built-in process, in extended precision
+    couplings: Couplings<DoubleFloat>,
+}
+//
+impl StabilityGuard {
+    /// Set up the guard of a simulation, if it is configured
+    pub fn new(cfg: &Configuration) -> Option<Self> {
+        let guard = cfg.stability_guard.as_ref()?;
+        Some(Self {
+            threshold: guard.threshold,
+            couplings: Couplings::new(
+                cfg.alpha_scheme.alpha(cfg).into(),
+                cfg.alpha_z.into(),
+                cfg.sin2_weinberg.into(),
+                cfg.m_z0.into(),
+            ),
+        })
+    }
+
+    /// Truth that some pair of particles of an event has an invariant mass
+    /// below the threshold, so that its matrix elements should be recomputed
+    pub fn is_unstable(&self, event: &Event) -> bool {
+        let momenta = event.all_momenta();
+        let invariant = |i: usize, j: usize| {
+            let (p_i, p_j) = (momenta.row(i), momenta.row(j));
+            2. * (p_i[E] * p_j[E] - p_i[X] * p_j[X] - p_i[Y] * p_j[Y] - p_i[Z] * p_j[Z])
+        };
+        let limit = self.threshold * event.collision_energy().powi(2);
+        (0..NUM_PARTICLES)
+            .flat_map(|i| (i + 1..NUM_PARTICLES).map(move |j| (i, j)))
+            .any(|(i, j)| invariant(i, j) < limit)
+    }
+
+    /// Compute the matrix element contributions of an event in extended
+    /// precision, then round them back to Float
+    pub fn contributions(&self, event: &Event) -> MEsContributions {
+        let extended_event = Event::with_weight_factor(
+            event.all_momenta().map(DoubleFloat::from),
+            event.weight_factor().into(),
+        );
+        let extended = MEsContributions::new(&self.couplings, &extended_event);
+        MEsContributions::from_parts(
+            extended.per_helicity().map(DoubleFloat::value),
+            (extended.spin_interference()).map(|z| Complex::new(z.re.value(), z.im.value())),
+        )
+    }
+}
@@ -0,0 +1,194 @@
+//! This module propagates the uncertainties on the electroweak parameters to
+//! the total cross-section, without simulating again
+//!
+//! The sums of squared matrix elements which are accumulated during the
+//! simulation do not depend on the Z⁰ mass and width, on the Weinberg angle
+//! or on the fine structure constant at the Z⁰ peak, except through a global
+//! scale factor of the anomalous amplitudes. Their contributions to the
+//! cross-section can thus be recomputed analytically for varied parameters.
+
+use crate::{
+    config::Configuration,
+    error::ensure,
+    matelems::{A, B_M, B_P, I_MX, R_MX},
+    numeric::Float,
+    resacc::Contributions,
+    resfin::FinalResults,
+    Result,
+};
+use log::info;
+use prefix_num_ops::real::*;
+use serde::{Deserialize, Serialize};
+
+/// Uncertainties (one standard deviation) on the electroweak parameters
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SystematicsConfig {
+    /// Uncertainty on the Z⁰ mass (GeV)
+    pub m_z0: Float,
+
+    /// Uncertainty on the Z⁰ width (GeV)
+    pub g_z0: Float,
+
+    /// Uncertainty on sin²𝜃_W
+    pub sin2_weinberg: Float,
+
+    /// Uncertainty on the fine structure constant at the Z⁰ peak
+    pub alpha_z: Float,
+}
+//
+impl SystematicsConfig {
+    /// Check that the uncertainties make sense for a certain configuration
+    pub fn check(&self, cfg: &Configuration) -> Result<()> {
+        for (name, uncertainty, field) in self.parameters() {
+            let value = *field(&mut cfg.clone());
+            ensure!(
+                uncertainty >= 0.,
+                InvalidConfig,
+                "The uncertainty on {name} must not be negative"
+            );
+            ensure!(
+                value - uncertainty > 0.,
+                InvalidConfig,
+                "The uncertainty on {name} must be smaller than its value"
+            );
+        }
+        ensure!(
+            cfg.sin2_weinberg + self.sin2_weinberg < 1.,
+            InvalidConfig,
+            "sin2_weinberg must stay below 1 within its uncertainty"
+        );
+
+        // With a varying collision energy, the Z⁰ propagator was evaluated
+        // event by event, so it cannot be reweighted afterwards
+        ensure!(
+            !cfg.varying_collision_energy() || (self.m_z0 == 0. && self.g_z0 == 0.),
+            InvalidConfig,
+            "The Z0 mass and width cannot be varied along with initial-state radiation or beam \
+             energy spread"
+        );
+        Ok(())
+    }
+
+    /// Name, uncertainty and configuration field of each parameter
+    fn parameters(&self) -> [(&'static str, Float, ParameterField); 4] {
+        [
+            ("m_z0", self.m_z0, |cfg| &mut cfg.m_z0),
+            ("g_z0", self.g_z0, |cfg| &mut cfg.g_z0),
+            ("sin2_weinberg", self.sin2_weinberg, |cfg| {
+                &mut cfg.sin2_weinberg
+            }),
+            ("alpha_z", self.alpha_z, |cfg| &mut cfg.alpha_z),
+        ]
+    }
+}
+
+/// Accessor to the configuration field of a varied parameter
+type ParameterField = fn(&mut Configuration) -> &mut Float;
+
+/// Shift of the total cross-section when a parameter is varied by one standard
+/// deviation in each direction
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ParameterVariation {
+    /// Parameter which was varied
+    pub parameter: &'static str,
+
+    /// Cross-section shift when the parameter is decreased (pb)
+    pub down: Float,
+
+    /// Cross-section shift when the parameter is increased (pb)
+    pub up: Float,
+}
+
+/// Systematic uncertainty band of the total cross-section
+#[derive(Clone, Debug, Serialize)]
+pub struct SystematicUncertainty {
+    /// Effect of each parameter which has a nonzero uncertainty
+    pub variations: Vec<ParameterVariation>,
+
+    /// Downward uncertainty, summed in quadrature over parameters (pb)
+    pub lower: Float,
+
+    /// Upward uncertainty, summed in quadrature over parameters (pb)
+    pub upper: Float,
+}
+//
+impl SystematicUncertainty {
+    /// Propagate the uncertainties on the electroweak parameters to the total
+    /// cross-section of some simulation results
+    pub fn new(res: &FinalResults, uncertainties: &SystematicsConfig) -> Self {
+        let cfg = &*res.cfg;
+        let sigma = varied_sigma(res, cfg);
+
+        let mut variations = Vec::new();
+        let (mut lower2, mut upper2) = (0., 0.);
+        for (parameter, uncertainty, field) in uncertainties.parameters() {
+            if uncertainty == 0. {
+                continue;
+            }
+            let shift = |sign: Float| {
+                let mut varied_cfg = cfg.clone();
+                *field(&mut varied_cfg) += sign * uncertainty;
+                varied_sigma(res, &varied_cfg) - sigma
+            };
+            let (down, up) = (shift(-1.), shift(1.));
+
+            // Each parameter contributes its largest shift in each direction
+            lower2 += Float::min(0., Float::min(down, up)).powi(2);
+            upper2 += Float::max(0., Float::max(down, up)).powi(2);
+            variations.push(ParameterVariation {
+                parameter,
+                down,
+                up,
+            });
+        }
+
+        SystematicUncertainty {
+            variations,
+            lower: sqrt(lower2),
+            upper: sqrt(upper2),
+        }
+    }
+
+    /// Display the uncertainty band next to the statistical error
+    pub fn print(&self, res: &FinalResults) {
+        info!("Systematics        :    Down (pb) |      Up (pb)");
+        for variation in &self.variations {
+            info!(
+                "{:<19}: {:>+12.5e} | {:>+12.5e}",
+                variation.parameter, variation.down, variation.up
+            );
+        }
+        info!(
+            "Sigma (pb)         : {:.6e} +/- {:.2e} (stat.) +{:.2e} -{:.2e} (syst.)",
+            res.sigma,
+            res.sigma * res.prec,
+            self.upper,
+            self.lower
+        );
+        info!("");
+    }
+}
+
+/// Total cross-section for another configuration of the electroweak
+/// parameters, recomputed from the accumulated sums of matrix elements
+fn varied_sigma(res: &FinalResults, varied_cfg: &Configuration) -> Float {
+    let cfg = &*res.cfg;
+
+    // The Standard Model contribution does not depend on the varied
+    // parameters, which allows recovering the normalized event weight
+    let norm_weight = res.unit_contribs[A] / Contributions::new(cfg, 1.).unit_contribs[A];
+    let mut contribs = Contributions::new(varied_cfg, norm_weight).sigma_contribs;
+
+    // The anomalous amplitudes scale as sqrt(𝛼_Z/(cos²𝜃_W sin²𝜃_W))/M_Z⁴
+    let coupling2 = |cfg: &Configuration| {
+        cfg.alpha_z / ((1. - cfg.sin2_weinberg) * cfg.sin2_weinberg * cfg.m_z0.powi(8))
+    };
+    let coupling2_ratio = coupling2(varied_cfg) / coupling2(cfg);
+    contribs[B_P] *= coupling2_ratio;
+    contribs[B_M] *= coupling2_ratio;
+    contribs[R_MX] *= sqrt(coupling2_ratio);
+    contribs[I_MX] *= sqrt(coupling2_ratio);
+
+    res.me_sums.dot(&contribs) * res.incident_flux
+}
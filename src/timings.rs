@@ -0,0 +1,132 @@
+//! Breakdown of the simulation time into its phases, which allows localizing
+//! performance regressions without an external profiler
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Phases of the simulation
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Phase {
+    /// Random number generation
+    Rng,
+
+    /// Phase space sampling, minus random number generation
+    PhaseSpace,
+
+    /// Event validation (if enabled) and cuts
+    Cuts,
+
+    /// Matrix element computation
+    MatrixElements,
+
+    /// Integration of events into the results, and event output
+    Accumulation,
+
+    /// Computation of the final results
+    Finalization,
+}
+
+/// Number of simulation phases
+pub const NUM_PHASES: usize = 6;
+
+/// Display names of the simulation phases, in the order of the Phase enum
+const PHASE_NAMES: [&str; NUM_PHASES] = [
+    "Random numbers",
+    "Phase space",
+    "Cuts",
+    "Matrix elements",
+    "Accumulation",
+    "Finalization",
+];
+
+/// Time spent in each phase of the simulation, summed over threads
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct PhaseTimings {
+    /// Time spent in each phase, in the order of the Phase enum
+    pub durations: [Duration; NUM_PHASES],
+}
+//
+impl PhaseTimings {
+    /// Account for some time spent in a certain phase
+    pub fn add(&mut self, phase: Phase, duration: Duration) {
+        self.durations[phase as usize] += duration;
+    }
+
+    /// Integrate the timings of another part of the simulation
+    pub fn merge(&mut self, other: &Self) {
+        for (duration, other_duration) in self.durations.iter_mut().zip(other.durations) {
+            *duration += other_duration;
+        }
+    }
+
+    /// Display the time spent in each phase, in total and per generated event
+    pub fn print(&self, num_events: usize) {
+        let total = self.durations.iter().sum::<Duration>().as_secs_f64();
+        info!("Timings            :   Time (s) |  Share | ns/event");
+        for (name, duration) in PHASE_NAMES.into_iter().zip(self.durations) {
+            let secs = duration.as_secs_f64();
+            info!(
+                "{name:<19}: {secs:>10.4} | {:>5.1}% | {:>8.1}",
+                100. * secs / total,
+                1e9 * secs / (num_events as f64)
+            );
+        }
+        info!(
+            "{:<19}: {total:>10.4} | {:>5.1}% | {:>8.1}",
+            "Total",
+            100.,
+            1e9 * total / (num_events as f64)
+        );
+        info!("");
+    }
+}
+
+/// Stopwatch which attributes the time elapsed between successive laps to
+/// simulation phases, and does nothing if timings are disabled
+pub struct PhaseTimer {
+    /// Time of the last lap, if timings are enabled
+    last_lap: Option<Instant>,
+
+    /// Timings measured so far
+    timings: PhaseTimings,
+}
+//
+impl PhaseTimer {
+    /// Start measuring time, if timings are enabled
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            last_lap: enabled.then(Instant::now),
+            timings: PhaseTimings::default(),
+        }
+    }
+
+    /// Attribute the time elapsed since the previous lap to a certain phase
+    #[inline]
+    pub fn lap(&mut self, phase: Phase) {
+        if let Some(last_lap) = &mut self.last_lap {
+            let now = Instant::now();
+            self.timings.add(phase, now - *last_lap);
+            *last_lap = now;
+        }
+    }
+
+    /// Attribute some time which was accounted to a phase to another phase
+    /// instead (e.g. random number generation during phase space sampling)
+    pub fn reattribute(&mut self, duration: Duration, from: Phase, to: Phase) {
+        let from_duration = &mut self.timings.durations[from as usize];
+        let duration = duration.min(*from_duration);
+        *from_duration -= duration;
+        self.timings.add(to, duration);
+    }
+
+    /// Truth that timings are enabled
+    pub fn enabled(&self) -> bool {
+        self.last_lap.is_some()
+    }
+
+    /// Stop measuring time, and return the timings if they were enabled
+    pub fn finish(self) -> Option<PhaseTimings> {
+        self.last_lap.map(|_| self.timings)
+    }
+}
@@ -0,0 +1,64 @@
+//! Matrix element validation mode, which compares the matrix elements of the
+//! simulation with an alternate computation over a sample of generated events
+
+use crate::cli::ValidateMeArgs;
+use anyhow::{ensure, Context, Result};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use trois_photons::{
+    alternate::MatrixElementComparison,
+    config::Configuration,
+    coupling::Couplings,
+    matelems::{CONTRIBUTIONS, NUM_MAT_ELEMS},
+    numeric::Float,
+    random::{RandGenerator, RandomGenerator},
+    setup_event_generation,
+};
+
+/// Compute the matrix elements of events which pass the cuts both as the
+/// simulation does and in the alternate way, and report the largest relative
+/// deviation of each contribution, failing if any exceeds the tolerance
+pub fn run(args: &ValidateMeArgs) -> Result<()> {
+    // Set up event generation as for a simulation
+    let cfg = Configuration::load_with_overlay(&args.args.config, &args.args.set, |cfg| {
+        args.args.apply_overrides(cfg)
+    })
+    .context("Failed to load the configuration")?;
+    let (_process, evgen) =
+        setup_event_generation(&cfg).context("Failed to set up event generation")?;
+    let couplings = Couplings::from(&cfg);
+    let seed = cfg.rng.effective_seed(cfg.seed);
+    let mut rng = RandGenerator::<Xoshiro256PlusPlus>::with_seed(seed);
+
+    // Compare the matrix elements of the events that pass the cuts
+    let mut checked = 0;
+    let mut max_deviations: [Float; NUM_MAT_ELEMS] = [0.; NUM_MAT_ELEMS];
+    while checked < args.events {
+        let event = evgen.generate(&mut rng);
+        if cfg.event_cut.apply(&event).is_err() {
+            continue;
+        }
+        let comparison = MatrixElementComparison::new(&couplings, &event);
+        for (max_deviation, &deviation) in max_deviations.iter_mut().zip(&comparison.deviations) {
+            *max_deviation = max_deviation.max(deviation);
+        }
+        checked += 1;
+    }
+
+    // Report the largest deviation of each contribution
+    println!();
+    println!("Checked events     : {checked}");
+    println!("Maximal relative deviation of each contribution:");
+    for (name, deviation) in CONTRIBUTIONS.iter().zip(max_deviations) {
+        println!("  {name:<10}: {deviation:.3e}");
+    }
+    for (name, deviation) in CONTRIBUTIONS.iter().zip(max_deviations) {
+        ensure!(
+            deviation <= args.tolerance,
+            "The {name} contribution deviates from its alternate computation beyond the \
+             tolerance of {}",
+            args.tolerance
+        );
+    }
+    println!("Matrix element validation passed");
+    Ok(())
+}
@@ -0,0 +1,207 @@
+//! VEGAS-style adaptive importance sampling of the random parameters of the
+//! event generator, which reduces the variance of the cross-section estimate
+
+use crate::{error::ensure, numeric::Float, Result};
+use prefix_num_ops::real::*;
+use serde::{Deserialize, Serialize};
+
+/// Number of adaptively sampled random parameters of an event: the polar angle
+/// and the two energy parameters of each photon (azimuthal angles are sampled
+/// uniformly since the physics does not favor any of them)
+pub const NUM_DIMS: usize = 4;
+
+/// Bin of the adaptive sampling grid used for each random parameter
+pub type GridBins = [usize; NUM_DIMS];
+
+/// Minimal importance of a bin, relative to the average, during grid
+/// refinement. Ensures that no region of phase space gets a zero sampling
+/// probability, which would bias the results.
+const MIN_RELATIVE_IMPORTANCE: Float = 1e-3;
+
+/// Configuration of adaptive importance sampling (not in the legacy format)
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdaptiveSamplingConfig {
+    /// Number of warm-up iterations during which the sampling grid adapts
+    pub iterations: usize,
+
+    /// Number of events simulated during each warm-up iteration
+    pub events_per_iteration: usize,
+
+    /// Number of bins of the sampling grid along each random parameter
+    pub bins: usize,
+
+    /// Damping of the grid adaptation (larger values adapt faster, but may
+    /// lead to instabilities)
+    pub damping: Float,
+}
+//
+impl AdaptiveSamplingConfig {
+    /// Check that the configuration is sensible
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            self.bins > 0,
+            InvalidConfig,
+            "The adaptive sampling grid must have at least one bin"
+        );
+        ensure!(
+            self.iterations == 0 || self.events_per_iteration > 0,
+            InvalidConfig,
+            "Adaptive sampling iterations must simulate at least one event"
+        );
+        ensure!(
+            self.damping > 0.,
+            InvalidConfig,
+            "Adaptive sampling damping must be positive"
+        );
+        Ok(())
+    }
+}
+//
+impl Default for AdaptiveSamplingConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            events_per_iteration: 100_000,
+            bins: 50,
+            damping: 1.5,
+        }
+    }
+}
+
+/// Adaptive sampling grid, which maps uniformly distributed random numbers to
+/// random parameters that are more densely distributed where events weigh more
+///
+/// Along each parameter, the unit interval is split into bins which have the
+/// same sampling probability but different widths.
+///
+#[derive(Clone)]
+pub struct VegasGrid {
+    /// Bin edges along each random parameter, from 0 to 1
+    edges: [Vec<Float>; NUM_DIMS],
+}
+//
+impl VegasGrid {
+    /// Set up a uniform grid with a certain number of bins per parameter
+    pub fn new(num_bins: usize) -> Self {
+        assert!(num_bins > 0, "Sampling grids must have at least one bin");
+        let uniform_edges = (0..=num_bins)
+            .map(|edge| (edge as Float) / (num_bins as Float))
+            .collect::<Vec<_>>();
+        Self {
+            edges: std::array::from_fn(|_dim| uniform_edges.clone()),
+        }
+    }
+
+    /// Number of bins along each random parameter
+    pub fn num_bins(&self) -> usize {
+        self.edges[0].len() - 1
+    }
+
+    /// Map uniformly distributed random numbers to random parameters, in place
+    ///
+    /// Returns the Jacobian of the mapping, by which the weight of the event
+    /// must be multiplied, and records which bin each random number fell in.
+    ///
+    pub fn map(&self, params: &mut [Float; NUM_DIMS], bins: &mut GridBins) -> Float {
+        let num_bins = self.num_bins();
+        let mut jacobian = 1.;
+        for ((param, bin), edges) in params.iter_mut().zip(bins.iter_mut()).zip(&self.edges) {
+            let pos = *param * (num_bins as Float);
+            let index = (pos as usize).min(num_bins - 1);
+            let width = edges[index + 1] - edges[index];
+            *param = edges[index] + (pos - (index as Float)) * width;
+            *bin = index;
+            jacobian *= width * (num_bins as Float);
+        }
+        jacobian
+    }
+
+    /// Refine the grid given the squared event weights accumulated in each bin
+    ///
+    /// This follows the grid refinement procedure of G.P. Lepage's VEGAS
+    /// algorithm, where bin sizes are adjusted so that each bin contributes
+    /// equally to the variance of the integral.
+    ///
+    pub fn refine(&mut self, weights2: &GridWeights, damping: Float) {
+        let num_bins = self.num_bins();
+        if num_bins < 2 {
+            return;
+        }
+        for (edges, sums) in self.edges.iter_mut().zip(&weights2.sums) {
+            // Smooth the accumulated weights with those of neighbouring bins
+            let smoothed = (0..num_bins)
+                .map(|bin| {
+                    let neighbours = bin.saturating_sub(1)..(bin + 2).min(num_bins);
+                    let count = neighbours.len() as Float;
+                    sums[neighbours].iter().sum::<Float>() / count
+                })
+                .collect::<Vec<_>>();
+            let total = smoothed.iter().sum::<Float>();
+            if total <= 0. {
+                continue;
+            }
+
+            // Compute the damped importance of each bin
+            let mut importance = smoothed
+                .iter()
+                .map(|&sum| {
+                    let fraction = sum / total;
+                    if fraction <= 0. {
+                        0.
+                    } else if fraction >= 1. {
+                        1.
+                    } else {
+                        powf((1. - fraction) / ln(1. / fraction), damping)
+                    }
+                })
+                .collect::<Vec<_>>();
+            let mean = importance.iter().sum::<Float>() / (num_bins as Float);
+            for imp in &mut importance {
+                *imp = imp.max(MIN_RELATIVE_IMPORTANCE * mean);
+            }
+            let step = importance.iter().sum::<Float>() / (num_bins as Float);
+
+            // Move the bin edges so that each new bin gets the same importance
+            let mut new_edges = Vec::with_capacity(num_bins + 1);
+            new_edges.push(0.);
+            let mut old_bin = 0;
+            let mut accumulated = 0.;
+            for new_bin in 1..num_bins {
+                let target = (new_bin as Float) * step;
+                while old_bin < num_bins - 1 && accumulated + importance[old_bin] < target {
+                    accumulated += importance[old_bin];
+                    old_bin += 1;
+                }
+                let fraction = ((target - accumulated) / importance[old_bin]).clamp(0., 1.);
+                let width = edges[old_bin + 1] - edges[old_bin];
+                new_edges.push(edges[old_bin] + fraction * width);
+            }
+            new_edges.push(1.);
+            *edges = new_edges;
+        }
+    }
+}
+
+/// Squared event weights accumulated in each bin of an adaptive sampling grid
+pub struct GridWeights {
+    /// Accumulated squared weights, for each random parameter and bin
+    sums: [Vec<Float>; NUM_DIMS],
+}
+//
+impl GridWeights {
+    /// Prepare to accumulate squared weights for a certain grid
+    pub fn new(grid: &VegasGrid) -> Self {
+        Self {
+            sums: std::array::from_fn(|_dim| vec![0.; grid.num_bins()]),
+        }
+    }
+
+    /// Record the weight of an event, given the bins it was sampled from
+    pub fn fill(&mut self, bins: &GridBins, weight: Float) {
+        let weight2 = weight.powi(2);
+        for (sums, &bin) in self.sums.iter_mut().zip(bins) {
+            sums[bin] += weight2;
+        }
+    }
+}
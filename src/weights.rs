@@ -0,0 +1,435 @@
+//! Distribution of the event weights, which diagnoses the efficiency of phase
+//! space sampling and guides the choice of the maximal weight of unweighting,
+//! and diagnostics of pathological event weights
+//!
+//! Weights are those of the events which pass the cuts, in pb, as shown to
+//! observers and written to event files. Their decimal logarithm is
+//! histogrammed with a fixed number of bins per decade, which needs no
+//! configured range and can be merged across threads and simulation runs, and
+//! which the tail percentiles of the distribution are estimated from.
+//!
+//! Events whose matrix elements are not finite (NaN or infinite) are left out
+//! of the results, rather than silently turning all sums into NaN, and counted
+//! along with the events of negative weight, which are integrated normally.
+//! The first offending events can be recorded, so that their phase space
+//! points can be written to a file for debugging.
+
+use crate::{
+    config::Configuration,
+    event::{Event, NUM_PARTICLES},
+    matelems::{MEsVector, NUM_MAT_ELEMS},
+    momentum::MOMENTUM_DIM,
+    numeric::{CompensatedSum, Float},
+    output::compression::OutputFile,
+    Result,
+};
+use log::{info, warn};
+use prefix_num_ops::real::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::BTreeMap, io::Write};
+
+/// Number of histogram bins per decade of weights
+const BINS_PER_DECADE: Float = 5.;
+
+/// Percentiles of the weight distribution which are estimated
+const PERCENTILES: [Float; 5] = [50., 90., 99., 99.9, 99.99];
+
+/// Accumulator of the event weight distribution
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct WeightAccumulator {
+    /// Number of recorded events
+    count: usize,
+
+    /// Number of recorded events whose weight is not positive, which are left
+    /// out of the logarithmic histogram
+    non_positive: usize,
+
+    /// Smallest and largest recorded weights, if any
+    range: Option<[Float; 2]>,
+
+    /// Sum of the recorded weights
+    sum: CompensatedSum,
+
+    /// Sum of the squared recorded weights
+    sum2: CompensatedSum,
+
+    /// Number of positive weights in each bin, identified by the index of the
+    /// bin along the logarithmic axis
+    bins: BTreeMap<i32, usize>,
+}
+//
+impl WeightAccumulator {
+    /// Set up the accumulation of the weight distribution, if it is enabled
+    pub fn new(cfg: &Configuration) -> Option<Self> {
+        cfg.weight_distribution.then(Self::default)
+    }
+
+    /// Record the weight of an event (pb)
+    pub fn fill(&mut self, weight: Float) {
+        self.count += 1;
+        self.range = Some(match self.range {
+            Some([min, max]) => [min.min(weight), max.max(weight)],
+            None => [weight, weight],
+        });
+        self.sum.add(weight);
+        self.sum2.add(weight.powi(2));
+        if weight > 0. {
+            let bin = floor(log10(weight) * BINS_PER_DECADE) as i32;
+            *self.bins.entry(bin).or_default() += 1;
+        } else {
+            self.non_positive += 1;
+        }
+    }
+
+    /// Integrate the contents of another accumulator
+    ///
+    /// Unlike other accumulators, this one needs no rescaling when merging the
+    /// results of simulation runs with different numbers of events, since
+    /// event weights in pb do not depend on it.
+    ///
+    pub fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        self.non_positive += other.non_positive;
+        self.range = match (self.range, other.range) {
+            (Some([min, max]), Some([other_min, other_max])) => {
+                Some([min.min(other_min), max.max(other_max)])
+            }
+            (range, None) | (None, range) => range,
+        };
+        self.sum.merge(other.sum);
+        self.sum2.merge(other.sum2);
+        for (&bin, &count) in &other.bins {
+            *self.bins.entry(bin).or_default() += count;
+        }
+    }
+
+    /// Compute the statistics of the weight distribution
+    pub fn finalize(&self) -> WeightDistribution {
+        let n = self.count as Float;
+        let [min, max] = self.range.unwrap_or([0., 0.]);
+        let mean = self.sum.value() / n;
+        let rms = sqrt(self.sum2.value() / n);
+        let histogram = (self.bins.iter())
+            .map(|(&bin, &count)| WeightBin {
+                low: Self::bin_edge(bin),
+                high: Self::bin_edge(bin + 1),
+                count,
+            })
+            .collect();
+        let percentiles = PERCENTILES
+            .iter()
+            .map(|&percent| (percent, self.percentile(percent, [min, max])))
+            .collect();
+        WeightDistribution {
+            events: self.count,
+            non_positive: self.non_positive,
+            min,
+            max,
+            mean,
+            rms,
+            max_over_mean: max / mean,
+            percentiles,
+            histogram,
+        }
+    }
+
+    /// Lower edge of a histogram bin
+    fn bin_edge(bin: i32) -> Float {
+        powf(10., bin as Float / BINS_PER_DECADE)
+    }
+
+    /// Estimate a percentile of the positive weights, by log-linear
+    /// interpolation within the histogram bin where it falls, clamped to the
+    /// range of recorded weights
+    fn percentile(&self, percent: Float, [min, max]: [Float; 2]) -> Float {
+        let num_positive = self.count - self.non_positive;
+        if num_positive == 0 {
+            return 0.;
+        }
+        let rank = percent / 100. * num_positive as Float;
+        let mut below = 0;
+        for (&bin, &count) in &self.bins {
+            if (below + count) as Float >= rank {
+                let fraction = (rank - below as Float) / count as Float;
+                let log_weight = (bin as Float + fraction) / BINS_PER_DECADE;
+                return powf(10., log_weight).clamp(min.max(0.), max);
+            }
+            below += count;
+        }
+        max
+    }
+}
+
+/// Statistics of the event weight distribution
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WeightDistribution {
+    /// Number of events which passed the cuts
+    pub events: usize,
+
+    /// Number of these events whose weight is not positive
+    pub non_positive: usize,
+
+    /// Smallest weight (pb)
+    pub min: Float,
+
+    /// Largest weight (pb)
+    pub max: Float,
+
+    /// Mean weight (pb)
+    pub mean: Float,
+
+    /// Root mean square of the weights (pb)
+    pub rms: Float,
+
+    /// Ratio of the largest weight to the mean one, the inverse of the
+    /// efficiency of unweighting with the largest weight as maximal weight
+    pub max_over_mean: Float,
+
+    /// Estimated percentiles of the positive weights, as (percentage, weight
+    /// in pb) pairs
+    pub percentiles: Vec<(Float, Float)>,
+
+    /// Logarithmic histogram of the positive weights (non-empty bins only)
+    pub histogram: Vec<WeightBin>,
+}
+//
+impl WeightDistribution {
+    /// Display the statistics and the histogram of the weights
+    pub fn print(&self) {
+        info!("Event weights (pb) : {} events", self.events);
+        info!("Min. / Max.        : {:e} / {:e}", self.min, self.max);
+        info!("Mean / RMS         : {:e} / {:e}", self.mean, self.rms);
+        info!("Max. / Mean        : {:.3}", self.max_over_mean);
+        if self.non_positive > 0 {
+            info!("Non-positive       : {}", self.non_positive);
+        }
+        for (percent, weight) in &self.percentiles {
+            let name = format!("{percent}th percentile");
+            info!("{name:<19}: {weight:e}");
+        }
+        let max_count = (self.histogram.iter())
+            .map(|bin| bin.count)
+            .max()
+            .unwrap_or(0);
+        for bin in &self.histogram {
+            /// Width of the bar of the most populated bin, in characters
+            const BAR_WIDTH: usize = 40;
+            let bar = "#".repeat(bin.count * BAR_WIDTH / max_count);
+            info!(
+                "[{:9.3e}, {:9.3e}[ : {:>10} {bar}",
+                bin.low, bin.high, bin.count
+            );
+        }
+        info!("");
+    }
+}
+
+/// Bin of the logarithmic histogram of the weights
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct WeightBin {
+    /// Lower edge of the bin (pb)
+    pub low: Float,
+
+    /// Upper edge of the bin (pb)
+    pub high: Float,
+
+    /// Number of events whose weight falls in the bin
+    pub count: usize,
+}
+
+/// Largest number of offending events which are recorded
+const MAX_RECORDED_EVENTS: usize = 1000;
+
+/// Statistics of the events whose weight is not finite or negative
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct WeightIssues {
+    /// Number of events whose matrix elements are not finite, which were left
+    /// out of the results
+    non_finite: usize,
+
+    /// Number of events of negative weight
+    negative: usize,
+
+    /// Offending events which were recorded (if enabled), up to
+    /// MAX_RECORDED_EVENTS of them
+    recorded: Vec<BadEvent>,
+
+    /// Whether offending events should be recorded
+    record: bool,
+}
+//
+impl WeightIssues {
+    /// Prepare to diagnose the event weights, recording the offending events
+    /// if they are to be written to a file
+    pub fn new(cfg: &Configuration) -> Self {
+        Self {
+            record: cfg.bad_events_output.is_some(),
+            ..Self::default()
+        }
+    }
+
+    /// Check the matrix elements of an event and its weight (pb), recording
+    /// it if it is pathological, and tell whether it can be integrated
+    pub fn check(&mut self, event: &Event, m2_sums: &MEsVector, weight: Float) -> bool {
+        let issue = if !weight.is_finite() || m2_sums.iter().any(|m2| !m2.is_finite()) {
+            self.non_finite += 1;
+            WeightIssue::NonFinite
+        } else if weight < 0. {
+            self.negative += 1;
+            WeightIssue::Negative
+        } else {
+            return true;
+        };
+        if self.record && self.recorded.len() < MAX_RECORDED_EVENTS {
+            self.recorded
+                .push(BadEvent::new(issue, event, m2_sums, weight));
+        }
+        issue != WeightIssue::NonFinite
+    }
+
+    /// Integrate the statistics of another diagnostic, keeping the first
+    /// recorded events of both
+    pub fn merge(&mut self, other: &Self) {
+        self.non_finite += other.non_finite;
+        self.negative += other.negative;
+        let free_slots = MAX_RECORDED_EVENTS.saturating_sub(self.recorded.len());
+        (self.recorded).extend(other.recorded.iter().take(free_slots).cloned());
+    }
+
+    /// Replace the statistics with those of a checkpoint, but keep recording
+    /// offending events as this simulation is configured to
+    pub fn restore(&mut self, checkpoint: Self) {
+        *self = Self {
+            record: self.record,
+            ..checkpoint
+        };
+    }
+
+    /// Number of events whose matrix elements are not finite
+    pub fn non_finite(&self) -> usize {
+        self.non_finite
+    }
+
+    /// Number of events of negative weight
+    pub fn negative(&self) -> usize {
+        self.negative
+    }
+
+    /// Display the statistics, if any event weight was pathological
+    pub fn print(&self) {
+        if self.non_finite > 0 {
+            warn!(
+                "{} events had non-finite matrix elements, and were left out of the results",
+                self.non_finite
+            );
+        }
+        if self.negative > 0 {
+            info!("Negative weights   : {} events", self.negative);
+        }
+        if self.non_finite > 0 || self.negative > 0 {
+            info!("");
+        }
+    }
+
+    /// Write the recorded events to a file, in the JSON Lines format
+    ///
+    /// Each line holds the `issue` (`non-finite` or `negative`), the `weight`
+    /// (pb), the `momenta` of the event (rows are e-, e+ and photons, columns
+    /// are X, Y, Z and E), its `weight_factor` and the sum over helicities of
+    /// each contribution to the squared matrix element (`m2_sums`). Numbers
+    /// which are not finite are written as strings ("NaN", "inf" or "-inf").
+    ///
+    pub fn write_events(&self, path: &str) -> Result<()> {
+        let mut file = OutputFile::create(path)?;
+        for event in &self.recorded {
+            serde_json::to_writer(&mut file, event)?;
+            writeln!(file)?;
+        }
+        file.finish()?;
+        Ok(())
+    }
+}
+
+/// Kind of pathological event weight
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WeightIssue {
+    /// The matrix elements are not finite
+    NonFinite,
+
+    /// The weight is negative
+    Negative,
+}
+
+/// Event whose weight is pathological, as recorded for debugging
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct BadEvent {
+    /// What is wrong with its weight
+    issue: WeightIssue,
+
+    /// Weight of the event (pb)
+    weight: AnyFloat,
+
+    /// 4-momenta of the particles
+    momenta: [[Float; MOMENTUM_DIM]; NUM_PARTICLES],
+
+    /// Factor by which the event weight is multiplied
+    weight_factor: Float,
+
+    /// Sum over helicities of each contribution to the squared matrix element
+    m2_sums: [AnyFloat; NUM_MAT_ELEMS],
+}
+//
+impl BadEvent {
+    /// Record an event
+    fn new(issue: WeightIssue, event: &Event, m2_sums: &MEsVector, weight: Float) -> Self {
+        let momenta = event.all_momenta();
+        Self {
+            issue,
+            weight: AnyFloat(weight),
+            momenta: std::array::from_fn(|par| std::array::from_fn(|coord| momenta[(par, coord)])),
+            weight_factor: event.weight_factor(),
+            m2_sums: std::array::from_fn(|res| AnyFloat(m2_sums[res])),
+        }
+    }
+}
+
+/// Floating-point number which may not be finite
+///
+/// JSON numbers cannot represent NaN and infinities, so in human-readable
+/// formats, such numbers are serialized as strings ("NaN", "inf" or "-inf").
+///
+#[derive(Clone, Copy, Debug)]
+struct AnyFloat(Float);
+//
+impl Serialize for AnyFloat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        if self.0.is_finite() || !serializer.is_human_readable() {
+            self.0.serialize(serializer)
+        } else {
+            serializer.serialize_str(&self.0.to_string())
+        }
+    }
+}
+//
+impl<'de> Deserialize<'de> for AnyFloat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        /// Serialized form of an AnyFloat in human-readable formats
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            /// Finite number
+            Number(Float),
+
+            /// Non-finite number
+            Text(String),
+        }
+        if !deserializer.is_human_readable() {
+            return Float::deserialize(deserializer).map(Self);
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Number(x) => Ok(Self(x)),
+            Repr::Text(text) => text.parse().map(Self).map_err(serde::de::Error::custom),
+        }
+    }
+}
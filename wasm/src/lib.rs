@@ -0,0 +1,48 @@
+//! WebAssembly interface of the 3photons simulation
+//!
+//! This lets JavaScript code, such as an educational web page, run the
+//! simulation in the browser. Build it with `wasm-pack build --target web`.
+
+#![warn(missing_docs)]
+
+use anyhow::{ensure, Context, Result};
+use trois_photons::{
+    config::{ConfigFormat, Configuration},
+    simulation::Simulation,
+};
+use wasm_bindgen::prelude::*;
+
+/// Simulate a number of events, given a configuration in TOML format (see
+/// `valeurs.toml`), and return the results in the JSON format of the
+/// `json_output` configuration entry
+///
+/// Configuration entries which require file I/O, such as event output or
+/// checkpointing, are not supported.
+///
+#[wasm_bindgen]
+pub fn simulate(config_text: &str, num_events: usize) -> std::result::Result<String, JsError> {
+    run(config_text, num_events).map_err(|e| JsError::new(&format!("{e:#}")))
+}
+
+/// Implementation of simulate(), with more convenient error handling
+fn run(config_text: &str, num_events: usize) -> Result<String> {
+    // Load the configuration, and make sure it does not require file I/O
+    let cfg = Configuration::parse(config_text, ConfigFormat::Toml, |cfg| {
+        cfg.num_events = num_events;
+    })
+    .context("Failed to load the configuration")?;
+    ensure!(
+        cfg.event_output.is_none()
+            && cfg.json_output.is_none()
+            && cfg.summary_yaml.is_none()
+            && cfg.checkpoint.is_none()
+            && cfg.coupling_grid.is_none(),
+        "Event output, JSON output, YAML summaries, checkpointing and coupling grids are not \
+         supported in WebAssembly"
+    );
+
+    // Set up and run the simulation
+    let simulation = Simulation::new(&cfg).context("Failed to set up the simulation")?;
+    let result = simulation.run().context("Failed to run the simulation")?;
+    Ok(result.to_json()?)
+}